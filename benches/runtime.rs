@@ -0,0 +1,51 @@
+//! Times the interpreter binary against the scripts under `benches/scripts`.
+//!
+//! Run with `cargo bench`; pass a name filter to time a single script,
+//! e.g. `cargo bench -- arrays`.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 10;
+
+fn time_script(path: &Path) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let status = Command::new(env!("CARGO_BIN_EXE_filipe"))
+            .arg("run")
+            .arg(path)
+            .stdout(Stdio::null())
+            .status()
+            .expect("failed to spawn filipe");
+        total += start.elapsed();
+        assert!(status.success(), "{} failed", path.display());
+    }
+    total / RUNS
+}
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let scripts_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/scripts");
+
+    let mut scripts = fs::read_dir(scripts_dir)
+        .expect("missing benches/scripts")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "fl"))
+        .collect::<Vec<_>>();
+    scripts.sort();
+
+    for script in scripts {
+        let name = script.file_stem().unwrap().to_string_lossy().to_string();
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !name.contains(filter.as_str()))
+        {
+            continue;
+        }
+        let mean = time_script(&script);
+        println!("{:<20} {:>10.2?} (mean of {} runs)", name, mean, RUNS);
+    }
+}
@@ -0,0 +1,13 @@
+//! Whatever bytes it is given, the front end must come out with a program
+//! or syntax errors, never a panic.
+
+#![no_main]
+
+use filipe::frontend::lexer::Lexer;
+use filipe::frontend::parser::Parser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut l = Lexer::from_reader(data);
+    Parser::new(&mut l).parse();
+});
@@ -0,0 +1,51 @@
+/* The C interface of filipe, built with `cargo build --release --features ffi`
+ * as the shared library target/release/libfilipe.{so,dylib,dll}. See src/ffi.rs. */
+#ifndef FILIPE_H
+#define FILIPE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct FilipeRuntime FilipeRuntime;
+typedef struct FilipeValue FilipeValue;
+
+/* What filipe_value_type returns. */
+#define FILIPE_NULL 0
+#define FILIPE_INT 1
+#define FILIPE_FLOAT 2
+#define FILIPE_BOOL 3
+#define FILIPE_STRING 4
+#define FILIPE_ARRAY 5
+#define FILIPE_OTHER 6
+
+FilipeRuntime *filipe_new_runtime(void);
+void filipe_free_runtime(FilipeRuntime *runtime);
+
+/* The value of the last statement, or NULL on errors, a NULL source and
+ * panics, after which the runtime starts over. */
+FilipeValue *filipe_run(FilipeRuntime *runtime, const char *source);
+/* Why the last filipe_run failed, owned by the runtime; NULL if it didn't. */
+const char *filipe_get_error(const FilipeRuntime *runtime);
+
+void filipe_free_value(FilipeValue *value);
+int filipe_value_type(const FilipeValue *value);
+int64_t filipe_value_as_int(const FilipeValue *value);
+double filipe_value_as_float(const FilipeValue *value);
+bool filipe_value_as_bool(const FilipeValue *value);
+/* Free with filipe_free_string. */
+char *filipe_value_to_string(const FilipeValue *value);
+void filipe_free_string(char *string);
+size_t filipe_array_len(const FilipeValue *value);
+/* NULL when there is no item at index. */
+FilipeValue *filipe_array_get(const FilipeValue *value, size_t index);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,78 @@
+//! Programs as JSON, for tools that read or write filipe code without
+//! parsing it themselves: the AST of `frontend::ast` with serde's default
+//! layout, where an enum variant is an object keyed by its name:
+//!
+//! ```json
+//! [{ "kind": { "Expr": { "kind": { "Call": [
+//!     { "kind": { "Identifier": "print" } },
+//!     [{ "kind": { "Literal": { "String": "hi" } } }]
+//! ] } } } }]
+//! ```
+//!
+//! Spans can be left out of programs written by hand. Scopes are resolved
+//! again when a program is read, so slots don't have to be right either.
+
+use crate::frontend::ast::Program;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::{Parser, ParserError};
+use crate::frontend::resolver::resolve;
+use crate::runtime::context::Context;
+use crate::runtime::object::Object;
+use crate::runtime::Runtime;
+use crate::shared::{Lock, Shared};
+
+pub const EXTENSION: &str = "json";
+
+pub fn to_json(program: &Program) -> String {
+    serde_json::to_string_pretty(program).expect("AST is always serializable")
+}
+
+pub fn from_json(json: &str) -> Result<Program, String> {
+    let mut program =
+        serde_json::from_str::<Program>(json).map_err(|err| format!("Invalid JSON AST: {err}"))?;
+    resolve(&mut program);
+    Ok(program)
+}
+
+/// The AST of `source` as JSON, or what keeps it from parsing.
+pub fn parse_to_json(source: &str) -> Result<String, Vec<ParserError>> {
+    let input = source.chars().collect::<Vec<char>>();
+    let mut l = Lexer::new(&input);
+    let mut p = Parser::new(&mut l);
+    let program = p.parse();
+    match p.has_error() {
+        true => Err(p.errors().to_vec()),
+        false => Ok(to_json(&program)),
+    }
+}
+
+/// Runs a program given as JSON in `env`, returning the value it ends with.
+pub fn eval_json_ast(json: &str, env: Shared<Lock<Context>>) -> Result<Object, String> {
+    let program = from_json(json)?;
+    Runtime::new(env)
+        .eval(program)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::flstdlib::builtins;
+
+    #[test]
+    fn test_json_ast_roundtrip() {
+        let json = parse_to_json("define double(n: int): int {\n  return n * 2\n}\ndouble(21)");
+        let env = Shared::new(Lock::new(Context::make_global(builtins())));
+        let value = eval_json_ast(&json.unwrap_or_default(), env).unwrap();
+        assert_eq!(value.to_string(), "42");
+
+        // spans are optional and identifiers resolve to locals on load
+        let json = r#"[{"kind": {"Expr": {"kind": {"Infix": [
+            {"kind": {"Literal": {"Int": 1}}}, "Plus", {"kind": {"Literal": {"Int": 2}}}
+        ]}}}}]"#;
+        let env = Shared::new(Lock::new(Context::make_global(builtins())));
+        assert_eq!(eval_json_ast(json, env).unwrap().to_string(), "3");
+        assert!(parse_to_json("let = 1").is_err());
+        assert!(from_json("{}").unwrap_err().starts_with("Invalid JSON AST"));
+    }
+}
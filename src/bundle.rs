@@ -0,0 +1,169 @@
+//! `filipe bundle`: a single executable for a script. The modules it
+//! imports are put in place of their `import` statements, once each like
+//! at run time, and the program is precompiled (see `flbc`) and appended to
+//! a copy of the interpreter, followed by its length and a magic trailer.
+//! An interpreter that finds the trailer at its own end runs that program
+//! instead of reading its command line.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::flbc;
+use crate::frontend::ast::{Program, StmtKind};
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::messages::msg;
+use crate::runtime::modules::resolve;
+
+const MAGIC: &[u8; 8] = b"FLBUNDLE";
+/// The chunk's length as a little-endian `u64`, then the magic.
+const TRAILER_LEN: usize = 8 + MAGIC.len();
+
+/// The program bundled into the running executable, if it is a bundle.
+pub fn embedded() -> Option<Result<Program, String>> {
+    let mut exe = File::open(env::current_exe().ok()?).ok()?;
+    let end = exe.seek(SeekFrom::End(-(TRAILER_LEN as i64))).ok()?;
+    let mut trailer = [0; TRAILER_LEN];
+    exe.read_exact(&mut trailer).ok()?;
+    if !trailer.ends_with(MAGIC) {
+        return None;
+    }
+
+    let len = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+    let mut chunk = vec![];
+    let read = exe
+        .seek(SeekFrom::Start(end.checked_sub(len)?))
+        .and_then(|_| exe.take(len).read_to_end(&mut chunk));
+    Some(match read {
+        Ok(_) => flbc::decode(&chunk),
+        Err(err) => Err(format!("Couldn't read the bundled program: {err}")),
+    })
+}
+
+/// `program`, read from `path`, with its imports and theirs put in its
+/// place. Packages are also looked for in `search_paths`, see `modules`.
+pub fn inline_imports(
+    program: Program,
+    path: &str,
+    search_paths: &[PathBuf],
+) -> Result<Program, String> {
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    inline(program, dir, search_paths, &mut HashSet::new())
+}
+
+fn inline(
+    program: Program,
+    dir: &Path,
+    search_paths: &[PathBuf],
+    loaded: &mut HashSet<PathBuf>,
+) -> Result<Program, String> {
+    let mut bundled = Vec::with_capacity(program.len());
+    for stmt in program {
+        let StmtKind::Import(name) = &stmt.kind else {
+            bundled.push(stmt);
+            continue;
+        };
+        let path =
+            resolve(dir, search_paths, name).ok_or_else(|| msg!("E020.not_found", name = name))?;
+        if !loaded.insert(path.canonicalize().unwrap_or_else(|_| path.clone())) {
+            continue;
+        }
+        let display = path.to_string_lossy().to_string();
+        let source = fs::read_to_string(&path)
+            .map_err(|err| msg!("E020.unreadable", path = display, error = err))?;
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let module = p.parse();
+        if let Some(error) = p.errors().first() {
+            let msg = msg!(
+                "E020.syntax",
+                path = display,
+                span = error.span,
+                msg = error.msg
+            );
+            return Err(msg);
+        }
+        let module_dir = path.parent().unwrap_or(Path::new(""));
+        bundled.extend(inline(module, module_dir, search_paths, loaded)?);
+    }
+    Ok(bundled)
+}
+
+/// Writes a copy of the running interpreter with `program` in it to
+/// `output`.
+pub fn write(program: &Program, output: &str) -> Result<(), String> {
+    let exe = env::current_exe().map_err(|err| format!("Couldn't find the interpreter: {err}"))?;
+    let mut interpreter =
+        fs::read(&exe).map_err(|err| format!("Couldn't read {}: {err}", exe.display()))?;
+    // bundling from a bundle replaces its program
+    if interpreter.ends_with(MAGIC) {
+        let len = u64::from_le_bytes(
+            interpreter[interpreter.len() - TRAILER_LEN..][..8]
+                .try_into()
+                .unwrap(),
+        );
+        let start = interpreter.len().saturating_sub(TRAILER_LEN + len as usize);
+        interpreter.truncate(start);
+    }
+
+    let chunk = flbc::encode(program);
+    let written = File::create(output).and_then(|mut file| {
+        file.write_all(&interpreter)?;
+        file.write_all(&chunk)?;
+        file.write_all(&(chunk.len() as u64).to_le_bytes())?;
+        file.write_all(MAGIC)
+    });
+    written.map_err(|err| format!("Couldn't write {output}: {err}"))?;
+    make_executable(output)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &str) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|err| format!("Couldn't make {path} executable: {err}"))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_: &str) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Program {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        Parser::new(&mut l).parse()
+    }
+
+    #[test]
+    fn test_imports_are_inlined_once() {
+        let dir = env::temp_dir().join("filipe_bundle_test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.fl"), "import \"./b\"\nlet a = b + 1").unwrap();
+        fs::write(dir.join("b.fl"), "let b = 1").unwrap();
+
+        let main = dir.join("main.fl").to_string_lossy().to_string();
+        let program = parse("import \"./a\"\nimport \"./b\"\nprint(a)");
+        let bundled = inline_imports(program, &main, &[]).unwrap();
+        let missing = inline_imports(parse("import \"./c\""), &main, &[]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names = bundled
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Let(name, ..) => name.0.as_str(),
+                _ => "print",
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["b", "a", "print"]);
+        assert_eq!(missing.unwrap_err(), "module './c' not found");
+    }
+}
@@ -0,0 +1,904 @@
+//! C for `filipe build --target c`, an experimental backend for scripts
+//! that do a lot of arithmetic. The checker's types become C types, so it
+//! compiles the part of filipe whose types are all known before running:
+//! ints, floats, booleans, strings and arrays of them, functions declared
+//! at the top level, `if`, `for` over ranges and arrays, and `assert`.
+//! Anything else, like `try` or iterators, is an error naming the line.
+//!
+//! ```c
+//! static int64_t fib_(int64_t n_) {
+//!   if (n_ < 2) {
+//!     return n_;
+//!   }
+//!   return fl_add(fib_(fl_sub(n_, 1)), fib_(fl_sub(n_, 2)));
+//! }
+//! ```
+//!
+//! Strings and arrays are reference counted by the runtime in `runtime.c`,
+//! which the output starts with. Every expression gives a reference of its
+//! own that whatever uses it drops, and variables drop theirs when they go
+//! out of scope. Names get a trailing `_`, which filipe names can't have,
+//! so they can't clash with C's.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::frontend::ast::*;
+use crate::frontend::checker::Analysis;
+use crate::frontend::token::Span;
+use crate::runtime::type_system::{expr_type_to_object_type, Type};
+
+const RUNTIME: &str = include_str!("runtime.c");
+
+/// `program` as a C program, to be built with e.g. `cc -O2 script.c -lm`.
+/// Imports must already be inlined.
+pub fn compile(program: &Program, analysis: &Analysis) -> Result<String, String> {
+    let mut generator = Generator {
+        analysis,
+        globals: HashMap::new(),
+        functions: HashSet::new(),
+        scopes: vec![],
+        returns: None,
+        temps: 0,
+        out: String::new(),
+        indent: 0,
+    };
+    let declarations = generator.declare(program)?;
+
+    let mut functions = String::new();
+    generator.indent = 1;
+    let mut main = String::new();
+    for stmt in program {
+        match &stmt.kind {
+            StmtKind::Func(name, params, body, returns, ..) => {
+                let returns = generator.return_type(stmt, returns.as_ref(), &name.0)?;
+                generator.out = String::new();
+                generator.function(name, params, body, &returns)?;
+                functions.push_str(&generator.out);
+            }
+            _ => {
+                generator.out = String::new();
+                generator.stmt(stmt)?;
+                main.push_str(&generator.out);
+            }
+        }
+    }
+
+    Ok(format!(
+        "{RUNTIME}{declarations}\n{functions}int main(void) {{\n{main}  return 0;\n}}\n"
+    ))
+}
+
+/// A variable in scope: its C name and type.
+struct Local {
+    name: String,
+    c_name: String,
+    type_: Type,
+}
+
+struct Generator<'a> {
+    analysis: &'a Analysis,
+    /// Variables declared at the top level, which are C globals so that
+    /// functions can use them.
+    globals: HashMap<String, Type>,
+    /// Names of the functions.
+    functions: HashSet<String>,
+    /// Locals of the function or top level block being compiled, innermost
+    /// scope last.
+    scopes: Vec<Vec<Local>>,
+    /// Return type of the function being compiled.
+    returns: Option<Type>,
+    /// Count of the hidden variables made for loops, to name them.
+    temps: usize,
+    out: String,
+    indent: usize,
+}
+
+impl Generator<'_> {
+    /// Collects the globals and functions, returning their C declarations.
+    fn declare(&mut self, program: &Program) -> Result<String, String> {
+        let mut globals = String::new();
+        let mut prototypes = String::new();
+        for stmt in program {
+            match &stmt.kind {
+                StmtKind::Let(Identifier(name), annotation, _) => {
+                    let type_ = self.let_type(stmt, annotation.as_ref(), name)?;
+                    let _ = writeln!(globals, "static {} {}_;", c_type(&type_, stmt.span)?, name);
+                    self.globals.insert(name.clone(), type_);
+                }
+                StmtKind::Func(.., true) => {
+                    return Err(unsupported(stmt.span, "async functions"));
+                }
+                StmtKind::Func(Identifier(name), ..) => {
+                    self.functions.insert(name.clone());
+                }
+                _ => {}
+            }
+        }
+        for stmt in program {
+            if let StmtKind::Func(Identifier(name), params, _, returns, ..) = &stmt.kind {
+                let returns = self.return_type(stmt, returns.as_ref(), name)?;
+                let signature = self.signature(name, params, &returns, stmt.span)?;
+                let _ = writeln!(prototypes, "{};", signature);
+            }
+        }
+        Ok(format!("{globals}\n{prototypes}"))
+    }
+
+    fn signature(
+        &self,
+        name: &str,
+        params: &[(Identifier, ExprType)],
+        returns: &Type,
+        span: Span,
+    ) -> Result<String, String> {
+        let params = params
+            .iter()
+            .map(|(Identifier(name), type_)| {
+                let type_ = c_type(&expr_type_to_object_type(type_), span)?;
+                Ok(format!("{} {}_", type_, name))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let params = match params.is_empty() {
+            true => "void".to_string(),
+            false => params.join(", "),
+        };
+        let returns = match returns {
+            Type::Void => "void",
+            returns => c_type(returns, span)?,
+        };
+        Ok(format!("static {} {}_({})", returns, name, params))
+    }
+
+    fn let_type(
+        &self,
+        stmt: &Stmt,
+        annotation: Option<&ExprType>,
+        name: &str,
+    ) -> Result<Type, String> {
+        match annotation {
+            Some(annotation) => Ok(expr_type_to_object_type(annotation)),
+            None => self
+                .analysis
+                .let_types
+                .get(&stmt.span.offset)
+                .cloned()
+                .ok_or_else(|| unsupported(stmt.span, &format!("'{name}' without a known type"))),
+        }
+    }
+
+    fn return_type(
+        &self,
+        stmt: &Stmt,
+        annotation: Option<&ExprType>,
+        name: &str,
+    ) -> Result<Type, String> {
+        match annotation {
+            Some(annotation) => Ok(expr_type_to_object_type(annotation)),
+            None => self
+                .analysis
+                .return_types
+                .get(&stmt.span.offset)
+                .cloned()
+                .ok_or_else(|| {
+                    unsupported(stmt.span, &format!("'{name}' without a known return type"))
+                }),
+        }
+    }
+
+    fn line(&mut self, code: &str) {
+        self.out.push_str(&"  ".repeat(self.indent));
+        self.out.push_str(code);
+        self.out.push('\n');
+    }
+
+    fn function(
+        &mut self,
+        Identifier(name): &Identifier,
+        params: &[(Identifier, ExprType)],
+        body: &BlockStmt,
+        returns: &Type,
+    ) -> Result<(), String> {
+        let span = body.first().map_or(Span::default(), |stmt| stmt.span);
+        self.out.push_str(&format!(
+            "{} {{\n",
+            self.signature(name, params, returns, span)?
+        ));
+        let params = params
+            .iter()
+            .map(|(Identifier(name), type_)| Local {
+                name: name.clone(),
+                c_name: format!("{name}_"),
+                type_: expr_type_to_object_type(type_),
+            })
+            .collect();
+        self.scopes.push(params);
+        self.returns = Some(returns.clone());
+        let compiled = self.block(body);
+        self.returns = None;
+        compiled?;
+        let scope = self.scopes.pop().unwrap_or_default();
+        if !ends_with_return(body) {
+            self.drop_locals(scope.iter().rev());
+        }
+        if *returns != Type::Void && !ends_with_return(body) {
+            self.line(&format!(
+                "fl_fail(\"TypeError\", \"'{name}' ended without returning a value\");"
+            ));
+        }
+        self.out.push_str("}\n\n");
+        Ok(())
+    }
+
+    /// Compiles `block` in a scope of its own, dropping its locals at the
+    /// end.
+    fn scoped(&mut self, block: &BlockStmt, locals: Vec<Local>) -> Result<(), String> {
+        self.indent += 1;
+        self.scopes.push(locals);
+        let compiled = self.block(block);
+        let scope = self.scopes.pop().unwrap_or_default();
+        // a `return` at the top level doesn't leave the block
+        let returned = self.returns.is_some() && ends_with_return(block);
+        if compiled.is_ok() && !returned {
+            self.drop_locals(scope.iter().rev());
+        }
+        self.indent -= 1;
+        compiled
+    }
+
+    fn block(&mut self, block: &BlockStmt) -> Result<(), String> {
+        block.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn drop_locals<'l>(&mut self, locals: impl IntoIterator<Item = &'l Local>) {
+        for local in locals {
+            if is_heap(&local.type_) {
+                self.line(&format!("fl_drop({});", local.c_name));
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => self.expr_stmt(expr)?,
+            StmtKind::Let(Identifier(name), annotation, value) => {
+                let type_ = self.let_type(stmt, annotation.as_ref(), name)?;
+                let Some(value) = value else {
+                    return Err(unsupported(stmt.span, "'let' without a value"));
+                };
+                let value = self.expr(value)?;
+                if self.scopes.is_empty() {
+                    self.line(&format!("{}_ = {};", name, value));
+                } else {
+                    let c_name = format!("{name}_");
+                    self.line(&format!(
+                        "{} {} = {};",
+                        c_type(&type_, stmt.span)?,
+                        c_name,
+                        value
+                    ));
+                    self.declare_local(name, c_name, type_);
+                }
+            }
+            StmtKind::Func(..) => {
+                return Err(unsupported(stmt.span, "functions declared in a block"));
+            }
+            // at the top level it only evaluates its value
+            StmtKind::Return(Some(value)) if self.returns.is_none() => self.expr_stmt(value)?,
+            StmtKind::Return(value) => {
+                let value = value.as_ref().map(|value| self.expr(value)).transpose()?;
+                match (&self.returns, value) {
+                    (None, _) => {}
+                    (Some(returns), value) => {
+                        let returns = returns.clone();
+                        let locals = self
+                            .scopes
+                            .iter()
+                            .flatten()
+                            .any(|local| is_heap(&local.type_));
+                        match value {
+                            Some(value) if locals => {
+                                let c_type = c_type(&returns, stmt.span)?;
+                                self.line("{");
+                                self.indent += 1;
+                                self.line(&format!("{} fl_result = {};", c_type, value));
+                                self.drop_all_locals();
+                                self.line("return fl_result;");
+                                self.indent -= 1;
+                                self.line("}");
+                            }
+                            Some(value) => self.line(&format!("return {};", value)),
+                            None => {
+                                self.drop_all_locals();
+                                self.line("return;");
+                            }
+                        }
+                    }
+                }
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let condition = self.condition(condition)?;
+                self.line(&format!("if ({}) {{", condition));
+                self.scoped(consequence, vec![])?;
+                if let Some(alternative) = alternative {
+                    self.line("} else {");
+                    self.scoped(alternative, vec![])?;
+                }
+                self.line("}");
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => self.for_loop(cursor, iterable, block)?,
+            StmtKind::Try { .. } => return Err(unsupported(stmt.span, "'try'")),
+            StmtKind::Assert(condition) => {
+                let source = c_string(&condition.to_string());
+                let condition = self.condition(condition)?;
+                self.line(&format!("fl_assert({}, {});", condition, source));
+            }
+            StmtKind::Import(_) => return Err(unsupported(stmt.span, "'import'")),
+        }
+        Ok(())
+    }
+
+    fn declare_local(&mut self, name: &str, c_name: String, type_: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Local {
+                name: name.to_string(),
+                c_name,
+                type_,
+            });
+        }
+    }
+
+    /// Drops every local of the function, before it returns.
+    fn drop_all_locals(&mut self) {
+        let scopes = std::mem::take(&mut self.scopes);
+        self.drop_locals(scopes.iter().rev().flat_map(|scope| scope.iter().rev()));
+        self.scopes = scopes;
+    }
+
+    fn expr_stmt(&mut self, expr: &Expr) -> Result<(), String> {
+        match &expr.kind {
+            ExprKind::Assign(Identifier(name), value) => {
+                let (c_name, type_) = self.variable(name, expr.span)?;
+                let value = self.expr(value)?;
+                match is_heap(&type_) {
+                    true => self.line(&format!("fl_set(&{}, {});", c_name, value)),
+                    false => self.line(&format!("{} = {};", c_name, value)),
+                }
+            }
+            ExprKind::IndexAssign(Identifier(name), index, value) => {
+                let (c_name, type_) = self.variable(name, expr.span)?;
+                let item = match type_ {
+                    Type::Array(Some(item)) => *item,
+                    _ => {
+                        return Err(unsupported(
+                            expr.span,
+                            &format!("'{name}' without a known type"),
+                        ))
+                    }
+                };
+                let index = self.expr(index)?;
+                let value = self.expr(value)?;
+                self.line(&format!(
+                    "fl_array_set(&{}, {}, (fl_value){{.{} = {}}});",
+                    c_name,
+                    index,
+                    member(&item, expr.span)?,
+                    value
+                ));
+            }
+            _ => {
+                let type_ = self.type_of(expr)?;
+                let code = self.expr(expr)?;
+                match is_heap(&type_) {
+                    true => self.line(&format!("fl_drop({});", code)),
+                    false => self.line(&format!("{};", code)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn for_loop(&mut self, cursor: &str, iterable: &Expr, block: &BlockStmt) -> Result<(), String> {
+        self.temps += 1;
+        let (i, end) = (
+            format!("fl_i{}", self.temps),
+            format!("fl_end{}", self.temps),
+        );
+        let c_name = format!("{cursor}_");
+
+        if let ExprKind::Call(func, args) = &iterable.kind {
+            if self.is_builtin(func, "range") {
+                let start = self.expr(&args[0])?;
+                let stop = self.expr(&args[1])?;
+                // a step of 0 fails when the loop starts, as in the interpreter
+                let step = args
+                    .get(2)
+                    .map(int_literal)
+                    .unwrap_or(Some(1))
+                    .filter(|step| *step != 0);
+                let header = match step {
+                    Some(step) if step < 0 => {
+                        format!(
+                            "{i} = {start}, {end} = {stop}; {i} > {end}; {i} -= {}",
+                            -step
+                        )
+                    }
+                    Some(step) => {
+                        format!("{i} = {start}, {end} = {stop}; {i} < {end}; {i} += {step}")
+                    }
+                    None => {
+                        let step_name = format!("fl_step{}", self.temps);
+                        let step = self.expr(&args[2])?;
+                        format!(
+                            "{i} = {start}, {end} = {stop}, {step_name} = fl_range_step({step}); \
+                             {step_name} > 0 ? {i} < {end} : {i} > {end}; {i} += {step_name}"
+                        )
+                    }
+                };
+                self.line(&format!("for (int64_t {}) {{", header));
+                self.line(&format!("  int64_t {} = {};", c_name, i));
+                let cursor = Local {
+                    name: cursor.to_string(),
+                    c_name,
+                    type_: Type::Int,
+                };
+                self.scoped(block, vec![cursor])?;
+                self.line("}");
+                return Ok(());
+            }
+        }
+
+        let item = match self.type_of(iterable)? {
+            Type::Array(Some(item)) => *item,
+            Type::Array(None) => {
+                return Err(unsupported(iterable.span, "arrays without a known type"))
+            }
+            found => return Err(unsupported(iterable.span, &format!("'for' over a {found}"))),
+        };
+        let items = format!("fl_items{}", self.temps);
+        let array = self.expr(iterable)?;
+        self.line(&format!("fl_array {} = {};", items, array));
+        // in a scope of its own, so a `return` in the loop drops it too
+        self.scopes.push(vec![Local {
+            name: String::new(),
+            c_name: items.clone(),
+            type_: Type::Array(None),
+        }]);
+        self.line(&format!(
+            "for (int64_t {i} = 0; {i} < {items}->len; {i}++) {{"
+        ));
+        self.line(&format!(
+            "  {} {} = fl_get(fl_ref({}), {}).{};",
+            c_type(&item, iterable.span)?,
+            c_name,
+            items,
+            i,
+            member(&item, iterable.span)?
+        ));
+        let cursor = Local {
+            name: cursor.to_string(),
+            c_name,
+            type_: item,
+        };
+        let compiled = self.scoped(block, vec![cursor]);
+        self.scopes.pop();
+        compiled?;
+        self.line("}");
+        self.line(&format!("fl_drop({});", items));
+        Ok(())
+    }
+
+    /// C name and type of the variable `name` where it is used.
+    fn variable(&self, name: &str, span: Span) -> Result<(String, Type), String> {
+        let local = self
+            .scopes
+            .iter()
+            .rev()
+            .flatten()
+            .find(|local| local.name == name);
+        if let Some(local) = local {
+            return Ok((local.c_name.clone(), local.type_.clone()));
+        }
+        match self.globals.get(name) {
+            Some(type_) => Ok((format!("{name}_"), type_.clone())),
+            None => Err(unsupported(
+                span,
+                &format!("'{name}' from the caller's scope, functions only see globals"),
+            )),
+        }
+    }
+
+    fn is_builtin(&self, func: &Expr, name: &str) -> bool {
+        match &func.kind {
+            ExprKind::Identifier(Identifier(called)) | ExprKind::Local(Identifier(called), _) => {
+                called == name && !self.functions.contains(name)
+            }
+            _ => false,
+        }
+    }
+
+    fn type_of(&self, expr: &Expr) -> Result<Type, String> {
+        let known = self.analysis.expr_types.get(&expr.span.offset);
+        if let ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) =
+            &expr.kind
+        {
+            // `true` and `false` aren't variables
+            let variable = self.variable(name, expr.span);
+            if variable.is_ok() || known.is_none() {
+                return variable.map(|(_, type_)| type_);
+            }
+        }
+        known
+            .cloned()
+            .ok_or_else(|| unsupported(expr.span, &format!("'{expr}' without a known type")))
+    }
+
+    /// `expr` as a C expression giving a reference of its own when its
+    /// value is a string or an array.
+    fn expr(&self, expr: &Expr) -> Result<String, String> {
+        Ok(match &expr.kind {
+            ExprKind::Literal(literal) => self.literal(literal, expr)?,
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                if self.functions.contains(name) {
+                    return Err(unsupported(expr.span, "functions as values"));
+                }
+                let (c_name, type_) = match (self.variable(name, expr.span), name.as_str()) {
+                    (Ok(variable), _) => variable,
+                    (Err(_), "true" | "false") => return Ok(name.clone()),
+                    (Err(err), _) => return Err(err),
+                };
+                match is_heap(&type_) {
+                    true => format!("fl_ref({c_name})"),
+                    false => c_name,
+                }
+            }
+            ExprKind::Call(func, args) => self.call(func, args, expr)?,
+            ExprKind::Infix(lhs, infix, rhs) => {
+                let type_ = self.type_of(lhs)?;
+                let (lhs, rhs) = (self.operand(lhs)?, self.operand(rhs)?);
+                match (type_, infix) {
+                    (_, Infix::In) => return Err(unsupported(expr.span, "'in'")),
+                    (Type::Int, Infix::Plus) => format!("fl_add({lhs}, {rhs})"),
+                    (Type::Int, Infix::Minus) => format!("fl_sub({lhs}, {rhs})"),
+                    (Type::Int, Infix::Multiply) => format!("fl_mul({lhs}, {rhs})"),
+                    (Type::Int, Infix::Devide) => format!("fl_div({lhs}, {rhs})"),
+                    (Type::Int, Infix::Remainder) => format!("fl_rem({lhs}, {rhs})"),
+                    (Type::Float, Infix::Remainder) => format!("fmod({lhs}, {rhs})"),
+                    (Type::String, Infix::Plus) => format!("fl_concat({lhs}, {rhs})"),
+                    (Type::String, Infix::Equal) => format!("fl_str_eq({lhs}, {rhs})"),
+                    (Type::String, Infix::NotEqual) => format!("!fl_str_eq({lhs}, {rhs})"),
+                    (Type::Int | Type::Float | Type::Boolean, _) => format!("{lhs} {infix} {rhs}"),
+                    (found, _) => {
+                        return Err(unsupported(expr.span, &format!("'{infix}' on a {found}")))
+                    }
+                }
+            }
+            ExprKind::Prefix(prefix, operand) => match (prefix, self.type_of(operand)?) {
+                (Prefix::Not, Type::Boolean) => format!("!{}", self.operand(operand)?),
+                (Prefix::Minus, Type::Int) => format!("fl_sub(0, {})", self.expr(operand)?),
+                (Prefix::Minus | Prefix::Plus, Type::Int | Type::Float) => {
+                    format!("{}{}", prefix, self.operand(operand)?)
+                }
+                (_, found) => {
+                    return Err(unsupported(expr.span, &format!("'{prefix}' on a {found}")))
+                }
+            },
+            ExprKind::Postfix(operand, Postfix::Increment) => {
+                format!("fl_add({}, 1)", self.expr(operand)?)
+            }
+            ExprKind::Postfix(operand, Postfix::Decrement) => {
+                format!("fl_sub({}, 1)", self.expr(operand)?)
+            }
+            ExprKind::Assign(Identifier(name), value) => {
+                let (c_name, type_) = self.variable(name, expr.span)?;
+                if is_heap(&type_) {
+                    return Err(unsupported(
+                        expr.span,
+                        "assigning strings or arrays inside an expression",
+                    ));
+                }
+                format!("({} = {})", c_name, self.expr(value)?)
+            }
+            ExprKind::Index(array, index) => {
+                let item = match self.type_of(array)? {
+                    Type::Array(Some(item)) => item,
+                    _ => {
+                        return Err(unsupported(
+                            expr.span,
+                            &format!("'{array}' without a known type"),
+                        ))
+                    }
+                };
+                format!(
+                    "fl_get({}, {}).{}",
+                    self.expr(array)?,
+                    self.expr(index)?,
+                    member(&item, expr.span)?
+                )
+            }
+            ExprKind::IndexAssign(..) => {
+                return Err(unsupported(
+                    expr.span,
+                    "assigning array items inside an expression",
+                ))
+            }
+            ExprKind::Await(_) => return Err(unsupported(expr.span, "await")),
+            ExprKind::Method(..) => return Err(unsupported(expr.span, "method calls")),
+            ExprKind::Cast(operand, type_) => match (self.type_of(operand)?, type_) {
+                (Type::Int, ExprType::Int)
+                | (Type::Float, ExprType::Float)
+                | (Type::Boolean, ExprType::Boolean) => self.operand(operand)?,
+                (Type::Int, ExprType::Float) => format!("((double){})", self.operand(operand)?),
+                (Type::Int | Type::Float, ExprType::Boolean) => {
+                    format!("({} != 0)", self.operand(operand)?)
+                }
+                (found, _) => {
+                    return Err(unsupported(
+                        expr.span,
+                        &format!("casting a {found} to {type_}"),
+                    ))
+                }
+            },
+        })
+    }
+
+    /// `expr` where it is part of a bigger expression.
+    fn operand(&self, expr: &Expr) -> Result<String, String> {
+        let code = self.expr(expr)?;
+        // int arithmetic and string operators are calls already
+        let is_call = match &expr.kind {
+            ExprKind::Infix(lhs, infix, _) => match self.type_of(lhs)? {
+                Type::Int => matches!(
+                    infix,
+                    Infix::Plus | Infix::Minus | Infix::Multiply | Infix::Devide | Infix::Remainder
+                ),
+                Type::String => !matches!(infix, Infix::NotEqual),
+                _ => false,
+            },
+            ExprKind::Prefix(Prefix::Minus, operand) => self.type_of(operand)? == Type::Int,
+            ExprKind::Prefix(..) => false,
+            _ => true,
+        };
+        Ok(match is_call {
+            true => code,
+            false => format!("({code})"),
+        })
+    }
+
+    fn condition(&self, expr: &Expr) -> Result<String, String> {
+        match self.type_of(expr)? {
+            Type::Boolean => self.expr(expr),
+            Type::Int | Type::Float => Ok(format!("{} != 0", self.operand(expr)?)),
+            found => Err(unsupported(expr.span, &format!("a {found} as a condition"))),
+        }
+    }
+
+    fn call(&self, func: &Expr, args: &[Expr], call: &Expr) -> Result<String, String> {
+        let (ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _)) =
+            &func.kind
+        else {
+            return Err(unsupported(call.span, "calling a value"));
+        };
+        let compiled = args
+            .iter()
+            .map(|arg| self.expr(arg))
+            .collect::<Result<Vec<_>, String>>()?;
+        if self.functions.contains(name) {
+            return Ok(format!("{}_({})", name, compiled.join(", ")));
+        }
+
+        Ok(match (name.as_str(), &compiled[..]) {
+            ("print", _) => {
+                let mut printed = vec![args.len().to_string()];
+                for (arg, code) in args.iter().zip(compiled) {
+                    let type_ = self.type_of(arg)?;
+                    // variadic arguments aren't converted to the expected type
+                    let code = match arg.kind {
+                        ExprKind::Literal(Literal::Int(_)) => format!("(int64_t){code}"),
+                        _ => code,
+                    };
+                    printed.push(kind(&type_, arg.span)?.to_string());
+                    printed.push(code);
+                }
+                format!("fl_print({})", printed.join(", "))
+            }
+            ("len", [value]) => format!("fl_len({value})"),
+            ("exit", []) => "exit(0)".to_string(),
+            ("exit", [code]) => format!("exit(fl_exit_code({code}))"),
+            ("random", []) => "fl_random()".to_string(),
+            ("random", [max]) => format!("fl_random_upto({max})"),
+            ("random", [min, max]) => format!("fl_random_between({min}, {max})"),
+            ("typeof", [value]) => {
+                let type_ = self.type_of(&args[0])?;
+                let name = type_.to_string();
+                let value = match is_heap(&type_) {
+                    true => format!("fl_drop({value})"),
+                    false => format!("(void)({value})"),
+                };
+                format!(
+                    "({}, fl_str_new({}, {}))",
+                    value,
+                    c_string(&name),
+                    name.len()
+                )
+            }
+            _ => return Err(unsupported(call.span, &format!("'{name}'"))),
+        })
+    }
+
+    fn literal(&self, literal: &Literal, expr: &Expr) -> Result<String, String> {
+        Ok(match literal {
+            Literal::Null => return Err(unsupported(expr.span, "'null'")),
+            Literal::Int(val) => val.to_string(),
+            Literal::Float(val) => format!("{:?}", val),
+            Literal::String(val) => format!("fl_str_new({}, {})", c_string(val), val.len()),
+            Literal::Boolean(val) => val.to_string(),
+            Literal::Array(items) if items.is_empty() => "fl_array_of(FL_INT, 0, NULL)".to_string(),
+            Literal::Array(items) => {
+                let item = match self.type_of(expr)? {
+                    Type::Array(Some(item)) => *item,
+                    _ => return Err(unsupported(expr.span, "arrays of mixed types")),
+                };
+                let member = member(&item, expr.span)?;
+                let items = items
+                    .iter()
+                    .map(|item| Ok(format!("{{.{} = {}}}", member, self.expr(item)?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                format!(
+                    "fl_array_of({}, {}, (fl_value[]){{{}}})",
+                    kind(&item, expr.span)?,
+                    items.len(),
+                    items.join(", ")
+                )
+            }
+        })
+    }
+}
+
+fn ends_with_return(block: &BlockStmt) -> bool {
+    matches!(
+        block.last(),
+        Some(Stmt {
+            kind: StmtKind::Return(_),
+            ..
+        })
+    )
+}
+
+fn int_literal(expr: &Expr) -> Option<i64> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Int(val)) => Some(*val),
+        ExprKind::Prefix(Prefix::Minus, operand) => int_literal(operand).map(|val| -val),
+        _ => None,
+    }
+}
+
+/// Whether values of `type_` are reference counted.
+fn is_heap(type_: &Type) -> bool {
+    matches!(type_, Type::String | Type::TypeAnnot | Type::Array(_))
+}
+
+fn c_type(type_: &Type, span: Span) -> Result<&'static str, String> {
+    match type_ {
+        Type::Int => Ok("int64_t"),
+        Type::Float => Ok("double"),
+        Type::Boolean => Ok("bool"),
+        Type::String | Type::TypeAnnot => Ok("fl_str"),
+        Type::Array(_) => Ok("fl_array"),
+        found => Err(unsupported(span, &format!("values of type {found}"))),
+    }
+}
+
+/// The runtime's `fl_kind` of `type_`.
+fn kind(type_: &Type, span: Span) -> Result<&'static str, String> {
+    match type_ {
+        Type::Int => Ok("FL_INT"),
+        Type::Float => Ok("FL_FLOAT"),
+        Type::Boolean => Ok("FL_BOOL"),
+        Type::String | Type::TypeAnnot => Ok("FL_STR"),
+        Type::Array(_) => Ok("FL_ARRAY"),
+        found => Err(unsupported(span, &format!("values of type {found}"))),
+    }
+}
+
+/// The `fl_value` member holding a `type_`.
+fn member(type_: &Type, span: Span) -> Result<&'static str, String> {
+    match type_ {
+        Type::Int => Ok("i"),
+        Type::Float => Ok("f"),
+        Type::Boolean => Ok("b"),
+        Type::String | Type::TypeAnnot => Ok("s"),
+        Type::Array(_) => Ok("a"),
+        found => Err(unsupported(span, &format!("values of type {found}"))),
+    }
+}
+
+/// A C string literal of `text`. Non-ASCII bytes are octal escapes, which
+/// unlike hex ones can't run into the next character, and `?` is escaped so
+/// it can't start a trigraph.
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'?' => literal.push_str("\\?"),
+            b'\n' => literal.push_str("\\n"),
+            b'\t' => literal.push_str("\\t"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => {
+                let _ = write!(literal, "\\{:03o}", byte);
+            }
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn unsupported(span: Span, what: &str) -> String {
+    format!("{span}: the C backend doesn't support {what}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::checker::analyze;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    fn compile_source(source: &str) -> Result<String, String> {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+        compile(&program, &analyze(&program)).map(|c| c[RUNTIME.len()..].to_string())
+    }
+
+    #[test]
+    fn test_compile_to_c() {
+        let c = compile_source(
+            "define shout(word: string): string {\n  let loud = word + \"!\"\n  return loud\n}\n\
+             let total = 0\n\
+             for i in range(0, 10, 2) {\n  total = total + i / 2\n}\n\
+             print(shout(\"hi\"), total)",
+        );
+        assert_eq!(
+            c.unwrap(),
+            "static int64_t total_;\n\
+             \n\
+             static fl_str shout_(fl_str word_);\n\
+             \n\
+             static fl_str shout_(fl_str word_) {\n\
+             \x20 fl_str loud_ = fl_concat(fl_ref(word_), fl_str_new(\"!\", 1));\n\
+             \x20 {\n\
+             \x20   fl_str fl_result = fl_ref(loud_);\n\
+             \x20   fl_drop(loud_);\n\
+             \x20   fl_drop(word_);\n\
+             \x20   return fl_result;\n\
+             \x20 }\n\
+             }\n\
+             \n\
+             int main(void) {\n\
+             \x20 total_ = 0;\n\
+             \x20 for (int64_t fl_i1 = 0, fl_end1 = 10; fl_i1 < fl_end1; fl_i1 += 2) {\n\
+             \x20   int64_t i_ = fl_i1;\n\
+             \x20   total_ = fl_add(total_, fl_div(i_, 2));\n\
+             \x20 }\n\
+             \x20 fl_print(2, FL_STR, shout_(fl_str_new(\"hi\", 2)), FL_INT, total_);\n\
+             \x20 return 0;\n\
+             }\n"
+        );
+
+        let error = compile_source("try {\n  print(1)\n} catch e {\n  print(e)\n}");
+        assert_eq!(
+            error.unwrap_err(),
+            "1:1: the C backend doesn't support 'try'"
+        );
+    }
+}
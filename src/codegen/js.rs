@@ -0,0 +1,442 @@
+//! JavaScript for `filipe build --target js`. The program becomes the body
+//! of a function passed to `$f.run`, after a copy of `runtime.js` with the
+//! builtins and helpers it uses:
+//!
+//! ```js
+//! function double(n) {
+//!   return n * 2;
+//! }
+//! let x = $f.idiv(double(21), 2);
+//! print(x);
+//! ```
+//!
+//! Most of filipe maps to the same JavaScript. Where the two differ the
+//! checker's types pick the code: `/` on ints truncates and `if` only
+//! tests JavaScript truthiness when the condition isn't a boolean. Ints
+//! are JavaScript numbers, exact only up to 2^53, so `+`, `-` and `*` on
+//! ints raise an `OverflowError` past that, well before filipe's 64-bit
+//! ints would, and ints whose type the checker doesn't know lose
+//! precision there instead. Arrays
+//! are copied when an item is assigned, as they are values in filipe.
+//! `async define` and `await` become their JavaScript counterparts, so
+//! `await` can only be compiled in async functions and at the top level.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use crate::frontend::ast::*;
+use crate::runtime::type_system::Type;
+
+const RUNTIME: &str = include_str!("runtime.js");
+
+/// The builtins `runtime.js` defines, under their JavaScript names.
+const BUILTINS: &[&str] = &[
+    "print",
+    "exit",
+    "len",
+    "random",
+    "typeof_",
+    "range",
+    "iter",
+    "next",
+    "map",
+    "filter",
+    "take",
+    "collect",
+    "set",
+    "union",
+    "intersection",
+    "difference",
+    "error",
+    "raise",
+    "errorKind",
+    "errorMessage",
+    "breakpoint",
+];
+
+/// Names filipe allows that JavaScript doesn't, or that mean something else
+/// there. They get a trailing `_`, which filipe names can't have.
+const RESERVED: &[&str] = &[
+    "arguments",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// `program` as a JavaScript program. `expr_types` are the types the
+/// checker found, see `checker::Analysis`; imports must already be inlined.
+pub fn compile(program: &Program, expr_types: &HashMap<usize, Type>) -> Result<String, String> {
+    let mut generator = Generator {
+        out: String::new(),
+        indent: 1,
+        in_function: false,
+        awaited: Cell::new(false),
+        expr_types,
+    };
+    generator.block(program)?;
+
+    let main = if generator.awaited.get() {
+        "async () =>"
+    } else {
+        "() =>"
+    };
+    Ok(format!(
+        "{RUNTIME}const {{ {} }} = $f;\n\n$f.run({} {{\n{}}});\n",
+        BUILTINS.join(", "),
+        main,
+        generator.out
+    ))
+}
+
+struct Generator<'a> {
+    out: String,
+    indent: usize,
+    /// Whether `return` leaves a function; at the top level it only
+    /// evaluates its value.
+    in_function: bool,
+    /// Whether the function or top level being compiled has an `await`.
+    awaited: Cell<bool>,
+    expr_types: &'a HashMap<usize, Type>,
+}
+
+impl Generator<'_> {
+    fn line(&mut self, code: &str) {
+        self.out.push_str(&"  ".repeat(self.indent));
+        self.out.push_str(code);
+        self.out.push('\n');
+    }
+
+    fn nested(&mut self, open: &str, block: &BlockStmt) -> Result<(), String> {
+        self.line(open);
+        self.indent += 1;
+        let compiled = self.block(block);
+        self.indent -= 1;
+        compiled
+    }
+
+    fn block(&mut self, block: &BlockStmt) -> Result<(), String> {
+        block.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => {
+                let code = format!("{};", self.expr(expr));
+                self.line(&code);
+            }
+            StmtKind::Let(Identifier(name), _, value) => {
+                let value = value
+                    .as_ref()
+                    .map_or("null".to_string(), |value| self.expr(value));
+                self.line(&format!("let {} = {};", js_name(name), value));
+            }
+            StmtKind::Func(Identifier(name), params, body, .., is_async) => {
+                let params = params
+                    .iter()
+                    .map(|(Identifier(name), _)| js_name(name))
+                    .collect::<Vec<_>>();
+                let keyword = if *is_async {
+                    "async function"
+                } else {
+                    "function"
+                };
+                let open = format!("{} {}({}) {{", keyword, js_name(name), params.join(", "));
+                let in_function = std::mem::replace(&mut self.in_function, true);
+                let awaited = self.awaited.replace(false);
+                let compiled = self.nested(&open, body);
+                self.in_function = in_function;
+                let body_awaited = self.awaited.replace(awaited);
+                compiled?;
+                if body_awaited && !is_async {
+                    return Err(format!(
+                        "{}: '{}' awaits but isn't async, which JavaScript doesn't allow",
+                        stmt.span, name
+                    ));
+                }
+                self.line("}");
+            }
+            StmtKind::Return(value) => {
+                let value = value.as_ref().map(|value| self.expr(value));
+                match (self.in_function, value) {
+                    (true, Some(value)) => self.line(&format!("return {};", value)),
+                    (true, None) => self.line("return;"),
+                    (false, Some(value)) => self.line(&format!("{};", value)),
+                    (false, None) => {}
+                }
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let open = format!("if ({}) {{", self.condition(condition));
+                self.nested(&open, consequence)?;
+                if let Some(alternative) = alternative {
+                    self.nested("} else {", alternative)?;
+                }
+                self.line("}");
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                let open = format!(
+                    "for (let {} of $f.items({})) {{",
+                    js_name(cursor),
+                    self.expr(iterable)
+                );
+                self.nested(&open, block)?;
+                self.line("}");
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.nested("try {", block)?;
+                self.line("} catch ($e) {");
+                self.indent += 1;
+                self.line(&format!("const {} = $f.caught($e);", js_name(binding)));
+                let compiled = self.block(handler);
+                self.indent -= 1;
+                compiled?;
+                self.line("}");
+            }
+            StmtKind::Assert(condition) => {
+                let source = serde_json::to_string(&condition.to_string()).unwrap_or_default();
+                let code = format!("$f.assert({}, {});", self.expr(condition), source);
+                self.line(&code);
+            }
+            StmtKind::Import(name) => {
+                return Err(format!(
+                    "{}: import \"{}\" has to be inlined before compiling",
+                    stmt.span, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(literal) => self.literal(literal),
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                js_name(name)
+            }
+            ExprKind::Call(func, args) => {
+                let args = args.iter().map(|arg| self.expr(arg)).collect::<Vec<_>>();
+                format!("{}({})", self.operand(func), args.join(", "))
+            }
+            ExprKind::Method(receiver, Identifier(name), args, true) => {
+                // `$` names can't clash with filipe ones
+                let args = std::iter::once("$v".to_string())
+                    .chain(args.iter().map(|arg| self.expr(arg)))
+                    .collect::<Vec<_>>();
+                format!(
+                    "$f.maybe({}, ($v) => {}({}))",
+                    self.expr(receiver),
+                    js_name(name),
+                    args.join(", ")
+                )
+            }
+            ExprKind::Method(receiver, Identifier(name), args, false) => {
+                let args = std::iter::once(receiver.as_ref())
+                    .chain(args)
+                    .map(|arg| self.expr(arg))
+                    .collect::<Vec<_>>();
+                format!("{}({})", js_name(name), args.join(", "))
+            }
+            ExprKind::Infix(lhs, infix, rhs) => {
+                // types are compared to their names, see `Runtime::apply_infix`
+                let is_type = [lhs, rhs].iter().any(|operand| {
+                    self.expr_types.get(&operand.span.offset) == Some(&Type::TypeAnnot)
+                });
+                let (lhs, rhs) = (self.operand(lhs), self.operand(rhs));
+                let type_ = self.expr_types.get(&expr.span.offset);
+                match (infix, type_) {
+                    (Infix::Equal, _) if is_type => format!("String({lhs}) === String({rhs})"),
+                    (Infix::NotEqual, _) if is_type => format!("String({lhs}) !== String({rhs})"),
+                    (Infix::Plus, Some(Type::Int)) => format!("$f.iadd({lhs}, {rhs})"),
+                    (Infix::Minus, Some(Type::Int)) => format!("$f.isub({lhs}, {rhs})"),
+                    (Infix::Multiply, Some(Type::Int)) => format!("$f.imul({lhs}, {rhs})"),
+                    (Infix::Devide, Some(Type::Int)) => format!("$f.idiv({lhs}, {rhs})"),
+                    (Infix::Remainder, Some(Type::Int)) => format!("$f.irem({lhs}, {rhs})"),
+                    (Infix::Devide, None) => format!("$f.div({lhs}, {rhs})"),
+                    (Infix::Remainder, None) => format!("$f.rem({lhs}, {rhs})"),
+                    (Infix::Equal, _) => format!("{lhs} === {rhs}"),
+                    (Infix::NotEqual, _) => format!("{lhs} !== {rhs}"),
+                    (Infix::In, _) => format!("$f.contains({rhs}, {lhs})"),
+                    _ => format!("{lhs} {infix} {rhs}"),
+                }
+            }
+            ExprKind::Prefix(Prefix::Not, operand) => match self.is_boolean(operand) {
+                true => format!("!{}", self.operand(operand)),
+                false => format!("$f.not({})", self.expr(operand)),
+            },
+            ExprKind::Prefix(prefix, operand) => format!("{}{}", prefix, self.operand(operand)),
+            ExprKind::Postfix(operand, Postfix::Increment) => {
+                format!("{} + 1", self.operand(operand))
+            }
+            ExprKind::Postfix(operand, Postfix::Decrement) => {
+                format!("{} - 1", self.operand(operand))
+            }
+            ExprKind::Assign(Identifier(name), value) => {
+                format!("{} = {}", js_name(name), self.expr(value))
+            }
+            ExprKind::Index(array, index) => {
+                format!("$f.index({}, {})", self.expr(array), self.expr(index))
+            }
+            ExprKind::IndexAssign(Identifier(name), index, value) => {
+                let name = js_name(name);
+                let (index, value) = (self.expr(index), self.expr(value));
+                format!("{name} = $f.setItem({name}, {index}, {value})")
+            }
+            ExprKind::Await(operand) => {
+                self.awaited.set(true);
+                format!("await {}", self.operand(operand))
+            }
+            ExprKind::Cast(operand, type_) => {
+                format!("$f.cast({}, \"{}\")", self.expr(operand), type_)
+            }
+        }
+    }
+
+    /// `expr` where it is part of a bigger expression.
+    fn operand(&self, expr: &Expr) -> String {
+        match expr.kind {
+            ExprKind::Infix(..)
+            | ExprKind::Prefix(..)
+            | ExprKind::Postfix(..)
+            | ExprKind::Await(..)
+            | ExprKind::Assign(..)
+            | ExprKind::IndexAssign(..) => format!("({})", self.expr(expr)),
+            _ => self.expr(expr),
+        }
+    }
+
+    fn condition(&self, expr: &Expr) -> String {
+        match self.is_boolean(expr) {
+            true => self.expr(expr),
+            false => format!("$f.truthy({})", self.expr(expr)),
+        }
+    }
+
+    fn is_boolean(&self, expr: &Expr) -> bool {
+        self.expr_types.get(&expr.span.offset) == Some(&Type::Boolean)
+    }
+
+    fn literal(&self, literal: &Literal) -> String {
+        match literal {
+            Literal::Null => "null".to_string(),
+            Literal::Int(val) => val.to_string(),
+            Literal::Float(val) => format!("{:?}", val),
+            Literal::String(val) => serde_json::to_string(val).unwrap_or_default(),
+            Literal::Boolean(val) => val.to_string(),
+            Literal::Array(items) => {
+                let items = items.iter().map(|item| self.expr(item)).collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+fn js_name(name: &str) -> String {
+    match RESERVED.contains(&name) {
+        true => format!("{name}_"),
+        false => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::checker::analyze;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    fn compile_source(source: &str) -> String {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+        let analysis = analyze(&program);
+        let js = compile(&program, &analysis.expr_types).unwrap();
+        js[RUNTIME.len()..].to_string()
+    }
+
+    #[test]
+    fn test_compile_to_js() {
+        let js = compile_source(
+            "define half(n: int): int {\n  return n / 2\n}\n\
+             let xs = [1.5, 2.0]\n\
+             xs[0] = xs[1] / 2.0\n\
+             let odd = half(3) == 1\n\
+             if !odd {\n  print(typeof(xs), 2 * 3 + 1)\n}",
+        );
+        assert_eq!(
+            js,
+            "const { print, exit, len, random, typeof_, range, iter, next, map, filter, take, \
+             collect, set, union, intersection, difference, error, raise, errorKind, errorMessage, \
+             breakpoint } = $f;\n\
+             \n\
+             $f.run(() => {\n\
+             \x20 function half(n) {\n\
+             \x20   return $f.idiv(n, 2);\n\
+             \x20 }\n\
+             \x20 let xs = [1.5, 2.0];\n\
+             \x20 xs = $f.setItem(xs, 0, $f.index(xs, 1) / 2.0);\n\
+             \x20 let odd = half(3) === 1;\n\
+             \x20 if (!odd) {\n\
+             \x20   print(typeof_(xs), $f.iadd(($f.imul(2, 3)), 1));\n\
+             \x20 }\n\
+             });\n"
+        );
+    }
+
+    #[test]
+    fn test_int_arithmetic_fails_past_what_numbers_hold() {
+        let js = compile_source("let big = 9223372036854775807 + 1\nlet half = 0.5 + 1.0");
+        assert!(js.contains("let big = $f.iadd(9223372036854775807, 1);"));
+        assert!(js.contains("let half = 0.5 + 1.0;"));
+    }
+}
@@ -0,0 +1,41 @@
+//! `filipe build`: compiling checked programs to other languages, to run
+//! them where filipe isn't installed.
+
+pub mod c;
+pub mod js;
+
+use crate::frontend::ast::Program;
+use crate::frontend::checker::Analysis;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Js,
+    C,
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "js" => Some(Self::Js),
+            "c" => Some(Self::C),
+            _ => None,
+        }
+    }
+
+    /// Extension of the files it compiles to.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Js => "js",
+            Self::C => "c",
+        }
+    }
+}
+
+/// `program` in the language of `target`, using what the checker found
+/// out about it.
+pub fn compile(program: &Program, analysis: &Analysis, target: Target) -> Result<String, String> {
+    match target {
+        Target::Js => js::compile(program, &analysis.expr_types),
+        Target::C => c::compile(program, analysis),
+    }
+}
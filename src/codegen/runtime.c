@@ -0,0 +1,369 @@
+/* The runtime of filipe programs compiled to C: reference counted strings
+ * and arrays, int arithmetic that stops on overflow like filipe does, and
+ * the builtins. Values passed to these functions are consumed: they hold a
+ * reference the function drops when it is done with the value. */
+#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+typedef struct fl_string {
+  int64_t refs;
+  int64_t len;
+  char data[];
+} *fl_str;
+
+typedef enum { FL_INT, FL_FLOAT, FL_BOOL, FL_STR, FL_ARRAY } fl_kind;
+
+typedef struct fl_array *fl_array;
+
+typedef union {
+  int64_t i;
+  double f;
+  bool b;
+  fl_str s;
+  fl_array a;
+} fl_value;
+
+struct fl_array {
+  int64_t refs;
+  int64_t len;
+  fl_kind kind;
+  fl_value items[];
+};
+
+/* Reports an error like `filipe run` and stops the program. */
+static inline void fl_fail(const char *kind, const char *format, ...) {
+  fflush(stdout);
+  fprintf(stderr, "[%s]: ", kind);
+  va_list args;
+  va_start(args, format);
+  vfprintf(stderr, format, args);
+  va_end(args);
+  fputc('\n', stderr);
+  exit(1);
+}
+
+static inline void *fl_alloc(size_t size) {
+  void *memory = malloc(size);
+  if (memory == NULL) fl_fail("MemoryLimitExceeded", "out of memory");
+  return memory;
+}
+
+static inline fl_str fl_str_new(const char *data, int64_t len) {
+  fl_str s = fl_alloc(sizeof(struct fl_string) + len + 1);
+  s->refs = 1;
+  s->len = len;
+  memcpy(s->data, data, len);
+  s->data[len] = '\0';
+  return s;
+}
+
+static inline fl_str fl_str_ref(fl_str s) {
+  s->refs++;
+  return s;
+}
+
+static inline void fl_str_drop(fl_str s) {
+  if (s != NULL && --s->refs == 0) free(s);
+}
+
+static inline fl_array fl_array_ref(fl_array xs) {
+  xs->refs++;
+  return xs;
+}
+
+static inline void fl_value_drop(fl_kind kind, fl_value value);
+
+static inline void fl_array_drop(fl_array xs) {
+  if (xs == NULL || --xs->refs > 0) return;
+  for (int64_t i = 0; i < xs->len; i++) fl_value_drop(xs->kind, xs->items[i]);
+  free(xs);
+}
+
+static inline fl_value fl_value_ref(fl_kind kind, fl_value value) {
+  if (kind == FL_STR) fl_str_ref(value.s);
+  if (kind == FL_ARRAY) fl_array_ref(value.a);
+  return value;
+}
+
+static inline void fl_value_drop(fl_kind kind, fl_value value) {
+  if (kind == FL_STR) fl_str_drop(value.s);
+  if (kind == FL_ARRAY) fl_array_drop(value.a);
+}
+
+/* Another reference to a string or an array. */
+#define fl_ref(x) _Generic((x), fl_str: fl_str_ref, fl_array: fl_array_ref)(x)
+/* Drops a reference, freeing the value once it was the last. */
+#define fl_drop(x) _Generic((x), fl_str: fl_str_drop, fl_array: fl_array_drop)(x)
+/* Assigns a string or an array to a variable, dropping its old value. */
+#define fl_set(variable, value)                                                \
+  do {                                                                         \
+    __typeof__(*(variable)) fl_old = *(variable);                              \
+    *(variable) = (value);                                                     \
+    fl_drop(fl_old);                                                           \
+  } while (0)
+
+static inline fl_str fl_concat(fl_str a, fl_str b) {
+  fl_str s = fl_alloc(sizeof(struct fl_string) + a->len + b->len + 1);
+  s->refs = 1;
+  s->len = a->len + b->len;
+  memcpy(s->data, a->data, a->len);
+  memcpy(s->data + a->len, b->data, b->len);
+  s->data[s->len] = '\0';
+  fl_str_drop(a);
+  fl_str_drop(b);
+  return s;
+}
+
+static inline bool fl_str_eq(fl_str a, fl_str b) {
+  bool equal = a->len == b->len && memcmp(a->data, b->data, a->len) == 0;
+  fl_str_drop(a);
+  fl_str_drop(b);
+  return equal;
+}
+
+/* An array of `len` items of `kind`, taking over the references of `items`. */
+static inline fl_array fl_array_of(fl_kind kind, int64_t len, const fl_value *items) {
+  fl_array xs = fl_alloc(sizeof(struct fl_array) + len * sizeof(fl_value));
+  xs->refs = 1;
+  xs->len = len;
+  xs->kind = kind;
+  if (len > 0) memcpy(xs->items, items, len * sizeof(fl_value));
+  return xs;
+}
+
+static inline void fl_check_index(fl_array xs, int64_t i) {
+  if (i < 0 || i >= xs->len) {
+    fl_fail("ValueError", "index %" PRId64 " out of range for array of length %" PRId64, i,
+            xs->len);
+  }
+}
+
+static inline int64_t fl_range_step(int64_t step) {
+  if (step == 0) fl_fail("ValueError", "range can't step by 0");
+  return step;
+}
+
+static inline int fl_exit_code(int64_t code) {
+  if (code < 0 || code > 255) fl_fail("ValueError", "exit codes go from 0 to 255, not %lld", (long long)code);
+  return (int)code;
+}
+
+static inline fl_value fl_get(fl_array xs, int64_t i) {
+  fl_check_index(xs, i);
+  fl_value item = fl_value_ref(xs->kind, xs->items[i]);
+  fl_array_drop(xs);
+  return item;
+}
+
+/* `xs[i] = value`. Arrays are values, so a shared array is copied first. */
+static inline void fl_array_set(fl_array *xs, int64_t i, fl_value value) {
+  fl_check_index(*xs, i);
+  if ((*xs)->refs > 1) {
+    fl_array copy = fl_array_of((*xs)->kind, (*xs)->len, (*xs)->items);
+    for (int64_t j = 0; j < copy->len; j++) fl_value_ref(copy->kind, copy->items[j]);
+    (*xs)->refs--;
+    *xs = copy;
+  }
+  fl_value_drop((*xs)->kind, (*xs)->items[i]);
+  (*xs)->items[i] = value;
+}
+
+static inline int64_t fl_str_len(fl_str s) {
+  int64_t len = s->len;
+  fl_str_drop(s);
+  return len;
+}
+
+static inline int64_t fl_array_len(fl_array xs) {
+  int64_t len = xs->len;
+  fl_array_drop(xs);
+  return len;
+}
+
+#define fl_len(x) _Generic((x), fl_str: fl_str_len, fl_array: fl_array_len)(x)
+
+static inline int64_t fl_add(int64_t a, int64_t b) {
+  int64_t result;
+  if (__builtin_add_overflow(a, b, &result)) {
+    fl_fail("OverflowError", "'%" PRId64 " + %" PRId64 "' overflows type int", a, b);
+  }
+  return result;
+}
+
+static inline int64_t fl_sub(int64_t a, int64_t b) {
+  int64_t result;
+  if (__builtin_sub_overflow(a, b, &result)) {
+    fl_fail("OverflowError", "'%" PRId64 " - %" PRId64 "' overflows type int", a, b);
+  }
+  return result;
+}
+
+static inline int64_t fl_mul(int64_t a, int64_t b) {
+  int64_t result;
+  if (__builtin_mul_overflow(a, b, &result)) {
+    fl_fail("OverflowError", "'%" PRId64 " * %" PRId64 "' overflows type int", a, b);
+  }
+  return result;
+}
+
+static inline int64_t fl_div(int64_t a, int64_t b) {
+  if (b == 0) fl_fail("DivisionByZero", "'%" PRId64 " / %" PRId64 "' divides by zero", a, b);
+  if (a == INT64_MIN && b == -1) {
+    fl_fail("OverflowError", "'%" PRId64 " / %" PRId64 "' overflows type int", a, b);
+  }
+  return a / b;
+}
+
+static inline int64_t fl_rem(int64_t a, int64_t b) {
+  if (b == 0) fl_fail("DivisionByZero", "'%" PRId64 " %% %" PRId64 "' divides by zero", a, b);
+  if (a == INT64_MIN && b == -1) {
+    fl_fail("OverflowError", "'%" PRId64 " %% %" PRId64 "' overflows type int", a, b);
+  }
+  return a % b;
+}
+
+/* Writes a float the way filipe prints it: the shortest digits that read
+ * back as the same float, without an exponent. */
+static inline void fl_write_float(double x) {
+  if (isnan(x)) {
+    fputs("NaN", stdout);
+    return;
+  }
+  if (isinf(x)) {
+    fputs(x > 0 ? "inf" : "-inf", stdout);
+    return;
+  }
+  char scientific[32];
+  for (int precision = 0; precision < 17; precision++) {
+    snprintf(scientific, sizeof scientific, "%.*e", precision, x);
+    if (strtod(scientific, NULL) == x) break;
+  }
+
+  char digits[32];
+  int len = 0;
+  char *c = scientific;
+  if (*c == '-') {
+    putchar('-');
+    c++;
+  }
+  for (; *c != 'e'; c++) {
+    if (*c != '.') digits[len++] = *c;
+  }
+  int exponent = atoi(c + 1);
+  if (exponent < 0) {
+    fputs("0.", stdout);
+    for (int i = -1; i > exponent; i--) putchar('0');
+    fwrite(digits, 1, len, stdout);
+  } else if (exponent + 1 >= len) {
+    fwrite(digits, 1, len, stdout);
+    for (int i = len; i <= exponent; i++) putchar('0');
+  } else {
+    fwrite(digits, 1, exponent + 1, stdout);
+    putchar('.');
+    fwrite(digits + exponent + 1, 1, len - exponent - 1, stdout);
+  }
+}
+
+/* Writes a value like `print`, quoting strings inside arrays. */
+static inline void fl_write(fl_kind kind, fl_value value, bool nested) {
+  switch (kind) {
+  case FL_INT:
+    printf("%" PRId64, value.i);
+    break;
+  case FL_FLOAT:
+    fl_write_float(value.f);
+    break;
+  case FL_BOOL:
+    fputs(value.b ? "true" : "false", stdout);
+    break;
+  case FL_STR:
+    if (nested) putchar('\'');
+    fwrite(value.s->data, 1, value.s->len, stdout);
+    if (nested) putchar('\'');
+    break;
+  case FL_ARRAY:
+    putchar('[');
+    for (int64_t i = 0; i < value.a->len; i++) {
+      if (i > 0) fputs(", ", stdout);
+      fl_write(value.a->kind, value.a->items[i], true);
+    }
+    putchar(']');
+    break;
+  }
+}
+
+/* `print`: `count` pairs of a kind and a value of that kind. */
+static inline void fl_print(int count, ...) {
+  va_list args;
+  va_start(args, count);
+  for (int i = 0; i < count; i++) {
+    fl_kind kind = (fl_kind)va_arg(args, int);
+    fl_value value;
+    switch (kind) {
+    case FL_INT:
+      value.i = va_arg(args, int64_t);
+      break;
+    case FL_FLOAT:
+      value.f = va_arg(args, double);
+      break;
+    case FL_BOOL:
+      value.b = va_arg(args, int);
+      break;
+    case FL_STR:
+      value.s = va_arg(args, fl_str);
+      break;
+    case FL_ARRAY:
+      value.a = va_arg(args, fl_array);
+      break;
+    }
+    fl_write(kind, value, false);
+    fl_value_drop(kind, value);
+  }
+  va_end(args);
+  putchar('\n');
+}
+
+static uint64_t fl_seed;
+
+static inline uint64_t fl_next_random(void) {
+  if (fl_seed == 0) fl_seed = (uint64_t)time(NULL) * 0x9E3779B97F4A7C15u | 1;
+  fl_seed ^= fl_seed << 13;
+  fl_seed ^= fl_seed >> 7;
+  fl_seed ^= fl_seed << 17;
+  return fl_seed;
+}
+
+/* `random()`: a float from 0 up to, not including, 1. */
+static inline double fl_random(void) {
+  return (fl_next_random() >> 11) * (1.0 / 9007199254740992.0);
+}
+
+/* `random(max)` */
+static inline int64_t fl_random_upto(int64_t max) {
+  if (max < 0) fl_fail("ValueError", "Argument for 'random' must be a non-negative integer");
+  return (int64_t)(fl_next_random() % ((uint64_t)max + 1));
+}
+
+/* `random(min, max)` */
+static inline int64_t fl_random_between(int64_t min, int64_t max) {
+  if (min < 0 || max < 0) {
+    fl_fail("ValueError", "Arguments for 'random' must be non-negative integers");
+  }
+  if (min > max) {
+    fl_fail("ValueError",
+            "The first argument for 'random' must be less than or equal to the second argument");
+  }
+  return min + (int64_t)(fl_next_random() % ((uint64_t)(max - min) + 1));
+}
+
+static inline void fl_assert(bool holds, const char *condition) {
+  if (!holds) fl_fail("AssertionError", "assertion failed: %s", condition);
+}
+
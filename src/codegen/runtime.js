@@ -0,0 +1,354 @@
+// The runtime of filipe programs compiled to JavaScript: the builtins, and
+// the helpers for what plain JavaScript does differently, like integer
+// division, bounds-checked indexing and copying arrays on write.
+"use strict";
+const $f = (() => {
+  class FilipeError extends Error {
+    constructor(kind, message) {
+      super(message);
+      this.kind = kind;
+    }
+    toString() {
+      return `${this.kind}: ${this.message}`;
+    }
+  }
+
+  /** Thrown by `exit` outside of node, to stop the program. */
+  class Exit {
+    constructor(code) {
+      this.code = code;
+    }
+  }
+
+  class Range {
+    constructor(start, end, step) {
+      this.start = start;
+      this.end = end;
+      this.step = step;
+    }
+    *[Symbol.iterator]() {
+      const step = this.step;
+      for (let i = this.start; step > 0 ? i < this.end : i > this.end; i += step) {
+        yield i;
+      }
+    }
+    toString() {
+      return `range(${this.start}, ${this.end}, ${this.step})`;
+    }
+  }
+
+  class Iterator {
+    constructor(items) {
+      this.items = items[Symbol.iterator]();
+    }
+    [Symbol.iterator]() {
+      return this.items;
+    }
+  }
+
+  class Type {
+    constructor(name) {
+      this.name = name;
+    }
+    toString() {
+      return this.name;
+    }
+  }
+
+  const node = typeof process !== "undefined" && process.stdout !== undefined;
+  const write = node
+    ? (text) => process.stdout.write(text)
+    : (text) => console.log(text.slice(0, -1));
+  const writeError = node
+    ? (text) => process.stderr.write(text)
+    : (text) => console.error(text.slice(0, -1));
+
+  const fail = (kind, message) => {
+    throw new FilipeError(kind, message);
+  };
+
+  /**
+   * Fails like an int overflowing in filipe once `result` is past what a
+   * JavaScript number holds exactly.
+   */
+  const checkInt = (result, a, op, b) => {
+    if (!Number.isSafeInteger(result)) fail("OverflowError", `'${a} ${op} ${b}' overflows type int`);
+    return result;
+  };
+
+  /** How `print` shows a value, quoting strings inside arrays. */
+  const show = (value, nested) => {
+    if (value === null || value === undefined) return "null";
+    if (typeof value === "string") return nested ? `'${value}'` : value;
+    if (Array.isArray(value)) return `[${value.map((item) => show(item, true)).join(", ")}]`;
+    if (value instanceof FilipeError) return nested ? `[Error ${value}]` : `${value}`;
+    if (value instanceof Set) return `Set{${[...value].map((item) => show(item, true)).join(", ")}}`;
+    if (value instanceof Iterator) return "[Iterator]";
+    if (typeof value === "function") {
+      return builtins.has(value) ? "[Builtin Function]" : "[User Defined Function]";
+    }
+    return String(value);
+  };
+
+  const typeName = (value) => {
+    if (value === null || value === undefined) return "null";
+    if (typeof value === "number") return Number.isInteger(value) ? "int" : "float";
+    if (typeof value === "string") return "string";
+    if (typeof value === "boolean") return "boolean";
+    if (typeof value === "function") return "function";
+    if (value instanceof Range) return "range";
+    if (value instanceof Iterator) return "iterator";
+    if (value instanceof Set) return "Set";
+    if (value instanceof FilipeError) return "error";
+    if (value instanceof Type) return "[Type Annotation]";
+    if (value.length === 0) return "Array<any>";
+    return `Array<${typeName(value[0])}>`;
+  };
+
+  const checkIndex = (xs, i) => {
+    if (!Array.isArray(xs)) fail("TypeError", `can't index into a value of type '${typeName(xs)}'`);
+    if (!Number.isInteger(i)) {
+      fail("TypeError", `array index must be of type 'int' but provided '${typeName(i)}'`);
+    }
+    if (i < 0 || i >= xs.length) {
+      fail("ValueError", `index ${i} out of range for array of length ${xs.length}`);
+    }
+  };
+
+  const items = (value) => {
+    if (Array.isArray(value) || typeof value === "string") return value;
+    if (value instanceof Range || value instanceof Iterator || value instanceof Set) return value;
+    fail("TypeError", `type '${typeName(value)}' is not iterable`);
+  };
+
+  /** The two sets `union`, `intersection` and `difference` take. */
+  const checkSets = (name, a, b) => {
+    [a, b].forEach((value, i) => {
+      if (!(value instanceof Set)) {
+        fail(
+          "ArgumentError",
+          `argument ${i + 1} of '${name}' must be of type 'Set' but provided '${typeName(value)}'`,
+        );
+      }
+    });
+  };
+
+  const utf8 = new TextEncoder();
+
+  const builtins = new Set([
+    function print(...values) {
+      write(values.map((value) => show(value, false)).join("") + "\n");
+      return null;
+    },
+    function exit(code = 0) {
+      if (code < 0 || code > 255) fail("ValueError", `exit codes go from 0 to 255, not ${code}`);
+      if (node) process.exit(code);
+      throw new Exit(code);
+    },
+    function len(value) {
+      if (typeof value === "string") return utf8.encode(value).length;
+      if (Array.isArray(value)) return value.length;
+      if (value instanceof Set) return value.size;
+      fail("TypeError", "'len' expects a string, an array or a set");
+    },
+    function random(min, max) {
+      if (min === undefined) return Math.random();
+      if (max === undefined) [min, max] = [0, min];
+      if (min < 0 || max < 0) fail("ValueError", "'random' expects positive arguments");
+      if (min > max) fail("ValueError", "'random' expects min to be at most max");
+      return min + Math.floor(Math.random() * (max - min + 1));
+    },
+    function typeof_(value) {
+      return new Type(typeName(value));
+    },
+    function range(start, end, step = 1) {
+      if (step === 0) fail("ValueError", "range can't step by 0");
+      return new Range(start, end, step);
+    },
+    function iter(value) {
+      return value instanceof Iterator ? value : new Iterator(items(value));
+    },
+    function next(iterator) {
+      if (!(iterator instanceof Iterator)) fail("TypeError", "'next' expects an iterator");
+      const item = iterator.items.next();
+      return item.done ? null : item.value;
+    },
+    function map(value, f) {
+      const source = items(value);
+      return new Iterator((function* () {
+        for (const item of source) yield f(item);
+      })());
+    },
+    function filter(value, f) {
+      const source = items(value);
+      return new Iterator((function* () {
+        for (const item of source) if ($f.truthy(f(item))) yield item;
+      })());
+    },
+    function take(value, n) {
+      const source = items(value)[Symbol.iterator]();
+      return new Iterator((function* () {
+        for (let i = 0; i < n; i++) {
+          const item = source.next();
+          if (item.done) return;
+          yield item.value;
+        }
+      })());
+    },
+    function collect(value) {
+      return [...items(value)];
+    },
+    function set(value) {
+      const set = new Set(value === undefined ? [] : items(value));
+      for (const item of set) {
+        if (typeof item === "object" && item !== null) {
+          fail("TypeError", `type '${typeName(item)}' can't be in a set`);
+        }
+      }
+      return set;
+    },
+    function union(a, b) {
+      checkSets("union", a, b);
+      return new Set([...a, ...b]);
+    },
+    function intersection(a, b) {
+      checkSets("intersection", a, b);
+      return new Set([...a].filter((item) => b.has(item)));
+    },
+    function difference(a, b) {
+      checkSets("difference", a, b);
+      return new Set([...a].filter((item) => !b.has(item)));
+    },
+    function error(kind, message) {
+      return new FilipeError(kind, message);
+    },
+    function raise(error) {
+      throw error;
+    },
+    function errorKind(error) {
+      return error.kind;
+    },
+    function errorMessage(error) {
+      return error.message;
+    },
+    function breakpoint() {
+      return null;
+    },
+  ]);
+
+  return {
+    ...Object.fromEntries([...builtins].map((builtin) => [builtin.name, builtin])),
+
+    /** `!value`: true only for `false` and `null`. */
+    not: (value) => value === false || value === null || value === undefined,
+    /** Whether a condition holds: anything but `false`, `null` and zero. */
+    truthy: (value) => value !== false && value !== null && value !== undefined && value !== 0,
+
+    /** `value as type`, for the types the parser allows there. */
+    cast: (value, type) => {
+      if (type === "boolean") return $f.truthy(value);
+      const found = typeName(value);
+      if (!["int", "float", "string"].includes(found)) {
+        fail("TypeError", `can't convert '${found}' to '${type}'`);
+      }
+      if (type === "string") return String(value);
+      let number = value;
+      if (found === "string") {
+        const int = /^\s*[+-]?\d+\s*$/.test(value);
+        number = value.trim() === "" || (type === "int" && !int) ? NaN : Number(value);
+      }
+      const fits =
+        type === "float"
+          ? found !== "string" || !Number.isNaN(number)
+          : Number.isFinite(number) && Math.abs(number) < 2 ** 63;
+      if (!fits) {
+        const shown = found === "string" ? `'${value}'` : value;
+        fail("ValueError", `${shown} can't be converted to '${type}'`);
+      }
+      return type === "int" ? Math.trunc(number) : number;
+    },
+
+    /** `a + b` on ints. */
+    iadd: (a, b) => checkInt(a + b, a, "+", b),
+    /** `a - b` on ints. */
+    isub: (a, b) => checkInt(a - b, a, "-", b),
+    /** `a * b` on ints. */
+    imul: (a, b) => checkInt(a * b, a, "*", b),
+    /** `a / b` on ints. */
+    idiv: (a, b) => {
+      if (b === 0) fail("DivisionByZero", `'${a} / ${b}' divides by zero`);
+      return Math.trunc(a / b);
+    },
+    /** `a % b` on ints. */
+    irem: (a, b) => {
+      if (b === 0) fail("DivisionByZero", `'${a} % ${b}' divides by zero`);
+      return a % b;
+    },
+    /** `a / b` on numbers whose type wasn't known when compiling. */
+    div: (a, b) => (Number.isInteger(a) && Number.isInteger(b) ? $f.idiv(a, b) : a / b),
+    /** `a % b` on numbers whose type wasn't known when compiling. */
+    rem: (a, b) => (Number.isInteger(a) && Number.isInteger(b) ? $f.irem(a, b) : a % b),
+
+    index: (xs, i) => {
+      checkIndex(xs, i);
+      return xs[i];
+    },
+    /** `xs[i] = value`: arrays are values, so it makes a changed copy. */
+    setItem: (xs, i, value) => {
+      checkIndex(xs, i);
+      const copy = xs.slice();
+      copy[i] = value;
+      return copy;
+    },
+    items,
+    /** `value?.f(args)`: `call(value)`, unless the value is `null`. */
+    maybe: (value, call) => (value === null || value === undefined ? null : call(value)),
+    /** `item in collection`. */
+    contains: (collection, item) => {
+      if (collection instanceof Set) return collection.has(item);
+      if (Array.isArray(collection)) return collection.includes(item);
+      if (typeof collection === "string") {
+        if (typeof item !== "string") {
+          fail("TypeError", `'in' operation not allowed between types ${typeName(item)} and string`);
+        }
+        return collection.includes(item);
+      }
+      if (collection instanceof Range) {
+        const { start, end } = collection;
+        const step = collection.step === 0 ? 1 : collection.step;
+        if (!Number.isInteger(item)) return false;
+        const inside = step > 0 ? start <= item && item < end : end < item && item <= start;
+        return inside && (item - start) % step === 0;
+      }
+      fail("TypeError", `'in' needs an array, set, string or range on its right, found '${typeName(collection)}'`);
+    },
+
+    assert: (holds, condition) => {
+      if (!$f.truthy(holds)) fail("AssertionError", `assertion failed: ${condition}`);
+    },
+    /** The value a `catch` binds, for errors thrown by JavaScript too. */
+    caught: (error) => {
+      if (error instanceof Exit) throw error;
+      if (error instanceof FilipeError) return error;
+      if (error instanceof RangeError) return new FilipeError("ExecutionLimitExceeded", error.message);
+      return new FilipeError("Error", String(error && error.message));
+    },
+
+    /** Runs the program, reporting the error that stops it like `filipe run`. */
+    run: (main) => {
+      const report = (error) => {
+        if (error instanceof Exit) return;
+        const { kind, message } = $f.caught(error);
+        writeError(`[${kind}]: ${message}\n`);
+        if (node) process.exitCode = 1;
+      };
+      try {
+        // a promise when the program awaits
+        const done = main();
+        if (done instanceof Promise) done.catch(report);
+      } catch (error) {
+        report(error);
+      }
+    },
+  };
+})();
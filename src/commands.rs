@@ -1,28 +1,1025 @@
-use std::cell::RefCell;
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{self, stderr, LineWriter, Read};
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::rc::Rc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
+use crate::ast_json;
+use crate::bundle;
+use crate::codegen::{self, Target};
+use crate::debugger::{breakpoint_line, TerminalDebugger};
+use crate::diagnostics::{stderr_supports_color, Diagnostic, ErrorFormat, Renderer};
+use crate::explain::{explain, EXPLANATIONS};
+use crate::flbc;
+use crate::frontend::ast::Program;
 use crate::frontend::lexer::Lexer;
-use crate::frontend::parser::Parser;
+use crate::frontend::lint::{self, LintConfig, RuleLevel};
+use crate::frontend::parser::{Parser, ParserError, ParserErrorKind};
+use crate::frontend::token::Token;
+use crate::frontend::{checker, docgen, formatter, minifier, warnings};
+use crate::messages::{msg, set_lang, Lang};
+use crate::project::{self, Manifest, Warnings};
+use crate::repl::repl_in;
+use crate::runtime::coverage::{self, FileCoverage, ReportFormat};
+use crate::runtime::debugger::Debugger;
+use crate::runtime::interrupt::InterruptHandle;
+use crate::runtime::permissions::RuntimePermissions;
+use crate::runtime::runtime_error::{ErrorKind, RuntimeError};
 use crate::runtime::{context::Context, flstdlib::builtins, Runtime};
-use crate::utils::read_file;
+use crate::shared::{Lock, Shared};
+use crate::utils::{read_file, read_file_bytes, script_path, write_file};
 
-pub fn run_from_file(path: &str) {
-    let input = match read_file(path) {
+pub const STDIN_PATH: &str = "-";
+/// Name diagnostics give to code passed with `-e`.
+const EVAL_PATH: &str = "<eval>";
+/// What shells report for a program stopped by Ctrl-C.
+const SIGINT_EXIT_CODE: i32 = 130;
+
+/// What `run` does with the warnings found before running a script.
+#[derive(Default, PartialEq)]
+pub enum WarningLevel {
+    #[default]
+    Print,
+    Off,
+    /// Print them and refuse to run.
+    Error,
+}
+
+/// How `--dump-ast` prints the syntax tree.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AstFormat {
+    /// Rust's pretty debug layout.
+    Pretty,
+    Json,
+}
+
+#[derive(Default)]
+pub struct RunOptions {
+    pub max_steps: Option<u64>,
+    pub max_memory: Option<usize>,
+    pub max_call_depth: Option<usize>,
+    pub profile: bool,
+    pub wrapping: bool,
+    pub seed: Option<u64>,
+    pub no_color: bool,
+    pub warnings: WarningLevel,
+    /// Also warn when a binding hides one of an enclosing scope.
+    pub warn_shadowing: bool,
+    pub strict: bool,
+    pub error_format: ErrorFormat,
+    /// Print the tokens instead of running.
+    pub dump_tokens: bool,
+    /// Print the parsed program instead of running.
+    pub dump_ast: Option<AstFormat>,
+    /// Pause with a debugger prompt, see `debugger`.
+    pub debug: bool,
+    /// `[file:]line` locations given with `--break`.
+    pub breakpoints: Vec<String>,
+    /// Write a report of the lines that ran.
+    pub coverage: Option<ReportFormat>,
+    /// Start the REPL with the script's globals once it has run, see `-i`.
+    pub interactive: bool,
+    /// Log every statement run, to stderr or to the file given with
+    /// `--trace=<file>`.
+    pub trace: Option<Option<String>>,
+    /// Report how long each phase of the run took.
+    pub time: bool,
+    /// From the project's `[permissions]`, see `project`.
+    pub permissions: RuntimePermissions,
+    /// From the `paths` of the project's `[run]`.
+    pub import_paths: Vec<PathBuf>,
+    /// Native plugins given with `--plugin`, see `runtime::plugins`.
+    pub plugins: Vec<String>,
+}
+
+/// Wall-clock time of each phase of a run, for `--time`.
+#[derive(Default)]
+struct PhaseTimes {
+    lexing: Duration,
+    parsing: Duration,
+    /// `None` when the checks before running were turned off.
+    checking: Option<Duration>,
+    evaluation: Duration,
+}
+
+impl PhaseTimes {
+    fn report(&self) -> String {
+        let mut phases = vec![("lexing", self.lexing), ("parsing", self.parsing)];
+        if let Some(checking) = self.checking {
+            phases.push(("checking", checking));
+        }
+        phases.push(("evaluation", self.evaluation));
+        let total = phases.iter().map(|(_, time)| *time).sum::<Duration>();
+        phases.push(("total", total));
+
+        let mut report = String::new();
+        for (phase, time) in phases {
+            let share = match total.is_zero() {
+                true => 0.0,
+                false => time.as_secs_f64() / total.as_secs_f64() * 100.0,
+            };
+            let _ = writeln!(report, "{:<12} {:>12.2?} {:>6.1}%", phase, time, share);
+        }
+        report
+    }
+}
+
+impl RunOptions {
+    /// The options the manifest of the project in `dir` sets.
+    pub fn from_manifest(manifest: &Manifest, dir: &Path) -> Self {
+        let warnings = match manifest.run.warnings {
+            Some(Warnings::On) | None => WarningLevel::Print,
+            Some(Warnings::Off) => WarningLevel::Off,
+            Some(Warnings::Error) => WarningLevel::Error,
+        };
+        Self {
+            strict: manifest.run.strict,
+            warnings,
+            warn_shadowing: manifest.run.warn_shadowing,
+            permissions: manifest.permissions.clone(),
+            import_paths: manifest.import_paths(dir),
+            ..Self::default()
+        }
+    }
+
+    /// These options with the flags in `args` on top.
+    pub fn with_args(self, args: &[String]) -> Result<Self, String> {
+        let mut options = self;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--max-steps" => {
+                    let value = args.next().ok_or("Missing value for '--max-steps'")?;
+                    match value.parse::<u64>() {
+                        Ok(max_steps) => options.max_steps = Some(max_steps),
+                        Err(_) => return Err(format!("Invalid value for '--max-steps': {value}")),
+                    }
+                }
+                "--max-call-depth" => {
+                    let value = args.next().ok_or("Missing value for '--max-call-depth'")?;
+                    match value.parse::<usize>() {
+                        Ok(depth) => options.max_call_depth = Some(depth),
+                        Err(_) => {
+                            return Err(format!("Invalid value for '--max-call-depth': {value}"))
+                        }
+                    }
+                }
+                "--max-memory" => {
+                    let value = args.next().ok_or("Missing value for '--max-memory'")?;
+                    match parse_size(value) {
+                        Some(max_memory) => options.max_memory = Some(max_memory),
+                        None => return Err(format!("Invalid value for '--max-memory': {value}")),
+                    }
+                }
+                "--profile" => options.profile = true,
+                "--time" => options.time = true,
+                "--wrapping" => options.wrapping = true,
+                "--no-color" => options.no_color = true,
+                "--strict" => options.strict = true,
+                "--warnings=on" => options.warnings = WarningLevel::Print,
+                "--warnings=off" => options.warnings = WarningLevel::Off,
+                "--warnings=error" => options.warnings = WarningLevel::Error,
+                "--warn-shadowing" => options.warn_shadowing = true,
+                "--error-format=human" => options.error_format = ErrorFormat::Human,
+                "--error-format=json" => options.error_format = ErrorFormat::Json,
+                "--dump-tokens" => options.dump_tokens = true,
+                "--dump-ast" => options.dump_ast = Some(AstFormat::Pretty),
+                "--dump-ast=json" => options.dump_ast = Some(AstFormat::Json),
+                "--coverage" | "--coverage=lcov" => options.coverage = Some(ReportFormat::Lcov),
+                "--coverage=html" => options.coverage = Some(ReportFormat::Html),
+                "--debug" => options.debug = true,
+                "--trace" => options.trace = Some(None),
+                _ if arg.starts_with("--trace=") => {
+                    options.trace = Some(Some(arg["--trace=".len()..].to_string()))
+                }
+                "--break" => {
+                    let value = args.next().ok_or("Missing value for '--break'")?;
+                    options.breakpoints.push(value.clone());
+                    options.debug = true;
+                }
+                "--plugin" => {
+                    let value = args.next().ok_or("Missing value for '--plugin'")?;
+                    options.plugins.push(value.clone());
+                }
+                "--deterministic" => {
+                    options.seed.get_or_insert(0);
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("Missing value for '--seed'")?;
+                    match value.parse::<u64>() {
+                        Ok(seed) => options.seed = Some(seed),
+                        Err(_) => return Err(format!("Invalid value for '--seed': {value}")),
+                    }
+                }
+                _ => return Err(format!("Unknown option {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Takes `--lang <code>` or `--lang=<code>` out of the command line and
+/// switches the language of diagnostics. It applies to every command, so it
+/// can go anywhere.
+pub fn take_lang_option(args: &mut Vec<String>) -> Result<(), String> {
+    let Some(i) = args
+        .iter()
+        .position(|arg| arg == "--lang" || arg.starts_with("--lang="))
+    else {
+        return Ok(());
+    };
+    let (code, taken) = match args[i].strip_prefix("--lang=") {
+        Some(code) => (code, 1),
+        None => (
+            args.get(i + 1)
+                .map(String::as_str)
+                .ok_or("Missing value for '--lang'")?,
+            2,
+        ),
+    };
+    let lang =
+        Lang::from_code(code).ok_or(format!("Unknown language '{code}', use 'en' or 'pt'"))?;
+    set_lang(lang);
+    args.drain(i..i + taken);
+    Ok(())
+}
+
+/// Parses a byte count with an optional `K`, `M` or `G` suffix.
+fn parse_size(value: &str) -> Option<usize> {
+    let (digits, unit) = match value.char_indices().last()? {
+        (i, 'k' | 'K') => (&value[..i], 1 << 10),
+        (i, 'm' | 'M') => (&value[..i], 1 << 20),
+        (i, 'g' | 'G') => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// The manifest of the project `path` is in, and the project's directory.
+/// Code from stdin or `-e` is in the current directory's project. A broken
+/// manifest is reported and ends the program.
+pub fn project_of(path: Option<&str>) -> Option<(Manifest, PathBuf)> {
+    let root = match path {
+        Some(path) if path != STDIN_PATH => {
+            project::root_of(Path::new(path).parent().unwrap_or(Path::new("")))
+        }
+        _ => project::root(),
+    }?;
+    match Manifest::load(&root) {
+        Ok(manifest) => Some((manifest, root)),
+        Err(err) => {
+            eprintln!("[ERROR]: {}", err);
+            exit(1);
+        }
+    }
+}
+
+/// The options of the project `path` is in, see `project_of`.
+pub fn project_run_options(path: Option<&str>) -> RunOptions {
+    match project_of(path) {
+        Some((manifest, root)) => RunOptions::from_manifest(&manifest, &root),
+        None => RunOptions::default(),
+    }
+}
+
+/// The `paths` packages are also imported from in the project `path` is in.
+fn project_import_paths(path: &str) -> Vec<PathBuf> {
+    project_of(Some(path)).map_or(vec![], |(manifest, root)| manifest.import_paths(&root))
+}
+
+/// Runs a script; `-` as the path reads the source from stdin.
+pub fn run_from_file(path: &str, options: &RunOptions) {
+    let path = &match path {
+        STDIN_PATH => path.to_string(),
+        _ => script_path(path),
+    };
+    let renderer = Renderer::new(!options.no_color && stderr_supports_color())
+        .with_format(options.error_format);
+    let display_path = if path == STDIN_PATH { "<stdin>" } else { path };
+    if options.dump_tokens {
+        let dumped =
+            read_input(path).is_some_and(|bytes| dump_tokens(&bytes, display_path, &renderer));
+        if !dumped {
+            exit(1);
+        }
+        return;
+    }
+    let mut times = options.time.then(PhaseTimes::default);
+    let (program, source) = if path == STDIN_PATH {
+        let mut l = Lexer::from_reader(io::stdin().lock());
+        (
+            parse(&mut l, None, display_path, &renderer, times.as_mut()),
+            None,
+        )
+    } else {
+        match load_program(path, &renderer, times.as_mut()) {
+            Some((program, source)) => (Some(program), source),
+            None => (None, None),
+        }
+    };
+    let program = match program {
+        Some(program) => program,
+        None => exit(1),
+    };
+    run_program(
+        program,
+        source.as_deref(),
+        display_path,
+        options,
+        &renderer,
+        times,
+    );
+}
+
+/// Runs code passed on the command line with `-e`.
+pub fn run_code(code: &str, options: &RunOptions) {
+    let renderer = Renderer::new(!options.no_color && stderr_supports_color())
+        .with_format(options.error_format);
+    if options.dump_tokens {
+        if !dump_tokens(code.as_bytes(), EVAL_PATH, &renderer) {
+            exit(1);
+        }
+        return;
+    }
+    let mut times = options.time.then(PhaseTimes::default);
+    let Some(program) = parse_source(code, EVAL_PATH, &renderer, times.as_mut()) else {
+        exit(1);
+    };
+    run_program(program, Some(code), EVAL_PATH, options, &renderer, times);
+}
+
+fn run_program(
+    program: Program,
+    source: Option<&str>,
+    display_path: &str,
+    options: &RunOptions,
+    renderer: &Renderer,
+    mut times: Option<PhaseTimes>,
+) {
+    if let Some(format) = options.dump_ast {
+        match format {
+            AstFormat::Pretty => println!("{:#?}", program),
+            AstFormat::Json => println!("{}", ast_json::to_json(&program)),
+        }
+        return;
+    }
+
+    // strict mode refuses to run with warnings, whatever '--warnings' says
+    let warning_level = match options.strict {
+        true => &WarningLevel::Error,
+        false => &options.warnings,
+    };
+    if *warning_level != WarningLevel::Off {
+        let started = Instant::now();
+        let warnings = warnings::check(&program, options.warn_shadowing);
+        if let Some(times) = times.as_mut() {
+            times.checking = Some(started.elapsed());
+        }
+        let fatal = *warning_level == WarningLevel::Error;
+        for warning in &warnings {
+            let diagnostic = match fatal {
+                true => Diagnostic::from(warning).into_error(),
+                false => Diagnostic::from(warning),
+            };
+            eprint!(
+                "{}",
+                renderer.render(&diagnostic, source, Some(display_path))
+            );
+        }
+        if fatal && !warnings.is_empty() {
+            exit(1);
+        }
+    }
+
+    let env = Shared::new(Lock::new(Context::make_global(builtins())));
+    let mut evaltr = Runtime::new(Shared::clone(&env));
+    evaltr.set_script_path(display_path);
+    evaltr.set_max_steps(options.max_steps);
+    evaltr.set_max_call_depth(options.max_call_depth);
+    evaltr.set_memory_limit(options.max_memory);
+    evaltr.set_wrapping_arithmetic(options.wrapping);
+    evaltr.set_strict(options.strict);
+    evaltr.set_permissions(options.permissions.clone());
+    evaltr.set_import_paths(options.import_paths.clone());
+    evaltr.set_interrupt_handle(ctrl_c_handle());
+    if let Some(seed) = options.seed {
+        evaltr.set_random_seed(seed);
+    }
+    for path in &options.plugins {
+        load_plugin(&mut evaltr, path, renderer);
+    }
+    if options.profile {
+        evaltr.enable_profiling();
+    }
+    match &options.trace {
+        Some(Some(path)) => match File::create(path) {
+            Ok(file) => evaltr.set_tracer(Box::new(LineWriter::new(file))),
+            Err(err) => {
+                eprintln!("[IOError]: Couldn't create {path}: {err}");
+                exit(1);
+            }
+        },
+        Some(None) => evaltr.set_tracer(Box::new(stderr())),
+        None => {}
+    }
+    if options.debug {
+        let mut breakpoints = HashSet::new();
+        for location in &options.breakpoints {
+            match breakpoint_line(location, display_path) {
+                Some(line) => breakpoints.insert(line),
+                None => {
+                    eprintln!("[ERROR]: Not a line of {}: {}", display_path, location);
+                    exit(1);
+                }
+            };
+        }
+        let frontend = Box::new(TerminalDebugger::new(display_path, source));
+        // with no breakpoints, stop right away so some can be set
+        let stop_on_entry = breakpoints.is_empty();
+        evaltr.set_debugger(Debugger::new(frontend, breakpoints, stop_on_entry));
+    }
+    let mut coverage = options.coverage.map(|format| {
+        evaltr.enable_coverage();
+        (format, FileCoverage::new(display_path, source, &program))
+    });
+    let started = Instant::now();
+    let result = evaltr.eval(program);
+    if let Some(times) = times.as_mut() {
+        times.evaluation = started.elapsed();
+    }
+    if let Some(report) = evaltr.profile_report() {
+        eprint!("\n{}", report);
+    }
+    if let Some(times) = times {
+        eprint!("\n{}", times.report());
+    }
+    if let (Some((format, file)), Some(hits)) = (coverage.as_mut(), evaltr.coverage()) {
+        file.coverage.merge(hits);
+        write_coverage(*format, std::slice::from_ref(file));
+    }
+    if let Err(error) = result {
+        if let ErrorKind::Exit(code) = error.kind {
+            exit(code);
+        }
+        let diagnostic = Diagnostic::from(&error).with_help(run_hint(&error, options).as_deref());
+        eprint!(
+            "{}",
+            renderer.render(&diagnostic, source, Some(display_path))
+        );
+        // what ran before the error is still there to look at
+        if !options.interactive {
+            exit(match error.kind {
+                ErrorKind::Interrupted => SIGINT_EXIT_CODE,
+                _ => 1,
+            });
+        }
+    }
+    if options.interactive {
+        repl_in(env);
+    }
+}
+
+/// The handle Ctrl-C triggers, shared by every runtime of the process so it
+/// stops whichever is running. Pressing it again before the program stops
+/// ends the process, like Ctrl-C does without a handler.
+pub fn ctrl_c_handle() -> InterruptHandle {
+    static HANDLE: OnceLock<InterruptHandle> = OnceLock::new();
+    let handle = HANDLE.get_or_init(|| {
+        let handle = InterruptHandle::new();
+        let interrupted = handle.clone();
+        let installed = ctrlc::set_handler(move || match interrupted.is_interrupted() {
+            true => exit(SIGINT_EXIT_CODE),
+            false => interrupted.interrupt(),
+        });
+        if let Err(err) = installed {
+            eprintln!("[ERROR]: Couldn't handle Ctrl-C: {}", err);
+        }
+        handle
+    });
+    handle.clone()
+}
+
+/// Loads the plugin given with `--plugin`, or exits saying why it can't.
+#[cfg(feature = "plugins")]
+fn load_plugin(runtime: &mut Runtime, path: &str, renderer: &Renderer) {
+    // whoever runs the script chose the plugin, as they would a program
+    if let Err(error) = unsafe { runtime.load_plugin(path) } {
+        eprint!("{}", renderer.render(&Diagnostic::from(&error), None, None));
+        exit(1);
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugin(_: &mut Runtime, _: &str, _: &Renderer) {
+    eprintln!("[ERROR]: This filipe was built without the 'plugins' feature");
+    exit(1);
+}
+
+/// Writes the coverage report of `files` to the current directory and
+/// sums it up on stderr.
+pub fn write_coverage(format: ReportFormat, files: &[FileCoverage]) {
+    for file in files {
+        eprintln!("Coverage of {}: {}", file.path, file.summary());
+    }
+    let path = format.file_name();
+    if write_file(path, coverage::report(format, files).as_bytes()) {
+        eprintln!("Wrote {}", path);
+    }
+}
+
+/// Prints each token of a script with where it starts, for `--dump-tokens`.
+/// Returns false if the script can't be read or lexed.
+fn dump_tokens(bytes: &[u8], display_path: &str, renderer: &Renderer) -> bool {
+    let mut l = Lexer::from_reader(bytes);
+    loop {
+        match l.next_token() {
+            Ok(Token::Eof) => return true,
+            Ok(token) => println!("{}\t{:?}", l.span(), token),
+            Err(msg) => {
+                let error = ParserError {
+                    kind: ParserErrorKind::SyntaxError,
+                    msg,
+                    span: l.span(),
+                };
+                let source = String::from_utf8_lossy(bytes);
+                eprint!(
+                    "{}",
+                    renderer.render(&Diagnostic::from(&error), Some(&source), Some(display_path))
+                );
+                return false;
+            }
+        }
+    }
+}
+
+/// Contents of a script, or of stdin for `STDIN_PATH`.
+fn read_input(path: &str) -> Option<Vec<u8>> {
+    if path != STDIN_PATH {
+        return read_file_bytes(path);
+    }
+    let mut bytes = vec![];
+    if io::stdin().lock().read_to_end(&mut bytes).is_err() {
+        eprintln!("[IOError]: Couldn't read from stdin");
+        return None;
+    }
+    Some(bytes)
+}
+
+/// Points at the `run` flag that changes the behaviour behind an error.
+fn run_hint(error: &RuntimeError, options: &RunOptions) -> Option<String> {
+    let call_depth = options
+        .max_call_depth
+        .map(|max| msg!("E014.call_depth", max = max));
+    match error.kind {
+        ErrorKind::ExecutionLimitExceeded if call_depth.as_ref() == Some(&error.msg) => {
+            Some(msg!("E014.call_depth_help"))
+        }
+        ErrorKind::ExecutionLimitExceeded => Some(msg!("E014.help")),
+        ErrorKind::MemoryLimitExceeded => Some(msg!("E015.help")),
+        ErrorKind::OverflowError => Some(msg!("E016.help")),
+        _ => None,
+    }
+}
+
+pub fn compile_file(path: &str, output: Option<&str>) {
+    let path = &script_path(path);
+    let source = match read_file(path) {
         Some(contents) => contents,
         None => exit(1),
     };
+    let renderer = Renderer::new(stderr_supports_color());
+    let program = match parse_source(&source, path, &renderer, None) {
+        Some(program) => program,
+        None => exit(1),
+    };
+
+    let output = match output {
+        Some(output) => output.to_string(),
+        None => Path::new(path)
+            .with_extension(flbc::EXTENSION)
+            .to_string_lossy()
+            .to_string(),
+    };
+    if !write_file(&output, &flbc::encode(&program)) {
+        exit(1);
+    }
+}
 
-    let input = input.chars().collect::<Vec<char>>();
+/// Makes a single executable out of a script and the modules it imports,
+/// see `bundle`. It is named after the script unless `output` is given.
+pub fn bundle_file(path: &str, output: Option<&str>) {
+    let path = &script_path(path);
+    let source = match read_file(path) {
+        Some(contents) => contents,
+        None => exit(1),
+    };
+    let renderer = Renderer::new(stderr_supports_color());
+    let program = match parse_source(&source, path, &renderer, None) {
+        Some(program) => program,
+        None => exit(1),
+    };
+
+    let output = match output {
+        Some(output) => output.to_string(),
+        None => Path::new(path)
+            .with_extension(env::consts::EXE_EXTENSION)
+            .to_string_lossy()
+            .to_string(),
+    };
+    let written = bundle::inline_imports(program, path, &project_import_paths(path))
+        .and_then(|program| bundle::write(&program, &output));
+    if let Err(err) = written {
+        eprintln!("[ERROR]: Couldn't bundle {path}: {err}");
+        exit(1);
+    }
+    println!("Wrote {}", output);
+}
+
+/// Compiles a script and the modules it imports to `target`, see `codegen`.
+/// Programs with errors the checker finds aren't compiled. The output is
+/// named after the script unless `output` is given.
+pub fn build_file(path: &str, target: Target, output: Option<&str>) {
+    let path = &script_path(path);
+    let source = match read_file(path) {
+        Some(contents) => contents,
+        None => exit(1),
+    };
+    let renderer = Renderer::new(stderr_supports_color());
+    let program = match parse_source(&source, path, &renderer, None) {
+        Some(program) => program,
+        None => exit(1),
+    };
+    let program = match bundle::inline_imports(program, path, &project_import_paths(path)) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("[ERROR]: Couldn't build {path}: {err}");
+            exit(1);
+        }
+    };
+
+    let analysis = checker::analyze(&program);
+    if !analysis.errors.is_empty() {
+        for error in &analysis.errors {
+            eprint!(
+                "{}",
+                renderer.render(&Diagnostic::from(error), Some(&source), Some(path))
+            );
+        }
+        exit(1);
+    }
+    let compiled = match codegen::compile(&program, &analysis, target) {
+        Ok(compiled) => compiled,
+        Err(err) => {
+            eprintln!("[ERROR]: Couldn't build {path}: {err}");
+            exit(1);
+        }
+    };
+
+    let output = match output {
+        Some(output) => output.to_string(),
+        None => Path::new(path)
+            .with_extension(target.extension())
+            .to_string_lossy()
+            .to_string(),
+    };
+    if !write_file(&output, compiled.as_bytes()) {
+        exit(1);
+    }
+    println!("Wrote {}", output);
+}
+
+/// Prints a script and the modules it imports as one compact script, see
+/// `minifier`, or writes it to `output`. With `rename` locals get short
+/// names.
+pub fn minify_file(path: &str, rename: bool, output: Option<&str>) {
+    let path = &script_path(path);
+    let source = match read_file(path) {
+        Some(contents) => contents,
+        None => exit(1),
+    };
+    let renderer = Renderer::new(stderr_supports_color());
+    let program = match parse_source(&source, path, &renderer, None) {
+        Some(program) => program,
+        None => exit(1),
+    };
+    let program = match bundle::inline_imports(program, path, &project_import_paths(path)) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("[ERROR]: Couldn't minify {path}: {err}");
+            exit(1);
+        }
+    };
+
+    let minified = minifier::minify(&program, rename) + "\n";
+    match output {
+        Some(output) => {
+            if !write_file(output, minified.as_bytes()) {
+                exit(1);
+            }
+        }
+        None => print!("{}", minified),
+    }
+}
+
+/// Runs the program bundled into this executable, see `bundle`.
+pub fn run_bundled(program: Program) {
+    let options = RunOptions {
+        warnings: WarningLevel::Off,
+        ..RunOptions::default()
+    };
+    let renderer = Renderer::new(stderr_supports_color());
+    let name = env::args().next().unwrap_or_default();
+    run_program(program, None, &name, &options, &renderer, None);
+}
+
+/// Looks for syntax, type and name errors in scripts without running them,
+/// along with the warnings `run` would print. Every file is checked; the
+/// exit status is 1 if any of them has errors, or warnings when they are
+/// made errors with `--warnings=error` or `--strict`.
+pub fn check_files(paths: &[String], options: &RunOptions) {
+    let renderer = Renderer::new(!options.no_color && stderr_supports_color())
+        .with_format(options.error_format);
+    let warning_level = match options.strict {
+        true => &WarningLevel::Error,
+        false => &options.warnings,
+    };
+    let mut failed = false;
+    for path in paths {
+        let path = &script_path(path);
+        let Some((program, source)) = load_program(path, &renderer, None) else {
+            failed = true;
+            continue;
+        };
+        let render = |diagnostic: Diagnostic| {
+            eprint!(
+                "{}",
+                renderer.render(&diagnostic, source.as_deref(), Some(path))
+            );
+        };
+
+        let errors = checker::check(&program);
+        errors.iter().map(Diagnostic::from).for_each(render);
+        failed |= !errors.is_empty();
+
+        if *warning_level != WarningLevel::Off {
+            let fatal = *warning_level == WarningLevel::Error;
+            let warnings = warnings::check(&program, options.warn_shadowing);
+            for warning in &warnings {
+                match fatal {
+                    true => render(Diagnostic::from(warning).into_error()),
+                    false => render(Diagnostic::from(warning)),
+                }
+            }
+            failed |= fatal && !warnings.is_empty();
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+/// Runs the `lint` rules over scripts, with the rule levels of `config`
+/// or else of the `filipe-lint.json` nearest to each script. The exit
+/// status is 1 if a script has errors or a finding of an `error` rule.
+pub fn lint_files(paths: &[String], config: Option<&str>, options: &RunOptions) {
+    let renderer = Renderer::new(!options.no_color && stderr_supports_color())
+        .with_format(options.error_format);
+    let mut failed = false;
+    for path in paths {
+        let path = &script_path(path);
+        let config_path = config.map(str::to_string).or_else(|| {
+            let dir = Path::new(path)
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            LintConfig::find(dir)
+        });
+        let config = match &config_path {
+            Some(config_path) => {
+                let Some(json) = read_file(config_path) else {
+                    exit(1);
+                };
+                match LintConfig::parse(&json) {
+                    Ok(config) => config,
+                    Err(err) => {
+                        eprintln!("[ERROR]: Invalid {config_path}: {err}");
+                        exit(1);
+                    }
+                }
+            }
+            None => LintConfig::default(),
+        };
+        let Some((program, source)) = load_program(path, &renderer, None) else {
+            failed = true;
+            continue;
+        };
+        let render = |diagnostic: Diagnostic| {
+            eprint!(
+                "{}",
+                renderer.render(&diagnostic, source.as_deref(), Some(path))
+            );
+        };
+
+        let errors = checker::check(&program);
+        errors.iter().map(Diagnostic::from).for_each(render);
+        failed |= !errors.is_empty();
+
+        for (warning, level) in lint::lint(&program, &config) {
+            let mut diagnostic = Diagnostic::from(&warning);
+            if level == RuleLevel::Error {
+                diagnostic = diagnostic.into_error();
+                failed = true;
+            }
+            render(diagnostic);
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+/// Rewrites scripts in the canonical layout, see `formatter`. With `check`
+/// nothing is written; the scripts that would change are listed and the
+/// exit status is 1 if there are any.
+pub fn format_files(paths: &[String], check: bool) {
+    let renderer = Renderer::new(stderr_supports_color());
+    let mut failed = false;
+    for path in paths {
+        let path = &script_path(path);
+        let Some(source) = read_file(path) else {
+            failed = true;
+            continue;
+        };
+        let Some(program) = parse_source(&source, path, &renderer, None) else {
+            failed = true;
+            continue;
+        };
+        let indent = project_of(Some(path)).map_or(formatter::DEFAULT_INDENT, |(manifest, _)| {
+            manifest.format.indent
+        });
+        let formatted = formatter::format_with_indent(&program, &source, indent);
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("Would reformat {}", path);
+            failed = true;
+        } else if !write_file(path, formatted.as_bytes()) {
+            failed = true;
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+/// Writes a reference page for each script into `out_dir`: `<name>.md`,
+/// or `<name>.html` with `html`.
+pub fn document_files(paths: &[String], html: bool, out_dir: &str) {
+    let renderer = Renderer::new(stderr_supports_color());
+    if let Err(err) = std::fs::create_dir_all(out_dir) {
+        eprintln!("[IOError]: Couldn't create directory {out_dir}: {err}");
+        exit(1);
+    }
+    let mut failed = false;
+    for path in paths {
+        let path = &script_path(path);
+        let Some(source) = read_file(path) else {
+            failed = true;
+            continue;
+        };
+        let Some(program) = parse_source(&source, path, &renderer, None) else {
+            failed = true;
+            continue;
+        };
+        let module = Path::new(path)
+            .file_stem()
+            .map_or(path.clone(), |stem| stem.to_string_lossy().to_string());
+        let (page, extension) = match html {
+            true => (docgen::html(&module, &program), "html"),
+            false => (docgen::markdown(&module, &program), "md"),
+        };
+        let page_path = Path::new(out_dir).join(format!("{}.{}", module, extension));
+        let page_path = page_path.to_string_lossy();
+        if write_file(&page_path, page.as_bytes()) {
+            println!("Wrote {}", page_path);
+        } else {
+            failed = true;
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+/// Prints the long description of an error code, or lists all codes when
+/// none is given.
+pub fn explain_error(code: Option<&str>) {
+    let Some(code) = code else {
+        for explanation in EXPLANATIONS {
+            println!("{}  {}", explanation.code, explanation.title);
+        }
+        return;
+    };
+    match explain(code) {
+        Some(explanation) => print!(
+            "{} {}\n\n{}",
+            explanation.code, explanation.title, explanation.text
+        ),
+        None => {
+            eprintln!("[ERROR]: Unknown error code {code}");
+            exit(1);
+        }
+    }
+}
+
+/// Loads a program from a source file, a precompiled `.flbc` chunk or a
+/// JSON AST, along with its source when there is one.
+fn load_program(
+    path: &str,
+    renderer: &Renderer,
+    times: Option<&mut PhaseTimes>,
+) -> Option<(Program, Option<String>)> {
+    let bytes = read_file_bytes(path)?;
+    let started = Instant::now();
+    // a decoded program has no lexing, reading it counts as parsing
+    let decoded = |program: Program, times: Option<&mut PhaseTimes>| {
+        if let Some(times) = times {
+            times.parsing = started.elapsed();
+        }
+        Some((program, None))
+    };
+    if path.ends_with(&format!(".{}", ast_json::EXTENSION)) {
+        return match ast_json::from_json(&String::from_utf8_lossy(&bytes)) {
+            Ok(program) => decoded(program, times),
+            Err(err) => {
+                eprintln!("[ERROR]: {}", err);
+                None
+            }
+        };
+    }
+    if flbc::is_flbc(&bytes) {
+        return match flbc::decode(&bytes) {
+            Ok(program) => decoded(program, times),
+            Err(err) => {
+                eprintln!("[ERROR]: {}", err);
+                None
+            }
+        };
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(source) => {
+            let program = parse_source(&source, path, renderer, times)?;
+            Some((program, Some(source)))
+        }
+        Err(_) => {
+            eprintln!("[IOError]: Couldn't read from file {path}");
+            None
+        }
+    }
+}
+
+fn parse_source(
+    source: &str,
+    path: &str,
+    renderer: &Renderer,
+    times: Option<&mut PhaseTimes>,
+) -> Option<Program> {
+    let input = source.chars().collect::<Vec<char>>();
     let mut l = Lexer::new(&input);
-    let mut p = Parser::new(&mut l);
+    parse(&mut l, Some(source), path, renderer, times)
+}
+
+fn parse(
+    l: &mut Lexer,
+    source: Option<&str>,
+    path: &str,
+    renderer: &Renderer,
+    times: Option<&mut PhaseTimes>,
+) -> Option<Program> {
+    let started = Instant::now();
+    if times.is_some() {
+        l.enable_timing();
+    }
+    let mut p = Parser::new(l);
     let program = p.parse();
     if p.has_error() {
-        println!("{}", p.get_error().unwrap());
-        exit(1);
+        for error in p.errors() {
+            eprint!(
+                "{}",
+                renderer.render(&Diagnostic::from(error), source, Some(path))
+            );
+        }
+        return None;
     };
-
-    let env = Context::make_global(builtins());
-    let mut evaltr = Runtime::new(Rc::new(RefCell::new(env)));
-    evaltr.eval(program);
+    if let Some(times) = times {
+        times.lexing = l.time_spent().unwrap_or_default();
+        times.parsing = started.elapsed().saturating_sub(times.lexing);
+    }
+    Some(program)
 }
@@ -0,0 +1,468 @@
+//! Debug adapter started with `filipe dap`. It speaks the Debug Adapter
+//! Protocol over stdin and stdout, so editors can launch a script, set
+//! breakpoints, step, and look at the call stack and variables. What the
+//! script prints is sent to the editor as `output` events.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use crate::debugger::{breakpoint_line, evaluate};
+use crate::diagnostics::{Diagnostic, Renderer};
+use crate::frontend::ast::Program;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::frontend::token::Span;
+use crate::lsp::{read_message, write_message};
+use crate::runtime::debugger::{DebugFrontend, Debugger, Pause, PauseReason, Resume};
+use crate::runtime::flstdlib::{builtins, is_builtin};
+use crate::runtime::object::ObjectInfo;
+use crate::runtime::runtime_error::{ErrorKind, RuntimeError};
+use crate::runtime::streams::TextWriter;
+use crate::runtime::{context::Context, Runtime};
+use crate::shared::{Lock, Shared};
+use crate::utils::{read_file, script_path};
+
+/// The only thread of a script.
+const THREAD_ID: u8 = 1;
+// `variablesReference`s of the two scopes a paused frame has.
+const LOCALS_REFERENCE: u8 = 1;
+const GLOBALS_REFERENCE: u8 = 2;
+
+/// Talks to the editor until it disconnects. Returns the exit status, 1
+/// when the editor goes away without disconnecting.
+pub fn serve(input: impl BufRead + Send + 'static, output: impl Write + Send + 'static) -> i32 {
+    let connection = Arc::new(Connection {
+        input: Mutex::new(Box::new(input)),
+        output: Mutex::new((Box::new(output), 0)),
+    });
+    let mut launched: Option<Launch> = None;
+    let mut breakpoints = HashSet::new();
+    loop {
+        let Some(request) = connection.read() else {
+            return 1;
+        };
+        let arguments = &request["arguments"];
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                connection.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                );
+                connection.event("initialized", json!({}));
+            }
+            "launch" => match Launch::load(arguments) {
+                Ok(launch) => {
+                    launched = Some(launch);
+                    connection.respond(&request, json!({}));
+                }
+                Err(message) => connection.fail(&request, &message),
+            },
+            "setBreakpoints" => {
+                let lines = requested_lines(arguments, launched.as_ref().map(|l| &l.path));
+                let verified = verified_breakpoints(arguments, &lines);
+                breakpoints = lines;
+                connection.respond(&request, json!({ "breakpoints": verified }));
+            }
+            "configurationDone" => {
+                connection.respond(&request, json!({}));
+                if let Some(launch) = launched.take() {
+                    let breakpoints = std::mem::take(&mut breakpoints);
+                    let exit_code = launch.run(&connection, breakpoints);
+                    connection.event("exited", json!({ "exitCode": exit_code }));
+                    connection.event("terminated", json!({}));
+                }
+            }
+            "threads" => connection.respond(&request, threads()),
+            "disconnect" => {
+                connection.respond(&request, json!({}));
+                return 0;
+            }
+            command => connection.fail(&request, &format!("{} isn't supported now", command)),
+        }
+    }
+}
+
+/// Both ends of the protocol. They are locked one message at a time, so
+/// `print` can send output while the paused program is evaluating code.
+struct Connection {
+    input: Mutex<Box<dyn BufRead + Send>>,
+    /// The output and the sequence number of the last message sent.
+    output: Mutex<(Box<dyn Write + Send>, u64)>,
+}
+
+impl Connection {
+    /// The next request, `None` once the editor is gone.
+    fn read(&self) -> Option<Value> {
+        let mut input = self.input.lock().unwrap();
+        match read_message(&mut *input) {
+            Ok(message) => message,
+            Err(err) => {
+                eprintln!("[ERROR]: {}", err);
+                None
+            }
+        }
+    }
+
+    fn send(&self, mut message: Value) {
+        let mut output = self.output.lock().unwrap();
+        output.1 += 1;
+        message["seq"] = json!(output.1);
+        if let Err(err) = write_message(&mut output.0, &message) {
+            eprintln!("[ERROR]: {}", err);
+        }
+    }
+
+    fn respond(&self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    fn fail(&self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }));
+    }
+
+    fn event(&self, event: &str, body: Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+}
+
+/// The script the editor asked to run.
+struct Launch {
+    path: String,
+    source: String,
+    program: Program,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    fn load(arguments: &Value) -> Result<Self, String> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or("Missing the 'program' to launch")?;
+        let path = script_path(path);
+        let source = read_file(&path).ok_or_else(|| format!("Couldn't read {}", path))?;
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        if p.has_error() {
+            let renderer = Renderer::new(false);
+            return Err(p
+                .errors()
+                .iter()
+                .map(|error| renderer.render(&Diagnostic::from(error), Some(&source), Some(&path)))
+                .collect());
+        }
+        Ok(Self {
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+            path,
+            source,
+            program,
+        })
+    }
+
+    /// Runs the script with the editor as its debugger and returns its exit
+    /// status.
+    fn run(self, connection: &Arc<Connection>, breakpoints: HashSet<usize>) -> i32 {
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        let output = Arc::clone(connection);
+        runtime.set_stdout(TextWriter(move |text| {
+            output.event("output", json!({ "category": "stdout", "output": text }))
+        }));
+        runtime.set_script_path(&self.path);
+        let frontend = EditorFrontend {
+            connection: Arc::clone(connection),
+            path: self.path.clone(),
+        };
+        runtime.set_debugger(Debugger::new(
+            Box::new(frontend),
+            breakpoints,
+            self.stop_on_entry,
+        ));
+        let result = runtime.eval(self.program);
+
+        let error = match result {
+            Ok(_) => return 0,
+            Err(RuntimeError {
+                kind: ErrorKind::Exit(code),
+                ..
+            }) => return code,
+            Err(error) => error,
+        };
+        let renderer = Renderer::new(false);
+        let diagnostic = Diagnostic::from(&error);
+        let text = renderer.render(&diagnostic, Some(&self.source), Some(&self.path));
+        connection.event("output", json!({ "category": "stderr", "output": text }));
+        1
+    }
+}
+
+/// Answers the editor while the script is paused.
+struct EditorFrontend {
+    connection: Arc<Connection>,
+    path: String,
+}
+
+impl DebugFrontend for EditorFrontend {
+    fn paused(&mut self, runtime: &mut Runtime, pause: Pause) -> Resume {
+        let reason = match pause.reason {
+            PauseReason::Entry => "entry",
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step => "step",
+        };
+        self.connection.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+
+        loop {
+            // the editor is gone, so there's nobody to debug for
+            let Some(request) = self.connection.read() else {
+                std::process::exit(1);
+            };
+            let arguments = &request["arguments"];
+            let resume = match request["command"].as_str().unwrap_or_default() {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::Next),
+                "stepIn" => Some(Resume::Step),
+                "stepOut" => Some(Resume::Out),
+                _ => None,
+            };
+            if let Some(resume) = resume {
+                self.connection
+                    .respond(&request, json!({ "allThreadsContinued": true }));
+                return resume;
+            }
+
+            match request["command"].as_str().unwrap_or_default() {
+                "threads" => self.connection.respond(&request, threads()),
+                "stackTrace" => {
+                    let frames = self.stack_frames(runtime, pause.span);
+                    let body = json!({ "totalFrames": frames.len(), "stackFrames": frames });
+                    self.connection.respond(&request, body);
+                }
+                "scopes" => {
+                    let mut scopes = vec![];
+                    // callers' variables aren't reachable from the runtime
+                    if arguments["frameId"].as_u64().unwrap_or_default() == 0 {
+                        scopes.push(scope("Locals", LOCALS_REFERENCE));
+                    }
+                    scopes.push(scope("Globals", GLOBALS_REFERENCE));
+                    self.connection
+                        .respond(&request, json!({ "scopes": scopes }));
+                }
+                "variables" => {
+                    let bindings = match arguments["variablesReference"].as_u64() {
+                        Some(reference) if reference == u64::from(LOCALS_REFERENCE) => {
+                            runtime.locals()
+                        }
+                        Some(reference) if reference == u64::from(GLOBALS_REFERENCE) => runtime
+                            .globals()
+                            .into_iter()
+                            .filter(|(name, _)| !is_builtin(name))
+                            .collect(),
+                        _ => vec![],
+                    };
+                    let variables = bindings.iter().map(variable).collect::<Vec<_>>();
+                    self.connection
+                        .respond(&request, json!({ "variables": variables }));
+                }
+                "evaluate" => {
+                    let expression = arguments["expression"].as_str().unwrap_or_default();
+                    match evaluate(runtime, expression, &Renderer::new(false)) {
+                        Ok(value) => self.connection.respond(
+                            &request,
+                            json!({ "result": value.to_string(), "variablesReference": 0 }),
+                        ),
+                        Err(error) => self.connection.fail(&request, error.trim_end()),
+                    }
+                }
+                "setBreakpoints" => {
+                    let lines = requested_lines(arguments, Some(&self.path));
+                    let verified = verified_breakpoints(arguments, &lines);
+                    *pause.breakpoints = lines;
+                    self.connection
+                        .respond(&request, json!({ "breakpoints": verified }));
+                }
+                "disconnect" => {
+                    self.connection.respond(&request, json!({}));
+                    std::process::exit(0);
+                }
+                command => self
+                    .connection
+                    .fail(&request, &format!("{} isn't supported", command)),
+            }
+        }
+    }
+}
+
+impl EditorFrontend {
+    /// The paused statement, then where each call that hasn't returned was
+    /// made from, innermost first.
+    fn stack_frames(&self, runtime: &Runtime, span: Span) -> Vec<Value> {
+        let mut frames = vec![];
+        let mut location = span;
+        for call in runtime.call_stack().iter().rev() {
+            frames.push(self.stack_frame(frames.len(), &call.function, location));
+            location = call.call_site;
+        }
+        frames.push(self.stack_frame(frames.len(), "<main>", location));
+        frames
+    }
+
+    fn stack_frame(&self, id: usize, name: &str, span: Span) -> Value {
+        json!({
+            "id": id,
+            "name": name,
+            "line": span.line,
+            "column": span.col,
+            "source": { "path": self.path },
+        })
+    }
+}
+
+fn threads() -> Value {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
+
+fn scope(name: &str, reference: u8) -> Value {
+    json!({ "name": name, "variablesReference": reference, "expensive": false })
+}
+
+fn variable((name, info): &(String, ObjectInfo)) -> Value {
+    json!({
+        "name": name,
+        "value": info.value.to_string(),
+        "type": info.type_.to_string(),
+        "variablesReference": 0,
+    })
+}
+
+/// The lines of a `setBreakpoints` request that are in the script at
+/// `path`; all of them while no script is launched yet.
+fn requested_lines(arguments: &Value, path: Option<&String>) -> HashSet<usize> {
+    let source = arguments["source"]["path"].as_str().unwrap_or_default();
+    let lines = arguments["breakpoints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|breakpoint| breakpoint["line"].as_u64());
+    lines
+        .filter_map(|line| match path {
+            Some(path) => breakpoint_line(&format!("{}:{}", source, line), path),
+            None => usize::try_from(line).ok(),
+        })
+        .collect()
+}
+
+fn verified_breakpoints(arguments: &Value, lines: &HashSet<usize>) -> Vec<Value> {
+    let requested = arguments["breakpoints"].as_array().into_iter().flatten();
+    requested
+        .map(|breakpoint| {
+            let line = breakpoint["line"].as_u64().unwrap_or_default();
+            let verified = usize::try_from(line).is_ok_and(|line| lines.contains(&line));
+            json!({ "verified": verified, "line": line })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output the test can still read once the adapter owns the writer.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_adapter_stops_at_breakpoints_and_shows_variables() {
+        let path = std::env::temp_dir().join("filipe_dap_test.fl");
+        let source = "define double(n: int): int {\n  let twice = n * 2\n  return twice\n}\nprint(double(4))";
+        std::fs::write(&path, source).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let requests = [
+            ("initialize", json!({})),
+            ("launch", json!({ "program": path })),
+            (
+                "setBreakpoints",
+                json!({ "source": { "path": path }, "breakpoints": [{ "line": 3 }] }),
+            ),
+            ("configurationDone", json!({})),
+            ("stackTrace", json!({ "threadId": 1 })),
+            ("variables", json!({ "variablesReference": 1 })),
+            ("evaluate", json!({ "expression": "twice + 1" })),
+            ("continue", json!({ "threadId": 1 })),
+            ("disconnect", json!({})),
+        ];
+        let input = requests
+            .into_iter()
+            .enumerate()
+            .map(|(seq, (command, arguments))| {
+                let body = json!({ "seq": seq + 1, "type": "request", "command": command, "arguments": arguments }).to_string();
+                format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+            })
+            .collect::<String>();
+
+        let output = SharedOutput::default();
+        assert_eq!(serve(std::io::Cursor::new(input), output.clone()), 0);
+        let output = output.0.lock().unwrap();
+        let mut output = output.as_slice();
+        let mut messages = vec![];
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(message);
+        }
+        let find = |kind: &str, name: &str| {
+            let key = if kind == "event" { "event" } else { "command" };
+            messages
+                .iter()
+                .find(|message| message["type"] == kind && message[key] == name)
+                .unwrap_or_else(|| panic!("no {} {}", kind, name))
+        };
+
+        assert_eq!(find("event", "stopped")["body"]["reason"], "breakpoint");
+        let frames = &find("response", "stackTrace")["body"]["stackFrames"];
+        assert_eq!(frames[0]["name"], "double");
+        assert_eq!(frames[0]["line"], 3);
+        assert_eq!(frames[1]["name"], "<main>");
+        assert_eq!(frames[1]["line"], 5);
+        assert_eq!(
+            find("response", "variables")["body"]["variables"],
+            json!([
+                { "name": "twice", "value": "8", "type": "int", "variablesReference": 0 },
+                { "name": "n", "value": "4", "type": "int", "variablesReference": 0 },
+            ])
+        );
+        assert_eq!(find("response", "evaluate")["body"]["result"], "9");
+        assert_eq!(find("event", "output")["body"]["output"], "8\n");
+        assert_eq!(find("event", "exited")["body"]["exitCode"], 0);
+    }
+}
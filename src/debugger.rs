@@ -0,0 +1,195 @@
+//! `run --debug`: a prompt on the terminal whenever the program pauses, to
+//! step through it, look at variables and evaluate expressions.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::exit;
+
+use crate::diagnostics::{stderr_supports_color, Diagnostic, Renderer};
+use crate::frontend::ast::StmtKind;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::runtime::debugger::{DebugFrontend, Pause, PauseReason, Resume};
+use crate::runtime::flstdlib::is_builtin;
+use crate::runtime::object::Object;
+use crate::runtime::Runtime;
+
+const HELP: &str = "\
+    c, continue          run to the next breakpoint
+    s, step              run one statement, stopping inside calls
+    n, next              run one statement, stepping over calls
+    o, out               run until the current function returns
+    p, print <expr>      evaluate an expression where the program stopped
+    l, locals            variables of the current function
+    g, globals           variables and functions of the script
+    bt, backtrace        calls that haven't returned
+    b, break [<line>]    stop at a line, or list the breakpoints
+    d, delete <line>     remove a breakpoint
+    q, quit              stop the program
+An empty line repeats the last command.";
+
+pub struct TerminalDebugger {
+    path: String,
+    lines: Vec<String>,
+    last_command: String,
+}
+
+impl TerminalDebugger {
+    /// Debugs the script at `path`, whose source is shown around the
+    /// current line when it is known.
+    pub fn new(path: &str, source: Option<&str>) -> Self {
+        Self {
+            path: path.to_string(),
+            lines: source
+                .map(|source| source.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
+            last_command: String::new(),
+        }
+    }
+
+    fn show_line(&self, line: usize) {
+        if let Some(text) = line.checked_sub(1).and_then(|i| self.lines.get(i)) {
+            eprintln!("{:>4} | {}", line, text);
+        }
+    }
+}
+
+impl DebugFrontend for TerminalDebugger {
+    fn paused(&mut self, runtime: &mut Runtime, pause: Pause) -> Resume {
+        let why = match pause.reason {
+            PauseReason::Entry => "before the first statement",
+            PauseReason::Breakpoint => "at a breakpoint",
+            PauseReason::Step => "after a step",
+        };
+        eprintln!("Paused {} in {}:{}", why, self.path, pause.span);
+        self.show_line(pause.span.line);
+
+        let stdin = io::stdin();
+        loop {
+            eprint!("(debug) ");
+            let _ = io::stderr().flush();
+            let mut input = String::new();
+            // nobody left to ask, so let the program finish
+            if !matches!(stdin.lock().read_line(&mut input), Ok(read) if read > 0) {
+                eprintln!();
+                return Resume::Continue;
+            }
+            let input = match input.trim() {
+                "" => self.last_command.clone(),
+                input => input.to_string(),
+            };
+            self.last_command = input.clone();
+            let (command, arg) = input.split_once(' ').unwrap_or((&input, ""));
+            let arg = arg.trim();
+            match command {
+                "c" | "continue" => return Resume::Continue,
+                "s" | "step" => return Resume::Step,
+                "n" | "next" => return Resume::Next,
+                "o" | "out" => return Resume::Out,
+                "p" | "print" if arg.is_empty() => eprintln!("Usage: print <expr>"),
+                "p" | "print" => {
+                    let renderer = Renderer::new(stderr_supports_color());
+                    match evaluate(runtime, arg, &renderer) {
+                        Ok(value) => eprintln!("{}", value),
+                        Err(error) => eprint!("{}", error),
+                    }
+                }
+                "l" | "locals" => {
+                    let locals = runtime.locals();
+                    if locals.is_empty() {
+                        eprintln!("No local variables");
+                    }
+                    for (name, info) in locals {
+                        eprintln!("{}: {} = {}", name, info.type_, info.value);
+                    }
+                }
+                "g" | "globals" => {
+                    for (name, info) in runtime.globals() {
+                        if !is_builtin(&name) {
+                            eprintln!("{}: {} = {}", name, info.type_, info.value);
+                        }
+                    }
+                }
+                "bt" | "backtrace" => {
+                    eprintln!("  at {}:{}", self.path, pause.span);
+                    for frame in runtime.call_stack().iter().rev() {
+                        eprintln!("  {}", frame);
+                    }
+                }
+                "b" | "break" if arg.is_empty() => {
+                    let mut lines = pause.breakpoints.iter().collect::<Vec<_>>();
+                    lines.sort();
+                    if lines.is_empty() {
+                        eprintln!("No breakpoints");
+                    }
+                    for line in lines {
+                        eprintln!("{}:{}", self.path, line);
+                    }
+                }
+                "b" | "break" => match breakpoint_line(arg, &self.path) {
+                    Some(line) => {
+                        pause.breakpoints.insert(line);
+                    }
+                    None => eprintln!("Not a line of {}: {}", self.path, arg),
+                },
+                "d" | "delete" => match breakpoint_line(arg, &self.path) {
+                    Some(line) if pause.breakpoints.remove(&line) => {}
+                    _ => eprintln!("No breakpoint at {}", arg),
+                },
+                "q" | "quit" => exit(0),
+                "h" | "help" => eprintln!("{}", HELP),
+                _ => eprintln!("Unknown command {}, type help for the list", command),
+            }
+        }
+    }
+}
+
+/// Evaluates an expression in the scope the program is paused in. Errors
+/// come rendered by `renderer`.
+pub fn evaluate(
+    runtime: &mut Runtime,
+    source: &str,
+    renderer: &Renderer,
+) -> Result<Object, String> {
+    let input = source.chars().collect::<Vec<char>>();
+    let mut l = Lexer::new(&input);
+    let mut p = Parser::new(&mut l);
+    let program = p.parse();
+    if p.has_error() {
+        return Err(p
+            .errors()
+            .iter()
+            .map(|error| renderer.render(&Diagnostic::from(error), Some(source), None))
+            .collect());
+    }
+    // declarations would throw off the slots of the paused function
+    if !program
+        .iter()
+        .all(|stmt| matches!(stmt.kind, StmtKind::Expr(_)))
+    {
+        return Err("Only expressions can be evaluated\n".to_string());
+    }
+    runtime
+        .eval(program)
+        .map_err(|error| renderer.render(&Diagnostic::from(&error), Some(source), None))
+}
+
+/// The line of a `<line>` or `<file>:<line>` breakpoint in the script at
+/// `path`; the file can be given by name, with or without `.fl`.
+pub fn breakpoint_line(location: &str, path: &str) -> Option<usize> {
+    let (file, line) = match location.rsplit_once(':') {
+        Some((file, line)) => (Some(file), line),
+        None => (None, location),
+    };
+    let script = Path::new(path);
+    let same_file = |file: &str| {
+        let file = Path::new(file);
+        file == script
+            || file.file_name() == script.file_name()
+            || Some(file.as_os_str()) == script.file_stem()
+    };
+    if !file.is_none_or(same_file) {
+        return None;
+    }
+    line.parse().ok().filter(|line| *line > 0)
+}
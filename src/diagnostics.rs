@@ -0,0 +1,385 @@
+//! Renders parse and runtime errors together with the source line they
+//! point at:
+//!
+//! ```text
+//! [Type Error][E011]: '+' operation not allowed between types int and string
+//!  --> script.fl:2:12
+//!   |
+//! 2 |   return x + "s"
+//!   |            ^
+//!   = in 'a' called at 5:10
+//! ```
+//!
+//! or, with `--error-format=json`, as one JSON object per line for editors
+//! and CI to read.
+
+use std::env;
+use std::fmt::{self, Write};
+use std::io::{stderr, IsTerminal};
+
+use serde::Serialize;
+
+use crate::frontend::checker::CheckError;
+use crate::frontend::parser::ParserError;
+use crate::frontend::token::Span;
+use crate::frontend::warnings::Warning;
+use crate::messages::msg;
+use crate::runtime::call_stack::Frame;
+use crate::runtime::runtime_error::RuntimeError;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Bracketed error kind, e.g. `[Syntax Error]`.
+    pub kind: String,
+    /// Code that `filipe explain` knows about, e.g. `E011`.
+    pub code: &'static str,
+    pub msg: String,
+    pub span: Option<Span>,
+    /// Calls that were still running when a runtime error happened, innermost
+    /// first.
+    pub trace: Vec<Frame>,
+    /// Extra context shown under the snippet.
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn with_help(mut self, help: Option<&str>) -> Self {
+        self.help = help.map(str::to_string);
+        self
+    }
+
+    /// Reports a warning as an error, for warnings that fail the run with
+    /// `--warnings=error`, `--strict` or an `error` lint rule.
+    pub fn into_error(mut self) -> Self {
+        self.severity = Severity::Error;
+        self.kind = format!("[{}]", msg!("W.error_kind"));
+        self
+    }
+}
+
+/// The first line of the report, with the location, e.g. for errors that
+/// reach a program embedding filipe.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{}[{}] at {}: {}", self.kind, self.code, span, self.msg),
+            None => write!(f, "{}[{}]: {}", self.kind, self.code, self.msg),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        Self {
+            severity: Severity::Error,
+            kind: error.kind.to_string(),
+            code: error.kind.code(),
+            msg: error.msg.clone(),
+            span: Some(error.span),
+            trace: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+}
+
+impl From<&CheckError> for Diagnostic {
+    fn from(error: &CheckError) -> Self {
+        Self {
+            severity: Severity::Error,
+            kind: error.kind.to_string(),
+            code: error.kind.code(),
+            msg: error.msg.clone(),
+            span: Some(error.span),
+            trace: Vec::new(),
+            notes: error.notes.clone(),
+            help: None,
+        }
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Self {
+            severity: Severity::Warning,
+            kind: warning.kind.to_string(),
+            code: warning.kind.code(),
+            msg: warning.msg.clone(),
+            span: Some(warning.span),
+            trace: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        Self {
+            severity: Severity::Error,
+            kind: error.kind.to_string(),
+            code: error.kind.code(),
+            msg: match &error.expr {
+                Some(expr) => msg!("diagnostic.in_expr", msg = error.msg, expr = expr),
+                None => error.msg.clone(),
+            },
+            span: error.span,
+            trace: error.trace.clone(),
+            notes: error.notes.clone(),
+            help: None,
+        }
+    }
+}
+
+/// Whether stderr looks like it can show colors: it is a terminal and the
+/// `NO_COLOR` convention is not in effect.
+pub fn stderr_supports_color() -> bool {
+    stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+/// How diagnostics are written out, picked with `--error-format`.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// The shape of a diagnostic in `--error-format=json` output.
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    severity: Severity,
+    code: &'a str,
+    kind: &'a str,
+    message: &'a str,
+    file: Option<&'a str>,
+    span: Option<Span>,
+    trace: &'a [Frame],
+    notes: &'a [String],
+    help: Option<&'a str>,
+}
+
+pub struct Renderer {
+    color: bool,
+    format: ErrorFormat,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            format: ErrorFormat::Human,
+        }
+    }
+
+    pub fn with_format(mut self, format: ErrorFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Renders `diagnostic` as a multi-line report. The snippet is left out
+    /// when the source is not available (e.g. precompiled or piped programs)
+    /// and the location line when there is no path (e.g. the REPL).
+    pub fn render(
+        &self,
+        diagnostic: &Diagnostic,
+        source: Option<&str>,
+        path: Option<&str>,
+    ) -> String {
+        if self.format == ErrorFormat::Json {
+            return render_json(diagnostic, path);
+        }
+        let accent = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let mut out = format!(
+            "{}: {}\n",
+            self.paint(accent, &format!("{}[{}]", diagnostic.kind, diagnostic.code)),
+            self.paint(BOLD, &diagnostic.msg)
+        );
+
+        let line = diagnostic
+            .span
+            .and_then(|span| Some((span, source?.lines().nth(span.line.checked_sub(1)?)?)));
+        let gutter = match diagnostic.span {
+            Some(span) => " ".repeat(span.line.to_string().len()),
+            None => String::new(),
+        };
+
+        if let (Some(span), Some(path)) = (diagnostic.span, path) {
+            let _ = writeln!(out, "{gutter}{} {path}:{span}", self.paint(BLUE, "-->"));
+        }
+
+        if let Some((span, text)) = line {
+            // Reuse the line's own tabs so the caret lines up with the text.
+            let indent = text
+                .chars()
+                .take(span.col.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            let bar = self.paint(BLUE, "|");
+            let _ = writeln!(out, "{gutter} {bar}");
+            let _ = writeln!(
+                out,
+                "{} {bar} {text}",
+                self.paint(BLUE, &span.line.to_string())
+            );
+            let _ = writeln!(out, "{gutter} {bar} {indent}{}", self.paint(accent, "^"));
+        }
+
+        // runaway recursion repeats the same call hundreds of times
+        let mut frames = diagnostic.trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            let mut repeated = 0;
+            while frames
+                .next_if(|next| next.to_string() == frame.to_string())
+                .is_some()
+            {
+                repeated += 1;
+            }
+            let _ = writeln!(out, "{gutter} {} {frame}", self.paint(BLUE, "="));
+            if repeated > 0 {
+                let repeated = msg!("trace.repeated", count = repeated);
+                let _ = writeln!(out, "{gutter} {} {repeated}", self.paint(BLUE, "="));
+            }
+        }
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "{gutter} {} {note}", self.paint(BLUE, "="));
+        }
+        if let Some(help) = &diagnostic.help {
+            let _ = writeln!(
+                out,
+                "{gutter} {} {}: {help}",
+                self.paint(BLUE, "="),
+                self.paint(BOLD, &msg!("diagnostic.help"))
+            );
+        }
+        out
+    }
+}
+
+/// Renders `diagnostic` as a single line of JSON.
+fn render_json(diagnostic: &Diagnostic, path: Option<&str>) -> String {
+    let record = JsonDiagnostic {
+        severity: diagnostic.severity,
+        code: diagnostic.code,
+        kind: diagnostic
+            .kind
+            .trim_start_matches('[')
+            .trim_end_matches(']'),
+        message: &diagnostic.msg,
+        file: path,
+        span: diagnostic.span,
+        trace: &diagnostic.trace,
+        notes: &diagnostic.notes,
+        help: diagnostic.help.as_deref(),
+    };
+    let mut out = serde_json::to_string(&record).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_points_at_the_span() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            kind: "[Type Error]".to_string(),
+            code: "E011",
+            msg: "bad operands".to_string(),
+            span: Some(Span {
+                line: 2,
+                col: 7,
+                offset: 0,
+            }),
+            trace: vec![Frame {
+                function: "f".to_string(),
+                call_site: Span {
+                    line: 4,
+                    col: 1,
+                    offset: 30,
+                },
+            }],
+            notes: Vec::new(),
+            help: None,
+        }
+        .with_help(Some("check the types"));
+        let source = "let x = 1\nprint(x + \"s\")\n";
+
+        let rendered = Renderer::new(false).render(&diagnostic, Some(source), Some("main.fl"));
+        assert_eq!(
+            rendered,
+            "[Type Error][E011]: bad operands\n \
+             --> main.fl:2:7\n  \
+             |\n\
+             2 | print(x + \"s\")\n  \
+             |       ^\n  \
+             = in 'f' called at 4:1\n  \
+             = help: check the types\n"
+        );
+
+        let json = Renderer::new(false).with_format(ErrorFormat::Json).render(
+            &diagnostic,
+            Some(source),
+            Some("main.fl"),
+        );
+        assert_eq!(
+            json,
+            "{\"severity\":\"error\",\"code\":\"E011\",\"kind\":\"Type Error\",\
+             \"message\":\"bad operands\",\"file\":\"main.fl\",\
+             \"span\":{\"line\":2,\"col\":7,\"offset\":0},\
+             \"trace\":[{\"function\":\"f\",\"call_site\":{\"line\":4,\"col\":1,\"offset\":30}}],\
+             \"notes\":[],\"help\":\"check the types\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_warnings_that_fail_the_run_are_labelled_as_errors() {
+        use crate::frontend::warnings::WarningKind;
+
+        let warning = Warning {
+            kind: WarningKind::UnusedVariable,
+            msg: "variable 'x' is never used".to_string(),
+            span: Span {
+                line: 1,
+                col: 1,
+                offset: 0,
+            },
+        };
+        let diagnostic = Diagnostic::from(&warning).into_error();
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(
+            diagnostic.to_string(),
+            "[Error][W001] at 1:1: variable 'x' is never used"
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Long-form descriptions of the error and warning codes shown next to
+//! diagnostics, printed by `filipe explain <code>`.
+
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub text: &'static str,
+}
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E001",
+        title: "Syntax Error",
+        text: r#"The source does not follow filipe's grammar, so the program never starts
+running. The caret points at the token where the parser gave up.
+
+Erroneous example:
+
+    let = 2
+
+A `let` needs a name before the `=`:
+
+    let x = 2
+"#,
+    },
+    Explanation {
+        code: "E010",
+        title: "Name Error",
+        text: r#"A name was used that is not declared in any enclosing scope, was declared
+twice in the same scope, or cannot be assigned to. A redeclaration also
+points at the first declaration; inner scopes may reuse a name from an
+enclosing one.
+
+Erroneous example:
+
+    let total = 1
+    print(totl)
+
+Check the spelling, or declare the name with `let` before using it:
+
+    print(total)
+"#,
+    },
+    Explanation {
+        code: "E011",
+        title: "Type Error",
+        text: r#"A value of the wrong type was used: an operator got operands it does not
+support, a variable or parameter got a value that does not match its
+annotation, or a function returned something other than its declared type.
+
+Erroneous example:
+
+    let age: int = 30
+    print(age + " years")
+
+Only values of compatible types can be combined. Keep both sides the same
+type, or change the annotation to the type you actually store.
+"#,
+    },
+    Explanation {
+        code: "E012",
+        title: "Argument Error",
+        text: r#"A built-in function was called with the wrong number or kind of arguments.
+
+Erroneous example:
+
+    iter("a", "b")
+
+`iter` takes exactly one argument:
+
+    iter("a")
+"#,
+    },
+    Explanation {
+        code: "E013",
+        title: "Value Error",
+        text: r#"A value had the right type but was out of range for the operation, like a
+negative or too large array index, or bounds passed to `random` in the
+wrong order.
+
+Erroneous example:
+
+    let xs = [1, 2, 3]
+    print(xs[3])
+
+Indexes start at 0, so the last item of `xs` is `xs[2]`.
+"#,
+    },
+    Explanation {
+        code: "E014",
+        title: "Execution Limit Exceeded",
+        text: r#"The script executed more statements and loop iterations than
+`--max-steps` allows, or its function calls nested deeper than
+`--max-call-depth` or the runtime running it allows. This usually means a loop or recursion that never ends.
+
+Erroneous example (with `--max-steps 1000`):
+
+    define forever(): int {
+        return forever()
+    }
+    forever()
+
+Make sure the loop or recursion has an exit, or raise the limit.
+"#,
+    },
+    Explanation {
+        code: "E015",
+        title: "Memory Limit Exceeded",
+        text: r#"Live strings, arrays and what sets, deques, channels and mutexes hold
+grew past the size set with `--max-memory`.
+
+Erroneous example (with `--max-memory 1K`):
+
+    let s = "x"
+    for i in range(0, 20) {
+        s = s + s
+    }
+
+Build smaller values, or raise the limit.
+"#,
+    },
+    Explanation {
+        code: "E016",
+        title: "Overflow Error",
+        text: r#"Integer arithmetic produced a result that does not fit in a 64-bit `int`.
+
+Erroneous example:
+
+    let big: int = 9223372036854775807
+    print(big + 1)
+
+Use a `float` for values this large, or run with `--wrapping` to have
+integer arithmetic wrap around instead.
+"#,
+    },
+    Explanation {
+        code: "E017",
+        title: "Division By Zero",
+        text: r#"An integer was divided by zero, or the remainder of a division by zero was
+taken. This is an error even with `--wrapping`.
+
+Erroneous example:
+
+    let count = 0
+    print(10 / count)
+
+Check the divisor before dividing.
+"#,
+    },
+    Explanation {
+        code: "E018",
+        title: "Raised Error",
+        text: r#"A script raised an error of its own kind with `raise` and no `catch`
+handled it. The kind in brackets is the one given to `error`.
+
+Erroneous example:
+
+    raise(error("InvalidAge", "age can't be negative"))
+
+Wrap the code that may fail in `try`/`catch` to handle it:
+
+    try {
+        raise(error("InvalidAge", "age can't be negative"))
+    } catch e {
+        print(errorKind(e), ": ", errorMessage(e))
+    }
+
+Any runtime error except the execution and memory limits can be caught.
+"#,
+    },
+    Explanation {
+        code: "E019",
+        title: "Assertion Error",
+        text: r#"An `assert` found its condition false. The message shows the condition and,
+for a comparison, what each side evaluated to.
+
+Erroneous example:
+
+    let items = [1, 2, 3]
+    let x = 10
+    assert x < len(items)
+
+fails with `assertion failed: x < len(items) (x = 10, len(items) = 3)`. Fix
+the code that produced the unexpected values, or the assertion if it was
+wrong.
+"#,
+    },
+    Explanation {
+        code: "E020",
+        title: "Import Error",
+        text: r#"An `import` couldn't load its module: no file was found, it couldn't be
+read, or it has a syntax error. Errors raised while the module runs point at
+the `import`, with a note saying where in the module they happened.
+
+A name that is a path is looked up next to the importing script, with or
+without `.fl`:
+
+    import "./shapes"
+
+Any other name is a package, looked up in `fl_modules/` in the script's
+directory or the nearest one above it, and run from the `entry` of its
+`filipe.toml`, or its `main.fl`:
+
+    import "greetings"
+
+fails until the package is added with `filipe add <git-url>`, or restored
+from `filipe.lock` with `filipe install`.
+
+A plugin given with `--plugin <path>` raises it too when the library can't
+be loaded, doesn't export a plugin, or was built against another version
+of filipe; rebuild it against the one running it.
+"#,
+    },
+    Explanation {
+        code: "E021",
+        title: "Permission Error",
+        text: r#"A script did something its runtime doesn't permit. Projects turn
+permissions off in the `[permissions]` table of their `filipe.toml`:
+
+    [permissions]
+    exit = false
+    import = false
+
+With these, calling `exit` and running an `import` both raise this error,
+which can be caught like other errors. `timers = false` does the same for
+`setTimeout` and `setInterval`. Importing scripts reads them from
+disk, so `fs = false` turns off imports too. Otherwise `fs`, `net`,
+`process` and `env` are checked by the native functions of programs
+embedding filipe, as no builtin uses files, the network, other programs or
+environment variables. Those programs pick the permissions of each
+runtime with `Runtime::set_permissions`, and
+`RuntimePermissions::sandboxed()` turns all of them off. Builds without
+the `fs` cargo feature never permit `fs`, nor imports of scripts.
+
+Expressions evaluated with `Runtime::eval_expression` raise it too when
+they would assign a variable or call a builtin that does more than
+compute a value, like `print` or `random`.
+"#,
+    },
+    Explanation {
+        code: "E022",
+        title: "Exit",
+        text: r#"A script called `exit`, which stops it wherever it is: `try` doesn't
+catch it.
+
+    exit(3)
+
+`filipe run` then ends with the exit code, from 0 to 255 and 0 when none
+is given. A program embedding filipe keeps running and gets this error
+back from the runtime, with the code in `ErrorKind::Exit`.
+"#,
+    },
+    Explanation {
+        code: "E023",
+        title: "Interrupted",
+        text: r#"The program was stopped from outside before it finished: Ctrl-C
+was pressed while `filipe run` or the REPL was running it, or a program
+embedding filipe triggered the runtime's `InterruptHandle`. It stops at
+the next statement, and `try` doesn't catch it.
+
+Pressing Ctrl-C again while the program doesn't reach a statement, e.g.
+while it waits in the debugger, ends the process.
+"#,
+    },
+    Explanation {
+        code: "W001",
+        title: "Unused Variable",
+        text: r#"A variable is declared with `let` but never read or assigned afterwards.
+
+Example:
+
+    let limit = 10
+    print("done")
+
+Remove the declaration, or use the variable where it was meant to be used.
+Silence all warnings with `--warnings=off`.
+"#,
+    },
+    Explanation {
+        code: "W002",
+        title: "Unused Parameter",
+        text: r#"A function never uses one of its parameters.
+
+Example:
+
+    define greet(name: string): void {
+        print("Hello!")
+    }
+
+Remove the parameter, or use it in the body:
+
+    define greet(name: string): void {
+        print("Hello, ", name)
+    }
+"#,
+    },
+    Explanation {
+        code: "W003",
+        title: "Unreachable Code",
+        text: r#"A statement comes after a `return` in the same block, so it can never run.
+
+Example:
+
+    define double(x: int): int {
+        return x * 2
+        print("doubled")
+    }
+
+Move the statement before the `return`, or remove it.
+"#,
+    },
+    Explanation {
+        code: "W004",
+        title: "Constant Condition",
+        text: r#"An `if` condition is made only of literals, so it always takes the same
+branch.
+
+Example:
+
+    if 1 > 2 {
+        print("never printed")
+    }
+
+Compare against a variable instead, or remove the branch that can't run.
+"#,
+    },
+    Explanation {
+        code: "W005",
+        title: "Shadowing",
+        text: r#"A `let`, parameter, loop variable or `catch` binding has the same name as
+one in an enclosing scope, which can't be used until the inner scope ends.
+Only reported with `--warn-shadowing`.
+
+Example:
+
+    let total = 0
+    for price in [3, 4] {
+        let total = total + price
+    }
+    print(total)
+
+prints 0. Assign instead of declaring to update the outer variable:
+
+    total = total + price
+
+Declaring a name twice in the same scope is an error (E010), not a warning.
+"#,
+    },
+    Explanation {
+        code: "W006",
+        title: "Shadowed Builtin",
+        text: r#"A parameter, loop variable, `catch` binding or a `let` inside a block has
+the name of a builtin, which can't be called where the binding is visible.
+Only `filipe lint` reports it, as the `shadowed-builtin` rule; declaring a
+builtin's name globally is an error (E010).
+
+Example:
+
+    define total(len: int): int {
+        return len([1, 2]) * len
+    }
+
+fails, because `len` is an int in the function. Pick another name:
+
+    define total(size: int): int {
+"#,
+    },
+    Explanation {
+        code: "W007",
+        title: "Deep Nesting",
+        text: r#"A block is nested more levels deep than the project allows, 4 unless
+`maxDepth` in `filipe-lint.json` says otherwise. Function bodies, `if`,
+`else`, `for`, `try` and `catch` blocks each add a level. Only `filipe lint`
+reports it, as the `deep-nesting` rule.
+
+Move the inner blocks into a function of their own, or return early instead
+of wrapping the rest of a function in an `if`.
+"#,
+    },
+    Explanation {
+        code: "W008",
+        title: "Naming",
+        text: r#"A variable, function or parameter name starts with an uppercase letter.
+Names in filipe are camelCase, like `totalPrice`. Only `filipe lint` reports
+it, as the `naming` rule.
+
+Example:
+
+    let TotalPrice = 10
+
+Rename it:
+
+    let totalPrice = 10
+"#,
+    },
+];
+
+/// Looks up a code, ignoring case (`e011` finds `E011`).
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::parser::ParserErrorKind;
+    use crate::frontend::warnings::WarningKind;
+    use crate::runtime::runtime_error::ErrorKind;
+
+    #[test]
+    fn test_every_code_is_explained() {
+        let codes = [
+            ParserErrorKind::SyntaxError.code(),
+            ErrorKind::NameError.code(),
+            ErrorKind::TypeError.code(),
+            ErrorKind::ArgumentError.code(),
+            ErrorKind::ValueError.code(),
+            ErrorKind::ExecutionLimitExceeded.code(),
+            ErrorKind::MemoryLimitExceeded.code(),
+            ErrorKind::OverflowError.code(),
+            ErrorKind::DivisionByZero.code(),
+            ErrorKind::Custom("InvalidAge".to_string()).code(),
+            ErrorKind::AssertionError.code(),
+            ErrorKind::ImportError.code(),
+            ErrorKind::PermissionError.code(),
+            ErrorKind::Exit(0).code(),
+            ErrorKind::Interrupted.code(),
+            WarningKind::UnusedVariable.code(),
+            WarningKind::UnusedParameter.code(),
+            WarningKind::UnreachableCode.code(),
+            WarningKind::ConstantCondition.code(),
+            WarningKind::Shadowing.code(),
+            WarningKind::ShadowedBuiltin.code(),
+            WarningKind::DeepNesting.code(),
+            WarningKind::Naming.code(),
+        ];
+        for code in codes {
+            assert!(explain(code).is_some(), "{code} has no explanation");
+        }
+        assert_eq!(codes.len(), EXPLANATIONS.len());
+        assert_eq!(explain("e016").unwrap().title, "Overflow Error");
+    }
+}
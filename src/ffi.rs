@@ -0,0 +1,306 @@
+//! A C interface to the runtime, with the `ffi` feature, so programs in
+//! C, C++, Go or any language that can call C can embed filipe. The
+//! declarations are in `include/filipe.h`:
+//!
+//! ```c
+//! FilipeRuntime *runtime = filipe_new_runtime();
+//! FilipeValue *value = filipe_run(runtime, "let x = 20\nx * 2 + 2");
+//! if (value == NULL) {
+//!     fprintf(stderr, "%s\n", filipe_get_error(runtime));
+//! } else {
+//!     printf("%lld\n", (long long)filipe_value_as_int(value));
+//!     filipe_free_value(value);
+//! }
+//! filipe_free_runtime(runtime);
+//! ```
+//!
+//! Every pointer handed out is owned by the caller and freed with the
+//! `filipe_free_*` function of its type, except the error message, which
+//! the runtime keeps until its next run.
+
+use std::any::Any;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::runtime::flstdlib::to_text;
+use crate::runtime::object::{Object, Value};
+use crate::runtime::Runtime;
+
+pub const FILIPE_NULL: c_int = 0;
+pub const FILIPE_INT: c_int = 1;
+pub const FILIPE_FLOAT: c_int = 2;
+pub const FILIPE_BOOL: c_int = 3;
+pub const FILIPE_STRING: c_int = 4;
+pub const FILIPE_ARRAY: c_int = 5;
+/// Functions, ranges, errors and the other values C has no use for.
+pub const FILIPE_OTHER: c_int = 6;
+
+pub struct FilipeRuntime {
+    runtime: Runtime,
+    /// Message of the error the last run ended with.
+    error: Option<CString>,
+}
+
+pub struct FilipeValue(Value);
+
+/// A runtime with the builtins and nothing limited.
+#[no_mangle]
+pub extern "C" fn filipe_new_runtime() -> *mut FilipeRuntime {
+    Box::into_raw(Box::new(FilipeRuntime {
+        runtime: Runtime::builder().build(),
+        error: None,
+    }))
+}
+
+/// # Safety
+///
+/// `runtime` must come from `filipe_new_runtime` and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_free_runtime(runtime: *mut FilipeRuntime) {
+    if !runtime.is_null() {
+        drop(Box::from_raw(runtime));
+    }
+}
+
+/// Runs `source`, UTF-8 ending in a nul byte, and returns the value of its
+/// last statement, or NULL when it fails; `filipe_get_error` then says
+/// why. A NULL `source` fails too, and so does a panic, which can't unwind
+/// into C: the runtime then starts over without the globals it had.
+///
+/// # Safety
+///
+/// `runtime` must come from `filipe_new_runtime` and `source`, unless
+/// NULL, must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_run(
+    runtime: *mut FilipeRuntime,
+    source: *const c_char,
+) -> *mut FilipeValue {
+    let Some(runtime) = runtime.as_mut() else {
+        return ptr::null_mut();
+    };
+    if source.is_null() {
+        runtime.error = CString::new("the source is NULL").ok();
+        return ptr::null_mut();
+    }
+    let result = match CStr::from_ptr(source).to_str() {
+        Ok(source) => {
+            let run = panic::catch_unwind(AssertUnwindSafe(|| runtime.runtime.run_source(source)));
+            match run {
+                Ok(result) => result.map_err(|err| err.to_string()),
+                Err(payload) => {
+                    runtime.runtime = Runtime::builder().build();
+                    Err(format!("filipe panicked: {}", panic_message(&*payload)))
+                }
+            }
+        }
+        Err(err) => Err(err.to_string()),
+    };
+    match result {
+        Ok(value) => {
+            runtime.error = None;
+            Box::into_raw(Box::new(FilipeValue(value)))
+        }
+        Err(msg) => {
+            runtime.error = CString::new(msg).ok();
+            ptr::null_mut()
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => msg,
+        None => payload.downcast_ref::<String>().map_or("", String::as_str),
+    }
+}
+
+/// The error the last `filipe_run` failed with, or NULL if it didn't.
+///
+/// # Safety
+///
+/// `runtime` must come from `filipe_new_runtime`.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_get_error(runtime: *const FilipeRuntime) -> *const c_char {
+    runtime
+        .as_ref()
+        .and_then(|runtime| runtime.error.as_ref())
+        .map_or(ptr::null(), |error| error.as_ptr())
+}
+
+/// # Safety
+///
+/// `value` must come from this library and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_free_value(value: *mut FilipeValue) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// One of the `FILIPE_*` type constants.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_value_type(value: *const FilipeValue) -> c_int {
+    match value.as_ref().map(|value| &value.0) {
+        None | Some(Object::Null) => FILIPE_NULL,
+        Some(Object::Int(_)) => FILIPE_INT,
+        Some(Object::Float(_)) => FILIPE_FLOAT,
+        Some(Object::Boolean(_)) => FILIPE_BOOL,
+        Some(Object::String(_)) => FILIPE_STRING,
+        Some(Object::Array(_)) => FILIPE_ARRAY,
+        Some(_) => FILIPE_OTHER,
+    }
+}
+
+/// The int, or 0 for other values.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_value_as_int(value: *const FilipeValue) -> i64 {
+    match value.as_ref().map(|value| &value.0) {
+        Some(Object::Int(val)) => *val,
+        _ => 0,
+    }
+}
+
+/// The float, an int converted to one, or 0 for other values.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_value_as_float(value: *const FilipeValue) -> f64 {
+    match value.as_ref().map(|value| &value.0) {
+        Some(Object::Float(val)) => *val,
+        Some(Object::Int(val)) => *val as f64,
+        _ => 0.0,
+    }
+}
+
+/// The boolean, or false for other values.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_value_as_bool(value: *const FilipeValue) -> bool {
+    matches!(
+        value.as_ref().map(|value| &value.0),
+        Some(Object::Boolean(true))
+    )
+}
+
+/// The value as `print` writes it, to free with `filipe_free_string`.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_value_to_string(value: *const FilipeValue) -> *mut c_char {
+    let text = value
+        .as_ref()
+        .map_or("null".to_string(), |value| to_text(&value.0));
+    // a nul byte in the text ends it early in C anyway
+    let text = text.split('\0').next().unwrap_or_default();
+    CString::new(text).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// # Safety
+///
+/// `string` must come from `filipe_value_to_string` and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// How many items an array has, or 0 for other values.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_array_len(value: *const FilipeValue) -> usize {
+    match value.as_ref().map(|value| &value.0) {
+        Some(Object::Array(items)) => items.len(),
+        _ => 0,
+    }
+}
+
+/// The item of an array at `index`, or NULL when there is none.
+///
+/// # Safety
+///
+/// `value` must come from this library.
+#[no_mangle]
+pub unsafe extern "C" fn filipe_array_get(
+    value: *const FilipeValue,
+    index: usize,
+) -> *mut FilipeValue {
+    match value.as_ref().map(|value| &value.0) {
+        Some(Object::Array(items)) => items.get(index).map_or(ptr::null_mut(), |item| {
+            Box::into_raw(Box::new(FilipeValue(item.clone())))
+        }),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_through_the_c_interface() {
+        unsafe {
+            let runtime = filipe_new_runtime();
+            let source = CString::new("let xs = [1, 2, 3]\nxs").unwrap();
+            let value = filipe_run(runtime, source.as_ptr());
+            assert_eq!(filipe_value_type(value), FILIPE_ARRAY);
+            assert_eq!(filipe_array_len(value), 3);
+            let item = filipe_array_get(value, 2);
+            assert_eq!(filipe_value_as_int(item), 3);
+            let text = filipe_value_to_string(value);
+            assert_eq!(CStr::from_ptr(text).to_str(), Ok("[1, 2, 3]"));
+            filipe_free_string(text);
+            filipe_free_value(item);
+            filipe_free_value(value);
+
+            let source = CString::new("xs + 1").unwrap();
+            assert!(filipe_run(runtime, source.as_ptr()).is_null());
+            let error = CStr::from_ptr(filipe_get_error(runtime));
+            assert!(error.to_str().unwrap().contains("E011"));
+            filipe_free_runtime(runtime);
+        }
+    }
+
+    #[test]
+    fn test_bad_runs_fail_instead_of_crossing_into_c() {
+        unsafe {
+            let runtime = filipe_new_runtime();
+            assert!(filipe_run(runtime, ptr::null()).is_null());
+            let error = CStr::from_ptr(filipe_get_error(runtime));
+            assert_eq!(error.to_str(), Ok("the source is NULL"));
+
+            (*runtime)
+                .runtime
+                .register_fn("broken", |_| panic!("out of order"));
+            let source = CString::new("broken()").unwrap();
+            assert!(filipe_run(runtime, source.as_ptr()).is_null());
+            let error = CStr::from_ptr(filipe_get_error(runtime));
+            assert_eq!(error.to_str(), Ok("filipe panicked: out of order"));
+            let source = CString::new("1 + 1").unwrap();
+            let value = filipe_run(runtime, source.as_ptr());
+            assert_eq!(filipe_value_as_int(value), 2);
+            filipe_free_value(value);
+            filipe_free_runtime(runtime);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Precompiled programs (`.flbc` files).
+//!
+//! A chunk is the resolved AST of a script encoded with bincode, behind a
+//! small header: the `FLBC` magic followed by the format version as a
+//! little-endian `u16`. Bump `FORMAT_VERSION` whenever the AST changes shape
+//! so stale chunks are rejected instead of misread.
+
+use crate::frontend::ast::Program;
+
+pub const EXTENSION: &str = "flbc";
+
+const MAGIC: &[u8; 4] = b"FLBC";
+const FORMAT_VERSION: u16 = 13;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+pub fn is_flbc(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn encode(program: &Program) -> Vec<u8> {
+    let mut chunk = Vec::from(&MAGIC[..]);
+    chunk.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    chunk.extend(bincode::serialize(program).expect("AST is always serializable"));
+    chunk
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Program, String> {
+    if !is_flbc(bytes) || bytes.len() < HEADER_LEN {
+        return Err("Not a precompiled filipe program".to_string());
+    }
+
+    let version = u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "Precompiled program has format version {} but this interpreter expects {}, recompile it",
+            version, FORMAT_VERSION
+        ));
+    }
+
+    bincode::deserialize(&bytes[HEADER_LEN..])
+        .map_err(|err| format!("Corrupted precompiled program: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, FORMAT_VERSION};
+    use crate::frontend::ast::StmtKind;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_chunk_roundtrip_and_version_check() {
+        let input = "let xs = [1, 2, 3]\nprint(len(xs))"
+            .chars()
+            .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+
+        let mut chunk = encode(&program);
+        let decoded = decode(&chunk).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert!(matches!(decoded[0].kind, StmtKind::Let(..)));
+
+        chunk[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(decode(&chunk).is_err());
+        assert!(decode(b"let x = 1").is_err());
+    }
+}
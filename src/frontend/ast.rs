@@ -1,9 +1,14 @@
 use core::fmt;
 
+use serde::{Deserialize, Serialize};
+
+use super::token::Span;
+use crate::shared::Shared;
+
 pub type Program = Vec<Stmt>;
 pub type BlockStmt = Vec<Stmt>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ExprType {
     Int,
     Void,
@@ -11,12 +16,23 @@ pub enum ExprType {
     String,
     Boolean,
     Array(Box<ExprType>),
+    Set,
+    /// `int | string`: a value of any of the types.
+    Union(Vec<ExprType>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Identifier(pub String);
 
-#[derive(Debug, Clone)]
+/// Position of a local in the runtime frames: `depth` scopes up from the
+/// current one, at `index` in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Literal {
     Null,
     Int(i64),
@@ -26,22 +42,66 @@ pub enum Literal {
     Array(Vec<Expr>),
 }
 
-#[derive(Debug, Clone)]
-pub enum Expr {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Expr {
+    pub kind: ExprKind,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ExprKind {
     Literal(Literal),
     Call(Box<Expr>, Vec<Expr>),
     Identifier(Identifier),
+    Local(Identifier, Slot),
     Infix(Box<Expr>, Infix, Box<Expr>),
     Prefix(Prefix, Box<Expr>),
     Postfix(Box<Expr>, Postfix),
     Assign(Identifier, Box<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    /// `xs[i] = value`
+    IndexAssign(Identifier, Box<Expr>, Box<Expr>),
+    /// `await task`, see `runtime::tasks`.
+    Await(Box<Expr>),
+    /// `value.name(args)`, which calls `name` with `value` before `args`.
+    /// When optional, `value?.name(args)`, it is `null` for a `null` value,
+    /// without evaluating `args`.
+    Method(Box<Expr>, Identifier, Vec<Expr>, bool),
+    /// `value as float`, converting the value to one of the types the
+    /// parser allows there, see `runtime::casts`.
+    Cast(Box<Expr>, ExprType),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    #[serde(default)]
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
-pub enum Stmt {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StmtKind {
     Expr(Expr),
     Let(Identifier, Option<ExprType>, Option<Expr>),
-    Func(Identifier, Vec<(Identifier, ExprType)>, BlockStmt, ExprType),
+    /// The body is shared with every function object created from it. The
+    /// return type is `None` when it was left out for the checker to infer.
+    /// The fifth field is the text of the `///` lines right above the
+    /// function, the last whether it was declared `async define`.
+    Func(
+        Identifier,
+        Vec<(Identifier, ExprType)>,
+        Shared<BlockStmt>,
+        Option<ExprType>,
+        Option<String>,
+        bool,
+    ),
     Return(Option<Expr>),
     If {
         condition: Expr,
@@ -53,6 +113,16 @@ pub enum Stmt {
         iterable: Expr,
         block: BlockStmt,
     },
+    /// `try { block } catch binding { handler }`
+    Try {
+        block: BlockStmt,
+        binding: String,
+        handler: BlockStmt,
+    },
+    /// `assert condition`
+    Assert(Expr),
+    /// `import "name"`, only at the top level, see `runtime::modules`.
+    Import(String),
 }
 
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
@@ -62,12 +132,13 @@ pub enum Precedence {
     Comparison, // x > 6
     Sum,        // +
     Product,    // *
+    Cast,       // x as float
     Prefix,     // !true || -5
     Postfix,    // 69++ || 10--
-    Call,       // myFunction(x)
+    Call,       // myFunction(x) || xs[0] || xs.f()
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Infix {
     Plus,
     Minus,
@@ -80,21 +151,111 @@ pub enum Infix {
     LessOrEqual,
     GratherThan,
     GratherOrEqual,
+    /// `item in collection`
+    In,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Prefix {
     Not,
     Plus,
     Minus,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Postfix {
     Increment,
     Decrement,
 }
 
+/// Writes the expression back as source, e.g. for error messages.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Literal(literal) => write!(f, "{}", literal),
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                write!(f, "{}", name)
+            }
+            ExprKind::Call(func, args) => {
+                write!(f, "{}(", func)?;
+                write_list(f, args)?;
+                write!(f, ")")
+            }
+            ExprKind::Infix(lhs, infix, rhs) => write!(f, "{} {} {}", lhs, infix, rhs),
+            ExprKind::Prefix(prefix, expr) => {
+                let operand = expr.to_string();
+                // `- -x`, since `--x` would be read back as a decrement
+                if operand.starts_with(&prefix.to_string()) && !matches!(prefix, Prefix::Not) {
+                    write!(f, "{} {}", prefix, operand)
+                } else {
+                    write!(f, "{}{}", prefix, operand)
+                }
+            }
+            ExprKind::Postfix(expr, postfix) => write!(f, "{}{}", expr, postfix),
+            ExprKind::Assign(Identifier(name), expr) => write!(f, "{} = {}", name, expr),
+            ExprKind::Index(target, index) => write!(f, "{}[{}]", target, index),
+            ExprKind::IndexAssign(Identifier(name), index, expr) => {
+                write!(f, "{}[{}] = {}", name, index, expr)
+            }
+            ExprKind::Await(expr) => write!(f, "await {}", expr),
+            ExprKind::Method(receiver, Identifier(name), args, optional) => {
+                let dot = if *optional { "?." } else { "." };
+                write!(f, "{}{}{}(", receiver, dot, name)?;
+                write_list(f, args)?;
+                write!(f, ")")
+            }
+            ExprKind::Cast(expr, type_) => write!(f, "{} as {}", expr, type_),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null => write!(f, "null"),
+            Literal::Int(val) => write!(f, "{}", val),
+            // never in exponent form, which the lexer can't read back
+            Literal::Float(val) if val.fract() == 0.0 => write!(f, "{:.1}", val),
+            Literal::Float(val) => write!(f, "{}", val),
+            Literal::String(val) => write!(f, "\"{}\"", val),
+            Literal::Boolean(val) => write!(f, "{}", val),
+            Literal::Array(items) => {
+                write!(f, "[")?;
+                write_list(f, items)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, exprs: &[Expr]) -> fmt::Result {
+    for (index, expr) in exprs.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", expr)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ExprType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int => write!(f, "int"),
+            Self::Void => write!(f, "void"),
+            Self::Float => write!(f, "float"),
+            Self::String => write!(f, "string"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Array(items_type) => write!(f, "Array<{}>", items_type),
+            Self::Set => write!(f, "Set"),
+            Self::Union(members) => {
+                let members = members.iter().map(|member| member.to_string());
+                write!(f, "{}", members.collect::<Vec<_>>().join(" | "))
+            }
+        }
+    }
+}
+
 impl fmt::Display for Postfix {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -128,6 +289,7 @@ impl fmt::Display for Infix {
             Infix::GratherThan => write!(f, ">"),
             Infix::GratherOrEqual => write!(f, ">="),
             Infix::Remainder => write!(f, "%"),
+            Infix::In => write!(f, "in"),
         }
     }
 }
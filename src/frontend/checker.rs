@@ -0,0 +1,1609 @@
+//! Static checks run by `filipe check`: type mismatches against the declared
+//! annotations, wrong argument counts, calls to things that aren't functions
+//! and names used before they are declared, all found without running the
+//! program.
+//!
+//! The return type of a `define` can be left out, and so can the type of a
+//! variable declared with `[]`: the checker infers the first from the
+//! values the function returns and the second from the first use that
+//! tells the type of the items, like assigning an array or passing it to a
+//! function. `annotate` writes what it inferred in the program before it
+//! runs, so it runs as if the types had been written.
+
+use std::collections::{HashMap, HashSet};
+
+use super::ast::*;
+use super::token::Span;
+use crate::messages::{count_args, msg};
+use crate::runtime::casts::can_cast;
+use crate::runtime::flstdlib::{builtins, is_builtin};
+use crate::runtime::object::{Object, ObjectInfo};
+use crate::runtime::runtime_error::ErrorKind;
+use crate::runtime::type_system::{expr_type_to_object_type, object_type_to_expr_type, Type};
+use crate::shared::Shared;
+use crate::utils::closest_name;
+
+/// Argument counts (`None` when there is no maximum) and return types of the
+/// builtins. A `None` return type means it depends on the arguments, see
+/// `Checker::generic_return`.
+const BUILTIN_SIGNATURES: &[(&str, usize, Option<usize>, Option<Type>)] = &[
+    ("print", 0, None, Some(Type::Null)),
+    ("exit", 0, Some(1), Some(Type::Null)),
+    ("len", 1, Some(1), Some(Type::Int)),
+    ("random", 0, Some(2), None),
+    ("typeof", 1, Some(1), Some(Type::TypeAnnot)),
+    ("range", 2, Some(3), Some(Type::Range)),
+    ("iter", 1, Some(1), None),
+    ("next", 1, Some(1), None),
+    ("map", 2, Some(2), None),
+    ("filter", 2, Some(2), None),
+    ("take", 2, Some(2), None),
+    ("collect", 1, Some(1), None),
+    ("set", 0, Some(1), Some(Type::Set)),
+    ("union", 2, Some(2), Some(Type::Set)),
+    ("intersection", 2, Some(2), Some(Type::Set)),
+    ("difference", 2, Some(2), Some(Type::Set)),
+    ("error", 2, Some(2), Some(Type::Error)),
+    ("raise", 1, Some(1), Some(Type::Null)),
+    ("errorKind", 1, Some(1), Some(Type::String)),
+    ("errorMessage", 1, Some(1), Some(Type::String)),
+    ("breakpoint", 0, Some(0), Some(Type::Null)),
+    ("arity", 1, Some(1), None),
+    ("methods", 1, Some(1), Some(Type::Array(None))),
+    ("globals", 0, Some(0), Some(Type::Array(None))),
+];
+
+/// The type of what calls to `async` functions return, see `runtime::tasks`.
+const TASK: &str = "task";
+
+/// A problem found before running. Its kind is the one of the runtime error
+/// the program would stop with.
+#[derive(Clone, Debug)]
+pub struct CheckError {
+    pub kind: ErrorKind,
+    pub msg: String,
+    pub span: Span,
+    pub notes: Vec<String>,
+}
+
+#[derive(Clone)]
+enum Symbol {
+    /// `None` type when it can't be known without running the program.
+    Value {
+        type_: Option<Type>,
+        assignable: bool,
+    },
+    Function(Signature),
+}
+
+#[derive(Clone)]
+enum Signature {
+    /// `None` return type while it is being inferred.
+    User {
+        params: Vec<Type>,
+        returns: Option<Type>,
+    },
+    Builtin {
+        min_args: usize,
+        max_args: Option<usize>,
+        returns: Option<Type>,
+    },
+}
+
+/// Checks the whole program and returns every problem found, in source
+/// order.
+pub fn check(program: &Program) -> Vec<CheckError> {
+    analyze(program).errors
+}
+
+/// What checking a program found out.
+pub struct Analysis {
+    /// Every problem found, in source order.
+    pub errors: Vec<CheckError>,
+    /// Type of each `let`, declared or worked out from its value, keyed by
+    /// the offset of the statement. Missing when it can't be known.
+    pub let_types: HashMap<usize, Type>,
+    /// Type of each expression, keyed by its offset, where it can be known.
+    /// A call starts where the name it calls does; the call's type is kept.
+    pub expr_types: HashMap<usize, Type>,
+    /// Return type of each `define`, declared or inferred, keyed by the
+    /// offset of the statement. Missing when it can't be inferred.
+    pub return_types: HashMap<usize, Type>,
+}
+
+/// `check`, also keeping the types of the variables, e.g. for editors to
+/// show.
+pub fn analyze(program: &Program) -> Analysis {
+    analyze_with(program, builtins().into_iter().collect())
+}
+
+fn analyze_with(program: &Program, bindings: Vec<(String, ObjectInfo)>) -> Analysis {
+    let mut checker = Checker::new(bindings);
+    checker.collect_declarations(program, true);
+    checker.check_block(program);
+    checker.report_unsettled();
+    checker.errors.sort_by_key(|error| error.span.offset);
+    Analysis {
+        errors: checker.errors,
+        let_types: checker.let_types,
+        expr_types: checker.expr_types,
+        return_types: checker.return_types,
+    }
+}
+
+/// Whether `program` leaves out a type for the checker to infer.
+pub fn has_missing_annotations(program: &BlockStmt) -> bool {
+    program.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Let(_, None, Some(expr)) => is_empty_array(expr),
+        StmtKind::Func(_, _, body, ret_type, ..) => {
+            ret_type.is_none() || has_missing_annotations(body)
+        }
+        StmtKind::If {
+            consequence,
+            alternative,
+            ..
+        } => {
+            has_missing_annotations(consequence)
+                || alternative.as_ref().is_some_and(has_missing_annotations)
+        }
+        StmtKind::ForLoop { block, .. } => has_missing_annotations(block),
+        StmtKind::Try { block, handler, .. } => {
+            has_missing_annotations(block) || has_missing_annotations(handler)
+        }
+        _ => false,
+    })
+}
+
+/// Writes the types the checker infers for `program` where they were left
+/// out. `bindings` are the names it can use, like the globals of a REPL
+/// session. Those it can't infer stay out, for the runtime to report.
+pub fn annotate(program: &mut Program, bindings: Vec<(String, ObjectInfo)>) {
+    let analysis = analyze_with(program, bindings);
+    fill_annotations(program, &analysis);
+}
+
+fn fill_annotations(block: &mut BlockStmt, analysis: &Analysis) {
+    for stmt in block {
+        let offset = stmt.span.offset;
+        match &mut stmt.kind {
+            StmtKind::Let(_, annotation @ None, Some(expr)) if is_empty_array(expr) => {
+                *annotation = analysis
+                    .let_types
+                    .get(&offset)
+                    .and_then(object_type_to_expr_type);
+            }
+            StmtKind::Func(_, _, body, ret_type, ..) => {
+                if ret_type.is_none() {
+                    *ret_type = analysis
+                        .return_types
+                        .get(&offset)
+                        .and_then(object_type_to_expr_type);
+                }
+                if has_missing_annotations(body) {
+                    fill_annotations(Shared::make_mut(body), analysis);
+                }
+            }
+            StmtKind::If {
+                consequence,
+                alternative,
+                ..
+            } => {
+                fill_annotations(consequence, analysis);
+                if let Some(alternative) = alternative {
+                    fill_annotations(alternative, analysis);
+                }
+            }
+            StmtKind::ForLoop { block, .. } => fill_annotations(block, analysis),
+            StmtKind::Try { block, handler, .. } => {
+                fill_annotations(block, analysis);
+                fill_annotations(handler, analysis);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Type of `expr` when the names it uses are `bindings`, like the globals
+/// of a REPL session. `Ok(None)` when it can't be known without running it.
+pub fn infer_type(
+    expr: &Expr,
+    bindings: Vec<(String, ObjectInfo)>,
+) -> Result<Option<Type>, Vec<CheckError>> {
+    let mut checker = Checker::new(bindings);
+    let type_ = checker.check_expr(expr);
+    if !checker.errors.is_empty() {
+        return Err(checker.errors);
+    }
+    Ok(type_)
+}
+
+/// What the checker knows about a value that already exists.
+fn binding_symbol(name: &str, info: &ObjectInfo) -> Symbol {
+    match &info.value {
+        Object::BuiltInFunction(_) | Object::NativeFunction(_) => {
+            Symbol::Function(builtin_signature(name))
+        }
+        Object::UserDefinedFunction(function) => Symbol::Function(Signature::User {
+            params: function
+                .params
+                .iter()
+                .map(|param| param.type_.clone())
+                .collect(),
+            returns: Some(match function.is_async {
+                true => Type::UserData(TASK),
+                false => function.return_type.clone(),
+            }),
+        }),
+        _ => Symbol::Value {
+            type_: Some(info.type_.clone()),
+            assignable: info.is_assignable,
+        },
+    }
+}
+
+fn builtin_signature(name: &str) -> Signature {
+    let signature = BUILTIN_SIGNATURES
+        .iter()
+        .find(|(builtin, ..)| *builtin == name);
+    match signature {
+        Some((_, min_args, max_args, returns)) => Signature::Builtin {
+            min_args: *min_args,
+            max_args: *max_args,
+            returns: returns.clone(),
+        },
+        None => Signature::Builtin {
+            min_args: 0,
+            max_args: None,
+            returns: None,
+        },
+    }
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Symbol>>,
+    /// Where the `let`s and `define`s of each scope in `scopes` are.
+    declared_at: Vec<HashMap<String, Span>>,
+    /// Name and return type of the function whose body is being checked,
+    /// `None` when it is inferred from `returned`.
+    function: Option<(String, Option<Type>)>,
+    /// Types of the values returned by the function whose return type is
+    /// being inferred, `None` where unknown and `void` for a bare `return`.
+    returned: Vec<Option<Type>>,
+    /// Top-level declarations. A function body runs when it is called, so
+    /// it sees the globals declared after it too.
+    globals: HashMap<String, Symbol>,
+    /// Every name declared anywhere. Functions see their caller's scope, so
+    /// inside a body any of these might be in scope when it runs.
+    declared: HashSet<String>,
+    /// Whether the program imports modules, whose names can't be known
+    /// without loading them.
+    imports: bool,
+    errors: Vec<CheckError>,
+    let_types: HashMap<usize, Type>,
+    expr_types: HashMap<usize, Type>,
+    return_types: HashMap<usize, Type>,
+    /// Variables declared with `[]` and no type whose items' type no use
+    /// told yet, by their scope's index in `scopes` and name: where the
+    /// `let` and the `[]` are.
+    unsettled: HashMap<(usize, String), (usize, Span)>,
+    /// Variables declared with a union type that a `typeof` guard narrowed
+    /// to some of its types where they are used.
+    narrowed: HashMap<String, Type>,
+}
+
+impl Checker {
+    fn new(globals: Vec<(String, ObjectInfo)>) -> Self {
+        let globals = globals
+            .iter()
+            .map(|(name, info)| (name.clone(), binding_symbol(name, info)))
+            .collect();
+        Self {
+            scopes: vec![globals],
+            declared_at: vec![HashMap::new()],
+            function: None,
+            returned: vec![],
+            globals: HashMap::new(),
+            declared: HashSet::new(),
+            imports: false,
+            errors: vec![],
+            let_types: HashMap::new(),
+            expr_types: HashMap::new(),
+            return_types: HashMap::new(),
+            unsettled: HashMap::new(),
+            narrowed: HashMap::new(),
+        }
+    }
+
+    fn collect_declarations(&mut self, block: &BlockStmt, top_level: bool) {
+        for stmt in block {
+            match &stmt.kind {
+                StmtKind::Let(Identifier(name), annotation, _) => {
+                    self.declared.insert(name.clone());
+                    if top_level {
+                        let symbol = Symbol::Value {
+                            type_: annotation.as_ref().map(expr_type_to_object_type),
+                            assignable: true,
+                        };
+                        self.globals.insert(name.clone(), symbol);
+                    }
+                }
+                StmtKind::Func(Identifier(name), params, body, ret_type, _, is_async) => {
+                    self.declared.insert(name.clone());
+                    self.declared
+                        .extend(params.iter().map(|(Identifier(name), _)| name.clone()));
+                    if top_level {
+                        let symbol =
+                            Symbol::Function(user_signature(params, ret_type.as_ref(), *is_async));
+                        self.globals.insert(name.clone(), symbol);
+                    }
+                    self.collect_declarations(body, false);
+                }
+                StmtKind::If {
+                    consequence,
+                    alternative,
+                    ..
+                } => {
+                    self.collect_declarations(consequence, false);
+                    if let Some(alternative) = alternative {
+                        self.collect_declarations(alternative, false);
+                    }
+                }
+                StmtKind::ForLoop { cursor, block, .. } => {
+                    self.declared.insert(cursor.clone());
+                    self.collect_declarations(block, false);
+                }
+                StmtKind::Try {
+                    block,
+                    binding,
+                    handler,
+                } => {
+                    self.declared.insert(binding.clone());
+                    self.collect_declarations(block, false);
+                    self.collect_declarations(handler, false);
+                }
+                StmtKind::Import(_) => self.imports = true,
+                StmtKind::Expr(_) | StmtKind::Return(_) | StmtKind::Assert(_) => {}
+            }
+        }
+    }
+
+    fn check_block(&mut self, block: &BlockStmt) {
+        for stmt in block {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_scoped_block(&mut self, block: &BlockStmt, symbols: HashMap<String, Symbol>) {
+        let narrowed = self.narrowed.clone();
+        self.scopes.push(symbols);
+        self.declared_at.push(HashMap::new());
+        self.check_block(block);
+        self.report_unsettled();
+        self.declared_at.pop();
+        self.scopes.pop();
+        self.narrowed = narrowed;
+    }
+
+    /// `block` with `narrowing`, a variable and the type it has there.
+    fn check_narrowed_block(&mut self, block: &BlockStmt, narrowing: Option<(String, Type)>) {
+        let narrowed = self.narrowed.clone();
+        self.narrowed.extend(narrowing);
+        self.check_scoped_block(block, HashMap::new());
+        self.narrowed = narrowed;
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Assert(expr) => {
+                self.check_expr(expr);
+            }
+            StmtKind::Let(Identifier(name), annotation, expr) => {
+                self.check_let(name, annotation.as_ref(), expr.as_ref(), stmt.span)
+            }
+            StmtKind::Func(Identifier(name), params, body, ret_type, _, is_async) => {
+                let signature = user_signature(params, ret_type.as_ref(), *is_async);
+                self.declare(name, Symbol::Function(signature), stmt.span);
+
+                let mut scope = HashMap::new();
+                for (Identifier(param), type_) in params {
+                    let symbol = Symbol::Value {
+                        type_: Some(expr_type_to_object_type(type_)),
+                        assignable: true,
+                    };
+                    if scope.insert(param.clone(), symbol).is_some() {
+                        self.error(
+                            ErrorKind::NameError,
+                            msg!("E010.param_declared", name = param),
+                            stmt.span,
+                        );
+                    }
+                }
+                let returns = ret_type.as_ref().map(expr_type_to_object_type);
+                if let Some(returns) = &returns {
+                    self.check_returns(name, returns, body, stmt.span);
+                }
+                // the body runs when it is called, where guards may not hold
+                let narrowed = std::mem::take(&mut self.narrowed);
+                let returned = std::mem::take(&mut self.returned);
+                let enclosing = self.function.replace((name.clone(), returns.clone()));
+                self.check_scoped_block(body, scope);
+                self.function = enclosing;
+                self.narrowed = narrowed;
+                let returned = std::mem::replace(&mut self.returned, returned);
+
+                let returns = match returns {
+                    Some(returns) => returns,
+                    None => {
+                        let Some(returns) = inferred_return(&returned) else {
+                            self.error(
+                                ErrorKind::TypeError,
+                                msg!("E011.infer_return", name = name),
+                                stmt.span,
+                            );
+                            return;
+                        };
+                        self.check_returns(name, &returns, body, stmt.span);
+                        // calls after the definition know what it returns
+                        if !*is_async {
+                            let symbol = Symbol::Function(Signature::User {
+                                params: params
+                                    .iter()
+                                    .map(|(_, type_)| expr_type_to_object_type(type_))
+                                    .collect(),
+                                returns: Some(returns.clone()),
+                            });
+                            if self.scopes.len() == 1 {
+                                self.globals.insert(name.clone(), symbol.clone());
+                            }
+                            if let Some(scope) = self.scopes.last_mut() {
+                                scope.insert(name.clone(), symbol);
+                            }
+                        }
+                        returns
+                    }
+                };
+                self.return_types.insert(stmt.span.offset, returns);
+            }
+            StmtKind::Return(expr) => {
+                let found = match expr {
+                    Some(expr) => self.check_expr(expr),
+                    None => Some(Type::Null),
+                };
+                let Some((name, expected)) = self.function.clone() else {
+                    return;
+                };
+                let Some(expected) = expected else {
+                    self.returned.push(match expr {
+                        Some(_) => found,
+                        None => Some(Type::Void),
+                    });
+                    return;
+                };
+                if let Some(expr) = expr {
+                    self.settle(expr, &expected);
+                }
+                let Some(found) = found else {
+                    return;
+                };
+                if !accepts(&expected, &found) {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!(
+                            "E011.return_type",
+                            name = name,
+                            expected = expected,
+                            found = found
+                        ),
+                        expr.as_ref().map_or(stmt.span, |expr| expr.span),
+                    );
+                }
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.check_expr(condition);
+                let guard = self.typeof_guard(condition);
+                let (holds, fails) = match guard {
+                    Some((name, holds, fails)) => {
+                        (Some((name.clone(), holds)), Some((name, fails)))
+                    }
+                    None => (None, None),
+                };
+                self.check_narrowed_block(consequence, holds);
+                match alternative {
+                    Some(alternative) => self.check_narrowed_block(alternative, fails),
+                    // what follows an `if` that returns only runs when it fails
+                    None if always_returns(consequence) => self.narrowed.extend(fails),
+                    None => {}
+                }
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                let cursor_type = match self.check_expr(iterable) {
+                    Some(type_) if is_iterable(&type_) => type_.items_type(),
+                    None => None,
+                    Some(type_) => {
+                        self.error(
+                            ErrorKind::TypeError,
+                            msg!("E011.not_iterable", found = type_),
+                            iterable.span,
+                        );
+                        None
+                    }
+                };
+                let cursor = (
+                    cursor.clone(),
+                    Symbol::Value {
+                        type_: cursor_type,
+                        assignable: true,
+                    },
+                );
+                self.check_scoped_block(block, HashMap::from([cursor]));
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.check_scoped_block(block, HashMap::new());
+                let binding = (
+                    binding.clone(),
+                    Symbol::Value {
+                        type_: Some(Type::Error),
+                        assignable: true,
+                    },
+                );
+                self.check_scoped_block(handler, HashMap::from([binding]));
+            }
+            StmtKind::Import(_) => {}
+        }
+    }
+
+    fn check_let(
+        &mut self,
+        name: &str,
+        annotation: Option<&ExprType>,
+        expr: Option<&Expr>,
+        stmt_span: Span,
+    ) {
+        let found = expr.and_then(|expr| self.check_expr(expr));
+        let span = expr.map_or(stmt_span, |expr| expr.span);
+
+        let type_ = match annotation {
+            Some(annotation) => {
+                let expected = expr_type_to_object_type(annotation);
+                if let Some(expr) = expr {
+                    self.settle(expr, &expected);
+                }
+                if matches!(expected, Type::Void)
+                    || matches!(&expected, Type::Array(Some(items)) if **items == Type::Void)
+                {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!("E011.void_declaration", name = name, found = expected),
+                        span,
+                    );
+                } else if let Some(found) = found.filter(|found| !accepts(&expected, found)) {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!(
+                            "E011.let_mismatch",
+                            found = found,
+                            name = name,
+                            expected = expected
+                        ),
+                        span,
+                    );
+                }
+                Some(expected)
+            }
+            None => {
+                match expr {
+                    None => self.error(
+                        ErrorKind::TypeError,
+                        msg!("E011.infer_type_annotate", name = name),
+                        span,
+                    ),
+                    // settled by the first use that tells the items' type
+                    Some(expr) if is_empty_array(expr) => {
+                        let depth = self.scopes.len() - 1;
+                        self.unsettled
+                            .insert((depth, name.to_string()), (stmt_span.offset, span));
+                    }
+                    Some(_) => {}
+                }
+                found
+            }
+        };
+        if let Some(type_) = &type_ {
+            self.let_types.insert(stmt_span.offset, type_.clone());
+        }
+        self.declare(
+            name,
+            Symbol::Value {
+                type_,
+                assignable: true,
+            },
+            stmt_span,
+        );
+    }
+
+    /// Checks `expr` and returns its type, `None` when it can't be known
+    /// without running the program.
+    fn check_expr(&mut self, expr: &Expr) -> Option<Type> {
+        let type_ = self.check_expr_kind(expr);
+        match &type_ {
+            Some(type_) => self.expr_types.insert(expr.span.offset, type_.clone()),
+            None => self.expr_types.remove(&expr.span.offset),
+        };
+        type_
+    }
+
+    fn check_expr_kind(&mut self, expr: &Expr) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => self.check_literal(literal),
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                match self.lookup(name) {
+                    Some(Symbol::Value {
+                        type_: Some(Type::Union(_)),
+                        ..
+                    }) if self.narrowed.contains_key(name) => self.narrowed.get(name).cloned(),
+                    Some(Symbol::Value { type_, .. }) => type_,
+                    Some(Symbol::Function(_)) => Some(Type::Function),
+                    None => {
+                        self.undeclared(name, expr.span);
+                        None
+                    }
+                }
+            }
+            ExprKind::Call(func, args) => self.check_call(func, args, expr.span),
+            ExprKind::Method(receiver, name, args, optional) => {
+                let func = Expr::new(ExprKind::Identifier(name.clone()), expr.span);
+                let args = std::iter::once(receiver.as_ref())
+                    .chain(args)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let returns = self.check_call(&func, &args, expr.span);
+                // `null` when the value is
+                returns.filter(|_| !optional)
+            }
+            ExprKind::Await(operand) => match self.check_expr(operand) {
+                Some(Type::UserData(TASK)) => None,
+                type_ => type_,
+            },
+            ExprKind::Cast(operand, type_) => {
+                let expected = expr_type_to_object_type(type_);
+                let found = self.check_expr(operand);
+                if let Some(found) = found.filter(|found| !can_cast(found, &expected)) {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!("E011.cast_type", found = found, expected = expected),
+                        expr.span,
+                    );
+                }
+                Some(expected)
+            }
+            ExprKind::Infix(lhs, infix, rhs) => {
+                let lhs = self.check_expr(lhs);
+                let rhs = self.check_expr(rhs);
+                self.check_infix(lhs, infix, rhs, expr.span)
+            }
+            ExprKind::Prefix(Prefix::Not, operand) => {
+                self.check_expr(operand);
+                Some(Type::Boolean)
+            }
+            ExprKind::Prefix(prefix, operand) => {
+                let type_ = self.check_expr(operand)?;
+                if !matches!(type_, Type::Int | Type::Float) {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!("E011.prefix_number", op = prefix),
+                        expr.span,
+                    );
+                    return None;
+                }
+                Some(type_)
+            }
+            ExprKind::Postfix(operand, postfix) => {
+                let type_ = self.check_expr(operand);
+                if type_.is_some_and(|type_| type_ != Type::Int) {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!("E011.postfix_number", op = postfix),
+                        expr.span,
+                    );
+                    return None;
+                }
+                Some(Type::Int)
+            }
+            ExprKind::Assign(Identifier(name), value) => {
+                let found = self.check_expr(value);
+                self.narrowed.remove(name);
+                if let Some(found) = &found {
+                    self.settle_name(name, found);
+                }
+                let expected = self.assignable_type(name, expr.span)?;
+                if let Some(found) = found.filter(|found| !accepts(&expected, found)) {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!(
+                            "E011.assign_mismatch",
+                            name = name,
+                            expected = expected,
+                            found = found
+                        ),
+                        value.span,
+                    );
+                }
+                None
+            }
+            ExprKind::Index(target, index) => {
+                let target_type = self.check_expr(target);
+                self.check_index(index);
+                match target_type? {
+                    Type::Array(items_type) => items_type.map(|type_| *type_),
+                    type_ => {
+                        self.error(
+                            ErrorKind::TypeError,
+                            msg!("E011.not_indexable", found = type_),
+                            target.span,
+                        );
+                        None
+                    }
+                }
+            }
+            ExprKind::IndexAssign(Identifier(name), index, value) => {
+                self.check_index(index);
+                let found = self.check_expr(value);
+                if let Some(found) = &found {
+                    self.settle_name(name, &Type::Array(Some(Box::new(found.clone()))));
+                }
+                match self.assignable_type(name, expr.span)? {
+                    Type::Array(Some(items_type)) => {
+                        if let Some(found) = found.filter(|found| !accepts(&items_type, found)) {
+                            self.error(
+                                ErrorKind::TypeError,
+                                msg!(
+                                    "E011.item_mismatch",
+                                    name = name,
+                                    expected = items_type,
+                                    found = found
+                                ),
+                                value.span,
+                            );
+                        }
+                    }
+                    Type::Array(None) => {}
+                    type_ => self.error(
+                        ErrorKind::TypeError,
+                        msg!("E011.not_indexable", found = type_),
+                        expr.span,
+                    ),
+                }
+                None
+            }
+        }
+    }
+
+    fn check_literal(&mut self, literal: &Literal) -> Option<Type> {
+        let type_ = match literal {
+            Literal::Null => Type::Null,
+            Literal::Int(_) => Type::Int,
+            Literal::Float(_) => Type::Float,
+            Literal::String(_) => Type::String,
+            Literal::Boolean(_) => Type::Boolean,
+            Literal::Array(items) => {
+                let types = items
+                    .iter()
+                    .map(|item| self.check_expr(item))
+                    .collect::<Vec<_>>();
+                // the items unify, e.g. `[[], [1]]` is an `Array<Array<int>>`
+                let mut items_type: Option<Type> = None;
+                for (item, type_) in items.iter().zip(types) {
+                    let (Some(known), Some(type_)) = (&items_type, &type_) else {
+                        items_type = items_type.or(type_);
+                        continue;
+                    };
+                    match known.unify(type_) {
+                        Some(unified) => items_type = Some(unified),
+                        None => {
+                            self.error(
+                                ErrorKind::TypeError,
+                                msg!("E011.array_items_mismatch"),
+                                item.span,
+                            );
+                            break;
+                        }
+                    }
+                }
+                Type::Array(items_type.map(Box::new))
+            }
+        };
+        Some(type_)
+    }
+
+    fn check_call(&mut self, func: &Expr, args: &[Expr], span: Span) -> Option<Type> {
+        let arg_types = args
+            .iter()
+            .map(|arg| self.check_expr(arg))
+            .collect::<Vec<_>>();
+        let name = match &func.kind {
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => name,
+            _ => {
+                self.error(ErrorKind::NameError, msg!("E010.function_name"), func.span);
+                return None;
+            }
+        };
+
+        let signature = match self.lookup(name) {
+            Some(Symbol::Function(signature)) => signature,
+            Some(Symbol::Value {
+                type_: Some(type_), ..
+            }) if type_ != Type::Function => {
+                self.error(
+                    ErrorKind::TypeError,
+                    msg!("E011.not_callable", name = name),
+                    func.span,
+                );
+                return None;
+            }
+            Some(Symbol::Value { .. }) => return None,
+            None => {
+                self.undeclared(name, func.span);
+                return None;
+            }
+        };
+
+        match signature {
+            Signature::User { params, returns } => {
+                if params.len() != args.len() {
+                    self.error(
+                        ErrorKind::TypeError,
+                        msg!(
+                            "E011.function_arg_count",
+                            name = name,
+                            expected = count_args(params.len(), Some(params.len())),
+                            found = args.len()
+                        ),
+                        span,
+                    );
+                } else {
+                    for ((param, arg), found) in params.iter().zip(args).zip(&arg_types) {
+                        self.settle(arg, param);
+                        if let Some(found) = found.as_ref().filter(|found| !accepts(param, found)) {
+                            self.error(
+                                ErrorKind::TypeError,
+                                msg!("E011.param_type", found = found, expected = param),
+                                arg.span,
+                            );
+                        }
+                    }
+                }
+                match returns {
+                    Some(Type::Void) => Some(Type::Null),
+                    returns => returns,
+                }
+            }
+            Signature::Builtin {
+                min_args,
+                max_args,
+                returns,
+            } => {
+                if args.len() < min_args || max_args.is_some_and(|max| args.len() > max) {
+                    self.error(
+                        ErrorKind::ArgumentError,
+                        msg!(
+                            "E012.arg_count",
+                            name = name,
+                            expected = count_args(min_args, max_args),
+                            found = args.len()
+                        ),
+                        span,
+                    );
+                }
+                returns.or_else(|| self.generic_return(name, args, &arg_types))
+            }
+        }
+    }
+
+    /// What the builtins whose return type depends on their arguments
+    /// return: iterators and arrays of the items of the iterables they are
+    /// given, or of what the function given to `map` returns.
+    fn generic_return(
+        &self,
+        name: &str,
+        args: &[Expr],
+        arg_types: &[Option<Type>],
+    ) -> Option<Type> {
+        let items_type = || {
+            arg_types
+                .first()
+                .cloned()
+                .flatten()
+                .filter(is_iterable)
+                .and_then(|type_| type_.items_type())
+                .map(Box::new)
+        };
+        match name {
+            "iter" | "filter" | "take" => Some(Type::Iterator(items_type())),
+            "collect" => Some(Type::Array(items_type())),
+            "map" => {
+                let returns = match args.get(1).map(|arg| &arg.kind) {
+                    Some(
+                        ExprKind::Identifier(Identifier(function))
+                        | ExprKind::Local(Identifier(function), _),
+                    ) => match self.lookup(function) {
+                        Some(Symbol::Function(Signature::User { returns, .. })) => returns,
+                        Some(Symbol::Function(Signature::Builtin { returns, .. })) => returns,
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                // a function that returns nothing gives `null`s
+                let returns = returns.map(|returns| match returns {
+                    Type::Void => Type::Null,
+                    returns => returns,
+                });
+                Some(Type::Iterator(returns.map(Box::new)))
+            }
+            _ => None,
+        }
+    }
+
+    fn check_infix(
+        &mut self,
+        lhs: Option<Type>,
+        infix: &Infix,
+        rhs: Option<Type>,
+        span: Span,
+    ) -> Option<Type> {
+        if matches!(infix, Infix::In) {
+            return self.check_in(lhs, rhs, span);
+        }
+        // `typeof(x) == "int"`: types are compared to their names
+        if let (Some(Type::TypeAnnot), Some(Type::String))
+        | (Some(Type::String), Some(Type::TypeAnnot)) = (&lhs, &rhs)
+        {
+            return self.check_infix(Some(Type::TypeAnnot), infix, Some(Type::TypeAnnot), span);
+        }
+        let is_arithmetic = matches!(
+            infix,
+            Infix::Plus | Infix::Minus | Infix::Devide | Infix::Multiply | Infix::Remainder
+        );
+        let operand = match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) if lhs != rhs => {
+                self.error(
+                    ErrorKind::TypeError,
+                    msg!("E011.infix_types", op = infix, lhs = lhs, rhs = rhs),
+                    span,
+                );
+                return None;
+            }
+            (Some(type_), _) | (_, Some(type_)) => type_,
+            (None, None) => return (!is_arithmetic).then_some(Type::Boolean),
+        };
+
+        let supported = match operand {
+            Type::Int | Type::Float => true,
+            Type::String => matches!(infix, Infix::Plus | Infix::Equal | Infix::NotEqual),
+            Type::Boolean => !is_arithmetic,
+            Type::TypeAnnot => matches!(infix, Infix::Equal | Infix::NotEqual),
+            _ => false,
+        };
+        if !supported {
+            self.error(
+                ErrorKind::TypeError,
+                msg!("E011.infix_not_implemented", op = infix, found = operand),
+                span,
+            );
+            return None;
+        }
+        Some(if is_arithmetic {
+            operand
+        } else {
+            Type::Boolean
+        })
+    }
+
+    /// `item in collection`: any item can be looked for in a set, array or
+    /// range, only strings in strings.
+    fn check_in(
+        &mut self,
+        item: Option<Type>,
+        collection: Option<Type>,
+        span: Span,
+    ) -> Option<Type> {
+        match (item, collection) {
+            (_, None | Some(Type::Set | Type::Array(_) | Type::Range)) => {}
+            (None | Some(Type::String), Some(Type::String)) => {}
+            (Some(item), Some(Type::String)) => {
+                self.error(
+                    ErrorKind::TypeError,
+                    msg!(
+                        "E011.infix_types",
+                        op = Infix::In,
+                        lhs = item,
+                        rhs = Type::String
+                    ),
+                    span,
+                );
+                return None;
+            }
+            (_, Some(collection)) => {
+                self.error(
+                    ErrorKind::TypeError,
+                    msg!("E011.in_type", found = collection),
+                    span,
+                );
+                return None;
+            }
+        }
+        Some(Type::Boolean)
+    }
+
+    fn check_index(&mut self, index: &Expr) {
+        if let Some(type_) = self.check_expr(index).filter(|type_| *type_ != Type::Int) {
+            self.error(
+                ErrorKind::TypeError,
+                msg!("E011.index_type", found = type_),
+                index.span,
+            );
+        }
+    }
+
+    /// The type of the variable `name` when it can be assigned to, reporting
+    /// why otherwise.
+    fn assignable_type(&mut self, name: &str, span: Span) -> Option<Type> {
+        match self.lookup(name) {
+            Some(Symbol::Value {
+                type_,
+                assignable: true,
+            }) => type_,
+            Some(_) => {
+                self.error(
+                    ErrorKind::NameError,
+                    msg!("E010.not_assignable", name = name),
+                    span,
+                );
+                None
+            }
+            None => {
+                self.undeclared(name, span);
+                None
+            }
+        }
+    }
+
+    /// `typeof(x) == "int"`, or `!=`, on a variable `x` declared with a union
+    /// type: its name, and its types where the condition holds and where it
+    /// fails.
+    fn typeof_guard(&self, condition: &Expr) -> Option<(String, Type, Type)> {
+        let ExprKind::Infix(lhs, infix, rhs) = &condition.kind else {
+            return None;
+        };
+        let is_equal = match infix {
+            Infix::Equal => true,
+            Infix::NotEqual => false,
+            _ => return None,
+        };
+        let (name, type_name) = match (typeof_operand(lhs), typeof_operand(rhs)) {
+            (Some(name), None) => (name, string_literal(rhs)?),
+            (None, Some(name)) => (name, string_literal(lhs)?),
+            _ => return None,
+        };
+        let members =
+            match self
+                .narrowed
+                .get(name)
+                .cloned()
+                .or_else(|| match self.lookup(name) {
+                    Some(Symbol::Value { type_, .. }) => type_,
+                    _ => None,
+                })? {
+                Type::Union(members) => members,
+                _ => return None,
+            };
+        let matched = members
+            .iter()
+            .find(|member| member.to_string() == type_name)?;
+        let mut rest = members
+            .iter()
+            .filter(|member| *member != matched)
+            .cloned()
+            .collect::<Vec<_>>();
+        let rest = match rest.len() {
+            1 => rest.pop()?,
+            _ => Type::Union(rest),
+        };
+        Some(match is_equal {
+            true => (name.to_string(), matched.clone(), rest),
+            false => (name.to_string(), rest, matched.clone()),
+        })
+    }
+
+    /// Reports `name` when `body` can end without returning the value it
+    /// should.
+    fn check_returns(&mut self, name: &str, returns: &Type, body: &BlockStmt, span: Span) {
+        if *returns != Type::Void && !always_returns(body) {
+            self.error(
+                ErrorKind::TypeError,
+                msg!("E011.may_not_return", name = name, expected = returns),
+                span,
+            );
+        }
+    }
+
+    /// Settles the type of the variable `expr` is, if it was declared with
+    /// `[]`, to `type_`, the type of where it is used.
+    fn settle(&mut self, expr: &Expr, type_: &Type) {
+        if let ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) =
+            &expr.kind
+        {
+            self.settle_name(name, type_);
+        }
+    }
+
+    fn settle_name(&mut self, name: &str, type_: &Type) {
+        if !matches!(type_, Type::Array(Some(_))) {
+            return;
+        }
+        let Some(depth) = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(name))
+        else {
+            return;
+        };
+        let Some((offset, _)) = self.unsettled.remove(&(depth, name.to_string())) else {
+            return;
+        };
+        let symbol = Symbol::Value {
+            type_: Some(type_.clone()),
+            assignable: true,
+        };
+        self.scopes[depth].insert(name.to_string(), symbol);
+        self.let_types.insert(offset, type_.clone());
+    }
+
+    /// Reports the variables of the innermost scope declared with `[]` whose
+    /// type no use settled.
+    fn report_unsettled(&mut self) {
+        let depth = self.scopes.len() - 1;
+        let mut unsettled = self
+            .unsettled
+            .keys()
+            .filter(|(scope, _)| *scope == depth)
+            .cloned()
+            .collect::<Vec<_>>();
+        unsettled.sort();
+        for key in unsettled {
+            if let Some((_, span)) = self.unsettled.remove(&key) {
+                self.error(
+                    ErrorKind::TypeError,
+                    msg!("E011.infer_type_annotate", name = key.1),
+                    span,
+                );
+            }
+        }
+    }
+
+    /// Adds `name` to the innermost scope, reporting it when that scope
+    /// already has it: redeclaring fails at runtime, only enclosing scopes
+    /// can be shadowed.
+    fn declare(&mut self, name: &str, symbol: Symbol, span: Span) {
+        let (Some(scope), Some(declared_at)) =
+            (self.scopes.last_mut(), self.declared_at.last_mut())
+        else {
+            return;
+        };
+        self.narrowed.remove(name);
+        let first = declared_at.get(name).copied();
+        declared_at.entry(name.to_string()).or_insert(span);
+        if scope.insert(name.to_string(), symbol).is_none() {
+            return;
+        }
+        let note = match first {
+            Some(first) => Some(msg!("E010.first_declared", name = name, span = first)),
+            None if self.scopes.len() == 1 && is_builtin(name) => {
+                Some(msg!("E010.builtin", name = name))
+            }
+            None => None,
+        };
+        self.errors.push(CheckError {
+            kind: ErrorKind::NameError,
+            msg: msg!("E010.already_declared", name = name),
+            span,
+            notes: note.into_iter().collect(),
+        });
+    }
+
+    /// What `name` refers to where it is used, or `None` if it can't be
+    /// declared by the time that code runs.
+    fn lookup(&self, name: &str) -> Option<Symbol> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(symbol) = scope.get(name) {
+                return Some(symbol.clone());
+            }
+        }
+        self.function.as_ref()?;
+        if let Some(symbol) = self.globals.get(name) {
+            return Some(symbol.clone());
+        }
+        self.declared.contains(name).then_some(Symbol::Value {
+            type_: None,
+            assignable: true,
+        })
+    }
+
+    fn undeclared(&mut self, name: &str, span: Span) {
+        if self.imports {
+            return;
+        }
+        let msg = if self.declared.contains(name) {
+            msg!("E010.used_before_declared", name = name)
+        } else {
+            let visible = self.scopes.iter().flat_map(|scope| scope.keys());
+            match closest_name(name, visible.map(String::as_str)) {
+                Some(closest) => msg!("E010.did_you_mean", name = name, closest = closest),
+                None => msg!("E010.not_declared", name = name),
+            }
+        };
+        self.error(ErrorKind::NameError, msg, span);
+    }
+
+    fn error(&mut self, kind: ErrorKind, msg: String, span: Span) {
+        self.errors.push(CheckError {
+            kind,
+            msg,
+            span,
+            notes: Vec::new(),
+        });
+    }
+}
+
+/// The variable `expr` gets the type of, if it is `typeof(variable)`.
+fn typeof_operand(expr: &Expr) -> Option<&str> {
+    let ExprKind::Call(func, args) = &expr.kind else {
+        return None;
+    };
+    match (&func.kind, args.as_slice()) {
+        (ExprKind::Identifier(Identifier(func)), [arg]) if func == "typeof" => match &arg.kind {
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                Some(name)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether a for-loop can go over a value of type `type_`.
+fn is_iterable(type_: &Type) -> bool {
+    matches!(
+        type_,
+        Type::Range | Type::String | Type::Array(_) | Type::Iterator(_) | Type::Set
+    )
+}
+
+fn is_empty_array(expr: &Expr) -> bool {
+    matches!(&expr.kind, ExprKind::Literal(Literal::Array(items)) if items.is_empty())
+}
+
+fn string_literal(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::String(val)) => Some(val),
+        _ => None,
+    }
+}
+
+/// Whether running `block` always ends in a `return`, or in a call that
+/// never comes back (`exit`, `raise`).
+fn always_returns(block: &BlockStmt) -> bool {
+    block.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Call(func, _),
+            ..
+        }) => matches!(
+            &func.kind,
+            ExprKind::Identifier(Identifier(name)) if name == "exit" || name == "raise"
+        ),
+        StmtKind::If {
+            consequence,
+            alternative: Some(alternative),
+            ..
+        } => always_returns(consequence) && always_returns(alternative),
+        // The handler only runs when the block fails before its `return`.
+        StmtKind::Try { block, handler, .. } => always_returns(block) && always_returns(handler),
+        _ => false,
+    })
+}
+
+/// Calls to `async` functions return a task, whose result isn't known.
+fn user_signature(
+    params: &[(Identifier, ExprType)],
+    ret_type: Option<&ExprType>,
+    is_async: bool,
+) -> Signature {
+    Signature::User {
+        params: params
+            .iter()
+            .map(|(_, type_)| expr_type_to_object_type(type_))
+            .collect(),
+        returns: match is_async {
+            true => Some(Type::UserData(TASK)),
+            false => ret_type.map(expr_type_to_object_type),
+        },
+    }
+}
+
+/// The return type of a function that returns values of the `returned`
+/// types: `void` without any, the one type they have or their union. Those
+/// unknown, e.g. of recursive calls, are left out, but some must be known,
+/// and it must be a type that can be declared.
+fn inferred_return(returned: &[Option<Type>]) -> Option<Type> {
+    let mut types: Vec<Type> = vec![];
+    for type_ in returned.iter().flatten() {
+        let members = match type_ {
+            Type::Union(members) => members.clone(),
+            type_ => vec![type_.clone()],
+        };
+        for member in members {
+            // e.g. `[]` and `[1]` are both `Array<int>`
+            let unified = types
+                .iter_mut()
+                .find_map(|type_| Some((type_.unify(&member)?, type_)));
+            match unified {
+                Some((unified, type_)) => *type_ = unified,
+                None => types.push(member),
+            }
+        }
+    }
+    let returns = match types.len() {
+        0 if returned.is_empty() => Type::Void,
+        0 => return None,
+        1 => types.pop()?,
+        _ if types.contains(&Type::Void) => return None,
+        _ => Type::Union(types),
+    };
+    object_type_to_expr_type(&returns).map(|_| returns)
+}
+
+/// Whether a value of type `found` can go where `expected` is declared.
+/// Arrays whose items are unknown, like `[]`, fit any array type, and a
+/// union takes values of any of its types.
+fn accepts(expected: &Type, found: &Type) -> bool {
+    match (expected, found) {
+        (_, Type::Union(found)) => found.iter().all(|found| accepts(expected, found)),
+        (Type::Union(members), _) => members.iter().any(|member| accepts(member, found)),
+        (Type::Void, Type::Null) | (Type::Null, Type::Void) => true,
+        (Type::Array(None), Type::Array(_)) | (Type::Array(_), Type::Array(None)) => true,
+        (Type::Array(Some(expected)), Type::Array(Some(found))) => accepts(expected, found),
+        (Type::Iterator(None), Type::Iterator(_)) | (Type::Iterator(_), Type::Iterator(None)) => {
+            true
+        }
+        (Type::Iterator(Some(expected)), Type::Iterator(Some(found))) => accepts(expected, found),
+        _ => expected == found,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+    use crate::runtime::{context::Context, Runtime};
+    use crate::shared::Lock;
+
+    fn source_program(source: &str) -> Program {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        assert!(!p.has_error());
+        program
+    }
+
+    fn check_source(source: &str) -> Vec<(String, usize)> {
+        check(&source_program(source))
+            .into_iter()
+            .map(|error| (error.kind.name().to_string(), error.span.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_reports_every_problem() {
+        let input = "let count: int = \"three\"\n\
+                     define twice(x: int): int {\n\
+                     \x20 return x * 2\n\
+                     }\n\
+                     twice(1, 2)\n\
+                     twice(\"a\")\n\
+                     count(1)\n\
+                     print(later)\n\
+                     let later = len(\"a\", \"b\")\n\
+                     let s: string = twice(2)\n\
+                     let count = 3";
+        assert_eq!(
+            check_source(input),
+            [
+                ("TypeError".to_string(), 1),
+                ("TypeError".to_string(), 5),
+                ("TypeError".to_string(), 6),
+                ("TypeError".to_string(), 7),
+                ("NameError".to_string(), 8),
+                ("ArgumentError".to_string(), 9),
+                ("TypeError".to_string(), 10),
+                ("NameError".to_string(), 11),
+            ]
+        );
+
+        assert!(check_source(include_str!("../../examples/main.fl")).is_empty());
+    }
+
+    #[test]
+    fn test_functions_return_on_every_path() {
+        let input = "define sign(x: int): int {\n\
+                     \x20 if x < 0 {\n\
+                     \x20   return -1\n\
+                     \x20 }\n\
+                     }\n\
+                     define check(x: int): int {\n\
+                     \x20 if x < 0 {\n\
+                     \x20   raise(error(\"Negative\", \"x < 0\"))\n\
+                     \x20 } else {\n\
+                     \x20   return x\n\
+                     \x20 }\n\
+                     }\n\
+                     define log(x: int): void {\n\
+                     \x20 print(x)\n\
+                     }";
+        assert_eq!(check_source(input), [("TypeError".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_types_left_out_are_inferred() {
+        let input = "define name(id: int) {\n\
+                     \x20 if id > 0 {\n\
+                     \x20   return \"user\"\n\
+                     \x20 }\n\
+                     \x20 return id\n\
+                     }\n\
+                     define log(s: string) {\n\
+                     \x20 print(s)\n\
+                     }\n\
+                     let ids = []\n\
+                     ids = [1]\n\
+                     let names = []\n\
+                     names[0] = name(1)\n\
+                     let x: int = name(2)\n\
+                     let y: int = log(\"a\")\n\
+                     let z = []\n\
+                     z = ids\n\
+                     z = [\"a\"]\n\
+                     let unused = []";
+        assert_eq!(
+            check_source(input),
+            [
+                ("TypeError".to_string(), 14),
+                ("TypeError".to_string(), 15),
+                ("TypeError".to_string(), 18),
+                ("TypeError".to_string(), 19),
+            ]
+        );
+
+        let input =
+            source_program("define f(b: boolean) {\n  if b {\n    return []\n  }\n  return [2]\n}");
+        let analysis = analyze(&input);
+        assert!(analysis.errors.is_empty());
+        assert_eq!(
+            analysis.return_types.get(&0),
+            Some(&Type::Array(Some(Box::new(Type::Int))))
+        );
+    }
+
+    #[test]
+    fn test_generic_types_track_their_items() {
+        let input = "define square(n: int): int {\n\
+                     \x20 return n * n\n\
+                     }\n\
+                     let squares = range(0, 5).map(square).take(2).collect()\n\
+                     let first: string = squares[0]\n\
+                     for letter in iter(\"ab\").filter(square) {\n\
+                     \x20 let n: int = letter\n\
+                     }\n\
+                     let nested = [[], [1]]\n\
+                     let names: Array<Array<string>> = nested\n\
+                     let mixed = [[1], [\"a\"]]";
+        assert_eq!(
+            check_source(input),
+            [
+                ("TypeError".to_string(), 5),
+                ("TypeError".to_string(), 7),
+                ("TypeError".to_string(), 10),
+                ("TypeError".to_string(), 11),
+            ]
+        );
+
+        let analysis = analyze(&source_program("let it = iter([1.5]).map(print)"));
+        assert_eq!(
+            analysis
+                .let_types
+                .get(&0)
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("iterator<null>")
+        );
+    }
+
+    #[test]
+    fn test_infer_type_uses_existing_bindings() {
+        let parse = |source: &str| {
+            let input = source.chars().collect::<Vec<char>>();
+            let mut l = Lexer::new(&input);
+            Parser::new(&mut l).parse()
+        };
+        let env = Shared::new(Lock::new(Context::make_global(builtins())));
+        let session = parse("let xs = [1, 2]\ndefine twice(x: int): int {\n  return x * 2\n}");
+        Runtime::new(Shared::clone(&env)).eval(session).unwrap();
+
+        let infer = |source: &str| {
+            let StmtKind::Expr(expr) = &parse(source)[0].kind else {
+                panic!("{source} is not an expression");
+            };
+            infer_type(expr, env.borrow().globals())
+        };
+        assert_eq!(infer("twice(xs[0])").unwrap(), Some(Type::Int));
+        assert_eq!(
+            infer("xs").unwrap(),
+            Some(Type::Array(Some(Box::new(Type::Int))))
+        );
+        assert!(infer("twice(\"2\")").is_err());
+        assert!(infer("ys").is_err());
+    }
+
+    #[test]
+    fn test_typeof_guards_narrow_unions() {
+        let input = "define size(x: int | string): int {\n\
+                     \x20 if typeof(x) == \"string\" {\n\
+                     \x20   return len(x)\n\
+                     \x20 }\n\
+                     \x20 return x + 1\n\
+                     }\n\
+                     let y: int | string = \"a\"\n\
+                     if typeof(y) != \"int\" {\n\
+                     \x20 print(y + \"b\")\n\
+                     } else {\n\
+                     \x20 print(y * 2)\n\
+                     }\n\
+                     y = 3\n\
+                     let z: int = y\n\
+                     size(true)\n\
+                     y = 1.5";
+        assert_eq!(
+            check_source(input),
+            [
+                ("TypeError".to_string(), 14),
+                ("TypeError".to_string(), 15),
+                ("TypeError".to_string(), 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_casts_have_their_target_type() {
+        let input = "let n: int = \"4\" as int\n\
+                     let x: float = n as float\n\
+                     let s: string = x\n\
+                     let b = [1] as boolean\n\
+                     let bad = true as int";
+        assert_eq!(
+            check_source(input),
+            [("TypeError".to_string(), 3), ("TypeError".to_string(), 5)]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Reference pages for `filipe doc`: every top-level function of a script
+//! with its signature and the `///` comment above it, as Markdown or HTML.
+
+use super::ast::*;
+use crate::utils::escape_html;
+
+/// `define name(a: int): int`, or `async define ...`
+fn signature(
+    name: &str,
+    params: &[(Identifier, ExprType)],
+    ret_type: Option<&ExprType>,
+    is_async: bool,
+) -> String {
+    let params = params
+        .iter()
+        .map(|(Identifier(name), type_)| format!("{}: {}", name, type_))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let keyword = if is_async { "async define" } else { "define" };
+    match ret_type {
+        Some(ret_type) => format!("{} {}({}): {}", keyword, name, params, ret_type),
+        None => format!("{} {}({})", keyword, name, params),
+    }
+}
+
+/// Name, signature and doc comment of each top-level function.
+fn functions(program: &Program) -> Vec<(&str, String, Option<&str>)> {
+    program
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StmtKind::Func(Identifier(name), params, _, ret_type, doc, is_async) => Some((
+                name.as_str(),
+                signature(name, params, ret_type.as_ref(), *is_async),
+                doc.as_deref(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn markdown(module: &str, program: &Program) -> String {
+    let mut out = format!("# {}\n", module);
+    for (name, signature, doc) in functions(program) {
+        out.push_str(&format!("\n## {}\n\n```\n{}\n```\n", name, signature));
+        if let Some(doc) = doc {
+            out.push_str(&format!("\n{}\n", doc));
+        }
+    }
+    out
+}
+
+pub fn html(module: &str, program: &Program) -> String {
+    let module = escape_html(module);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{module}</title>\n</head>\n<body>\n<h1>{module}</h1>\n"
+    );
+    for (name, signature, doc) in functions(program) {
+        out.push_str(&format!(
+            "<h2 id=\"{name}\">{name}</h2>\n<pre><code>{}</code></pre>\n",
+            escape_html(&signature)
+        ));
+        // blank lines split the comment into paragraphs
+        for paragraph in doc.unwrap_or_default().split("\n\n") {
+            if !paragraph.trim().is_empty() {
+                out.push_str(&format!("<p>{}</p>\n", escape_html(paragraph.trim())));
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_docs_are_attached_to_functions() {
+        let source = "/// Adds two numbers.\n\
+                      ///\n\
+                      /// Both must be ints.\n\
+                      define add(x: int, y: int): int { return x + y }\n\
+                      /// Not a function, so dropped.\n\
+                      let z = 10 / 2\n\
+                      define first(xs: Array<string>): string { return xs[0] }";
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        assert!(!p.has_error());
+
+        assert_eq!(
+            markdown("math", &program),
+            "# math\n\
+             \n## add\n\n```\ndefine add(x: int, y: int): int\n```\n\
+             \nAdds two numbers.\n\nBoth must be ints.\n\
+             \n## first\n\n```\ndefine first(xs: Array<string>): string\n```\n"
+        );
+        assert!(html("math", &program).contains(
+            "<h2 id=\"first\">first</h2>\n\
+             <pre><code>define first(xs: Array&lt;string&gt;): string</code></pre>\n"
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+//! Prints a parsed program back as source in the canonical layout used by
+//! `filipe fmt`: one statement per line, blocks indented by two spaces, or
+//! the `indent` of a project's `[format]` table, with the `{` on the line
+//! that opens them, single spaces around operators and after commas. A
+//! blank line between two statements is kept, several are folded into one,
+//! and doc comments stay above their function.
+
+use super::ast::*;
+
+pub const DEFAULT_INDENT: usize = 2;
+
+/// Formats `program`, which was parsed from `source`. The source is only
+/// looked at for the blank lines between statements and the `#!` line.
+pub fn format(program: &Program, source: &str) -> String {
+    format_with_indent(program, source, DEFAULT_INDENT)
+}
+
+/// `format` with blocks indented by `indent` spaces.
+pub fn format_with_indent(program: &Program, source: &str, indent: usize) -> String {
+    let lines = source.lines().collect::<Vec<_>>();
+    let shebang = lines.first().filter(|line| line.starts_with("#!"));
+    let mut formatter = Formatter {
+        out: shebang
+            .map(|line| format!("{}\n", line))
+            .unwrap_or_default(),
+        lines,
+        depth: 0,
+        indent: " ".repeat(indent),
+    };
+    formatter.block(program);
+    formatter.out
+}
+
+struct Formatter<'s> {
+    lines: Vec<&'s str>,
+    out: String,
+    depth: usize,
+    indent: String,
+}
+
+impl Formatter<'_> {
+    fn block(&mut self, block: &BlockStmt) {
+        for (index, stmt) in block.iter().enumerate() {
+            if index > 0 && self.follows_blank_line(stmt) {
+                self.out.push('\n');
+            }
+            self.stmt(stmt);
+        }
+    }
+
+    /// Whether the line above `stmt` is blank in the source.
+    fn follows_blank_line(&self, stmt: &Stmt) -> bool {
+        stmt.span.line >= 2
+            && self
+                .lines
+                .get(stmt.span.line - 2)
+                .is_some_and(|line| line.trim().is_empty())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.indent();
+        match &stmt.kind {
+            StmtKind::Expr(expr) => self.push(&expr.to_string()),
+            StmtKind::Let(Identifier(name), annotation, value) => {
+                self.push(&format!("let {}", name));
+                if let Some(annotation) = annotation {
+                    self.push(&format!(": {}", annotation));
+                }
+                if let Some(value) = value {
+                    self.push(&format!(" = {}", value));
+                }
+            }
+            StmtKind::Func(Identifier(name), params, body, ret_type, doc, is_async) => {
+                for line in doc.iter().flat_map(|doc| doc.lines()) {
+                    match line.is_empty() {
+                        true => self.push("///\n"),
+                        false => self.push(&format!("/// {}\n", line)),
+                    }
+                    self.indent();
+                }
+                let params = params
+                    .iter()
+                    .map(|(Identifier(name), type_)| format!("{}: {}", name, type_))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if *is_async {
+                    self.push("async ");
+                }
+                match ret_type {
+                    Some(ret_type) => {
+                        self.push(&format!("define {}({}): {} ", name, params, ret_type))
+                    }
+                    None => self.push(&format!("define {}({}) ", name, params)),
+                }
+                self.braced(body);
+            }
+            StmtKind::Return(Some(expr)) => self.push(&format!("return {}", expr)),
+            StmtKind::Return(None) => self.push("return"),
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.push(&format!("if {} ", condition));
+                self.braced(consequence);
+                if let Some(alternative) = alternative {
+                    self.push(" else ");
+                    self.braced(alternative);
+                }
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                self.push(&format!("for {} in {} ", cursor, iterable));
+                self.braced(block);
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.push("try ");
+                self.braced(block);
+                self.push(&format!(" catch {} ", binding));
+                self.braced(handler);
+            }
+            StmtKind::Assert(condition) => self.push(&format!("assert {}", condition)),
+            StmtKind::Import(name) => self.push(&format!("import \"{}\"", name)),
+        }
+        self.out.push('\n');
+    }
+
+    /// `{`, the block one level deeper, and `}` lined up with the statement.
+    fn braced(&mut self, block: &BlockStmt) {
+        if block.is_empty() {
+            self.push("{}");
+            return;
+        }
+        self.push("{\n");
+        self.depth += 1;
+        self.block(block);
+        self.depth -= 1;
+        self.indent();
+        self.push("}");
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(&self.indent);
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    fn format_source(source: &str) -> String {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        assert!(!p.has_error());
+        format(&program, source)
+    }
+
+    #[test]
+    fn test_format_is_canonical_and_stable() {
+        let input = "let  x:int=1+2*3\n\n\n\
+                     define   f(a:int,b :Array<int>):int{\n\
+                     if a>=b[0]{return -  -a}else{}\n\
+                     \x20   return a%2\n\
+                     }\n\
+                     for i in range(0,3) { print(i,\"  a  b \") }\n\
+                     try{raise(error(\"E\",\"m\"))}catch e{print(e)}\n\
+                     x++ assert x == 8\n\
+                     let y: float = 10.0\n\
+                     ///Adds one.\n\
+                     ///\n\
+                     ///   Indented.\n\
+                     define inc(n: int): int { return n + 1 }\n\
+                     ///Waits.\n\
+                     async  define get(t: int): int { return await  t }";
+        let expected = "let x: int = 1 + 2 * 3\n\
+                        \n\
+                        define f(a: int, b: Array<int>): int {\n\
+                        \x20 if a >= b[0] {\n\
+                        \x20   return - -a\n\
+                        \x20 } else {}\n\
+                        \x20 return a % 2\n\
+                        }\n\
+                        for i in range(0, 3) {\n\
+                        \x20 print(i, \"  a  b \")\n\
+                        }\n\
+                        try {\n\
+                        \x20 raise(error(\"E\", \"m\"))\n\
+                        } catch e {\n\
+                        \x20 print(e)\n\
+                        }\n\
+                        x++\n\
+                        assert x == 8\n\
+                        let y: float = 10.0\n\
+                        /// Adds one.\n\
+                        ///\n\
+                        ///   Indented.\n\
+                        define inc(n: int): int {\n\
+                        \x20 return n + 1\n\
+                        }\n\
+                        /// Waits.\n\
+                        async define get(t: int): int {\n\
+                        \x20 return await t\n\
+                        }\n";
+        assert_eq!(format_source(input), expected);
+        assert_eq!(format_source(expected), expected);
+    }
+}
@@ -1,53 +1,138 @@
-use super::token::Token;
+mod reader;
+
+use std::io::Read;
+use std::iter::Peekable;
+use std::time::{Duration, Instant};
+
+use super::token::{Span, Token};
+use crate::messages::msg;
+use reader::CharReader;
 
 const NULL_CHAR: char = '\0';
 
+type CharStream<'a> = Peekable<Box<dyn Iterator<Item = Result<char, String>> + 'a>>;
+
+/// Turns source text into tokens. The source is pulled one char at a time
+/// with a single char of lookahead, so lexing a `Read` never needs the whole
+/// input in memory.
 pub struct Lexer<'a> {
-    input: &'a [char],
+    input: CharStream<'a>,
     curr_char: char,
-    pos: usize,
-    read_pos: usize,
-    line: usize,
-    colm: usize,
+    /// Set once the input is used up, so a literal NUL in it isn't taken
+    /// for the end.
+    at_end: bool,
+    read_error: Option<String>,
+    /// Position of `curr_char`.
+    pos: Span,
+    token_start: Span,
+    /// `///` lines read since the last token.
+    doc_lines: Vec<String>,
+    /// Doc comment right above the token last returned by `next_token`.
+    token_doc: Option<String>,
+    /// Time spent reading tokens, once `enable_timing` was called.
+    time_spent: Option<Duration>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a [char]) -> Self {
+        Self::from_chars(Box::new(input.iter().map(|ch| Ok(*ch))))
+    }
+
+    /// Lexes straight from a reader, e.g. a pipe on stdin.
+    pub fn from_reader(reader: impl Read + 'a) -> Self {
+        Self::from_chars(Box::new(CharReader::new(reader)))
+    }
+
+    fn from_chars(input: Box<dyn Iterator<Item = Result<char, String>> + 'a>) -> Self {
         let mut l = Lexer {
-            input,
+            input: input.peekable(),
             curr_char: NULL_CHAR,
-            pos: 0,
-            read_pos: 0,
-            line: 1,
-            colm: 1,
+            at_end: false,
+            read_error: None,
+            pos: Span::default(),
+            token_start: Span::default(),
+            doc_lines: vec![],
+            token_doc: None,
+            time_spent: None,
         };
 
         l.read_char();
+        l.pos = Span {
+            line: 1,
+            col: 1,
+            offset: 0,
+        };
+        // `#!/usr/bin/env filipe`, so scripts can be run directly
+        if l.curr_char == '#' && l.next_char_is('!') {
+            l.chop_while(|x| x != '\n');
+        }
 
         l
     }
 
     fn read_char(&mut self) {
-        if self.read_pos >= self.input.len() {
-            self.pos = self.read_pos;
-            self.curr_char = NULL_CHAR;
-            return;
-        }
-
-        self.curr_char = self.input[self.read_pos];
-        self.pos = self.read_pos;
-        self.read_pos += 1;
-
         if self.curr_char == '\n' {
-            self.line += 1;
-            self.colm = 1;
+            self.pos.line += 1;
+            self.pos.col = 1;
         } else {
-            self.colm += 1;
+            self.pos.col += 1;
         }
+        self.pos.offset += self.curr_char.len_utf8();
+
+        self.curr_char = match self.input.next() {
+            Some(Ok(ch)) => ch,
+            Some(Err(err)) => {
+                self.read_error.get_or_insert(err);
+                self.at_end = true;
+                NULL_CHAR
+            }
+            None => {
+                self.at_end = true;
+                NULL_CHAR
+            }
+        };
+    }
+
+    /// Where the token last returned by `next_token` starts.
+    pub fn span(&self) -> Span {
+        self.token_start
+    }
+
+    /// The `///` doc comment above the token last returned by
+    /// `next_token`, one line per line of the comment.
+    pub fn doc(&self) -> Option<String> {
+        self.token_doc.clone()
+    }
+
+    /// Starts adding up the time `next_token` takes, see `time_spent`.
+    pub fn enable_timing(&mut self) {
+        self.time_spent.get_or_insert(Duration::ZERO);
+    }
+
+    pub fn time_spent(&self) -> Option<Duration> {
+        self.time_spent
     }
 
     pub fn next_token(&mut self) -> Result<Token, String> {
+        let started = self.time_spent.map(|_| Instant::now());
+        let token = self.read_token();
+        if let (Some(spent), Some(started)) = (self.time_spent.as_mut(), started) {
+            *spent += started.elapsed();
+        }
+        self.token_doc = match self.doc_lines.is_empty() {
+            true => None,
+            false => Some(std::mem::take(&mut self.doc_lines).join("\n")),
+        };
+        // a failed read looks like the end of input to the code above
+        match &self.read_error {
+            Some(err) => Err(err.clone()),
+            None => token,
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Token, String> {
         self.skip_whitespace();
+        self.token_start = self.pos;
         if self.eof() {
             return Ok(Token::Eof);
         }
@@ -61,8 +146,33 @@ impl<'a> Lexer<'a> {
             ']' => Some(Token::Rbracket),
             ',' => Some(Token::Comma),
             '*' => Some(Token::Asterisk),
-            '/' => Some(Token::Slash),
+            '/' => {
+                if self.next_char_is('/') {
+                    self.read_char();
+                    // `//` alone is still two slashes
+                    if !self.next_char_is('/') {
+                        return Ok(Token::Slash);
+                    }
+                    self.read_char();
+                    self.read_char();
+                    let line = self.chop_while(|x| x != '\n');
+                    let line = line.strip_prefix(' ').unwrap_or(&line);
+                    self.doc_lines.push(line.trim_end().to_string());
+                    return self.read_token();
+                }
+                Some(Token::Slash)
+            }
             ':' => Some(Token::Colon),
+            '.' => Some(Token::Dot),
+            '|' => Some(Token::Pipe),
+            '?' => {
+                if self.next_char_is('.') {
+                    self.read_char();
+                    Some(Token::QuestionDot)
+                } else {
+                    None
+                }
+            }
             '%' => Some(Token::Percet),
             '-' => {
                 if self.next_char_is('-') {
@@ -120,17 +230,17 @@ impl<'a> Lexer<'a> {
             _ => None,
         };
 
-        if token.is_some() {
+        if let Some(token) = token {
             self.read_char();
-            return Ok(token.unwrap());
+            return Ok(token);
         }
 
         if self.curr_char.is_alphabetic() {
             return Ok(self.read_identifier());
         }
 
-        if self.curr_char.is_numeric() {
-            return Ok(self.read_number());
+        if self.curr_char.is_ascii_digit() {
+            return self.read_number();
         }
 
         let illegal = Token::Illegal(self.curr_char);
@@ -156,8 +266,16 @@ impl<'a> Lexer<'a> {
             "else" => Token::Else,
             "for" => Token::For,
             "in" => Token::In,
+            "try" => Token::Try,
+            "catch" => Token::Catch,
+            "assert" => Token::Assert,
+            "import" => Token::Import,
+            "async" => Token::Async,
+            "await" => Token::Await,
+            "as" => Token::As,
             "void" => Token::TypeVoid,
             "Array" => Token::ClassArray,
+            "Set" => Token::ClassSet,
             _ => Token::Identifier(literal),
         }
     }
@@ -166,17 +284,23 @@ impl<'a> Lexer<'a> {
         self.read_char();
         let literal = self.chop_while(|x| x != '"');
         if self.curr_char != '"' {
-            return Err(format!("Unbalanced '\"'"));
+            return Err(msg!("E001.unbalanced_quote"));
         }
         Ok(Token::String(literal))
     }
 
-    fn read_number(&mut self) -> Token {
-        let literal = self.chop_while(|x| x.is_numeric() || x == '.');
-        if literal.contains(".") {
-            return Token::Float(literal.parse::<f64>().unwrap());
+    fn read_number(&mut self) -> Result<Token, String> {
+        let literal = self.chop_while(|x| x.is_ascii_digit() || x == '.');
+        if literal.contains('.') {
+            return match literal.parse::<f64>() {
+                Ok(val) => Ok(Token::Float(val)),
+                Err(_) => Err(msg!("E001.invalid_number", literal = literal)),
+            };
+        }
+        match literal.parse::<i64>() {
+            Ok(val) => Ok(Token::Int(val)),
+            Err(_) => Err(msg!("E001.int_too_large", literal = literal)),
         }
-        return Token::Int(literal.parse::<i64>().unwrap());
     }
 
     fn skip_whitespace(&mut self) {
@@ -187,26 +311,20 @@ impl<'a> Lexer<'a> {
     where
         P: FnMut(char) -> bool,
     {
-        let start = self.pos;
+        let mut literal = String::new();
         while !self.eof() && predicate(self.curr_char) {
+            literal.push(self.curr_char);
             self.read_char();
         }
-        self.chop(start, self.pos)
-    }
-
-    fn chop(&mut self, begin: usize, end: usize) -> String {
-        self.input[begin..end].iter().collect::<String>()
+        literal
     }
 
     fn eof(&mut self) -> bool {
-        self.curr_char == NULL_CHAR
+        self.at_end
     }
 
     fn next_char_is(&mut self, x: char) -> bool {
-        if self.read_pos >= self.input.len() {
-            return false;
-        }
-        self.input[self.read_pos] == x
+        matches!(self.input.peek(), Some(Ok(ch)) if *ch == x)
     }
 }
 
@@ -343,4 +461,60 @@ for x in range(1, 10, 2) {
             assert_eq!(expected_token, lexer.next_token().unwrap());
         }
     }
+
+    #[test]
+    fn test_lexing_from_reader() {
+        let input = "let café = \"olá\" >= 10.5";
+        let chars = input.chars().collect::<Vec<char>>();
+        let mut from_slice = Lexer::new(&chars);
+        let mut from_reader = Lexer::from_reader(input.as_bytes());
+        loop {
+            let token = from_slice.next_token().unwrap();
+            assert_eq!(token, from_reader.next_token().unwrap());
+            if token == Token::Eof {
+                break;
+            }
+        }
+
+        let mut invalid = Lexer::from_reader(&b"let \"\xff\""[..]);
+        assert_eq!(invalid.next_token(), Ok(Token::Let));
+        assert!(invalid.next_token().is_err());
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "let é = 1\n  print(é)".chars().collect::<Vec<char>>();
+        let mut lexer = Lexer::new(&input);
+        let mut spans = vec![];
+        while lexer.next_token().unwrap() != Token::Eof {
+            let span = lexer.span();
+            spans.push((span.line, span.col, span.offset));
+        }
+        assert_eq!(
+            spans,
+            [
+                (1, 1, 0),
+                (1, 5, 4),
+                (1, 7, 7),
+                (1, 9, 9),
+                (2, 3, 13),
+                (2, 8, 18),
+                (2, 9, 19),
+                (2, 10, 21)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shebang_is_skipped() {
+        let input = "#!/usr/bin/env filipe\nx".chars().collect::<Vec<char>>();
+        let mut lexer = Lexer::new(&input);
+        assert_eq!(lexer.next_token(), Ok(Token::Identifier("x".to_string())));
+        assert_eq!(lexer.span().line, 2);
+
+        let input = "x #!".chars().collect::<Vec<char>>();
+        let mut lexer = Lexer::new(&input);
+        lexer.next_token().unwrap();
+        assert_eq!(lexer.next_token(), Ok(Token::Illegal('#')));
+    }
 }
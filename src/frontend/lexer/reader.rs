@@ -0,0 +1,57 @@
+use std::io::{BufReader, Bytes, Read};
+
+use crate::messages::msg;
+
+/// Decodes UTF-8 chars from a reader as they're needed.
+pub struct CharReader<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+}
+
+impl<R: Read> CharReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            bytes: BufReader::new(reader).bytes(),
+        }
+    }
+
+    fn next_byte(&mut self) -> Option<Result<u8, String>> {
+        self.bytes
+            .next()
+            .map(|byte| byte.map_err(|err| msg!("E001.unreadable", error = err)))
+    }
+}
+
+impl<R: Read> Iterator for CharReader<R> {
+    type Item = Result<char, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self.next_byte()? {
+            Ok(byte) => byte,
+            Err(err) => return Some(Err(err)),
+        };
+        let width = match first {
+            0x00..=0x7F => return Some(Ok(first as char)),
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return Some(Err(invalid_utf8())),
+        };
+
+        let mut buf = [first, 0, 0, 0];
+        for slot in &mut buf[1..width] {
+            *slot = match self.next_byte() {
+                Some(Ok(byte)) => byte,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return Some(Err(invalid_utf8())),
+            };
+        }
+        match std::str::from_utf8(&buf[..width]) {
+            Ok(decoded) => decoded.chars().next().map(Ok),
+            Err(_) => Some(Err(invalid_utf8())),
+        }
+    }
+}
+
+fn invalid_utf8() -> String {
+    msg!("E001.invalid_utf8")
+}
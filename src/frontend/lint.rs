@@ -0,0 +1,244 @@
+//! `filipe lint`: the warnings `run` prints plus style rules, each with a
+//! name a project can turn off or make an error in a `filipe-lint.json`
+//! file next to its scripts or in a directory above them:
+//!
+//! ```json
+//! { "rules": { "naming": "error", "shadowing": "off" }, "maxDepth": 3 }
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::ast::*;
+use super::token::Span;
+use super::warnings::{self, Warning, WarningKind};
+use crate::messages::msg;
+use crate::runtime::flstdlib::is_builtin;
+
+pub const CONFIG_FILE: &str = "filipe-lint.json";
+
+/// Names of the rules, in the order of their codes.
+pub const RULES: [&str; 8] = [
+    "unused-variable",
+    "unused-parameter",
+    "unreachable-code",
+    "constant-condition",
+    "shadowing",
+    "shadowed-builtin",
+    "deep-nesting",
+    "naming",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct LintConfig {
+    /// Level of each rule by name; the ones left out warn.
+    pub rules: HashMap<String, RuleLevel>,
+    /// How many blocks deep code can be before `deep-nesting` complains.
+    pub max_depth: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            rules: HashMap::new(),
+            max_depth: 4,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let config = serde_json::from_str::<Self>(json).map_err(|err| err.to_string())?;
+        match config
+            .rules
+            .keys()
+            .find(|rule| !RULES.contains(&rule.as_str()))
+        {
+            Some(rule) => Err(format!("Unknown rule '{rule}'")),
+            None => Ok(config),
+        }
+    }
+
+    /// Path of the config that applies to scripts in `dir`: the nearest
+    /// `filipe-lint.json` in it or above it.
+    pub fn find(dir: &Path) -> Option<String> {
+        dir.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+            .map(|path| path.to_string_lossy().to_string())
+    }
+
+    pub fn level(&self, kind: &WarningKind) -> RuleLevel {
+        self.rules
+            .get(rule(kind))
+            .copied()
+            .unwrap_or(RuleLevel::Warn)
+    }
+}
+
+/// Name of the rule reporting `kind`.
+pub fn rule(kind: &WarningKind) -> &'static str {
+    match kind {
+        WarningKind::UnusedVariable => RULES[0],
+        WarningKind::UnusedParameter => RULES[1],
+        WarningKind::UnreachableCode => RULES[2],
+        WarningKind::ConstantCondition => RULES[3],
+        WarningKind::Shadowing => RULES[4],
+        WarningKind::ShadowedBuiltin => RULES[5],
+        WarningKind::DeepNesting => RULES[6],
+        WarningKind::Naming => RULES[7],
+    }
+}
+
+/// Everything the rules turned on in `config` find, in source order, with
+/// the level each was reported at.
+pub fn lint(program: &Program, config: &LintConfig) -> Vec<(Warning, RuleLevel)> {
+    let shadowing = config.level(&WarningKind::Shadowing) != RuleLevel::Off;
+    let mut warnings = warnings::check(program, shadowing);
+    let mut style = Style {
+        max_depth: config.max_depth,
+        warnings: vec![],
+    };
+    style.check_block(program, 0);
+    warnings.append(&mut style.warnings);
+    warnings.sort_by_key(|warning| warning.span.offset);
+    warnings
+        .into_iter()
+        .map(|warning| {
+            let level = config.level(&warning.kind);
+            (warning, level)
+        })
+        .filter(|(_, level)| *level != RuleLevel::Off)
+        .collect()
+}
+
+/// The rules that are about how code is written rather than what it does.
+struct Style {
+    max_depth: usize,
+    warnings: Vec<Warning>,
+}
+
+impl Style {
+    fn check_block(&mut self, block: &BlockStmt, depth: usize) {
+        for stmt in block {
+            self.check_stmt(stmt, depth);
+        }
+    }
+
+    /// Checks a block opened by `stmt` inside one `depth` levels deep.
+    fn check_nested(&mut self, block: &BlockStmt, depth: usize, stmt: &Stmt) {
+        let depth = depth + 1;
+        if depth == self.max_depth + 1 {
+            let msg = msg!("W007.too_deep", depth = depth, max = self.max_depth);
+            self.warn(WarningKind::DeepNesting, msg, stmt.span);
+        }
+        self.check_block(block, depth);
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt, depth: usize) {
+        match &stmt.kind {
+            StmtKind::Let(Identifier(name), ..) => self.check_name(name, stmt.span, depth),
+            StmtKind::Func(Identifier(name), params, body, ..) => {
+                self.check_name(name, stmt.span, depth);
+                for (Identifier(param), _) in params {
+                    self.check_name(param, stmt.span, depth + 1);
+                }
+                self.check_nested(body, depth, stmt);
+            }
+            StmtKind::If {
+                consequence,
+                alternative,
+                ..
+            } => {
+                self.check_nested(consequence, depth, stmt);
+                if let Some(alternative) = alternative {
+                    self.check_nested(alternative, depth, stmt);
+                }
+            }
+            StmtKind::ForLoop { cursor, block, .. } => {
+                self.check_name(cursor, stmt.span, depth + 1);
+                self.check_nested(block, depth, stmt);
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.check_nested(block, depth, stmt);
+                self.check_name(binding, stmt.span, depth + 1);
+                self.check_nested(handler, depth, stmt);
+            }
+            StmtKind::Expr(_) | StmtKind::Assert(_) | StmtKind::Return(_) | StmtKind::Import(_) => {
+            }
+        }
+    }
+
+    /// Checks a name declared `depth` levels deep.
+    fn check_name(&mut self, name: &str, span: Span, depth: usize) {
+        // redeclaring a builtin globally is already an error (E010)
+        if depth > 0 && is_builtin(name) {
+            let msg = msg!("W006.shadows_builtin", name = name);
+            self.warn(WarningKind::ShadowedBuiltin, msg, span);
+        }
+        if name.starts_with(|c: char| c.is_uppercase()) {
+            self.warn(WarningKind::Naming, msg!("W008.naming", name = name), span);
+        }
+    }
+
+    fn warn(&mut self, kind: WarningKind, msg: String, span: Span) {
+        self.warnings.push(Warning { kind, msg, span });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_rules_follow_the_config() {
+        let input = "let Total = 0\n\
+                     define add(len: int): int {\n\
+                     \x20 if len > 0 {\n\
+                     \x20   if len > 1 {\n\
+                     \x20     return len\n\
+                     \x20   }\n\
+                     \x20 }\n\
+                     \x20 return 0\n\
+                     }\n\
+                     print(add(Total))"
+            .chars()
+            .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+
+        let config = LintConfig::parse(r#"{"rules": {"naming": "error"}, "maxDepth": 2}"#).unwrap();
+        let found = lint(&program, &config)
+            .into_iter()
+            .map(|(warning, level)| (warning.kind, warning.span.line, level))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (WarningKind::Naming, 1, RuleLevel::Error),
+                (WarningKind::ShadowedBuiltin, 2, RuleLevel::Warn),
+                (WarningKind::DeepNesting, 4, RuleLevel::Warn),
+            ]
+        );
+        assert_eq!(
+            LintConfig::parse(r#"{"rules": {"tabs": "off"}}"#).unwrap_err(),
+            "Unknown rule 'tabs'"
+        );
+    }
+}
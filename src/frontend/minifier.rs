@@ -0,0 +1,400 @@
+//! Prints a parsed program back as compact source for `filipe minify`: no
+//! doc comments, and whitespace only where two tokens would otherwise run
+//! together, as statements don't need to be on lines of their own.
+//!
+//! With renaming, the locals of functions and blocks get the shortest
+//! names that are free, following the scopes the resolver gives them.
+//! Names the resolver leaves to be looked up at run time, like globals and
+//! variables of a caller's scope, are kept, and so are all locals with one
+//! of those names.
+
+use std::collections::HashSet;
+
+use super::ast::*;
+use crate::runtime::flstdlib::is_builtin;
+
+/// Words the lexer reads as keywords, which can't be names.
+const KEYWORDS: &[&str] = &[
+    "let", "define", "return", "true", "false", "null", "string", "int", "float", "boolean", "if",
+    "else", "for", "in", "try", "catch", "assert", "import", "async", "await", "as", "void",
+    "Array", "Set",
+];
+
+/// `program` as compact source. Imports are kept as they are.
+pub fn minify(program: &Program, rename: bool) -> String {
+    // a first pass finds the names looked up at run time, which the locals
+    // can't be renamed to or from
+    let mut dry_run = Minifier::new(HashSet::new(), false);
+    dry_run.block(program);
+    let mut minifier = Minifier::new(dry_run.unresolved, rename);
+    minifier.block(program);
+    minifier.out
+}
+
+struct Minifier {
+    out: String,
+    rename: bool,
+    /// Names of the scopes the resolver tracks, each with the one it is
+    /// printed as.
+    scopes: Vec<Vec<(String, String)>>,
+    /// Index of the first scope of the current function, like the resolver.
+    function_base: usize,
+    /// How many short names the current function gave out.
+    next_name: usize,
+    /// Names resolved at run time: `Identifier`s, and assignments to names
+    /// that aren't locals.
+    unresolved: HashSet<String>,
+}
+
+impl Minifier {
+    fn new(unresolved: HashSet<String>, rename: bool) -> Self {
+        Self {
+            out: String::new(),
+            rename,
+            scopes: vec![],
+            function_base: 0,
+            next_name: 0,
+            unresolved,
+        }
+    }
+
+    fn block(&mut self, block: &BlockStmt) {
+        for stmt in block {
+            self.stmt(stmt);
+        }
+    }
+
+    fn scoped_block(&mut self, block: &BlockStmt, names: &[String]) {
+        let next_name = self.next_name;
+        self.scopes.push(vec![]);
+        for name in names {
+            self.declare(name, true);
+        }
+        self.token("{");
+        self.block(block);
+        self.token("}");
+        self.scopes.pop();
+        self.next_name = next_name;
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => self.expr(expr),
+            StmtKind::Let(Identifier(name), annotation, value) => {
+                // declared after its value, which may read an outer `name`
+                let value = value.as_ref().map(|value| self.capture(|m| m.expr(value)));
+                let name = self.declare(name, false);
+                self.token("let");
+                self.token(&name);
+                if let Some(annotation) = annotation {
+                    self.token(":");
+                    self.token(&annotation.to_string());
+                }
+                if let Some(value) = value {
+                    self.token("=");
+                    self.token(&value);
+                }
+            }
+            StmtKind::Func(Identifier(name), params, body, ret_type, _, is_async) => {
+                let name = self.declare(name, false);
+                if *is_async {
+                    self.token("async");
+                }
+                self.token("define");
+                self.token(&name);
+
+                let enclosing = (self.function_base, self.next_name);
+                self.function_base = self.scopes.len();
+                self.next_name = 0;
+                self.scopes.push(vec![]);
+                self.token("(");
+                for (index, (Identifier(param), type_)) in params.iter().enumerate() {
+                    if index > 0 {
+                        self.token(",");
+                    }
+                    let param = self.declare(param, true);
+                    self.token(&param);
+                    self.token(":");
+                    self.token(&type_.to_string());
+                }
+                self.token(")");
+                if let Some(ret_type) = ret_type {
+                    self.token(":");
+                    self.token(&ret_type.to_string());
+                }
+                self.token("{");
+                self.block(body);
+                self.token("}");
+                self.scopes.pop();
+                (self.function_base, self.next_name) = enclosing;
+            }
+            StmtKind::Return(value) => {
+                self.token("return");
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.token("if");
+                self.expr(condition);
+                self.scoped_block(consequence, &[]);
+                if let Some(alternative) = alternative {
+                    self.token("else");
+                    self.scoped_block(alternative, &[]);
+                }
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                // the cursor is only known inside the loop's scope
+                let iterable = self.capture(|m| m.expr(iterable));
+                self.token("for");
+                let next_name = self.next_name;
+                self.scopes.push(vec![]);
+                let cursor = self.declare(cursor, true);
+                self.token(&cursor);
+                self.token("in");
+                self.token(&iterable);
+                self.token("{");
+                self.block(block);
+                self.token("}");
+                self.scopes.pop();
+                self.next_name = next_name;
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.token("try");
+                self.scoped_block(block, &[]);
+                self.token("catch");
+                let next_name = self.next_name;
+                self.scopes.push(vec![]);
+                let binding = self.declare(binding, true);
+                self.token(&binding);
+                self.token("{");
+                self.block(handler);
+                self.token("}");
+                self.scopes.pop();
+                self.next_name = next_name;
+            }
+            StmtKind::Assert(condition) => {
+                self.token("assert");
+                self.expr(condition);
+            }
+            StmtKind::Import(name) => {
+                self.token("import");
+                self.token(&format!("\"{}\"", name));
+            }
+        }
+    }
+
+    /// What `print` adds to the output, taken out of it.
+    fn capture(&mut self, print: impl FnOnce(&mut Self)) -> String {
+        let out = std::mem::take(&mut self.out);
+        print(self);
+        std::mem::replace(&mut self.out, out)
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Array(items)) => {
+                self.token("[");
+                self.list(items);
+                self.token("]");
+            }
+            ExprKind::Literal(literal) => self.token(&literal.to_string()),
+            ExprKind::Identifier(Identifier(name)) => {
+                self.unresolved.insert(name.clone());
+                self.token(name);
+            }
+            ExprKind::Local(Identifier(name), _) => {
+                let name = self.lookup(name);
+                self.token(&name);
+            }
+            ExprKind::Call(func, args) => {
+                self.expr(func);
+                self.token("(");
+                self.list(args);
+                self.token(")");
+            }
+            ExprKind::Infix(lhs, infix, rhs) => {
+                self.expr(lhs);
+                self.token(&infix.to_string());
+                self.expr(rhs);
+            }
+            ExprKind::Prefix(prefix, operand) => {
+                self.token(&prefix.to_string());
+                self.expr(operand);
+            }
+            ExprKind::Postfix(operand, postfix) => {
+                self.expr(operand);
+                self.token(&postfix.to_string());
+            }
+            ExprKind::Assign(Identifier(name), value) => {
+                let name = self.lookup(name);
+                self.token(&name);
+                self.token("=");
+                self.expr(value);
+            }
+            ExprKind::Index(target, index) => {
+                self.expr(target);
+                self.token("[");
+                self.expr(index);
+                self.token("]");
+            }
+            ExprKind::IndexAssign(Identifier(name), index, value) => {
+                let name = self.lookup(name);
+                self.token(&name);
+                self.token("[");
+                self.expr(index);
+                self.token("]");
+                self.token("=");
+                self.expr(value);
+            }
+            ExprKind::Await(operand) => {
+                self.token("await");
+                self.expr(operand);
+            }
+            ExprKind::Cast(operand, type_) => {
+                self.expr(operand);
+                self.token("as");
+                self.token(&type_.to_string());
+            }
+            ExprKind::Method(receiver, Identifier(name), args, optional) => {
+                self.expr(receiver);
+                self.token(if *optional { "?." } else { "." });
+                let name = self.lookup(name);
+                self.token(&name);
+                self.token("(");
+                self.list(args);
+                self.token(")");
+            }
+        }
+    }
+
+    fn list(&mut self, exprs: &[Expr]) {
+        for (index, expr) in exprs.iter().enumerate() {
+            if index > 0 {
+                self.token(",");
+            }
+            self.expr(expr);
+        }
+    }
+
+    /// Adds `name` to the innermost scope, unless it is there already, and
+    /// returns the name it is printed as. `always` pushes it anyway, like
+    /// the resolver does for parameters and loop cursors.
+    fn declare(&mut self, name: &str, always: bool) -> String {
+        let Some(scope) = self.scopes.last() else {
+            return name.to_string();
+        };
+        if !always {
+            if let Some((_, printed)) = scope.iter().find(|(declared, _)| declared == name) {
+                return printed.clone();
+            }
+        }
+        let printed = match self.rename && !self.unresolved.contains(name) {
+            true => self.short_name(),
+            false => name.to_string(),
+        };
+        let scope = self.scopes.last_mut().unwrap();
+        scope.push((name.to_string(), printed.clone()));
+        printed
+    }
+
+    /// What a local `name` of the current function is printed as. Names
+    /// that aren't locals are resolved at run time.
+    fn lookup(&mut self, name: &str) -> String {
+        for scope in self.scopes[self.function_base..].iter().rev() {
+            if let Some((_, printed)) = scope.iter().find(|(declared, _)| declared == name) {
+                return printed.clone();
+            }
+        }
+        self.unresolved.insert(name.to_string());
+        name.to_string()
+    }
+
+    /// The next of `a`, `b`, ..., `z`, `aa`, `ab`, ... that no name looked
+    /// up at run time, keyword or builtin uses.
+    fn short_name(&mut self) -> String {
+        loop {
+            let mut n = self.next_name;
+            self.next_name += 1;
+            let mut name = String::new();
+            loop {
+                name.insert(0, (b'a' + (n % 26) as u8) as char);
+                match n / 26 {
+                    0 => break,
+                    rest => n = rest - 1,
+                }
+            }
+            let taken = self.unresolved.contains(&name)
+                || KEYWORDS.contains(&name.as_str())
+                || is_builtin(&name);
+            if !taken {
+                return name;
+            }
+        }
+    }
+
+    /// Appends `text`, after a space if it would run into the previous
+    /// token: two words, or two operators like the `-`s of `a - -b`.
+    fn token(&mut self, text: &str) {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+        let is_operator = |c: char| "+-*/%=<>!&|".contains(c);
+        if let (Some(last), Some(first)) = (self.out.chars().last(), text.chars().next()) {
+            if is_word(last) && is_word(first) || is_operator(last) && is_operator(first) {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    fn minify_source(source: &str, rename: bool) -> String {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        assert!(!p.has_error());
+        minify(&program, rename)
+    }
+
+    #[test]
+    fn test_minify() {
+        let source = "/// Sums the items.\n\
+                      define sum(items: Array<int>): int {\n\
+                      \x20 let total = 0\n\
+                      \x20 for item in items {\n\
+                      \x20   total = total + item\n\
+                      \x20 }\n\
+                      \x20 count = count + 1\n\
+                      \x20 return total - -1\n\
+                      }\n\
+                      let count = 0\n\
+                      print(sum([1, 2, 3]), count)";
+        assert_eq!(
+            minify_source(source, false),
+            "define sum(items:Array<int>):int{let total=0 for item in items{total=total+item}\
+             count=count+1 return total- -1}let count=0 print(sum([1,2,3]),count)"
+        );
+        assert_eq!(
+            minify_source(source, true),
+            "define sum(a:Array<int>):int{let b=0 for c in a{b=b+c}\
+             count=count+1 return b- -1}let count=0 print(sum([1,2,3]),count)"
+        );
+    }
+}
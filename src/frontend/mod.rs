@@ -1,4 +1,11 @@
-pub mod token;
+pub mod ast;
+pub mod checker;
+pub mod docgen;
+pub mod formatter;
 pub mod lexer;
+pub mod lint;
+pub mod minifier;
 pub mod parser;
-pub mod ast;
+pub mod resolver;
+pub mod token;
+pub mod warnings;
@@ -1,39 +1,57 @@
 use core::fmt;
 
-use super::super::token::Token;
+use super::super::ast::ExprType;
+use super::super::token::{Span, Token};
+use crate::messages::msg;
 
 #[derive(Clone)]
 pub enum ParserErrorKind {
     SyntaxError,
 }
 
+impl ParserErrorKind {
+    /// Stable code that `filipe explain` describes in detail.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserErrorKind::SyntaxError => "E001",
+        }
+    }
+}
+
 impl fmt::Display for ParserErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ParserErrorKind::SyntaxError => write!(f, "[Syntax Error]"),
+            ParserErrorKind::SyntaxError => write!(f, "[{}]", msg!("E001.kind")),
         }
     }
 }
 
 #[derive(Clone)]
 pub struct ParserError {
-    kind: ParserErrorKind,
-    msg: String,
+    pub kind: ParserErrorKind,
+    pub msg: String,
+    pub span: Span,
 }
 
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.kind, self.msg)
+        write!(f, "{} at {}: {}", self.kind, self.span, self.msg)
     }
 }
 
 pub struct ParserErrorHandler {
+    /// Error that aborted the statement being parsed.
     error: Option<ParserError>,
+    /// Errors of statements the parser already skipped past.
+    reported: Vec<ParserError>,
 }
 
 impl ParserErrorHandler {
     pub fn new() -> Self {
-        Self { error: None }
+        Self {
+            error: None,
+            reported: vec![],
+        }
     }
 
     pub fn has_error(&self) -> bool {
@@ -43,49 +61,107 @@ impl ParserErrorHandler {
         false
     }
 
-    pub fn set_error(&mut self, kind: ParserErrorKind, msg: String) {
-        self.error = Some(ParserError { kind, msg });
+    pub fn set_error(&mut self, kind: ParserErrorKind, msg: String, span: Span) {
+        self.error = Some(ParserError { kind, msg, span });
     }
 
-    pub fn get_error(&self) -> Option<ParserError> {
-        if self.error.is_none() {
-            return None;
+    /// Moves the pending error to the reported ones so parsing can go on.
+    pub fn report(&mut self) {
+        if let Some(error) = self.error.take() {
+            self.reported.push(error);
         }
-        Some(self.error.clone().unwrap())
     }
 
-    pub fn set_invalid_left_side_of_assignment_error(&mut self) {
+    pub fn errors(&self) -> &[ParserError] {
+        &self.reported
+    }
+
+    pub fn set_invalid_left_side_of_assignment_error(&mut self, span: Span) {
+        self.error = Some(ParserError {
+            kind: ParserErrorKind::SyntaxError,
+            msg: msg!("E001.invalid_assign_target"),
+            span,
+        });
+    }
+
+    pub fn set_assign_to_constant_error(&mut self, target: &str, span: Span) {
+        self.error = Some(ParserError {
+            kind: ParserErrorKind::SyntaxError,
+            msg: msg!("E001.assign_to_constant", name = target),
+            span,
+        });
+    }
+
+    pub fn set_assign_to_builtin_error(&mut self, name: &str, span: Span) {
+        self.error = Some(ParserError {
+            kind: ParserErrorKind::SyntaxError,
+            msg: msg!("E001.assign_to_builtin", name = name),
+            span,
+        });
+    }
+
+    pub fn set_identifier_error(&mut self, token: &Token, span: Span) {
+        self.error = Some(ParserError {
+            kind: ParserErrorKind::SyntaxError,
+            msg: msg!("E001.invalid_identifier", token = token),
+            span,
+        });
+    }
+
+    pub fn set_expected_but_provided_error(
+        &mut self,
+        expected: &Token,
+        provided: &Token,
+        span: Span,
+    ) {
+        self.error = Some(ParserError {
+            kind: ParserErrorKind::SyntaxError,
+            msg: msg!(
+                "E001.expected_but_provided",
+                expected = expected,
+                found = provided
+            ),
+            span,
+        });
+    }
+
+    pub fn set_unexpexted_token_error(&mut self, token: &Token, span: Span) {
         self.error = Some(ParserError {
             kind: ParserErrorKind::SyntaxError,
-            msg: format!("Left side of assignment must an identifier"),
+            msg: msg!("E001.unexpected_token", token = token),
+            span,
         });
     }
 
-    pub fn set_identifier_error(&mut self, token: &Token) {
+    pub fn set_nested_import_error(&mut self, span: Span) {
         self.error = Some(ParserError {
             kind: ParserErrorKind::SyntaxError,
-            msg: format!("'{token}' cannot be used as identifier"),
+            msg: msg!("E001.nested_import"),
+            span,
         });
     }
 
-    pub fn set_expected_but_provided_error(&mut self, expected: &Token, provided: &Token) {
+    pub fn set_too_deeply_nested_error(&mut self, span: Span) {
         self.error = Some(ParserError {
             kind: ParserErrorKind::SyntaxError,
-            msg: format!("expected '{}' but provided '{}'", expected, provided),
+            msg: msg!("E001.too_deeply_nested", max = super::MAX_NESTING),
+            span,
         });
     }
 
-    pub fn set_unexpexted_token_error(&mut self, token: &Token) {
+    pub fn set_not_type_annot_error(&mut self, token: &Token, span: Span) {
         self.error = Some(ParserError {
             kind: ParserErrorKind::SyntaxError,
-            msg: format!("unexpected {}", token),
+            msg: msg!("E001.invalid_type", token = token),
+            span,
         });
     }
 
-    pub fn set_not_type_annot_error(&mut self, token: &Token) {
+    pub fn set_cast_target_error(&mut self, type_: &ExprType, span: Span) {
         self.error = Some(ParserError {
             kind: ParserErrorKind::SyntaxError,
-            msg: format!("Invalid type: {}", token),
+            msg: msg!("E001.cast_target", found = type_),
+            span,
         });
     }
 }
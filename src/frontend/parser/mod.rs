@@ -4,25 +4,46 @@ mod parsers;
 use self::parsers::if_parser::parse_if_stmt;
 use super::ast::*;
 use super::lexer::Lexer;
-use super::token::Token;
+use super::resolver::resolve;
+use super::token::{Span, Token};
+use crate::runtime::flstdlib::is_builtin;
 use error_handler::*;
+pub use error_handler::{ParserError, ParserErrorKind};
 use parsers::forloop_parser::parse_forloop_stmt;
 use parsers::func_parser::parse_func_stmt;
 use parsers::let_parser::parse_let_stmt;
+use parsers::try_parser::parse_try_stmt;
 
-pub struct Parser<'a> {
-    l: &'a mut Lexer<'a>,
+/// How deep expressions and blocks may nest. Parsing is recursive, so this
+/// keeps hostile input from overflowing the stack.
+const MAX_NESTING: usize = 200;
+
+pub struct Parser<'l, 'a> {
+    l: &'l mut Lexer<'a>,
     curr_token: Token,
     next_token: Token,
+    curr_span: Span,
+    next_span: Span,
+    /// Doc comments above `curr_token` and `next_token`.
+    curr_doc: Option<String>,
+    next_doc: Option<String>,
+    lexer_failed: bool,
+    nesting: usize,
     error_handler: ParserErrorHandler,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(l: &'a mut Lexer<'a>) -> Self {
+impl<'l, 'a> Parser<'l, 'a> {
+    pub fn new(l: &'l mut Lexer<'a>) -> Self {
         let mut p = Parser {
             l,
             curr_token: Token::Eof,
             next_token: Token::Eof,
+            curr_span: Span::default(),
+            next_span: Span::default(),
+            curr_doc: None,
+            next_doc: None,
+            lexer_failed: false,
+            nesting: 0,
             error_handler: ParserErrorHandler::new(),
         };
 
@@ -33,69 +54,175 @@ impl<'a> Parser<'a> {
     }
 
     fn bump(&mut self) {
-        let next_token = self.l.next_token();
-        if next_token.is_err() {
-            self.error_handler
-                .set_error(ParserErrorKind::SyntaxError, next_token.err().unwrap());
+        self.curr_token = self.next_token.clone();
+        self.curr_span = self.next_span;
+        self.curr_doc = self.next_doc.take();
+        if self.lexer_failed {
             return;
         }
 
-        self.curr_token = self.next_token.clone();
-        self.next_token = next_token.unwrap();
+        match self.l.next_token() {
+            Ok(token) => {
+                self.next_token = token;
+                self.next_span = self.l.span();
+                self.next_doc = self.l.doc();
+            }
+            // The lexer can't resume after an error, so treat it as the end
+            // of the input.
+            Err(msg) => {
+                self.error_handler
+                    .set_error(ParserErrorKind::SyntaxError, msg, self.l.span());
+                self.next_token = Token::Eof;
+                self.lexer_failed = true;
+            }
+        }
     }
 
+    /// Parses the whole input. Statements with syntax errors are skipped and
+    /// left out of the returned program; the errors are in `errors()`.
     pub fn parse(&mut self) -> Program {
         let mut program: Program = vec![];
-        while !self.current_token_is(&Token::Eof) && !self.error_handler.has_error() {
+        while !self.current_token_is(&Token::Eof) {
             match self.parse_stmt() {
                 Some(stmt) => program.push(stmt),
+                None if self.error_handler.has_error() => self.recover(),
                 None => {}
             }
             self.bump();
         }
+        self.error_handler.report();
+        log::debug!(
+            "parsed {} statements, {} errors",
+            program.len(),
+            self.errors().len()
+        );
+        resolve(&mut program);
         program
     }
 
+    /// Records the pending error and skips ahead until the next token can
+    /// start a statement, i.e. it is a statement keyword or the first token
+    /// of a line, or closes the enclosing block. Nested blocks are skipped
+    /// whole.
+    fn recover(&mut self) {
+        self.error_handler.report();
+        let mut depth = usize::from(self.current_token_is(&Token::Lbrace));
+        loop {
+            let starts_stmt = self.next_span.line > self.curr_span.line
+                || matches!(
+                    self.next_token,
+                    Token::Let
+                        | Token::Func
+                        | Token::Async
+                        | Token::Return
+                        | Token::If
+                        | Token::For
+                        | Token::Try
+                        | Token::Assert
+                        | Token::Import
+                        | Token::Rbrace
+                );
+            match self.next_token {
+                Token::Eof => return,
+                _ if depth == 0 && starts_stmt => return,
+                Token::Lbrace => depth += 1,
+                Token::Rbrace => depth -= 1,
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
     fn parse_stmt(&mut self) -> Option<Stmt> {
-        if self.has_error() {
+        if self.error_handler.has_error() {
             return None;
         }
 
-        match self.curr_token {
+        let span = self.curr_span;
+        let kind = match self.curr_token {
             Token::Let => parse_let_stmt(self),
-            Token::Func => parse_func_stmt(self),
+            Token::Func | Token::Async => parse_func_stmt(self),
             Token::Return => self.parse_return_stmt(),
             Token::If => parse_if_stmt(self),
             Token::For => parse_forloop_stmt(self),
+            Token::Try => parse_try_stmt(self),
+            Token::Assert => self.parse_assert_stmt(),
+            Token::Import => self.parse_import_stmt(),
             _ => self.parse_expr_stmt(),
+        }?;
+        Some(Stmt { kind, span })
+    }
+
+    /// Runs a sub-parser one nesting level deeper, failing instead once
+    /// `MAX_NESTING` is reached.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.nesting >= MAX_NESTING {
+            self.error_handler
+                .set_too_deeply_nested_error(self.curr_span);
+            return None;
         }
+        self.nesting += 1;
+        let parsed = parse(self);
+        self.nesting -= 1;
+        parsed
     }
 
     fn parse_block_stmt(&mut self) -> Option<Vec<Stmt>> {
+        self.nested(Self::parse_block_stmt_inner)
+    }
+
+    fn parse_block_stmt_inner(&mut self) -> Option<Vec<Stmt>> {
         self.bump();
         let mut block: Vec<Stmt> = vec![];
         while !self.current_token_is(&Token::Rbrace) && !self.current_token_is(&Token::Eof) {
             match self.parse_stmt() {
+                // modules are loaded into the global scope
+                Some(Stmt {
+                    kind: StmtKind::Import(_),
+                    span,
+                }) => {
+                    self.error_handler.set_nested_import_error(span);
+                    self.recover();
+                }
                 Some(stmt) => block.push(stmt),
-                _ => return None,
+                None if self.error_handler.has_error() => self.recover(),
+                None => return None,
             }
             self.bump();
         }
         if !self.current_token_is(&Token::Rbrace) {
-            self.error_handler
-                .set_expected_but_provided_error(&Token::Rbrace, &self.curr_token);
+            self.error_handler.set_expected_but_provided_error(
+                &Token::Rbrace,
+                &self.curr_token,
+                self.curr_span,
+            );
             return None;
         }
         Some(block)
     }
 
-    fn parse_return_stmt(&mut self) -> Option<Stmt> {
+    fn parse_return_stmt(&mut self) -> Option<StmtKind> {
         self.bump();
-        let expr = match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => expr,
-            None => return None,
-        };
-        Some(Stmt::Return(Some(expr)))
+        let expr = self.parse_expr(Precedence::Lowest)?;
+        Some(StmtKind::Return(Some(expr)))
+    }
+
+    fn parse_assert_stmt(&mut self) -> Option<StmtKind> {
+        self.bump();
+        let condition = self.parse_expr(Precedence::Lowest)?;
+        Some(StmtKind::Assert(condition))
+    }
+
+    fn parse_import_stmt(&mut self) -> Option<StmtKind> {
+        self.bump();
+        match &self.curr_token {
+            Token::String(name) => Some(StmtKind::Import(name.clone())),
+            token => {
+                self.error_handler
+                    .set_unexpexted_token_error(token, self.curr_span);
+                None
+            }
+        }
     }
 
     fn parse_identifier(&mut self) -> Option<Identifier> {
@@ -105,15 +232,16 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_expr_stmt(&mut self) -> Option<Stmt> {
-        match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => Some(Stmt::Expr(expr)),
-            None => None,
-        }
+    fn parse_expr_stmt(&mut self) -> Option<StmtKind> {
+        self.parse_expr(Precedence::Lowest).map(StmtKind::Expr)
     }
 
     fn parse_expr(&mut self, precedence: Precedence) -> Option<Expr> {
-        if self.has_error() {
+        self.nested(|p| p.parse_expr_inner(precedence))
+    }
+
+    fn parse_expr_inner(&mut self, precedence: Precedence) -> Option<Expr> {
+        if self.error_handler.has_error() {
             return None;
         }
 
@@ -122,22 +250,21 @@ impl<'a> Parser<'a> {
             Token::String(_) => self.parse_string_expr(),
             Token::Int(_) => self.parse_int_expr(),
             Token::Float(_) => self.parse_float_expr(),
-            Token::True => Some(Expr::Literal(Literal::Boolean(true))),
-            Token::False => Some(Expr::Literal(Literal::Boolean(false))),
-            Token::Null => Some(Expr::Literal(Literal::Null)),
+            Token::True => Some(self.expr_here(ExprKind::Literal(Literal::Boolean(true)))),
+            Token::False => Some(self.expr_here(ExprKind::Literal(Literal::Boolean(false)))),
+            Token::Null => Some(self.expr_here(ExprKind::Literal(Literal::Null))),
             Token::Bang | Token::Plus | Token::Minus => self.parse_prefix_expr(),
+            Token::Await => self.parse_await_expr(),
             Token::Lbracket => self.parse_array_expr(),
             _ => {
                 let token = self.curr_token.clone();
-                self.error_handler.set_unexpexted_token_error(&token);
+                self.error_handler
+                    .set_unexpexted_token_error(&token, self.curr_span);
                 return None;
             }
-        };
+        }?;
 
         while precedence < self.next_token_precedence() {
-            if left.is_none() {
-                return None;
-            }
             match self.next_token {
                 Token::Plus
                 | Token::Minus
@@ -149,39 +276,52 @@ impl<'a> Parser<'a> {
                 | Token::LessThan
                 | Token::Percet
                 | Token::GratherOrEqual
-                | Token::LessOrEqual => {
+                | Token::LessOrEqual
+                | Token::In => {
                     self.bump();
-                    left = self.parse_infix_expr(left.unwrap());
+                    left = self.parse_infix_expr(left)?;
                 }
                 Token::Lparen => {
                     self.bump();
-                    left = self.parse_call_expr(left.unwrap());
+                    left = self.parse_call_expr(left)?;
+                }
+                Token::Lbracket => {
+                    self.bump();
+                    left = self.parse_index_expr(left)?;
+                }
+                Token::Dot | Token::QuestionDot => {
+                    self.bump();
+                    left = self.parse_method_expr(left)?;
                 }
                 Token::Equal => {
                     self.bump();
-                    left = self.parse_assign_expr(left.unwrap());
+                    left = self.parse_assign_expr(left)?;
                 }
                 Token::DoublePlus | Token::DoubleMinus => {
                     self.bump();
-                    left = self.parse_postfix_expr(left.unwrap());
+                    left = self.parse_postfix_expr(left)?;
+                }
+                Token::As => {
+                    self.bump();
+                    left = self.parse_cast_expr(left)?;
                 }
-                _ => return left,
+                _ => return Some(left),
             }
         }
-        left
+        Some(left)
     }
 
     fn parse_int_expr(&mut self) -> Option<Expr> {
         match self.curr_token {
-            Token::Int(val) => Some(Expr::Literal(Literal::Int(val))),
-            _ => return None,
+            Token::Int(val) => Some(self.expr_here(ExprKind::Literal(Literal::Int(val)))),
+            _ => None,
         }
     }
 
     fn parse_float_expr(&mut self) -> Option<Expr> {
         match self.curr_token {
-            Token::Float(val) => Some(Expr::Literal(Literal::Float(val))),
-            _ => return None,
+            Token::Float(val) => Some(self.expr_here(ExprKind::Literal(Literal::Float(val)))),
+            _ => None,
         }
     }
 
@@ -192,10 +332,11 @@ impl<'a> Parser<'a> {
             _ => return None,
         };
 
-        Some(Expr::Postfix(Box::new(left), postfix))
+        Some(self.expr_here(ExprKind::Postfix(Box::new(left), postfix)))
     }
 
     fn parse_prefix_expr(&mut self) -> Option<Expr> {
+        let span = self.curr_span;
         let prefix = match self.curr_token {
             Token::Bang => Prefix::Not,
             Token::Plus => Prefix::Plus,
@@ -205,29 +346,56 @@ impl<'a> Parser<'a> {
 
         self.bump();
 
-        let expr = match self.parse_expr(Precedence::Prefix) {
-            Some(expr) => expr,
-            None => return None,
-        };
+        let expr = self.parse_expr(Precedence::Prefix)?;
 
-        Some(Expr::Prefix(prefix, Box::new(expr)))
+        Some(Expr::new(ExprKind::Prefix(prefix, Box::new(expr)), span))
     }
 
+    /// `await` binds like a prefix, so `await a + await b` adds two results.
+    fn parse_await_expr(&mut self) -> Option<Expr> {
+        let span = self.curr_span;
+        self.bump();
+        let expr = self.parse_expr(Precedence::Prefix)?;
+        Some(Expr::new(ExprKind::Await(Box::new(expr)), span))
+    }
+
+    /// Only variables and array items can be assigned to, and builtins
+    /// can't, so those mistakes are caught before running.
     fn parse_assign_expr(&mut self, left: Expr) -> Option<Expr> {
-        let identifier = match left {
-            Expr::Identifier(identifier) => identifier,
+        let span = left.span;
+        if let ExprKind::Literal(_) = left.kind {
+            self.error_handler
+                .set_assign_to_constant_error(&left.to_string(), span);
+            return None;
+        }
+        let (identifier, index) = match left.kind {
+            ExprKind::Identifier(identifier) => (identifier, None),
+            ExprKind::Index(target, index) => match target.kind {
+                ExprKind::Identifier(identifier) => (identifier, Some(index)),
+                _ => {
+                    self.error_handler
+                        .set_invalid_left_side_of_assignment_error(span);
+                    return None;
+                }
+            },
             _ => {
                 self.error_handler
-                    .set_invalid_left_side_of_assignment_error();
+                    .set_invalid_left_side_of_assignment_error(span);
                 return None;
             }
         };
+        if is_builtin(&identifier.0) {
+            self.error_handler
+                .set_assign_to_builtin_error(&identifier.0, span);
+            return None;
+        }
         self.bump();
-        let expr = match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => expr,
-            None => return None,
+        let expr = Box::new(self.parse_expr(Precedence::Lowest)?);
+        let kind = match index {
+            Some(index) => ExprKind::IndexAssign(identifier, index, expr),
+            None => ExprKind::Assign(identifier, expr),
         };
-        Some(Expr::Assign(identifier, Box::new(expr)))
+        Some(Expr::new(kind, span))
     }
 
     fn parse_infix_expr(&mut self, left: Expr) -> Option<Expr> {
@@ -243,22 +411,74 @@ impl<'a> Parser<'a> {
             Token::LessOrEqual => Infix::LessOrEqual,
             Token::GratherThan => Infix::GratherThan,
             Token::GratherOrEqual => Infix::GratherOrEqual,
+            Token::In => Infix::In,
             _ => return None,
         };
+        let span = self.curr_span;
         let precedence = self.current_token_precedence();
         self.bump();
-        match self.parse_expr(precedence) {
-            Some(expr) => Some(Expr::Infix(Box::new(left), infix, Box::new(expr))),
-            None => None,
+        self.parse_expr(precedence)
+            .map(|expr| Expr::new(ExprKind::Infix(Box::new(left), infix, Box::new(expr)), span))
+    }
+
+    /// `value as float`. It binds tighter than arithmetic and looser than
+    /// prefixes, so `-x as float + 1` adds to the converted `-x`.
+    fn parse_cast_expr(&mut self, left: Expr) -> Option<Expr> {
+        let span = self.curr_span;
+        self.bump();
+        let type_span = self.curr_span;
+        let type_ = self.parse_single_type()?;
+        if !matches!(
+            type_,
+            ExprType::Int | ExprType::Float | ExprType::String | ExprType::Boolean
+        ) {
+            self.error_handler.set_cast_target_error(&type_, type_span);
+            return None;
         }
+        Some(Expr::new(ExprKind::Cast(Box::new(left), type_), span))
     }
 
     fn parse_call_expr(&mut self, func: Expr) -> Option<Expr> {
-        let args = match self.parse_expr_list(Token::Rparen) {
-            Some(exprs) => exprs,
-            None => return None,
+        let span = func.span;
+        let args = self.parse_expr_list(Token::Rparen)?;
+        Some(Expr::new(ExprKind::Call(Box::new(func), args), span))
+    }
+
+    /// `value.name(args)` or `value?.name(args)`; there are no fields, so
+    /// the call is required.
+    fn parse_method_expr(&mut self, receiver: Expr) -> Option<Expr> {
+        let span = receiver.span;
+        let optional = self.current_token_is(&Token::QuestionDot);
+        let name = match self.next_token.clone() {
+            Token::Identifier(name) => Identifier(name),
+            token => {
+                self.error_handler
+                    .set_unexpexted_token_error(&token, self.next_span);
+                return None;
+            }
         };
-        return Some(Expr::Call(Box::new(func), args));
+        self.bump();
+        if !self.bump_expected_next(&Token::Lparen) {
+            return None;
+        }
+        let args = self.parse_expr_list(Token::Rparen)?;
+        Some(Expr::new(
+            ExprKind::Method(Box::new(receiver), name, args, optional),
+            span,
+        ))
+    }
+
+    fn parse_index_expr(&mut self, target: Expr) -> Option<Expr> {
+        let span = self.curr_span;
+        self.bump();
+        let index = self.parse_expr(Precedence::Lowest)?;
+        if !self.bump_expected_next(&Token::Rbracket) {
+            return None;
+        }
+        Some(Expr::new(
+            ExprKind::Index(Box::new(target), Box::new(index)),
+            span,
+        ))
     }
 
     fn parse_expr_list(&mut self, stop: Token) -> Option<Vec<Expr>> {
@@ -287,45 +507,58 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_identifier_expr(&mut self) -> Option<Expr> {
-        match self.parse_identifier() {
-            Some(identifier) => Some(Expr::Identifier(identifier)),
-            None => None,
-        }
+        let identifier = self.parse_identifier()?;
+        Some(self.expr_here(ExprKind::Identifier(identifier)))
     }
 
     fn parse_string_expr(&self) -> Option<Expr> {
         match self.curr_token.clone() {
-            Token::String(val) => Some(Expr::Literal(Literal::String(val))),
+            Token::String(val) => Some(self.expr_here(ExprKind::Literal(Literal::String(val)))),
             _ => None,
         }
     }
 
     fn parse_array_expr(&mut self) -> Option<Expr> {
-        let items = match self.parse_expr_list(Token::Rbracket) {
-            Some(items) => items,
-            None => return None,
-        };
-        return Some(Expr::Literal(Literal::Array(items)));
+        let span = self.curr_span;
+        let items = self.parse_expr_list(Token::Rbracket)?;
+        Some(Expr::new(ExprKind::Literal(Literal::Array(items)), span))
     }
 
+    /// Expression starting at the current token.
+    fn expr_here(&self, kind: ExprKind) -> Expr {
+        Expr::new(kind, self.curr_span)
+    }
+
+    /// A type annotation, which may be a union of types: `int | string`.
     fn parse_type(&mut self) -> Option<ExprType> {
+        let mut members = vec![self.parse_single_type()?];
+        while self.next_token_is(&Token::Pipe) {
+            self.bump();
+            self.bump();
+            members.push(self.parse_single_type()?);
+        }
+        match members.len() {
+            1 => members.pop(),
+            _ => Some(ExprType::Union(members)),
+        }
+    }
+
+    fn parse_single_type(&mut self) -> Option<ExprType> {
         match self.curr_token {
             Token::TypeInt => Some(ExprType::Int),
             Token::TypeVoid => Some(ExprType::Void),
             Token::TypeFloat => Some(ExprType::Float),
             Token::TypeString => Some(ExprType::String),
             Token::TypeBoolean => Some(ExprType::Boolean),
+            Token::ClassSet => Some(ExprType::Set),
             Token::ClassArray => {
-                let generic_type = match self.parse_generic_type() {
-                    Some(generic_type) => generic_type,
-                    None => return None,
-                };
+                let generic_type = self.nested(Self::parse_generic_type)?;
                 Some(ExprType::Array(Box::new(generic_type)))
             }
             _ => {
                 self.error_handler
-                    .set_not_type_annot_error(&self.curr_token);
-                return None;
+                    .set_not_type_annot_error(&self.curr_token, self.curr_span);
+                None
             }
         }
     }
@@ -335,10 +568,7 @@ impl<'a> Parser<'a> {
             return None;
         }
         self.bump();
-        let generic_type = match self.parse_type() {
-            Some(expr_type) => expr_type,
-            None => return None,
-        };
+        let generic_type = self.parse_type()?;
         if !self.bump_expected_next(&Token::GratherThan) {
             return None;
         }
@@ -349,14 +579,16 @@ impl<'a> Parser<'a> {
         match token {
             Token::Plus | Token::Minus => Precedence::Sum,
             Token::Asterisk | Token::Slash | Token::Percet => Precedence::Product,
-            Token::Lparen => Precedence::Call,
+            Token::As => Precedence::Cast,
+            Token::Lparen | Token::Lbracket | Token::Dot | Token::QuestionDot => Precedence::Call,
             Token::Equal => Precedence::Assign,
             Token::DoubleEqual
             | Token::NotEqual
             | Token::LessThan
             | Token::LessOrEqual
             | Token::GratherThan
-            | Token::GratherOrEqual => Precedence::Comparison,
+            | Token::GratherOrEqual
+            | Token::In => Precedence::Comparison,
             Token::DoublePlus | Token::DoubleMinus => Precedence::Postfix,
             _ => Precedence::Lowest,
         }
@@ -376,7 +608,7 @@ impl<'a> Parser<'a> {
             return true;
         }
         self.error_handler
-            .set_expected_but_provided_error(token, &self.next_token);
+            .set_expected_but_provided_error(token, &self.next_token, self.next_span);
         false
     }
 
@@ -388,11 +620,102 @@ impl<'a> Parser<'a> {
         self.next_token == *token
     }
 
-    pub fn get_error(&self) -> Option<ParserError> {
-        self.error_handler.get_error()
+    /// Every syntax error found by `parse`, in source order.
+    pub fn errors(&self) -> &[ParserError] {
+        self.error_handler.errors()
     }
 
     pub fn has_error(&self) -> bool {
-        self.error_handler.has_error()
+        !self.errors().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_recovers_to_report_every_error() {
+        let input = "let = 2\nprint(1)\ndefine f(): int {\n  let y = * 3\n  return 1\n}\nif 1 + {\n  print(2)\n}\nprint(3)"
+            .chars()
+            .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+
+        let lines = p.errors().iter().map(|e| e.span.line).collect::<Vec<_>>();
+        assert_eq!(lines, [1, 4, 7]);
+        // print(1), the function and print(3) survive.
+        assert_eq!(program.len(), 3);
+    }
+
+    #[test]
+    fn test_rejects_invalid_assignment_targets() {
+        let input = "true = 1\nprint = 2\nlen[0] = 3\nlen(\"a\") = 4\nlet xs = [1]\nxs[0] = 5"
+            .chars()
+            .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+
+        let errors = p
+            .errors()
+            .iter()
+            .map(|error| (error.span.line, error.msg.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                (1, "Can't assign to 'true', it is a constant"),
+                (2, "Can't assign to 'print', it is a builtin"),
+                (3, "Can't assign to 'len', it is a builtin"),
+                (
+                    4,
+                    "Left side of assignment must be a variable or an array item"
+                ),
+            ]
+        );
+        assert_eq!(program.len(), 2);
+    }
+
+    /// Random bytes mixed with bits of syntax, from a fixed seed, must come
+    /// out as a program or as syntax errors, never a panic. A quick check
+    /// for `cargo test`; `fuzz/` has the fuzz target that searches further.
+    #[test]
+    fn test_seeded_random_input_never_panics() {
+        let fragments = [
+            "let", "define", "return", "if", "else", "for", "in", "try", "catch", "assert", "x",
+            "1", "2.5", "\"s", "(", ")", "[", "]", "{", "}", ",", ":", "=", "+", "-", "--", "!",
+            "<", ">", "int", "Array", "\n", " ",
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..2000 {
+            let mut input: Vec<u8> = vec![];
+            for _ in 0..rng.gen_range(0..64) {
+                match rng.gen_range(0..4) {
+                    0 => input.push(rng.gen()),
+                    _ => {
+                        let fragment = fragments[rng.gen_range(0..fragments.len())];
+                        input.extend_from_slice(fragment.as_bytes());
+                    }
+                }
+            }
+            let mut l = Lexer::from_reader(&input[..]);
+            Parser::new(&mut l).parse();
+        }
+
+        for deep in [
+            "[".repeat(10_000),
+            "!".repeat(10_000),
+            "if 1 {".repeat(10_000),
+        ] {
+            let input = deep.chars().collect::<Vec<char>>();
+            let mut l = Lexer::new(&input);
+            let mut p = Parser::new(&mut l);
+            p.parse();
+            assert!(p.has_error());
+        }
     }
 }
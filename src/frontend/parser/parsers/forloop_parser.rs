@@ -1,10 +1,10 @@
 use crate::frontend::{
-    ast::{Identifier, Precedence, Stmt},
+    ast::{Identifier, Precedence, StmtKind},
     parser::Parser,
     token::Token,
 };
 
-pub fn parse_forloop_stmt(p: &mut Parser) -> Option<Stmt> {
+pub fn parse_forloop_stmt(p: &mut Parser) -> Option<StmtKind> {
     p.bump();
     let loop_cursor_name = match p.parse_identifier() {
         Some(identifier) => {
@@ -12,7 +12,8 @@ pub fn parse_forloop_stmt(p: &mut Parser) -> Option<Stmt> {
             name
         }
         None => {
-            p.error_handler.set_identifier_error(&p.curr_token);
+            p.error_handler
+                .set_identifier_error(&p.curr_token, p.curr_span);
             return None;
         }
     };
@@ -22,18 +23,12 @@ pub fn parse_forloop_stmt(p: &mut Parser) -> Option<Stmt> {
     }
 
     p.bump();
-    let iterable = match p.parse_expr(Precedence::Lowest) {
-        Some(expr) => expr,
-        None => return None,
-    };
+    let iterable = p.parse_expr(Precedence::Lowest)?;
     p.bump();
 
-    let block = match p.parse_block_stmt() {
-        Some(block) => block,
-        None => return None,
-    };
+    let block = p.parse_block_stmt()?;
 
-    Some(Stmt::ForLoop {
+    Some(StmtKind::ForLoop {
         cursor: loop_cursor_name,
         iterable,
         block,
@@ -1,12 +1,21 @@
-use crate::frontend::ast::{ExprType, Identifier, Stmt};
+use crate::frontend::ast::{ExprType, Identifier, StmtKind};
 use crate::frontend::parser::{Parser, ParserErrorKind};
 use crate::frontend::token::Token;
+use crate::messages::msg;
+use crate::shared::Shared;
 
-pub fn parse_func_stmt(p: &mut Parser) -> Option<Stmt> {
+/// `define`, or `async define` when the current token is `async`.
+pub fn parse_func_stmt(p: &mut Parser) -> Option<StmtKind> {
+    let doc = p.curr_doc.take();
+    let is_async = p.current_token_is(&Token::Async);
+    if is_async && !p.bump_expected_next(&Token::Func) {
+        return None;
+    }
     let fn_name = match p.next_token.clone() {
         Token::Identifier(name) => Identifier(name),
         _ => {
-            p.error_handler.set_identifier_error(&p.next_token);
+            p.error_handler
+                .set_identifier_error(&p.next_token, p.next_span);
             return None;
         }
     };
@@ -14,30 +23,29 @@ pub fn parse_func_stmt(p: &mut Parser) -> Option<Stmt> {
     if !p.bump_expected_next(&Token::Lparen) {
         return None;
     }
-    let fn_params = match parse_func_params(p) {
-        Some(params) => params,
-        None => return None,
-    };
-
-    if !p.bump_expected_next(&Token::Colon) {
-        return None;
-    }
+    let fn_params = parse_func_params(p)?;
 
-    p.bump();
-
-    let return_type = match p.parse_type() {
-        Some(ret_type) => ret_type,
-        None => return None,
+    let return_type = match p.next_token_is(&Token::Colon) {
+        true => {
+            p.bump();
+            p.bump();
+            Some(p.parse_type()?)
+        }
+        false => None,
     };
 
     if !p.bump_expected_next(&Token::Lbrace) {
         return None;
     }
-    let body = match p.parse_block_stmt() {
-        Some(block) => block,
-        None => return None,
-    };
-    Some(Stmt::Func(fn_name, fn_params, body, return_type))
+    let body = p.parse_block_stmt()?;
+    Some(StmtKind::Func(
+        fn_name,
+        fn_params,
+        Shared::new(body),
+        return_type,
+        doc,
+        is_async,
+    ))
 }
 
 fn parse_func_params(p: &mut Parser) -> Option<Vec<(Identifier, ExprType)>> {
@@ -50,21 +58,22 @@ fn parse_func_params(p: &mut Parser) -> Option<Vec<(Identifier, ExprType)>> {
     let identifier = match p.parse_identifier() {
         Some(identifier) => identifier,
         _ => {
-            p.error_handler.set_identifier_error(&p.curr_token);
+            p.error_handler
+                .set_identifier_error(&p.curr_token, p.curr_span);
             return None;
         }
     };
+    if !p.bump_expected_next(&Token::Colon) {
+        return None;
+    }
     p.bump();
-    p.bump();
-    let param_type = match p.parse_type() {
-        Some(type_) => type_,
-        None => return None,
-    };
+    let param_type = p.parse_type()?;
 
     if param_type == ExprType::Void {
         p.error_handler.set_error(
             ParserErrorKind::SyntaxError,
-            format!("Function parameter can't not be of type 'void'"),
+            msg!("E001.void_param"),
+            p.curr_span,
         );
         return None;
     }
@@ -78,20 +87,21 @@ fn parse_func_params(p: &mut Parser) -> Option<Vec<(Identifier, ExprType)>> {
         let identifier = match p.parse_identifier() {
             Some(identifier) => identifier,
             _ => {
-                p.error_handler.set_identifier_error(&p.curr_token);
+                p.error_handler
+                    .set_identifier_error(&p.curr_token, p.curr_span);
                 return None;
             }
         };
+        if !p.bump_expected_next(&Token::Colon) {
+            return None;
+        }
         p.bump();
-        p.bump();
-        let param_type = match p.parse_type() {
-            Some(type_) => type_,
-            None => return None,
-        };
+        let param_type = p.parse_type()?;
         if param_type == ExprType::Void {
             p.error_handler.set_error(
                 ParserErrorKind::SyntaxError,
-                format!("Function parameter can't not be of type 'void'"),
+                msg!("E001.void_param"),
+                p.curr_span,
             );
             return None;
         }
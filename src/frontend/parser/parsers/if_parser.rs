@@ -1,23 +1,17 @@
 use super::super::super::{
-    ast::{BlockStmt, Precedence, Stmt},
+    ast::{BlockStmt, Precedence, StmtKind},
     parser::Parser,
     token::Token,
 };
 
-pub fn parse_if_stmt(p: &mut Parser) -> Option<Stmt> {
+pub fn parse_if_stmt(p: &mut Parser) -> Option<StmtKind> {
     p.bump();
 
-    let condition = match p.parse_expr(Precedence::Lowest) {
-        Some(expr) => expr,
-        None => return None,
-    };
+    let condition = p.parse_expr(Precedence::Lowest)?;
 
     p.bump();
 
-    let consequence = match p.parse_block_stmt() {
-        Some(block) => block,
-        None => return None,
-    };
+    let consequence = p.parse_block_stmt()?;
 
     let alternative: Option<BlockStmt> = match p.next_token_is(&Token::Else) {
         true => {
@@ -28,7 +22,7 @@ pub fn parse_if_stmt(p: &mut Parser) -> Option<Stmt> {
         false => None,
     };
 
-    Some(Stmt::If {
+    Some(StmtKind::If {
         condition,
         consequence,
         alternative,
@@ -1,11 +1,12 @@
 use super::super::super::token::Token;
-use super::super::{Identifier, Parser, Precedence, Stmt};
+use super::super::{Identifier, Parser, Precedence, StmtKind};
 
-pub fn parse_let_stmt(p: &mut Parser) -> Option<Stmt> {
+pub fn parse_let_stmt(p: &mut Parser) -> Option<StmtKind> {
     let var_name = match p.next_token.clone() {
         Token::Identifier(val) => val,
         _ => {
-            p.error_handler.set_identifier_error(&p.next_token);
+            p.error_handler
+                .set_identifier_error(&p.next_token, p.next_span);
             return None;
         }
     };
@@ -15,36 +16,31 @@ pub fn parse_let_stmt(p: &mut Parser) -> Option<Stmt> {
         p.bump();
         p.bump();
 
-        let var_type = match p.parse_type() {
-            Some(type_) => type_,
-            None => return None,
-        };
-        p.bump();
+        let var_type = p.parse_type()?;
 
-        if !p.current_token_is(&Token::Equal) {
-            return Some(Stmt::Let(Identifier(var_name), Some(var_type), None));
+        if !p.next_token_is(&Token::Equal) {
+            return Some(StmtKind::Let(Identifier(var_name), Some(var_type), None));
         }
 
         p.bump();
-        let expr = match p.parse_expr(Precedence::Lowest) {
-            Some(expr) => expr,
-            None => return None,
-        };
+        p.bump();
+        let expr = p.parse_expr(Precedence::Lowest)?;
 
-        return Some(Stmt::Let(Identifier(var_name), Some(var_type), Some(expr)));
+        return Some(StmtKind::Let(
+            Identifier(var_name),
+            Some(var_type),
+            Some(expr),
+        ));
     }
 
     if !p.next_token_is(&Token::Equal) {
-        return Some(Stmt::Let(Identifier(var_name), None, None));
+        return Some(StmtKind::Let(Identifier(var_name), None, None));
     }
 
     p.bump();
     p.bump();
 
-    let expr = match p.parse_expr(Precedence::Lowest) {
-        Some(expr) => expr,
-        None => return None,
-    };
+    let expr = p.parse_expr(Precedence::Lowest)?;
 
-    Some(Stmt::Let(Identifier(var_name), None, Some(expr)))
+    Some(StmtKind::Let(Identifier(var_name), None, Some(expr)))
 }
@@ -2,3 +2,4 @@ pub mod forloop_parser;
 pub mod func_parser;
 pub mod if_parser;
 pub mod let_parser;
+pub mod try_parser;
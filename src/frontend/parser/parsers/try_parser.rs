@@ -0,0 +1,36 @@
+use crate::frontend::{
+    ast::{Identifier, StmtKind},
+    parser::Parser,
+    token::Token,
+};
+
+pub fn parse_try_stmt(p: &mut Parser) -> Option<StmtKind> {
+    if !p.bump_expected_next(&Token::Lbrace) {
+        return None;
+    }
+    let block = p.parse_block_stmt()?;
+
+    if !p.bump_expected_next(&Token::Catch) {
+        return None;
+    }
+    p.bump();
+    let binding = match p.parse_identifier() {
+        Some(Identifier(name)) => name,
+        None => {
+            p.error_handler
+                .set_identifier_error(&p.curr_token, p.curr_span);
+            return None;
+        }
+    };
+
+    if !p.bump_expected_next(&Token::Lbrace) {
+        return None;
+    }
+    let handler = p.parse_block_stmt()?;
+
+    Some(StmtKind::Try {
+        block,
+        binding,
+        handler,
+    })
+}
@@ -0,0 +1,146 @@
+use super::ast::*;
+use crate::shared::Shared;
+
+/// Assigns frame slots to identifiers that refer to locals of the enclosing
+/// function (or top-level block), so the runtime can read them by index
+/// instead of hashing names up the context chain. Anything that can't be
+/// resolved statically (globals, builtins, names coming from the caller's
+/// scope) is left as a plain `ExprKind::Identifier`.
+pub fn resolve(program: &mut Program) {
+    let mut resolver = Resolver {
+        scopes: vec![],
+        function_base: 0,
+    };
+    resolver.resolve_block(program);
+}
+
+struct Resolver {
+    scopes: Vec<Vec<String>>,
+    function_base: usize,
+}
+
+impl Resolver {
+    fn resolve_block(&mut self, block: &mut BlockStmt) {
+        for stmt in block {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_scoped_block(&mut self, block: &mut BlockStmt, names: Vec<String>) {
+        self.scopes.push(names);
+        self.resolve_block(block);
+        self.scopes.pop();
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Assert(expr) => self.resolve_expr(expr),
+            StmtKind::Let(Identifier(name), _, expr) => {
+                if let Some(expr) = expr {
+                    self.resolve_expr(expr);
+                }
+                self.declare(name);
+            }
+            StmtKind::Func(Identifier(name), params, body, ..) => {
+                self.declare(name);
+                let names = params
+                    .iter()
+                    .map(|(Identifier(name), _)| name.clone())
+                    .collect();
+                let enclosing_base = self.function_base;
+                self.function_base = self.scopes.len();
+                self.resolve_scoped_block(Shared::make_mut(body), names);
+                self.function_base = enclosing_base;
+            }
+            StmtKind::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.resolve_expr(expr);
+                }
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_scoped_block(consequence, vec![]);
+                if let Some(alternative) = alternative {
+                    self.resolve_scoped_block(alternative, vec![]);
+                }
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                self.resolve_expr(iterable);
+                self.resolve_scoped_block(block, vec![cursor.clone()]);
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.resolve_scoped_block(block, vec![]);
+                self.resolve_scoped_block(handler, vec![binding.clone()]);
+            }
+            StmtKind::Import(_) => {}
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            // a program read back from JSON may already have locals
+            ExprKind::Identifier(identifier) | ExprKind::Local(identifier, _) => {
+                expr.kind = match self.lookup(&identifier.0) {
+                    Some(slot) => ExprKind::Local(identifier.clone(), slot),
+                    None => ExprKind::Identifier(identifier.clone()),
+                };
+            }
+            ExprKind::Literal(Literal::Array(items)) => {
+                for item in items {
+                    self.resolve_expr(item);
+                }
+            }
+            ExprKind::Literal(_) => {}
+            ExprKind::Call(func, args) | ExprKind::Method(func, _, args, _) => {
+                self.resolve_expr(func);
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+            }
+            ExprKind::IndexAssign(_, index, expr) => {
+                self.resolve_expr(index);
+                self.resolve_expr(expr);
+            }
+            ExprKind::Infix(lhs, _, rhs) | ExprKind::Index(lhs, rhs) => {
+                self.resolve_expr(lhs);
+                self.resolve_expr(rhs);
+            }
+            ExprKind::Prefix(_, expr)
+            | ExprKind::Postfix(expr, _)
+            | ExprKind::Assign(_, expr)
+            | ExprKind::Await(expr)
+            | ExprKind::Cast(expr, _) => self.resolve_expr(expr),
+        }
+    }
+
+    fn declare(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.iter().any(|declared| declared == name) {
+                scope.push(name.to_string());
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Slot> {
+        let innermost = self.scopes.len().checked_sub(1)?;
+        for depth in 0..self.scopes.len() - self.function_base {
+            let scope = &self.scopes[innermost - depth];
+            if let Some(index) = scope.iter().position(|declared| declared == name) {
+                return Some(Slot { depth, index });
+            }
+        }
+        None
+    }
+}
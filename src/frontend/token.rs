@@ -1,5 +1,21 @@
 use core::fmt;
 
+use serde::{Deserialize, Serialize};
+
+/// Where something starts in the source. `offset` counts bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub offset: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Illegal(char),
@@ -13,7 +29,11 @@ pub enum Token {
     Rbrace,
     Comma,
     Colon,
-    
+    Dot,
+    /// `?.`, see `ExprKind::Method`.
+    QuestionDot,
+    Pipe,
+
     Plus,
     Minus,
     Asterisk,
@@ -41,11 +61,20 @@ pub enum Token {
     If,
     For,
     In,
+    Try,
+    Catch,
+    Assert,
+    Import,
+    Async,
+    Await,
+    /// `value as float`, see `ExprKind::Cast`.
+    As,
     Else,
     Func,
     Null,
     Return,
     ClassArray,
+    ClassSet,
     Identifier(String),
 
     TypeInt,
@@ -58,7 +87,7 @@ pub enum Token {
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.clone() {
-            Self::Illegal(val) => write!(f, "[Illegal Token] {}", val),
+            Self::Illegal(val) => write!(f, "[Illegal Token] {}", val.escape_debug()),
             Self::Eof => write!(f, "EOF"),
             Self::Lparen => write!(f, "("),
             Self::Rparen => write!(f, ")"),
@@ -86,6 +115,9 @@ impl fmt::Display for Token {
             Self::LessOrEqual => write!(f, "<="),
             Self::Null => write!(f, "null"),
             Self::Colon => write!(f, ":"),
+            Self::Dot => write!(f, "."),
+            Self::QuestionDot => write!(f, "?."),
+            Self::Pipe => write!(f, "|"),
             Self::TypeString => write!(f, "[Type Annotation] string"),
             Self::TypeBoolean => write!(f, "[Type Annotation] boolean"),
             Self::If => write!(f, "if"),
@@ -97,12 +129,20 @@ impl fmt::Display for Token {
             Self::Percet => write!(f, "%"),
             Self::For => write!(f, "for"),
             Self::In => write!(f, "in"),
+            Self::Try => write!(f, "try"),
+            Self::Catch => write!(f, "catch"),
+            Self::Assert => write!(f, "assert"),
+            Self::Import => write!(f, "import"),
+            Self::Async => write!(f, "async"),
+            Self::Await => write!(f, "await"),
+            Self::As => write!(f, "as"),
             Self::TypeInt => write!(f, "[Type Annotation] int"),
             Self::TypeFloat => write!(f, "[Type Annotation] float"),
             Self::Int(val) => write!(f, "{}", val),
             Self::Float(val) => write!(f, "{}", val),
             Self::TypeVoid => write!(f, "[Type Annotation] void"),
             Self::ClassArray => write!(f, "[Built-in Class] Array"),
+            Self::ClassSet => write!(f, "[Built-in Class] Set"),
         }
     }
 }
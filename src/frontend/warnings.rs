@@ -0,0 +1,390 @@
+use core::fmt;
+use std::collections::HashSet;
+
+use super::ast::*;
+use super::token::Span;
+use crate::messages::msg;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WarningKind {
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    ConstantCondition,
+    Shadowing,
+    /// Only reported by `filipe lint`, see `lint`.
+    ShadowedBuiltin,
+    DeepNesting,
+    Naming,
+}
+
+impl WarningKind {
+    /// Stable code that `filipe explain` describes in detail.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnusedVariable => "W001",
+            Self::UnusedParameter => "W002",
+            Self::UnreachableCode => "W003",
+            Self::ConstantCondition => "W004",
+            Self::Shadowing => "W005",
+            Self::ShadowedBuiltin => "W006",
+            Self::DeepNesting => "W007",
+            Self::Naming => "W008",
+        }
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]", msg!("W.kind"))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub msg: String,
+    pub span: Span,
+}
+
+/// Looks for code that is legal but most likely a mistake: variables and
+/// parameters that are never used, statements after a `return` and `if`
+/// conditions that can't change. With `shadowing`, also bindings that hide
+/// one of an enclosing scope. Nothing here stops the program from running.
+pub fn check(program: &Program, shadowing: bool) -> Vec<Warning> {
+    let mut checker = Checker {
+        scopes: vec![vec![]],
+        shadowing,
+        global_uses: HashSet::new(),
+        warnings: vec![],
+    };
+    checker.check_block(program);
+
+    // Functions read globals when they are called, so a global only counts
+    // as unused once the whole program has been seen.
+    let globals = checker.scopes.pop().unwrap_or_default();
+    for binding in globals {
+        if !binding.used && !checker.global_uses.contains(&binding.name) {
+            checker.warn_unused(binding);
+        }
+    }
+    checker.warnings.sort_by_key(|warning| warning.span.offset);
+    checker.warnings
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+    is_param: bool,
+    used: bool,
+}
+
+struct Checker {
+    scopes: Vec<Vec<Binding>>,
+    shadowing: bool,
+    /// Names used without a matching binding in an enclosing scope.
+    global_uses: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl Checker {
+    fn check_block(&mut self, block: &BlockStmt) {
+        let mut returned = false;
+        for stmt in block {
+            if returned {
+                self.warn(
+                    WarningKind::UnreachableCode,
+                    msg!("W003.unreachable"),
+                    stmt.span,
+                );
+                break;
+            }
+            self.check_stmt(stmt);
+            returned = matches!(stmt.kind, StmtKind::Return(_));
+        }
+    }
+
+    fn check_scoped_block(&mut self, block: &BlockStmt, bindings: Vec<Binding>) {
+        for binding in &bindings {
+            self.check_shadowing(binding, self.scopes.len());
+        }
+        self.scopes.push(bindings);
+        self.check_block(block);
+        for binding in self.scopes.pop().unwrap_or_default() {
+            if !binding.used {
+                self.warn_unused(binding);
+            }
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Assert(expr) => self.check_expr(expr),
+            StmtKind::Let(Identifier(name), _, expr) => {
+                if let Some(expr) = expr {
+                    self.check_expr(expr);
+                }
+                self.declare(name, stmt.span);
+            }
+            StmtKind::Func(Identifier(name), params, body, ..) => {
+                // Calling a function counts as using it, and recursion too.
+                self.declare(name, stmt.span);
+                self.mark_used(name);
+                let params = params
+                    .iter()
+                    .map(|(Identifier(name), _)| Binding {
+                        name: name.clone(),
+                        span: stmt.span,
+                        is_param: true,
+                        used: false,
+                    })
+                    .collect();
+                self.check_scoped_block(body, params);
+            }
+            StmtKind::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.check_expr(expr);
+                }
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                if is_constant(condition) {
+                    self.warn(
+                        WarningKind::ConstantCondition,
+                        msg!("W004.constant"),
+                        condition.span,
+                    );
+                }
+                self.check_expr(condition);
+                self.check_scoped_block(consequence, vec![]);
+                if let Some(alternative) = alternative {
+                    self.check_scoped_block(alternative, vec![]);
+                }
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                self.check_expr(iterable);
+                // Loops often run only for their side effects, so an unused
+                // cursor is not worth a warning.
+                let cursor = Binding {
+                    name: cursor.clone(),
+                    span: stmt.span,
+                    is_param: false,
+                    used: true,
+                };
+                self.check_scoped_block(block, vec![cursor]);
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.check_scoped_block(block, vec![]);
+                // Catching an error just to ignore it is fine.
+                let binding = Binding {
+                    name: binding.clone(),
+                    span: stmt.span,
+                    is_param: false,
+                    used: true,
+                };
+                self.check_scoped_block(handler, vec![binding]);
+            }
+            StmtKind::Import(_) => {}
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                self.mark_used(name)
+            }
+            ExprKind::Literal(Literal::Array(items)) => {
+                for item in items {
+                    self.check_expr(item);
+                }
+            }
+            ExprKind::Literal(_) => {}
+            ExprKind::Call(func, args) => {
+                self.check_expr(func);
+                for arg in args {
+                    self.check_expr(arg);
+                }
+            }
+            ExprKind::Method(receiver, Identifier(name), args, _) => {
+                self.mark_used(name);
+                self.check_expr(receiver);
+                for arg in args {
+                    self.check_expr(arg);
+                }
+            }
+            ExprKind::IndexAssign(Identifier(name), index, expr) => {
+                self.mark_used(name);
+                self.check_expr(index);
+                self.check_expr(expr);
+            }
+            ExprKind::Assign(Identifier(name), expr) => {
+                self.mark_used(name);
+                self.check_expr(expr);
+            }
+            ExprKind::Infix(lhs, _, rhs) | ExprKind::Index(lhs, rhs) => {
+                self.check_expr(lhs);
+                self.check_expr(rhs);
+            }
+            ExprKind::Prefix(_, expr)
+            | ExprKind::Postfix(expr, _)
+            | ExprKind::Await(expr)
+            | ExprKind::Cast(expr, _) => self.check_expr(expr),
+        }
+    }
+
+    fn declare(&mut self, name: &str, span: Span) {
+        let binding = Binding {
+            name: name.to_string(),
+            span,
+            is_param: false,
+            used: false,
+        };
+        self.check_shadowing(&binding, self.scopes.len().saturating_sub(1));
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(binding);
+        }
+    }
+
+    /// Warns when `binding` hides one with the same name in the first
+    /// `enclosing` scopes.
+    fn check_shadowing(&mut self, binding: &Binding, enclosing: usize) {
+        if !self.shadowing {
+            return;
+        }
+        let shadowed = self.scopes[..enclosing]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.iter().rev().find(|b| b.name == binding.name));
+        if let Some(shadowed) = shadowed {
+            let (name, span) = (&binding.name, shadowed.span);
+            let msg = match shadowed.is_param {
+                true => msg!("W005.shadows_parameter", name = name, span = span),
+                false => msg!("W005.shadows_variable", name = name, span = span),
+            };
+            self.warn(WarningKind::Shadowing, msg, binding.span);
+        }
+    }
+
+    fn mark_used(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope.iter_mut().rev().find(|b| b.name == name) {
+                binding.used = true;
+                return;
+            }
+        }
+        self.global_uses.insert(name.to_string());
+    }
+
+    fn warn_unused(&mut self, binding: Binding) {
+        let (kind, msg) = match binding.is_param {
+            true => (
+                WarningKind::UnusedParameter,
+                msg!("W002.unused", name = binding.name),
+            ),
+            false => (
+                WarningKind::UnusedVariable,
+                msg!("W001.unused", name = binding.name),
+            ),
+        };
+        self.warn(kind, msg, binding.span);
+    }
+
+    fn warn(&mut self, kind: WarningKind, msg: String, span: Span) {
+        self.warnings.push(Warning { kind, msg, span });
+    }
+}
+
+/// Whether `expr` is made only of literals, so it evaluates the same way
+/// every time.
+fn is_constant(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Array(items)) => items.iter().all(is_constant),
+        ExprKind::Literal(_) => true,
+        ExprKind::Prefix(_, expr) => is_constant(expr),
+        ExprKind::Infix(lhs, _, rhs) => is_constant(lhs) && is_constant(rhs),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_warnings() {
+        let input = "let unused = 1\n\
+                     define f(a: int, b: int): int {\n\
+                     \x20 return a\n\
+                     \x20 print(1)\n\
+                     }\n\
+                     if 1 < 2 {\n\
+                     \x20 f(1, 2)\n\
+                     }"
+        .chars()
+        .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+
+        let warnings = check(&program, false)
+            .into_iter()
+            .map(|warning| (warning.kind, warning.span.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                (WarningKind::UnusedVariable, 1),
+                (WarningKind::UnusedParameter, 2),
+                (WarningKind::UnreachableCode, 4),
+                (WarningKind::ConstantCondition, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowing_is_opt_in() {
+        let input = "let x = 1\n\
+                     define f(x: int): int {\n\
+                     \x20 for i in range(0, x) {\n\
+                     \x20   let x = i\n\
+                     \x20   print(x)\n\
+                     \x20 }\n\
+                     \x20 return x\n\
+                     }\n\
+                     print(f(x))"
+            .chars()
+            .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+
+        assert!(check(&program, false).is_empty());
+        let warnings = check(&program, true)
+            .into_iter()
+            .map(|warning| (warning.kind, warning.msg))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                (
+                    WarningKind::Shadowing,
+                    "'x' shadows the variable declared at 1:1".to_string()
+                ),
+                (
+                    WarningKind::Shadowing,
+                    "'x' shadows the parameter declared at 2:1".to_string()
+                ),
+            ]
+        );
+    }
+}
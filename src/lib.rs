@@ -0,0 +1,41 @@
+//! The filipe language: its frontend (lexer, parser, checks), the runtime
+//! that evaluates programs, and the pieces the `filipe` command builds on.
+//!
+//! Programs embedding filipe start from `Runtime::builder`, see
+//! `runtime::embedding`; the types they need are re-exported here.
+
+// Runtime errors carry their location, call trace and notes. They are
+// returned once per program, not in hot paths, so their size doesn't matter.
+#![allow(clippy::result_large_err)]
+
+pub mod ast_json;
+pub mod codegen;
+pub mod diagnostics;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod flbc;
+pub mod frontend;
+pub mod messages;
+pub mod project;
+#[cfg(feature = "python")]
+mod python;
+pub mod runtime;
+pub mod shared;
+pub mod template;
+pub mod utils;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+pub use diagnostics::Diagnostic;
+pub use frontend::token::Span;
+pub use runtime::convert::{ConversionError, FromFilipe, ToFilipe};
+pub use runtime::embedding::{IntoArgs, ScriptFunction};
+pub use runtime::expression::Bindings;
+pub use runtime::interrupt::InterruptHandle;
+pub use runtime::native::{Args, NativeFn, NativeModule};
+pub use runtime::object::{Object, ObjectInfo, Value};
+pub use runtime::permissions::RuntimePermissions;
+pub use runtime::plugins::{PluginDeclaration, PLUGIN_ABI_VERSION};
+pub use runtime::runtime_error::{ErrorKind, RuntimeError};
+pub use runtime::userdata::{UserData, UserType};
+pub use runtime::{FilipeArray, Runtime, RuntimeBuilder};
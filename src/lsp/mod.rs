@@ -0,0 +1,455 @@
+//! Language server started with `filipe lsp`. It speaks the Language Server
+//! Protocol over stdin and stdout: documents are checked as they change,
+//! and names can be hovered for their type, followed to their declaration
+//! and completed.
+
+mod symbols;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::frontend::ast::Program;
+use crate::frontend::checker::{self, Analysis};
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::{Parser, ParserError};
+use crate::frontend::token::Span;
+use crate::frontend::warnings;
+use crate::runtime::flstdlib::BUILTIN_DOCS;
+use symbols::{Declaration, DeclarationKind, Symbols};
+
+/// Keywords offered by completion.
+const KEYWORDS: &[&str] = &[
+    "let", "define", "return", "if", "else", "for", "in", "try", "catch", "assert", "import",
+    "async", "await", "as", "true", "false", "null", "int", "float", "string", "boolean", "void",
+    "Array",
+];
+
+// Completion item kinds of the protocol.
+const FUNCTION_ITEM: u8 = 3;
+const VARIABLE_ITEM: u8 = 6;
+const KEYWORD_ITEM: u8 = 14;
+
+/// Answers the editor on `output` until it says `exit`. Returns the exit
+/// status, which is 1 unless the editor asked to `shutdown` first.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> i32 {
+    let mut server = Server {
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => return 1,
+            Err(err) => {
+                eprintln!("[ERROR]: {}", err);
+                return 1;
+            }
+        };
+        if message["method"] == "exit" {
+            return i32::from(!server.shutdown);
+        }
+        for reply in server.handle(&message) {
+            if let Err(err) = write_message(&mut output, &reply) {
+                eprintln!("[ERROR]: {}", err);
+                return 1;
+            }
+        }
+    }
+}
+
+/// Reads one `Content-Length` framed message, `None` at the end of input.
+pub(crate) fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(io::Error::from)
+}
+
+pub(crate) fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+struct Server {
+    /// Text of the open documents by URI.
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    /// Responds to a request, or reacts to a notification, returning what
+    /// to send back.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "filipe" },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                // the whole text is sent on every change, see `initialize`
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|changes| changes.last()?["text"].as_str()) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, vec![])];
+            }
+            "textDocument/hover" => Some(self.hover(&uri, &params["position"])),
+            "textDocument/definition" => Some(self.definition(&uri, &params["position"])),
+            "textDocument/completion" => Some(self.completion(&uri)),
+            _ => None,
+        };
+
+        // notifications get no response
+        let Some(id) = message.get("id") else {
+            return vec![];
+        };
+        match result {
+            Some(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method {}", method) },
+            })],
+        }
+    }
+
+    /// Syntax errors of the document or, once it parses, what `filipe check`
+    /// reports.
+    fn diagnostics(&self, uri: &str) -> Value {
+        let source = self.source(uri);
+        let (program, parse_errors) = parse(source);
+        let mut diagnostics = parse_errors
+            .iter()
+            .map(Diagnostic::from)
+            .collect::<Vec<_>>();
+        if diagnostics.is_empty() {
+            let errors = checker::check(&program);
+            diagnostics.extend(errors.iter().map(Diagnostic::from));
+            let warnings = warnings::check(&program, false);
+            diagnostics.extend(warnings.iter().map(Diagnostic::from));
+        }
+        let diagnostics = diagnostics
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(source, diagnostic))
+            .collect();
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    fn hover(&self, uri: &str, position: &Value) -> Value {
+        let source = self.source(uri);
+        let (line, col) = from_lsp_position(source, position);
+        let (program, _) = parse(source);
+        let symbols = Symbols::collect(&program, source);
+
+        let contents = match symbols.declaration_at(line, col) {
+            Some(declaration) => {
+                let analysis = checker::analyze(&program);
+                format!("```filipe\n{}\n```", describe(declaration, &analysis))
+            }
+            None => {
+                let word = word_at(source, line, col);
+                let Some((_, usage, description)) =
+                    BUILTIN_DOCS.iter().find(|(name, ..)| *name == word)
+                else {
+                    return Value::Null;
+                };
+                format!("```filipe\n{}\n```\n{}", usage, description)
+            }
+        };
+        json!({ "contents": { "kind": "markdown", "value": contents } })
+    }
+
+    fn definition(&self, uri: &str, position: &Value) -> Value {
+        let source = self.source(uri);
+        let (line, col) = from_lsp_position(source, position);
+        let (program, _) = parse(source);
+        let symbols = Symbols::collect(&program, source);
+        match symbols.declaration_at(line, col) {
+            Some(declaration) => json!({
+                "uri": uri,
+                "range": lsp_range(source, declaration.span),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    /// Keywords, builtins and every name declared in the document.
+    fn completion(&self, uri: &str) -> Value {
+        let source = self.source(uri);
+        let (program, _) = parse(source);
+        let symbols = Symbols::collect(&program, source);
+
+        let keywords = KEYWORDS
+            .iter()
+            .map(|keyword| json!({ "label": keyword, "kind": KEYWORD_ITEM }));
+        let builtins = BUILTIN_DOCS.iter().map(|(name, usage, description)| {
+            json!({
+                "label": name,
+                "kind": FUNCTION_ITEM,
+                "detail": usage,
+                "documentation": description,
+            })
+        });
+        let mut seen = Vec::new();
+        let declared = symbols
+            .declarations
+            .iter()
+            .filter(|declaration| {
+                let new = !seen.contains(&declaration.name);
+                seen.push(declaration.name.clone());
+                new
+            })
+            .map(|declaration| {
+                let kind = match declaration.kind {
+                    DeclarationKind::Function(..) => FUNCTION_ITEM,
+                    _ => VARIABLE_ITEM,
+                };
+                json!({ "label": declaration.name, "kind": kind })
+            })
+            .collect::<Vec<_>>();
+        Value::Array(keywords.chain(builtins).chain(declared).collect())
+    }
+
+    fn source(&self, uri: &str) -> &str {
+        self.documents.get(uri).map_or("", String::as_str)
+    }
+}
+
+/// Parses what it can; statements with syntax errors are left out.
+fn parse(source: &str) -> (Program, Vec<ParserError>) {
+    let input = source.chars().collect::<Vec<char>>();
+    let mut l = Lexer::new(&input);
+    let mut p = Parser::new(&mut l);
+    let program = p.parse();
+    (program, p.errors().to_vec())
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn lsp_diagnostic(source: &str, diagnostic: &Diagnostic) -> Value {
+    let mut message = diagnostic.msg.clone();
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\n{}", note));
+    }
+    json!({
+        "range": lsp_range(source, diagnostic.span.unwrap_or_default()),
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "code": diagnostic.code,
+        "source": "filipe",
+        "message": message,
+    })
+}
+
+/// How hovering shows a declaration, e.g. `let total: int`.
+fn describe(declaration: &Declaration, analysis: &Analysis) -> String {
+    let name = &declaration.name;
+    match &declaration.kind {
+        DeclarationKind::Variable(Some(annotation)) => format!("let {}: {}", name, annotation),
+        DeclarationKind::Variable(None) => match analysis.let_types.get(&declaration.stmt_offset) {
+            Some(type_) => format!("let {}: {}", name, type_),
+            None => format!("let {}", name),
+        },
+        DeclarationKind::Function(params, ret_type) => {
+            let params = params
+                .iter()
+                .map(|(param, type_)| format!("{}: {}", param.0, type_))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let ret_type = ret_type.as_ref().map(ToString::to_string).or_else(|| {
+                let inferred = analysis.return_types.get(&declaration.stmt_offset);
+                inferred.map(ToString::to_string)
+            });
+            match ret_type {
+                Some(ret_type) => format!("define {}({}): {}", name, params, ret_type),
+                None => format!("define {}({})", name, params),
+            }
+        }
+        DeclarationKind::Parameter(type_) => format!("{}: {}", name, type_),
+        DeclarationKind::LoopVariable => format!("for {}", name),
+        DeclarationKind::CatchBinding => format!("catch {}", name),
+    }
+}
+
+/// Range of the word starting at `span`, or of the char there when it
+/// doesn't start a word.
+fn lsp_range(source: &str, span: Span) -> Value {
+    let line = source
+        .lines()
+        .nth(span.line.saturating_sub(1))
+        .unwrap_or_default();
+    let start = span.col.saturating_sub(1);
+    let word = line
+        .chars()
+        .skip(start)
+        .take_while(|ch| ch.is_alphanumeric())
+        .count()
+        .max(1);
+    json!({
+        "start": lsp_position(line, span.line, start),
+        "end": lsp_position(line, span.line, start + word),
+    })
+}
+
+/// The protocol counts lines from 0 and characters in UTF-16 units.
+fn lsp_position(line_text: &str, line: usize, chars: usize) -> Value {
+    let character = line_text
+        .chars()
+        .take(chars)
+        .map(char::len_utf16)
+        .sum::<usize>();
+    json!({ "line": line.saturating_sub(1), "character": character })
+}
+
+/// 1-based line and column of a protocol position.
+fn from_lsp_position(source: &str, position: &Value) -> (usize, usize) {
+    let line = position["line"].as_u64().unwrap_or_default() as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+    let text = source.lines().nth(line).unwrap_or_default();
+    let mut units = 0;
+    let col = text
+        .chars()
+        .take_while(|ch| {
+            units += ch.len_utf16();
+            units <= character
+        })
+        .count();
+    (line + 1, col + 1)
+}
+
+/// The word around `line:col`.
+fn word_at(source: &str, line: usize, col: usize) -> String {
+    let text = source.lines().nth(line - 1).unwrap_or_default();
+    let chars = text.chars().collect::<Vec<_>>();
+    let at = (col - 1).min(chars.len());
+    let start = chars[..at]
+        .iter()
+        .rposition(|ch| !ch.is_alphanumeric())
+        .map_or(0, |index| index + 1);
+    let end = chars[at..]
+        .iter()
+        .position(|ch| !ch.is_alphanumeric())
+        .map_or(chars.len(), |index| at + index);
+    chars[start..end].iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn replies(output: &[u8]) -> Vec<Value> {
+        let mut output = output;
+        let mut replies = vec![];
+        while let Some(reply) = read_message(&mut output).unwrap() {
+            replies.push(reply);
+        }
+        replies
+    }
+
+    #[test]
+    fn test_server_checks_hovers_and_finds_definitions() {
+        let uri = "file:///main.fl";
+        let text =
+            "let total = 1\ndefine add(x: int): int {\n  return x + total\n}\nprint(add(\"2\"))";
+        let position = |line: u32, character: u32| json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+        let input = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+                    "params": { "textDocument": { "uri": uri, "text": text } } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": position(2, 14) }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": position(4, 7) }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]
+        .map(frame)
+        .concat();
+
+        let mut output = vec![];
+        assert_eq!(serve(input.as_bytes(), &mut output), 0);
+        let replies = replies(&output);
+        assert_eq!(replies.len(), 5);
+        assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+
+        let diagnostics = &replies[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["code"], "E011");
+        assert_eq!(
+            diagnostics[0]["range"]["start"],
+            json!({ "line": 4, "character": 10 })
+        );
+
+        assert_eq!(
+            replies[2]["result"]["contents"]["value"],
+            "```filipe\nlet total: int\n```"
+        );
+        assert_eq!(
+            replies[3]["result"]["range"],
+            json!({
+                "start": { "line": 1, "character": 7 },
+                "end": { "line": 1, "character": 10 },
+            })
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Where each name of a document is declared and used, for go-to-definition,
+//! hover and completion.
+
+use std::collections::HashMap;
+
+use crate::frontend::ast::*;
+use crate::frontend::token::Span;
+
+pub enum DeclarationKind {
+    Variable(Option<ExprType>),
+    Function(Vec<(Identifier, ExprType)>, Option<ExprType>),
+    Parameter(ExprType),
+    LoopVariable,
+    CatchBinding,
+}
+
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    /// Where the name itself is.
+    pub span: Span,
+    /// Offset of the declaring statement, which `Analysis::let_types` is
+    /// keyed by.
+    pub stmt_offset: usize,
+}
+
+struct Reference {
+    span: Span,
+    /// Index in `declarations`, `None` for builtins and undeclared names.
+    declaration: Option<usize>,
+}
+
+pub struct Symbols {
+    pub declarations: Vec<Declaration>,
+    references: Vec<Reference>,
+}
+
+impl Symbols {
+    pub fn collect(program: &Program, source: &str) -> Self {
+        let mut collector = Collector {
+            source,
+            symbols: Symbols {
+                declarations: vec![],
+                references: vec![],
+            },
+            scopes: vec![HashMap::new()],
+        };
+        // functions run when they are called, so they see every global
+        for stmt in program {
+            collector.declare_global(stmt);
+        }
+        collector.block(program, false);
+        collector.symbols
+    }
+
+    /// The declaration of the name at `line:col`, which may be a use of the
+    /// name or the declaration itself.
+    pub fn declaration_at(&self, line: usize, col: usize) -> Option<&Declaration> {
+        let covers = |span: &Span, name: &str| {
+            span.line == line && (span.col..span.col + name.chars().count()).contains(&col)
+        };
+        if let Some(declaration) = self
+            .declarations
+            .iter()
+            .find(|declaration| covers(&declaration.span, &declaration.name))
+        {
+            return Some(declaration);
+        }
+        self.references.iter().find_map(|reference| {
+            let declaration = &self.declarations[reference.declaration?];
+            covers(&reference.span, &declaration.name).then_some(declaration)
+        })
+    }
+}
+
+struct Collector<'s> {
+    source: &'s str,
+    symbols: Symbols,
+    /// Names in scope, innermost last, mapped to their declaration.
+    scopes: Vec<HashMap<String, usize>>,
+}
+
+impl Collector<'_> {
+    fn declare_global(&mut self, stmt: &Stmt) {
+        let (name, kind) = match &stmt.kind {
+            StmtKind::Let(Identifier(name), annotation, _) => {
+                (name, DeclarationKind::Variable(annotation.clone()))
+            }
+            StmtKind::Func(Identifier(name), params, _, ret_type, ..) => (
+                name,
+                DeclarationKind::Function(params.clone(), ret_type.clone()),
+            ),
+            _ => return,
+        };
+        self.declare(name, kind, stmt.span.offset, stmt.span.offset);
+    }
+
+    /// Adds a declaration of `name`, looking for the name in the source from
+    /// `search_from` on.
+    fn declare(
+        &mut self,
+        name: &str,
+        kind: DeclarationKind,
+        search_from: usize,
+        stmt_offset: usize,
+    ) {
+        let span = find_word(self.source, search_from, name).unwrap_or_default();
+        self.symbols.declarations.push(Declaration {
+            name: name.to_string(),
+            kind,
+            span,
+            stmt_offset,
+        });
+        let index = self.symbols.declarations.len() - 1;
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.entry(name.to_string()).or_insert(index);
+        }
+    }
+
+    fn block(&mut self, block: &BlockStmt, scoped: bool) {
+        if scoped {
+            self.scopes.push(HashMap::new());
+        }
+        for stmt in block {
+            self.stmt(stmt, !scoped);
+        }
+        if scoped {
+            self.scopes.pop();
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, top_level: bool) {
+        match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Assert(expr) | StmtKind::Return(Some(expr)) => {
+                self.expr(expr)
+            }
+            StmtKind::Return(None) => {}
+            StmtKind::Let(Identifier(name), annotation, value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                if !top_level {
+                    let kind = DeclarationKind::Variable(annotation.clone());
+                    self.declare(name, kind, stmt.span.offset, stmt.span.offset);
+                }
+            }
+            StmtKind::Func(Identifier(name), params, body, ret_type, ..) => {
+                if !top_level {
+                    let kind = DeclarationKind::Function(params.clone(), ret_type.clone());
+                    self.declare(name, kind, stmt.span.offset, stmt.span.offset);
+                }
+                self.scopes.push(HashMap::new());
+                // parameters come after the function name
+                let after_name = find_word(self.source, stmt.span.offset, name)
+                    .map_or(stmt.span.offset, |span| span.offset + name.len());
+                for (Identifier(param), type_) in params {
+                    let kind = DeclarationKind::Parameter(type_.clone());
+                    self.declare(param, kind, after_name, stmt.span.offset);
+                }
+                self.block(body, true);
+                self.scopes.pop();
+            }
+            StmtKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.expr(condition);
+                self.block(consequence, true);
+                if let Some(alternative) = alternative {
+                    self.block(alternative, true);
+                }
+            }
+            StmtKind::ForLoop {
+                cursor,
+                iterable,
+                block,
+            } => {
+                self.expr(iterable);
+                self.scopes.push(HashMap::new());
+                let kind = DeclarationKind::LoopVariable;
+                self.declare(cursor, kind, stmt.span.offset, stmt.span.offset);
+                self.block(block, true);
+                self.scopes.pop();
+            }
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => {
+                self.block(block, true);
+                self.scopes.push(HashMap::new());
+                // the binding comes after the `catch` that follows the block
+                let catch = block
+                    .last()
+                    .map_or(stmt.span.offset, |last| last.span.offset);
+                let catch =
+                    find_word(self.source, catch, "catch").map_or(catch, |span| span.offset);
+                let kind = DeclarationKind::CatchBinding;
+                self.declare(binding, kind, catch + "catch".len(), stmt.span.offset);
+                self.block(handler, true);
+                self.scopes.pop();
+            }
+            StmtKind::Import(_) => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Array(items)) => {
+                items.iter().for_each(|item| self.expr(item))
+            }
+            ExprKind::Literal(_) => {}
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                self.reference(name, expr.span)
+            }
+            ExprKind::Call(func, args) => {
+                self.expr(func);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Method(receiver, Identifier(name), args, _) => {
+                self.expr(receiver);
+                self.reference(name, expr.span);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Infix(lhs, _, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Prefix(_, operand)
+            | ExprKind::Postfix(operand, _)
+            | ExprKind::Await(operand)
+            | ExprKind::Cast(operand, _) => self.expr(operand),
+            ExprKind::Assign(Identifier(name), value) => {
+                self.reference(name, expr.span);
+                self.expr(value);
+            }
+            ExprKind::Index(target, index) => {
+                self.expr(target);
+                self.expr(index);
+            }
+            ExprKind::IndexAssign(Identifier(name), index, value) => {
+                self.reference(name, expr.span);
+                self.expr(index);
+                self.expr(value);
+            }
+        }
+    }
+
+    fn reference(&mut self, name: &str, span: Span) {
+        let declaration = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied());
+        self.symbols
+            .references
+            .push(Reference { span, declaration });
+    }
+}
+
+/// Where `word` first appears as a whole word at or after byte `from`.
+fn find_word(source: &str, from: usize, word: &str) -> Option<Span> {
+    let is_word_char = |ch: char| ch.is_alphanumeric();
+    let mut start = from.min(source.len());
+    while let Some(found) = source.get(start..)?.find(word) {
+        let offset = start + found;
+        let end = offset + word.len();
+        let before = source[..offset].chars().next_back();
+        let after = source[end..].chars().next();
+        if !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char) {
+            return Some(span_at(source, offset));
+        }
+        start = end;
+    }
+    None
+}
+
+/// Line and column of byte `offset`.
+fn span_at(source: &str, offset: usize) -> Span {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Span {
+        line: before.matches('\n').count() + 1,
+        col: before[line_start..].chars().count() + 1,
+        offset,
+    }
+}
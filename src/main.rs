@@ -1,35 +1,359 @@
-mod repl;
+// Runtime errors carry their location, call trace and notes. They are
+// returned once per program, not in hot paths, so their size doesn't matter.
+#![allow(clippy::result_large_err)]
+
+mod bundle;
 mod commands;
-mod frontend;
-mod runtime;
-mod utils;
+mod dap;
+mod debugger;
+mod explain;
+mod lsp;
+mod packages;
+mod repl;
+mod serve;
+mod test_runner;
+mod watch;
 
-use std::{env, process::exit};
+use filipe::{
+    ast_json, codegen, diagnostics, flbc, frontend, messages, project, runtime, shared, utils,
+};
 
-use commands::run_from_file;
+use std::{
+    env,
+    io::{stdin, stdout, BufReader, IsTerminal},
+    path::Path,
+    process::exit,
+};
+
+use codegen::Target;
+use commands::{
+    build_file, bundle_file, check_files, compile_file, document_files, explain_error,
+    format_files, lint_files, minify_file, project_run_options, run_bundled, run_code,
+    run_from_file, take_lang_option, RunOptions, STDIN_PATH,
+};
 use repl::repl;
+use runtime::coverage::ReportFormat;
+use utils::script_path;
 
 fn main() {
-    let cli_args: Vec<String> = env::args().collect();
+    if let Some(program) = bundle::embedded() {
+        match program {
+            Ok(program) => run_bundled(program),
+            Err(err) => {
+                eprintln!("[ERROR]: {}", err);
+                exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut cli_args: Vec<String> = env::args().collect();
+    if let Err(err) = take_lang_option(&mut cli_args) {
+        eprintln!("[ERROR]: {}", err);
+        exit(1);
+    }
 
     if cli_args.len() <= 1 {
-        repl();
+        // `cat script.fl | filipe` runs the piped script
+        if stdin().is_terminal() {
+            repl();
+        } else {
+            run_from_file(STDIN_PATH, &RunOptions::default());
+        }
         return;
     }
 
     match cli_args[1].as_str() {
-        "run" => {
+        "run" => match cli_args.get(2) {
+            Some(path) if !path.starts_with("--") => {
+                run_from_file(path, &parse_run_options(Some(path), &cli_args[3..]))
+            }
+            // `filipe run` in a project runs its entry point
+            _ => {
+                let Some(root) = project::root() else {
+                    eprintln!(
+                        "[ERROR]: Missing file path, and no {} found",
+                        project::MANIFEST
+                    );
+                    exit(1);
+                };
+                let package = project::Package::load(&root).unwrap_or_else(|err| {
+                    eprintln!("[ERROR]: {}", err);
+                    exit(1);
+                });
+                let entry = root.join(package.entry).to_string_lossy().to_string();
+                run_from_file(&entry, &parse_run_options(Some(&entry), &cli_args[2..]));
+            }
+        },
+        // `filipe -e 'print(1 + 2)'`
+        "-e" | "--eval" => {
+            let Some(code) = cli_args.get(2) else {
+                eprintln!("[ERROR]: Missing code to run after '{}'", cli_args[1]);
+                exit(1);
+            };
+            run_code(code, &parse_run_options(None, &cli_args[3..]));
+        }
+        // `filipe -i script.fl` runs the script, then starts the REPL with
+        // what it defined
+        "-i" | "--interactive" => {
+            let Some(path) = cli_args.get(2) else {
+                eprintln!("[ERROR]: Usage: filipe -i <file> [run options]");
+                exit(1);
+            };
+            let mut options = parse_run_options(Some(path), &cli_args[3..]);
+            options.interactive = true;
+            run_from_file(path, &options);
+        }
+        STDIN_PATH => run_from_file(STDIN_PATH, &parse_run_options(None, &cli_args[2..])),
+        "compile" => {
             if cli_args.len() <= 2 {
                 eprintln!("[ERROR]: Missing file path");
                 exit(1);
             }
-            run_from_file(&cli_args[2]);
-        },
+            let output = match &cli_args[3..] {
+                [] => None,
+                [flag, output] if flag == "-o" => Some(output.as_str()),
+                _ => {
+                    eprintln!("[ERROR]: Usage: filipe compile <file> [-o <output>]");
+                    exit(1);
+                }
+            };
+            compile_file(&cli_args[2], output);
+        }
+        "bundle" => {
+            if cli_args.len() <= 2 {
+                eprintln!("[ERROR]: Missing file path");
+                exit(1);
+            }
+            let output = match &cli_args[3..] {
+                [] => None,
+                [flag, output] if flag == "-o" => Some(output.as_str()),
+                _ => {
+                    eprintln!("[ERROR]: Usage: filipe bundle <file> [-o <output>]");
+                    exit(1);
+                }
+            };
+            bundle_file(&cli_args[2], output);
+        }
         "build" => {
+            const USAGE: &str = "[ERROR]: Usage: filipe build --target <js|c> <file> [-o <output>]";
+            let (mut target, mut path, mut output) = (None, None, None);
+            let mut args = cli_args[2..].iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--target" => target = args.next().map(String::as_str),
+                    "-o" => output = args.next().map(String::as_str),
+                    _ => match arg.strip_prefix("--target=") {
+                        Some(name) => target = Some(name),
+                        None if path.is_none() => path = Some(arg.as_str()),
+                        None => {
+                            eprintln!("{USAGE}");
+                            exit(1);
+                        }
+                    },
+                }
+            }
+            let (Some(target), Some(path)) = (target, path) else {
+                eprintln!("{USAGE}");
+                exit(1);
+            };
+            let Some(target) = Target::from_name(target) else {
+                eprintln!("[ERROR]: Unknown target '{target}', expected js or c");
+                exit(1);
+            };
+            build_file(path, target, output);
+        }
+        "minify" => {
+            const USAGE: &str = "[ERROR]: Usage: filipe minify [--rename] <file> [-o <output>]";
+            let (mut rename, mut path, mut output) = (false, None, None);
+            let mut args = cli_args[2..].iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--rename" => rename = true,
+                    "-o" => output = args.next().map(String::as_str),
+                    _ if path.is_none() && !arg.starts_with('-') => path = Some(arg.as_str()),
+                    _ => {
+                        eprintln!("{USAGE}");
+                        exit(1);
+                    }
+                }
+            }
+            let Some(path) = path else {
+                eprintln!("{USAGE}");
+                exit(1);
+            };
+            minify_file(path, rename, output);
+        }
+        "check" => {
+            let (flags, paths): (Vec<String>, Vec<String>) = cli_args[2..]
+                .iter()
+                .cloned()
+                .partition(|arg| arg.starts_with("--"));
+            if paths.is_empty() {
+                eprintln!("[ERROR]: Missing file path");
+                exit(1);
+            }
+            check_files(&paths, &parse_run_options(None, &flags));
+        }
+        "lint" => {
+            let (flags, paths): (Vec<String>, Vec<String>) = cli_args[2..]
+                .iter()
+                .cloned()
+                .partition(|arg| arg.starts_with("--"));
+            let mut config = None;
+            let mut output_flags = vec![];
+            for flag in flags {
+                match flag.split_once('=') {
+                    Some(("--config", path)) if !path.is_empty() => config = Some(path.to_string()),
+                    Some(("--error-format", _)) => output_flags.push(flag),
+                    None if flag == "--no-color" => output_flags.push(flag),
+                    _ => {
+                        eprintln!("[ERROR]: Usage: filipe lint [--config=<file>] [--error-format=json] <file>...");
+                        exit(1);
+                    }
+                }
+            }
+            if paths.is_empty() {
+                eprintln!(
+                    "[ERROR]: Usage: filipe lint [--config=<file>] [--error-format=json] <file>..."
+                );
+                exit(1);
+            }
+            lint_files(
+                &paths,
+                config.as_deref(),
+                &parse_run_options(None, &output_flags),
+            );
+        }
+        "fmt" => {
+            let check = cli_args[2..].iter().any(|arg| arg == "--check");
+            let paths = cli_args[2..]
+                .iter()
+                .filter(|arg| *arg != "--check")
+                .cloned()
+                .collect::<Vec<_>>();
+            if paths.is_empty() {
+                eprintln!("[ERROR]: Usage: filipe fmt [--check] <file>...");
+                exit(1);
+            }
+            format_files(&paths, check);
+        }
+        "doc" => {
+            let (flags, paths): (Vec<String>, Vec<String>) = cli_args[2..]
+                .iter()
+                .cloned()
+                .partition(|arg| arg.starts_with("--"));
+            let mut html = false;
+            let mut out_dir = "doc".to_string();
+            for flag in &flags {
+                match flag.split_once('=') {
+                    None if flag == "--html" => html = true,
+                    Some(("--out", dir)) if !dir.is_empty() => out_dir = dir.to_string(),
+                    _ => {
+                        eprintln!("[ERROR]: Usage: filipe doc [--html] [--out=<dir>] <file>...");
+                        exit(1);
+                    }
+                }
+            }
+            if paths.is_empty() {
+                eprintln!("[ERROR]: Usage: filipe doc [--html] [--out=<dir>] <file>...");
+                exit(1);
+            }
+            document_files(&paths, html, &out_dir);
+        }
+        "test" => {
+            let (flags, mut paths): (Vec<String>, Vec<String>) = cli_args[2..]
+                .iter()
+                .cloned()
+                .partition(|arg| arg.starts_with("--"));
+            if paths.is_empty() {
+                paths.push(".".to_string());
+            }
+            let mut coverage = None;
+            for flag in &flags {
+                coverage = match flag.as_str() {
+                    "--coverage" | "--coverage=lcov" => Some(ReportFormat::Lcov),
+                    "--coverage=html" => Some(ReportFormat::Html),
+                    _ => {
+                        eprintln!("[ERROR]: Usage: filipe test [--coverage[=html]] [<path>...]");
+                        exit(1);
+                    }
+                };
+            }
+            if !test_runner::run_tests(&paths, coverage) {
+                exit(1);
+            }
+        }
+        "watch" => {
+            let Some(path) = cli_args.get(2) else {
+                eprintln!("[ERROR]: Usage: filipe watch <file> [run options]");
+                exit(1);
+            };
+            // checked here so a typo isn't repeated on every run
+            parse_run_options(Some(path), &cli_args[3..]);
+            watch::watch(path, &cli_args[3..]);
+        }
+        "new" => {
+            let Some(name) = cli_args.get(2) else {
+                eprintln!("[ERROR]: Usage: filipe new <name>");
+                exit(1);
+            };
+            if !project::new(name) {
+                exit(1);
+            }
+        }
+        "init" => {
+            if !project::init() {
+                exit(1);
+            }
+        }
+        "add" => {
+            if cli_args.len() <= 2 {
+                eprintln!("[ERROR]: Usage: filipe add <git-url|name>...");
+                exit(1);
+            }
+            if !packages::add(&cli_args[2..]) {
+                exit(1);
+            }
+        }
+        "install" => {
+            if !packages::install() {
+                exit(1);
+            }
+        }
+        "lsp" => exit(lsp::serve(stdin().lock(), stdout().lock())),
+        "dap" => exit(dap::serve(BufReader::new(stdin()), stdout())),
+        "serve" => match cli_args.get(2).map(String::as_str) {
+            None => exit(serve::serve_stdio(stdin().lock(), stdout())),
+            Some(arg) => match arg.strip_prefix("--port=").map(str::parse) {
+                Some(Ok(port)) => exit(serve::serve_tcp(port)),
+                _ => {
+                    eprintln!("[ERROR]: Usage: filipe serve [--port=<port>]");
+                    exit(1);
+                }
+            },
         },
+        "explain" => explain_error(cli_args.get(2).map(String::as_str)),
+        // `filipe script.fl`, which is also how a `#!/usr/bin/env filipe`
+        // script is started
+        path if Path::new(&script_path(path)).is_file() => {
+            run_from_file(path, &parse_run_options(Some(path), &cli_args[2..]))
+        }
         _ => {
             eprintln!("[ERROR]: Unknown command {}", cli_args[1]);
             exit(1);
         }
     }
 }
+
+/// The run options in `args`, over those of the project `script` is in,
+/// or the current directory's without one, see `project_of`.
+fn parse_run_options(script: Option<&str>, args: &[String]) -> RunOptions {
+    match project_run_options(script).with_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("[ERROR]: {}", err);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! English messages, also used for any key another language lacks.
+
+pub const MESSAGES: &[(&str, &str)] = &[
+    // Argument counts
+    ("args.one", "arg"),
+    ("args.many", "args"),
+    ("args.exactly", "{count} {args}"),
+    ("args.range", "{min} to {max} {args}"),
+    ("args.at_least", "at least {min} {args}"),
+    // Diagnostics
+    ("diagnostic.help", "help"),
+    ("diagnostic.in_expr", "{msg} in `{expr}`"),
+    ("trace.called_at", "in '{function}' called at {span}"),
+    (
+        "trace.repeated",
+        "... the call above repeated {count} more times",
+    ),
+    // Syntax errors
+    ("E001.kind", "Syntax Error"),
+    (
+        "E001.invalid_assign_target",
+        "Left side of assignment must be a variable or an array item",
+    ),
+    (
+        "E001.assign_to_constant",
+        "Can't assign to '{name}', it is a constant",
+    ),
+    (
+        "E001.assign_to_builtin",
+        "Can't assign to '{name}', it is a builtin",
+    ),
+    (
+        "E001.invalid_identifier",
+        "'{token}' cannot be used as identifier",
+    ),
+    (
+        "E001.expected_but_provided",
+        "expected '{expected}' but provided '{found}'",
+    ),
+    ("E001.unexpected_token", "unexpected {token}"),
+    (
+        "E001.too_deeply_nested",
+        "Code is nested more than {max} levels deep",
+    ),
+    ("E001.invalid_type", "Invalid type: {token}"),
+    (
+        "E001.void_param",
+        "Function parameter can't not be of type 'void'",
+    ),
+    ("E001.unbalanced_quote", "Unbalanced '\"'"),
+    ("E001.invalid_number", "Invalid number '{literal}'"),
+    (
+        "E001.int_too_large",
+        "Integer '{literal}' doesn't fit in type int",
+    ),
+    ("E001.invalid_utf8", "Source is not valid UTF-8"),
+    ("E001.unreadable", "Couldn't read source: {error}"),
+    (
+        "E001.nested_import",
+        "'import' can only be used at the top level of a script",
+    ),
+    ("E001.not_an_expression", "expected a single expression"),
+    ("E001.unclosed_placeholder", "'${' isn't closed"),
+    (
+        "E001.cast_target",
+        "'as' converts to int, float, string or boolean, not '{found}'",
+    ),
+    // Name errors
+    ("E010.kind", "Name error"),
+    ("E010.not_declared", "'{name}' is not declared"),
+    (
+        "E010.did_you_mean",
+        "'{name}' is not declared; did you mean '{closest}'?",
+    ),
+    (
+        "E010.used_before_declared",
+        "'{name}' is used before it's declared",
+    ),
+    (
+        "E010.already_declared",
+        "'{name}' is already declared in this scope",
+    ),
+    (
+        "E010.first_declared",
+        "'{name}' was first declared at {span}",
+    ),
+    ("E010.builtin", "'{name}' is a builtin"),
+    ("E010.not_assignable", "'{name}' is not assignable"),
+    ("E010.function_name", "Function name must be an identifier"),
+    ("E010.param_declared", "Param '{name}' already declared"),
+    // Type errors
+    ("E011.kind", "Type Error"),
+    ("E011.not_iterable", "type '{found}' is not iterable"),
+    (
+        "E011.strict_condition",
+        "condition must be of type 'boolean' in strict mode but found '{found}'",
+    ),
+    (
+        "E011.postfix_number",
+        "'{op}' operation is only allowed for type 'number'",
+    ),
+    ("E011.prefix_number", "'{op}' prefix is for type number"),
+    (
+        "E011.assign_mismatch",
+        "'{name}' expects value of type '{expected}' but provided value of type '{found}'",
+    ),
+    (
+        "E011.assign_array_items",
+        "'{name}' expects value of type Array<{expected}>",
+    ),
+    (
+        "E011.assign_array_mismatch",
+        "'{name}' expects array of type '{expected}' but provided array of type '{found}'",
+    ),
+    ("E011.not_indexable", "type '{found}' can't be indexed"),
+    (
+        "E011.item_mismatch",
+        "'{name}' expects items of type '{expected}' but provided value of type '{found}'",
+    ),
+    (
+        "E011.index_type",
+        "array index must be of type 'int' but provided '{found}'",
+    ),
+    (
+        "E011.infix_types",
+        "'{op}' operation not allowed between types {lhs} and {rhs}",
+    ),
+    (
+        "E011.infix_not_implemented",
+        "'{op}' operation not implemented for type {found}",
+    ),
+    ("E011.array_items_mismatch", "Array item's type mismatch"),
+    (
+        "E011.infer_type",
+        "Can't infer type of '{name}', define it's type or initialize it",
+    ),
+    (
+        "E011.infer_type_annotate",
+        "Can't infer type of '{name}', annotate its type",
+    ),
+    ("E011.infer_array", "Can't infer type of array '{name}'"),
+    (
+        "E011.infer_return",
+        "Can't infer the return type of '{name}', annotate it",
+    ),
+    ("E011.void_var", "Can't declared var of type 'void'"),
+    ("E011.void_array", "Can't declared array of type 'void'"),
+    (
+        "E011.void_declaration",
+        "Can't declare '{name}' of type '{found}'",
+    ),
+    (
+        "E011.strict_uninitialized",
+        "'{name}' must be initialized in strict mode instead of starting as null",
+    ),
+    (
+        "E011.let_mismatch",
+        "Assigning value of type {found} to variable '{name}' which has type {expected}",
+    ),
+    ("E011.not_callable", "'{name}' is not callable"),
+    (
+        "E011.function_arg_count",
+        "Function '{name}' expects {expected} but {found} were provided",
+    ),
+    (
+        "E011.builtin_arg_count",
+        "'{name}' expects {expected} but {found} were provided",
+    ),
+    (
+        "E011.param_type",
+        "Passing argument of type '{found}' to parameter of type '{expected}'",
+    ),
+    (
+        "E011.missing_return",
+        "Function '{name}' ended without returning a value of type '{expected}'",
+    ),
+    (
+        "E011.may_not_return",
+        "Function '{name}' may end without returning a value of type '{expected}'",
+    ),
+    (
+        "E011.return_type",
+        "Function '{name}' must return '{expected}' but found '{found}'",
+    ),
+    ("E011.len_type", "'len' only accepts iterable types"),
+    (
+        "E011.range_args",
+        "args for function 'range' must be of type number",
+    ),
+    (
+        "E011.next_type",
+        "'next' only accepts iterators, create one with 'iter'",
+    ),
+    (
+        "E011.call_by_name",
+        "'{name}' can only be called by its name",
+    ),
+    (
+        "E011.in_type",
+        "'in' needs an array, set, string or range on its right, found '{found}'",
+    ),
+    ("E011.set_item", "type '{found}' can't be in a set"),
+    ("E011.cast_type", "can't convert '{found}' to '{expected}'"),
+    // Argument errors
+    ("E012.kind", "Argument Error"),
+    (
+        "E012.arg_count",
+        "'{name}' expects {expected} but {found} were provided",
+    ),
+    (
+        "E012.random_negative",
+        "Argument for 'random' must be a non-negative integer",
+    ),
+    ("E012.random_int", "'random' expects an integer argument"),
+    (
+        "E012.random_negatives",
+        "Arguments for 'random' must be non-negative integers",
+    ),
+    ("E012.random_ints", "'random' expects two integer arguments"),
+    ("E012.random_count", "'random' expects 0, 1, or 2 arguments"),
+    ("E012.exit_type", "'exit' only accepts an integer argument"),
+    (
+        "E012.error_args",
+        "'error' expects a kind and a message, both strings",
+    ),
+    ("E012.error_value", "'{name}' expects 1 arg of type error"),
+    ("E012.arg_missing", "'{name}' expects an argument {index}"),
+    (
+        "E012.arg_type",
+        "argument {index} of '{name}' must be of type '{expected}' but provided '{found}'",
+    ),
+    // Value errors
+    ("E013.kind", "Value Error"),
+    ("E013.negative_index", "index {index} is negative"),
+    ("E013.range_step", "range can't step by 0"),
+    ("E013.exit_code", "exit codes go from 0 to 255, not {code}"),
+    (
+        "E013.index_out_of_range",
+        "index {index} out of range for array of length {len}",
+    ),
+    (
+        "E013.random_order",
+        "The first argument for 'random' must be less than or equal to the second argument",
+    ),
+    ("E013.task_lost", "the task stopped without a result"),
+    (
+        "E013.cast_value",
+        "{value} can't be converted to '{expected}'",
+    ),
+    ("E013.channel_closed", "the channel is closed"),
+    (
+        "E013.channel_empty",
+        "the channel is empty and no task can send to it while this waits",
+    ),
+    (
+        "E013.mutex_locked",
+        "the mutex is locked and no task can unlock it while this waits",
+    ),
+    ("E013.mutex_unlocked", "the mutex isn't locked"),
+    (
+        "E013.no_clock",
+        "there is no clock to time timers on this target",
+    ),
+    // Limits
+    ("E014.kind", "Execution Limit Exceeded"),
+    ("E014.exceeded", "Program exceeded the limit of {max} steps"),
+    (
+        "E014.call_depth",
+        "Calls nested deeper than the limit of {max}",
+    ),
+    (
+        "E014.help",
+        "make sure loops and recursion end, or raise the limit with '--max-steps'",
+    ),
+    (
+        "E014.timer_wait",
+        "Timers would wait longer than the limit of {max} ms",
+    ),
+    (
+        "E014.call_depth_help",
+        "make sure recursion ends, or raise the limit with '--max-call-depth'",
+    ),
+    ("E015.kind", "Memory Limit Exceeded"),
+    (
+        "E015.exceeded",
+        "Program exceeded the memory limit of {max} bytes",
+    ),
+    ("E015.help", "raise the limit with '--max-memory'"),
+    // Arithmetic
+    ("E016.kind", "Overflow Error"),
+    ("E016.overflow", "'{lhs} {op} {rhs}' overflows type int"),
+    (
+        "E016.help",
+        "run with '--wrapping' to wrap around on overflow",
+    ),
+    ("E017.kind", "Division By Zero"),
+    ("E017.divides_by_zero", "'{lhs} {op} {rhs}' divides by zero"),
+    // Assertions
+    ("E019.kind", "Assertion Error"),
+    ("E019.failed", "assertion failed: {condition}"),
+    (
+        "E019.failed_values",
+        "assertion failed: {condition} ({values})",
+    ),
+    // Imports
+    ("E020.kind", "Import Error"),
+    ("E020.not_found", "module '{name}' not found"),
+    ("E020.unreadable", "couldn't read module {path}: {error}"),
+    (
+        "E020.syntax",
+        "module {path} has a syntax error at {span}: {msg}",
+    ),
+    ("E020.raised_in", "raised in {path}:{span}"),
+    (
+        "E020.plugin_unloadable",
+        "couldn't load the plugin {path}: {error}",
+    ),
+    (
+        "E020.not_a_plugin",
+        "{path} isn't a plugin, it exports no FILIPE_PLUGIN",
+    ),
+    (
+        "E020.plugin_incompatible",
+        "the plugin {path} was built for another version of filipe",
+    ),
+    // Permissions
+    ("E021.kind", "Permission Error"),
+    ("E021.exit", "'exit' isn't permitted here"),
+    ("E021.import", "importing '{name}' isn't permitted here"),
+    ("E021.denied", "'{capability}' isn't permitted here"),
+    (
+        "E021.expression_call",
+        "'{name}' can't be called in an expression",
+    ),
+    (
+        "E021.expression_assign",
+        "'{name}' can't be changed in an expression",
+    ),
+    // Exit
+    ("E022.kind", "Exit"),
+    ("E022.exited", "the script exited with code {code}"),
+    // Interrupts
+    ("E023.kind", "Interrupted"),
+    ("E023.interrupted", "the program was interrupted"),
+    // Warnings
+    ("W.kind", "Warning"),
+    ("W.error_kind", "Error"),
+    ("W001.unused", "variable '{name}' is never used"),
+    ("W002.unused", "parameter '{name}' is never used"),
+    ("W003.unreachable", "unreachable statement after 'return'"),
+    (
+        "W004.constant",
+        "condition is constant, so the same branch always runs",
+    ),
+    (
+        "W005.shadows_variable",
+        "'{name}' shadows the variable declared at {span}",
+    ),
+    (
+        "W005.shadows_parameter",
+        "'{name}' shadows the parameter declared at {span}",
+    ),
+    (
+        "W006.shadows_builtin",
+        "'{name}' hides the builtin of the same name",
+    ),
+    (
+        "W007.too_deep",
+        "block is nested {depth} levels deep, more than the {max} allowed",
+    ),
+    (
+        "W008.naming",
+        "'{name}' should start with a lowercase letter",
+    ),
+];
@@ -0,0 +1,219 @@
+//! Catalog of the messages shown in diagnostics. Each message is keyed by
+//! the code of the error or warning it belongs to plus a name, e.g.
+//! `E010.not_declared`, and has `{name}` placeholders filled in when it is
+//! looked up:
+//!
+//! ```text
+//! msg!("E010.not_declared", name = "totl")  // 'totl' is not declared
+//! ```
+
+mod en;
+mod pt;
+
+use std::cell::Cell;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lang {
+    En = 0,
+    Pt = 1,
+}
+
+impl Lang {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "en" => Some(Self::En),
+            "pt" => Some(Self::Pt),
+            _ => None,
+        }
+    }
+
+    fn messages(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::En => en::MESSAGES,
+            Self::Pt => pt::MESSAGES,
+        }
+    }
+}
+
+/// Looks up a message in the current language:
+/// `msg!("E010.not_declared", name = name)`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! msg {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::messages::message(
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*],
+        )
+    };
+}
+pub use crate::msg;
+
+/// Language messages are shown in, picked once with `--lang`, unless the
+/// runtime running picked one of its own.
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+thread_local! {
+    /// The language of the runtime running on this thread, see
+    /// `Runtime::set_lang`.
+    static RUNTIME_LANG: Cell<Option<Lang>> = const { Cell::new(None) };
+}
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    if let Some(lang) = RUNTIME_LANG.with(Cell::get) {
+        return lang;
+    }
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Pt,
+        _ => Lang::En,
+    }
+}
+
+/// Runs `f` with messages in `lang`, or in the process' language with
+/// `None`, on this thread.
+pub(crate) fn with_runtime_lang<R>(lang: Option<Lang>, f: impl FnOnce() -> R) -> R {
+    let outer = RUNTIME_LANG.with(|current| current.replace(lang));
+    let result = f();
+    RUNTIME_LANG.with(|current| current.set(outer));
+    result
+}
+
+/// Looks up `key` in the catalog of the current language, see `msg!`.
+pub fn message(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    message_in(lang(), key, args)
+}
+
+/// Looks up `key` in the catalog of `lang`, falling back to English, and
+/// replaces each `{name}` placeholder with the argument called `name`.
+pub fn message_in(lang: Lang, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = lookup(lang.messages(), key)
+        .or_else(|| lookup(en::MESSAGES, key))
+        .unwrap_or(key);
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            out.push('{');
+            break;
+        };
+        let name = &rest[..end];
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => out.push_str(&value.to_string()),
+            None => out.push_str(&format!("{{{name}}}")),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn lookup(messages: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    messages
+        .iter()
+        .find(|(message, _)| *message == key)
+        .map(|(_, template)| *template)
+}
+
+/// "1 arg", "0 to 2 args", "at least 1 arg".
+pub fn count_args(min: usize, max: Option<usize>) -> String {
+    let args = |count: usize| match count {
+        1 => msg!("args.one"),
+        _ => msg!("args.many"),
+    };
+    match max {
+        Some(max) if max == min => msg!("args.exactly", count = min, args = args(min)),
+        Some(max) => msg!("args.range", min = min, max = max, args = args(max)),
+        None => msg!("args.at_least", min = min, args = args(min)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `{name}` placeholders of a template, sorted.
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names = template
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_every_message_is_translated() {
+        for (key, template) in en::MESSAGES {
+            let translated = lookup(pt::MESSAGES, key);
+            assert!(translated.is_some(), "{key} has no Portuguese message");
+            assert_eq!(placeholders(template), placeholders(translated.unwrap()));
+        }
+        assert_eq!(en::MESSAGES.len(), pt::MESSAGES.len());
+    }
+
+    #[test]
+    fn test_every_used_key_is_in_the_catalog() {
+        fn visit(dir: &std::path::Path, keys: &mut Vec<String>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    visit(&path, keys);
+                } else if path.extension().is_some_and(|ext| ext == "rs") {
+                    let source = std::fs::read_to_string(&path).unwrap();
+                    for call in source.split("msg!(").skip(1) {
+                        let call = call.trim_start();
+                        let key = call.strip_prefix('"').and_then(|c| c.split('"').next());
+                        // skips mentions of the macro that aren't calls
+                        let is_key = |key: &&str| {
+                            key.contains('.')
+                                && key
+                                    .chars()
+                                    .all(|c| c.is_alphanumeric() || c == '.' || c == '_')
+                        };
+                        if let Some(key) = key.filter(is_key) {
+                            keys.push(key.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        let mut keys = vec![];
+        visit(
+            std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src")),
+            &mut keys,
+        );
+        assert!(!keys.is_empty());
+        for key in keys {
+            assert!(
+                lookup(en::MESSAGES, &key).is_some(),
+                "{key} is not in the catalog"
+            );
+        }
+    }
+
+    #[test]
+    fn test_messages_are_interpolated() {
+        let name = "totl";
+        let args: &[(&str, &dyn Display)] = &[("name", &name), ("closest", &"total")];
+        assert_eq!(
+            message_in(Lang::En, "E010.did_you_mean", args),
+            "'totl' is not declared; did you mean 'total'?"
+        );
+        assert_eq!(
+            message_in(Lang::Pt, "E010.did_you_mean", args),
+            "'totl' não foi declarado; quis dizer 'total'?"
+        );
+        assert_eq!(message_in(Lang::Pt, "E001.kind", &[]), "Erro de Sintaxe");
+        assert_eq!(message_in(Lang::En, "missing.key", &[]), "missing.key");
+    }
+}
@@ -0,0 +1,423 @@
+//! Portuguese messages.
+
+pub const MESSAGES: &[(&str, &str)] = &[
+    // Argument counts
+    ("args.one", "argumento"),
+    ("args.many", "argumentos"),
+    ("args.exactly", "{count} {args}"),
+    ("args.range", "{min} a {max} {args}"),
+    ("args.at_least", "pelo menos {min} {args}"),
+    // Diagnostics
+    ("diagnostic.help", "ajuda"),
+    ("diagnostic.in_expr", "{msg} em `{expr}`"),
+    ("trace.called_at", "em '{function}' chamada em {span}"),
+    (
+        "trace.repeated",
+        "... a chamada acima se repetiu mais {count} vezes",
+    ),
+    // Syntax errors
+    ("E001.kind", "Erro de Sintaxe"),
+    (
+        "E001.invalid_assign_target",
+        "O lado esquerdo da atribuição deve ser uma variável ou um item de array",
+    ),
+    (
+        "E001.assign_to_constant",
+        "Não é possível atribuir a '{name}', é uma constante",
+    ),
+    (
+        "E001.assign_to_builtin",
+        "Não é possível atribuir a '{name}', é uma função embutida",
+    ),
+    (
+        "E001.invalid_identifier",
+        "'{token}' não pode ser usado como identificador",
+    ),
+    (
+        "E001.expected_but_provided",
+        "esperado '{expected}' mas encontrado '{found}'",
+    ),
+    ("E001.unexpected_token", "{token} inesperado"),
+    (
+        "E001.too_deeply_nested",
+        "O código tem mais de {max} níveis de aninhamento",
+    ),
+    ("E001.invalid_type", "Tipo inválido: {token}"),
+    (
+        "E001.void_param",
+        "Parâmetro de função não pode ser do tipo 'void'",
+    ),
+    ("E001.unbalanced_quote", "'\"' sem par"),
+    ("E001.invalid_number", "Número inválido '{literal}'"),
+    (
+        "E001.int_too_large",
+        "O inteiro '{literal}' não cabe no tipo int",
+    ),
+    ("E001.invalid_utf8", "O código fonte não é UTF-8 válido"),
+    (
+        "E001.unreadable",
+        "Não foi possível ler o código fonte: {error}",
+    ),
+    (
+        "E001.nested_import",
+        "'import' só pode ser usado no nível superior de um script",
+    ),
+    ("E001.not_an_expression", "esperava-se uma única expressão"),
+    ("E001.unclosed_placeholder", "'${' não foi fechado"),
+    (
+        "E001.cast_target",
+        "'as' converte para int, float, string ou boolean, não '{found}'",
+    ),
+    // Name errors
+    ("E010.kind", "Erro de Nome"),
+    ("E010.not_declared", "'{name}' não foi declarado"),
+    (
+        "E010.did_you_mean",
+        "'{name}' não foi declarado; quis dizer '{closest}'?",
+    ),
+    (
+        "E010.used_before_declared",
+        "'{name}' é usado antes de ser declarado",
+    ),
+    (
+        "E010.already_declared",
+        "'{name}' já foi declarado neste escopo",
+    ),
+    (
+        "E010.first_declared",
+        "'{name}' foi declarado primeiro em {span}",
+    ),
+    ("E010.builtin", "'{name}' é uma função embutida"),
+    ("E010.not_assignable", "'{name}' não pode ser atribuído"),
+    (
+        "E010.function_name",
+        "O nome da função deve ser um identificador",
+    ),
+    ("E010.param_declared", "Parâmetro '{name}' já foi declarado"),
+    // Type errors
+    ("E011.kind", "Erro de Tipo"),
+    ("E011.not_iterable", "o tipo '{found}' não é iterável"),
+    (
+        "E011.strict_condition",
+        "a condição deve ser do tipo 'boolean' no modo estrito mas é '{found}'",
+    ),
+    (
+        "E011.postfix_number",
+        "a operação '{op}' só é permitida para o tipo 'number'",
+    ),
+    (
+        "E011.prefix_number",
+        "o prefixo '{op}' é para o tipo number",
+    ),
+    (
+        "E011.assign_mismatch",
+        "'{name}' espera um valor do tipo '{expected}' mas recebeu um valor do tipo '{found}'",
+    ),
+    (
+        "E011.assign_array_items",
+        "'{name}' espera um valor do tipo Array<{expected}>",
+    ),
+    (
+        "E011.assign_array_mismatch",
+        "'{name}' espera um array do tipo '{expected}' mas recebeu um array do tipo '{found}'",
+    ),
+    (
+        "E011.not_indexable",
+        "o tipo '{found}' não pode ser indexado",
+    ),
+    (
+        "E011.item_mismatch",
+        "'{name}' espera itens do tipo '{expected}' mas recebeu um valor do tipo '{found}'",
+    ),
+    (
+        "E011.index_type",
+        "o índice do array deve ser do tipo 'int' mas é '{found}'",
+    ),
+    (
+        "E011.infix_types",
+        "a operação '{op}' não é permitida entre os tipos {lhs} e {rhs}",
+    ),
+    (
+        "E011.infix_not_implemented",
+        "a operação '{op}' não está implementada para o tipo {found}",
+    ),
+    (
+        "E011.array_items_mismatch",
+        "Os itens do array têm tipos diferentes",
+    ),
+    (
+        "E011.infer_type",
+        "Não é possível inferir o tipo de '{name}', defina o seu tipo ou inicialize-o",
+    ),
+    (
+        "E011.infer_type_annotate",
+        "Não é possível inferir o tipo de '{name}', anote o seu tipo",
+    ),
+    (
+        "E011.infer_array",
+        "Não é possível inferir o tipo do array '{name}'",
+    ),
+    (
+        "E011.infer_return",
+        "Não é possível inferir o tipo de retorno de '{name}', anote-o",
+    ),
+    (
+        "E011.void_var",
+        "Não é possível declarar variável do tipo 'void'",
+    ),
+    (
+        "E011.void_array",
+        "Não é possível declarar array do tipo 'void'",
+    ),
+    (
+        "E011.void_declaration",
+        "Não é possível declarar '{name}' do tipo '{found}'",
+    ),
+    (
+        "E011.strict_uninitialized",
+        "'{name}' deve ser inicializado no modo estrito em vez de começar como null",
+    ),
+    (
+        "E011.let_mismatch",
+        "Atribuindo valor do tipo {found} à variável '{name}' que tem o tipo {expected}",
+    ),
+    ("E011.not_callable", "'{name}' não pode ser chamado"),
+    (
+        "E011.function_arg_count",
+        "A função '{name}' espera {expected} mas {found} foram passados",
+    ),
+    (
+        "E011.builtin_arg_count",
+        "'{name}' espera {expected} mas {found} foram passados",
+    ),
+    (
+        "E011.param_type",
+        "Passando argumento do tipo '{found}' para parâmetro do tipo '{expected}'",
+    ),
+    (
+        "E011.missing_return",
+        "A função '{name}' terminou sem retornar um valor do tipo '{expected}'",
+    ),
+    (
+        "E011.may_not_return",
+        "A função '{name}' pode terminar sem retornar um valor do tipo '{expected}'",
+    ),
+    (
+        "E011.return_type",
+        "A função '{name}' deve retornar '{expected}' mas retornou '{found}'",
+    ),
+    ("E011.len_type", "'len' só aceita tipos iteráveis"),
+    (
+        "E011.range_args",
+        "os argumentos da função 'range' devem ser do tipo number",
+    ),
+    (
+        "E011.next_type",
+        "'next' só aceita iteradores, crie um com 'iter'",
+    ),
+    (
+        "E011.call_by_name",
+        "'{name}' só pode ser chamado pelo seu nome",
+    ),
+    (
+        "E011.in_type",
+        "'in' precisa de um array, conjunto, string ou intervalo à direita, encontrou '{found}'",
+    ),
+    (
+        "E011.set_item",
+        "o tipo '{found}' não pode estar num conjunto",
+    ),
+    (
+        "E011.cast_type",
+        "não é possível converter '{found}' para '{expected}'",
+    ),
+    // Argument errors
+    ("E012.kind", "Erro de Argumento"),
+    (
+        "E012.arg_count",
+        "'{name}' espera {expected} mas {found} foram passados",
+    ),
+    (
+        "E012.random_negative",
+        "O argumento de 'random' deve ser um inteiro não negativo",
+    ),
+    ("E012.random_int", "'random' espera um argumento inteiro"),
+    (
+        "E012.random_negatives",
+        "Os argumentos de 'random' devem ser inteiros não negativos",
+    ),
+    (
+        "E012.random_ints",
+        "'random' espera dois argumentos inteiros",
+    ),
+    ("E012.random_count", "'random' espera 0, 1 ou 2 argumentos"),
+    ("E012.exit_type", "'exit' só aceita um argumento inteiro"),
+    (
+        "E012.error_args",
+        "'error' espera um tipo e uma mensagem, ambos strings",
+    ),
+    (
+        "E012.error_value",
+        "'{name}' espera 1 argumento do tipo error",
+    ),
+    ("E012.arg_missing", "'{name}' espera um argumento {index}"),
+    (
+        "E012.arg_type",
+        "o argumento {index} de '{name}' deve ser do tipo '{expected}' mas foi fornecido '{found}'",
+    ),
+    // Value errors
+    ("E013.kind", "Erro de Valor"),
+    ("E013.negative_index", "o índice {index} é negativo"),
+    ("E013.range_step", "range não pode avançar de 0 em 0"),
+    (
+        "E013.exit_code",
+        "códigos de saída vão de 0 a 255, não {code}",
+    ),
+    (
+        "E013.index_out_of_range",
+        "o índice {index} está fora dos limites de um array de tamanho {len}",
+    ),
+    (
+        "E013.random_order",
+        "O primeiro argumento de 'random' deve ser menor ou igual ao segundo",
+    ),
+    ("E013.task_lost", "a tarefa parou sem um resultado"),
+    (
+        "E013.cast_value",
+        "{value} não pode ser convertido para '{expected}'",
+    ),
+    ("E013.channel_closed", "o canal está fechado"),
+    (
+        "E013.channel_empty",
+        "o canal está vazio e nenhuma tarefa pode enviar a ele enquanto esta espera",
+    ),
+    (
+        "E013.mutex_locked",
+        "o mutex está bloqueado e nenhuma tarefa pode desbloqueá-lo enquanto esta espera",
+    ),
+    ("E013.mutex_unlocked", "o mutex não está bloqueado"),
+    (
+        "E013.no_clock",
+        "não há relógio para os temporizadores neste alvo",
+    ),
+    // Limits
+    ("E014.kind", "Limite de Execução Excedido"),
+    (
+        "E014.exceeded",
+        "O programa excedeu o limite de {max} passos",
+    ),
+    (
+        "E014.call_depth",
+        "As chamadas se aninharam além do limite de {max}",
+    ),
+    (
+        "E014.help",
+        "garanta que laços e recursões terminem, ou aumente o limite com '--max-steps'",
+    ),
+    (
+        "E014.timer_wait",
+        "Os temporizadores esperariam além do limite de {max} ms",
+    ),
+    (
+        "E014.call_depth_help",
+        "garanta que recursões terminem, ou aumente o limite com '--max-call-depth'",
+    ),
+    ("E015.kind", "Limite de Memória Excedido"),
+    (
+        "E015.exceeded",
+        "O programa excedeu o limite de memória de {max} bytes",
+    ),
+    ("E015.help", "aumente o limite com '--max-memory'"),
+    // Arithmetic
+    ("E016.kind", "Erro de Overflow"),
+    ("E016.overflow", "'{lhs} {op} {rhs}' ultrapassa o tipo int"),
+    (
+        "E016.help",
+        "execute com '--wrapping' para dar a volta em caso de overflow",
+    ),
+    ("E017.kind", "Divisão Por Zero"),
+    ("E017.divides_by_zero", "'{lhs} {op} {rhs}' divide por zero"),
+    // Assertions
+    ("E019.kind", "Erro de Asserção"),
+    ("E019.failed", "a asserção falhou: {condition}"),
+    (
+        "E019.failed_values",
+        "a asserção falhou: {condition} ({values})",
+    ),
+    // Imports
+    ("E020.kind", "Erro de Importação"),
+    ("E020.not_found", "o módulo '{name}' não foi encontrado"),
+    (
+        "E020.unreadable",
+        "não foi possível ler o módulo {path}: {error}",
+    ),
+    (
+        "E020.syntax",
+        "o módulo {path} tem um erro de sintaxe em {span}: {msg}",
+    ),
+    ("E020.raised_in", "lançado em {path}:{span}"),
+    (
+        "E020.plugin_unloadable",
+        "não foi possível carregar o plugin {path}: {error}",
+    ),
+    (
+        "E020.not_a_plugin",
+        "{path} não é um plugin, não exporta FILIPE_PLUGIN",
+    ),
+    (
+        "E020.plugin_incompatible",
+        "o plugin {path} foi compilado para outra versão do filipe",
+    ),
+    // Permissions
+    ("E021.kind", "Erro de Permissão"),
+    ("E021.exit", "'exit' não é permitido aqui"),
+    ("E021.import", "importar '{name}' não é permitido aqui"),
+    ("E021.denied", "'{capability}' não é permitido aqui"),
+    (
+        "E021.expression_call",
+        "'{name}' não pode ser chamado em uma expressão",
+    ),
+    (
+        "E021.expression_assign",
+        "'{name}' não pode ser alterado em uma expressão",
+    ),
+    // Exit
+    ("E022.kind", "Saída"),
+    ("E022.exited", "o script terminou com o código {code}"),
+    // Interrupts
+    ("E023.kind", "Interrompido"),
+    ("E023.interrupted", "o programa foi interrompido"),
+    // Warnings
+    ("W.kind", "Aviso"),
+    ("W.error_kind", "Erro"),
+    ("W001.unused", "a variável '{name}' nunca é usada"),
+    ("W002.unused", "o parâmetro '{name}' nunca é usado"),
+    (
+        "W003.unreachable",
+        "instrução inalcançável depois de 'return'",
+    ),
+    (
+        "W004.constant",
+        "a condição é constante, por isso o mesmo ramo é sempre executado",
+    ),
+    (
+        "W005.shadows_variable",
+        "'{name}' esconde a variável declarada em {span}",
+    ),
+    (
+        "W005.shadows_parameter",
+        "'{name}' esconde o parâmetro declarado em {span}",
+    ),
+    (
+        "W006.shadows_builtin",
+        "'{name}' esconde a função nativa com o mesmo nome",
+    ),
+    (
+        "W007.too_deep",
+        "bloco aninhado em {depth} níveis, mais do que os {max} permitidos",
+    ),
+    (
+        "W008.naming",
+        "'{name}' deveria começar com uma letra minúscula",
+    ),
+];
@@ -0,0 +1,256 @@
+//! `filipe add` and `filipe install`. A package is a git repository with
+//! an entry point, see `project`, cloned into `fl_modules/<name>` where
+//! `import "name"` finds it. `filipe.lock` pins each package to a commit,
+//! so `install` brings back the same code on another machine. Both live in
+//! the project's root, or the current directory outside of a project.
+//!
+//! Packages are added by git URL, or by name from a registry: a JSON object
+//! of names to git URLs, read from the file or URL in `FILIPE_REGISTRY`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::{self, entry_point};
+use crate::runtime::modules::MODULES_DIR;
+use crate::utils::{read_file, write_file};
+
+pub const LOCKFILE: &str = "filipe.lock";
+const REGISTRY_VAR: &str = "FILIPE_REGISTRY";
+
+#[derive(Default, Serialize, Deserialize)]
+struct Lockfile {
+    packages: BTreeMap<String, LockedPackage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LockedPackage {
+    /// Git URL it was cloned from.
+    source: String,
+    /// Commit it was at.
+    rev: String,
+}
+
+/// Clones the packages into `fl_modules`, replacing the ones already there,
+/// and records them in the lockfile. `false` if any couldn't be added.
+pub fn add(packages: &[String]) -> bool {
+    let Some(mut lockfile) = read_lockfile() else {
+        return false;
+    };
+    let mut added = true;
+    for package in packages {
+        let result = source_of(package).and_then(|(name, source)| {
+            let rev = fetch(&name, &source, None)?;
+            Ok((name, LockedPackage { source, rev }))
+        });
+        match result {
+            Ok((name, locked)) => {
+                println!(
+                    "Added {} at {}",
+                    name,
+                    &locked.rev[..locked.rev.len().min(7)]
+                );
+                lockfile.packages.insert(name, locked);
+            }
+            Err(err) => {
+                eprintln!("[ERROR]: Couldn't add {package}: {err}");
+                added = false;
+            }
+        }
+    }
+    write_lockfile(&lockfile) && added
+}
+
+/// Clones every package of the lockfile that isn't in `fl_modules` yet, at
+/// the commit it was locked to.
+pub fn install() -> bool {
+    let Some(lockfile) = read_lockfile() else {
+        return false;
+    };
+    let mut installed = true;
+    for (name, locked) in &lockfile.packages {
+        let installed_already = package_dir(name).map(|dir| entry_point(&dir).is_file());
+        if let Ok(true) = installed_already {
+            continue;
+        }
+        match installed_already.and_then(|_| fetch(name, &locked.source, Some(&locked.rev))) {
+            Ok(_) => println!("Installed {}", name),
+            Err(err) => {
+                eprintln!("[ERROR]: Couldn't install {name}: {err}");
+                installed = false;
+            }
+        }
+    }
+    installed
+}
+
+/// Name and git URL of a package given as a URL or a registry name.
+fn source_of(package: &str) -> Result<(String, String), String> {
+    if package.contains('/') || package.contains(':') {
+        let name = package
+            .trim_end_matches('/')
+            .rsplit(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(".git");
+        return match name.is_empty() {
+            true => Err("can't tell the package name from the URL".to_string()),
+            false => check_name(name).map(|_| (name.to_string(), package.to_string())),
+        };
+    }
+    match registry()?.remove(package) {
+        Some(source) => Ok((package.to_string(), source)),
+        None => Err("not in the registry".to_string()),
+    }
+}
+
+fn registry() -> Result<HashMap<String, String>, String> {
+    let location = env::var(REGISTRY_VAR)
+        .map_err(|_| format!("not a git URL, and no registry is set in {REGISTRY_VAR}"))?;
+    let index = match location.starts_with("http://") || location.starts_with("https://") {
+        true => run("curl", &["-fsSL", &location])?,
+        false => fs::read_to_string(&location).map_err(|err| format!("{location}: {err}"))?,
+    };
+    serde_json::from_str(&index).map_err(|err| format!("invalid registry {location}: {err}"))
+}
+
+/// Clones `source` into the package's directory, at `rev` if given, and
+/// returns the commit it ended up at. The package already there is only
+/// replaced once the new one is ready.
+fn fetch(name: &str, source: &str, rev: Option<&str>) -> Result<String, String> {
+    let dir = package_dir(name)?;
+    let staging = inside(&modules_dir(), &format!(".{name}"))?;
+    let _ = fs::remove_dir_all(&staging);
+    let result = clone(source, rev, &staging.to_string_lossy()).and_then(|rev| {
+        if dir.exists() {
+            fs::remove_dir_all(&dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+        }
+        fs::rename(&staging, &dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+        Ok(rev)
+    });
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn clone(source: &str, rev: Option<&str>, dir: &str) -> Result<String, String> {
+    run("git", &["clone", "--quiet", "--", source, dir])?;
+    if let Some(rev) = rev {
+        // a revision can't come after `--`, which is for paths, so only
+        // commit ids are let through to where git reads options
+        if rev.is_empty() || !rev.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid revision '{rev}'"));
+        }
+        run("git", &["-C", dir, "checkout", "--quiet", rev, "--"])?;
+    }
+    let entry = entry_point(Path::new(dir));
+    if !entry.is_file() {
+        let entry = entry.file_name().unwrap_or_default().to_string_lossy();
+        return Err(format!("it has no {entry}"));
+    }
+    Ok(run("git", &["-C", dir, "rev-parse", "HEAD"])?
+        .trim()
+        .to_string())
+}
+
+/// Where the package goes. Names come from lockfiles and URLs, so those
+/// that could point out of `fl_modules` are refused.
+fn package_dir(name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    inside(&modules_dir(), name)
+}
+
+fn modules_dir() -> PathBuf {
+    project::root().unwrap_or_default().join(MODULES_DIR)
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let invalid = name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name.contains("..");
+    match invalid {
+        true => Err(format!("invalid package name '{name}'")),
+        false => Ok(()),
+    }
+}
+
+/// `entry` of `modules`, as long as it doesn't resolve to somewhere else,
+/// through a symlink for instance.
+fn inside(modules: &Path, entry: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(modules).map_err(|err| format!("{}: {err}", modules.display()))?;
+    let modules = modules
+        .canonicalize()
+        .map_err(|err| format!("{}: {err}", modules.display()))?;
+    let dir = modules.join(entry);
+    let resolved = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+    match resolved.parent() == Some(modules.as_path()) {
+        true => Ok(dir),
+        false => Err(format!("{} is outside of {MODULES_DIR}", dir.display())),
+    }
+}
+
+fn lockfile_path() -> String {
+    let path = project::root().unwrap_or_default().join(LOCKFILE);
+    path.to_string_lossy().to_string()
+}
+
+/// Runs a command and returns what it printed, or why it failed.
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|err| format!("couldn't run {program}: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{program} failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn read_lockfile() -> Option<Lockfile> {
+    let path = lockfile_path();
+    if !Path::new(&path).exists() {
+        return Some(Lockfile::default());
+    }
+    match serde_json::from_str(&read_file(&path)?) {
+        Ok(lockfile) => Some(lockfile),
+        Err(err) => {
+            eprintln!("[ERROR]: Invalid {path}: {err}");
+            None
+        }
+    }
+}
+
+fn write_lockfile(lockfile: &Lockfile) -> bool {
+    let mut json = serde_json::to_string_pretty(lockfile).unwrap_or_default();
+    json.push('\n');
+    write_file(&lockfile_path(), json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hostile_package_names_stay_out_of_the_filesystem() {
+        for name in ["../../victim", "..", ".hidden", "a/b", "a\\b", "a..b", ""] {
+            assert!(check_name(name).is_err(), "{name}");
+        }
+        assert!(source_of("https://example.com/x/..git").is_err());
+        assert!(check_name("greetings").is_ok());
+
+        let modules = env::temp_dir().join("filipe_packages_test");
+        let _ = fs::remove_dir_all(&modules);
+        assert!(inside(&modules, "greetings").is_ok());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(env::temp_dir(), modules.join("victim")).unwrap();
+            assert!(inside(&modules, "victim").is_err());
+        }
+        let _ = fs::remove_dir_all(&modules);
+    }
+}
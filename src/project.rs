@@ -0,0 +1,275 @@
+//! Projects: a directory with a `filipe.toml` manifest naming the project
+//! and the script it starts from. `filipe new` and `filipe init` lay one
+//! out, `filipe run` without a path runs its entry point, and packages are
+//! projects too, imported through their entry point.
+//!
+//! The manifest can also set how the project's scripts are run and
+//! formatted, in place of flags:
+//!
+//! ```toml
+//! [run]
+//! strict = true
+//! warnings = "error"     # or "on", "off"
+//! warn-shadowing = true
+//! paths = ["lib"]        # where else packages are imported from
+//!
+//! [permissions]
+//! exit = false
+//! import = true
+//!
+//! [format]
+//! indent = 4
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::frontend::formatter::DEFAULT_INDENT;
+use crate::runtime::modules::MODULES_DIR;
+use crate::runtime::permissions::RuntimePermissions;
+use crate::utils::write_file;
+
+pub const MANIFEST: &str = "filipe.toml";
+/// Entry point of projects whose manifest doesn't name one, and of
+/// packages without a manifest.
+pub const DEFAULT_ENTRY: &str = "main.fl";
+
+#[derive(Deserialize)]
+pub struct Manifest {
+    pub package: Package,
+    #[serde(default)]
+    pub run: RunSettings,
+    #[serde(default)]
+    pub permissions: RuntimePermissions,
+    #[serde(default)]
+    pub format: FormatSettings,
+}
+
+/// The `[package]` table. Keys it doesn't know, like `version`, are for
+/// people and other tools.
+#[derive(Deserialize)]
+pub struct Package {
+    pub name: String,
+    #[serde(default = "default_entry")]
+    pub entry: String,
+}
+
+fn default_entry() -> String {
+    DEFAULT_ENTRY.to_string()
+}
+
+/// The `[run]` table: defaults for running the project's scripts, which
+/// flags on the command line override.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RunSettings {
+    pub strict: bool,
+    pub warnings: Option<Warnings>,
+    pub warn_shadowing: bool,
+    /// Directories, relative to the project, to import packages from when
+    /// they aren't in `fl_modules`.
+    pub paths: Vec<PathBuf>,
+}
+
+/// What `warnings` in `[run]` does, like `--warnings=<on|off|error>`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Warnings {
+    On,
+    Off,
+    Error,
+}
+
+/// The `[format]` table, for `filipe fmt`.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatSettings {
+    /// Spaces per level of indentation.
+    pub indent: usize,
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self {
+            indent: DEFAULT_INDENT,
+        }
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest in `dir`.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(MANIFEST);
+        let text = fs::read_to_string(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let invalid = |err: &str| format!("Invalid {}: {}", path.display(), err.trim());
+        let manifest = toml::from_str::<Manifest>(&text).map_err(|err| invalid(err.message()))?;
+        match manifest.package.name.is_empty() {
+            true => Err(invalid("the package name is empty")),
+            false => Ok(manifest),
+        }
+    }
+
+    /// The `paths` of `[run]`, for the project in `dir`.
+    pub fn import_paths(&self, dir: &Path) -> Vec<PathBuf> {
+        self.run.paths.iter().map(|path| dir.join(path)).collect()
+    }
+}
+
+impl Package {
+    /// Reads the manifest in `dir`.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        Manifest::load(dir).map(|manifest| manifest.package)
+    }
+}
+
+/// The nearest directory with a manifest, from the current one up.
+pub fn root() -> Option<PathBuf> {
+    root_of(&env::current_dir().ok()?)
+}
+
+/// The nearest directory with a manifest, from `dir` up.
+pub fn root_of(dir: &Path) -> Option<PathBuf> {
+    let dir = match dir.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => dir,
+    };
+    dir.canonicalize()
+        .ok()?
+        .ancestors()
+        .find(|dir| dir.join(MANIFEST).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The script a package in `dir` starts from: its manifest's entry, or
+/// `main.fl` when it has none.
+pub fn entry_point(dir: &Path) -> PathBuf {
+    match Package::load(dir) {
+        Ok(package) => dir.join(package.entry),
+        Err(_) => dir.join(DEFAULT_ENTRY),
+    }
+}
+
+/// The files of a new project called `name`.
+fn skeleton(name: &str) -> [(&'static str, String); 5] {
+    [
+        (
+            MANIFEST,
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nentry = \"{DEFAULT_ENTRY}\"\n"
+            ),
+        ),
+        (
+            DEFAULT_ENTRY,
+            "import \"./greeting\"\n\nprint(greet(\"world\"))\n".to_string(),
+        ),
+        (
+            "greeting.fl",
+            "/// The greeting for `name`.\n\
+             define greet(name: string): string {\n\
+             \x20 return \"Hello, \" + name + \"!\"\n\
+             }\n"
+            .to_string(),
+        ),
+        (
+            "tests/greeting.fl",
+            "import \"../greeting\"\n\n\
+             define testGreet(): void {\n\
+             \x20 assert greet(\"world\") == \"Hello, world!\"\n\
+             }\n"
+            .to_string(),
+        ),
+        (
+            ".gitignore",
+            format!("/{MODULES_DIR}/\n/doc/\n/lcov.info\n/coverage.html\n"),
+        ),
+    ]
+}
+
+/// Creates the project `name` in a new directory of that name.
+pub fn new(name: &str) -> bool {
+    let dir = Path::new(name);
+    if dir.exists() {
+        eprintln!("[ERROR]: {name} already exists, use 'filipe init' inside it instead");
+        return false;
+    }
+    let name = dir
+        .file_name()
+        .map_or(name.to_string(), |name| name.to_string_lossy().to_string());
+    create(dir, &name)
+}
+
+/// Turns the current directory into a project named after it. Files that
+/// are already there are kept.
+pub fn init() -> bool {
+    let Ok(dir) = env::current_dir() else {
+        eprintln!("[ERROR]: Couldn't tell the current directory");
+        return false;
+    };
+    let name = dir.file_name().map_or("project".to_string(), |name| {
+        name.to_string_lossy().to_string()
+    });
+    create(Path::new("."), &name)
+}
+
+fn create(dir: &Path, name: &str) -> bool {
+    for (file, contents) in skeleton(name) {
+        let path = dir.join(file);
+        if path.exists() {
+            println!("Kept {}", path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                eprintln!(
+                    "[IOError]: Couldn't create directory {}: {err}",
+                    parent.display()
+                );
+                return false;
+            }
+        }
+        if !write_file(&path.to_string_lossy(), contents.as_bytes()) {
+            return false;
+        }
+        println!("Created {}", path.display());
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_entry_defaults_to_main() {
+        let manifest = toml::from_str::<Manifest>("[package]\nname = \"app\"\nversion = \"1.0.0\"");
+        assert_eq!(manifest.unwrap().package.entry, DEFAULT_ENTRY);
+
+        let manifest = toml::from_str::<Manifest>(&skeleton("app")[0].1).unwrap();
+        assert_eq!(manifest.package.name, "app");
+    }
+
+    #[test]
+    fn test_manifest_run_settings() {
+        let manifest = toml::from_str::<Manifest>(
+            "[package]\nname = \"app\"\n\
+             [run]\nwarnings = \"error\"\nwarn-shadowing = true\npaths = [\"lib\"]\n\
+             [permissions]\nexit = false\n\
+             [format]\nindent = 4",
+        )
+        .unwrap();
+        assert!(matches!(manifest.run.warnings, Some(Warnings::Error)));
+        assert!(manifest.run.warn_shadowing && !manifest.run.strict);
+        assert_eq!(
+            manifest.import_paths(Path::new("app")),
+            [Path::new("app/lib")]
+        );
+        assert!(!manifest.permissions.exit && manifest.permissions.import);
+        assert_eq!(manifest.format.indent, 4);
+
+        let typo = toml::from_str::<Manifest>("[package]\nname = \"app\"\n[run]\nstrcit = true");
+        assert!(typo.is_err());
+    }
+}
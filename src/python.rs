@@ -0,0 +1,212 @@
+//! Python bindings, with the `python` feature, for sandboxing small
+//! scripts from Python, e.g. in notebooks:
+//!
+//! ```python
+//! import filipe
+//!
+//! filipe.run("[1, 2, 3]")  # [1, 2, 3]
+//!
+//! runtime = filipe.Runtime()
+//! runtime.register("mean", lambda xs: sum(xs) / len(xs))
+//! runtime.run("mean([1.0, 2.0, 6.0])")  # 3.0
+//! ```
+//!
+//! Runtimes are sandboxed, see `RuntimePermissions::sandboxed`, and limit
+//! how deep calls nest, see `DEFAULT_MAX_CALL_DEPTH`, unless made with
+//! `Runtime(trusted=True)`, and `max_steps` bounds how long a script runs. Values become the Python values they match, `null` being
+//! `None` and arrays lists; the others, like functions, become the text
+//! `print` shows for them. Errors raise `filipe.FilipeError`. Build the
+//! `filipe-py` package with `maturin build --release`, see `pyproject.toml`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+use crate::runtime::flstdlib::to_text;
+use crate::runtime::native::Args;
+use crate::runtime::object::{Object, Value};
+use crate::runtime::permissions::RuntimePermissions;
+use crate::runtime::runtime_error::{ErrorKind, RuntimeError};
+use crate::runtime::type_system::object_to_type;
+use crate::runtime::{Runtime, DEFAULT_MAX_CALL_DEPTH};
+
+create_exception!(filipe, FilipeError, PyException);
+
+/// A runtime that keeps its globals between runs, see `Runtime`.
+#[pyclass(name = "Runtime", unsendable)]
+struct PyRuntime {
+    runtime: Runtime,
+}
+
+#[pymethods]
+impl PyRuntime {
+    #[new]
+    #[pyo3(signature = (*, trusted = false, max_steps = None))]
+    fn new(trusted: bool, max_steps: Option<u64>) -> Self {
+        let mut builder = match trusted {
+            true => Runtime::builder(),
+            false => Runtime::builder()
+                .permissions(RuntimePermissions::sandboxed())
+                .max_call_depth(DEFAULT_MAX_CALL_DEPTH),
+        };
+        if let Some(steps) = max_steps {
+            builder = builder.max_steps(steps);
+        }
+        Self {
+            runtime: builder.build(),
+        }
+    }
+
+    /// Runs `source` and returns the value of its last statement.
+    fn run(&mut self, py: Python<'_>, source: &str) -> PyResult<PyObject> {
+        match self.runtime.run_source(source) {
+            Ok(value) => Ok(to_python(py, &value)?.unbind()),
+            Err(error) => Err(FilipeError::new_err(error.to_string())),
+        }
+    }
+
+    /// Makes `function`, any Python callable, callable by scripts as
+    /// `name`. An exception it raises is a `PythonError` in the script.
+    fn register(&mut self, name: &str, function: PyObject) {
+        self.runtime.register_fn(name, move |args: &Args| {
+            Python::with_gil(|py| {
+                let values = (0..args.len())
+                    .filter_map(|index| args.get(index))
+                    .map(|value| to_python(py, value))
+                    .collect::<PyResult<Vec<_>>>()
+                    .map_err(python_error)?;
+                let args = PyTuple::new(py, values).map_err(python_error)?;
+                let returned = function.call1(py, args).map_err(python_error)?;
+                from_python(returned.bind(py)).map_err(python_error)
+            })
+        });
+    }
+}
+
+/// Runs `source` in a fresh, sandboxed runtime and returns the value of
+/// its last statement.
+#[pyfunction]
+fn run(py: Python<'_>, source: &str) -> PyResult<PyObject> {
+    PyRuntime::new(false, None).run(py, source)
+}
+
+#[pymodule]
+fn filipe(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(run, m)?)?;
+    m.add_class::<PyRuntime>()?;
+    m.add("FilipeError", m.py().get_type::<FilipeError>())?;
+    Ok(())
+}
+
+fn python_error(error: PyErr) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Custom("PythonError".to_string()),
+        error.to_string(),
+    )
+}
+
+fn to_python<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Object::Null => py.None().into_bound(py),
+        Object::Int(val) => val.into_pyobject(py)?.into_any(),
+        Object::Float(val) => val.into_pyobject(py)?.into_any(),
+        Object::Boolean(val) => PyBool::new(py, *val).to_owned().into_any(),
+        Object::String(val) => PyString::new(py, val).into_any(),
+        Object::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| to_python(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any()
+        }
+        other => PyString::new(py, &to_text(other)).into_any(),
+    })
+}
+
+/// `value` as a value of a script. Lists and tuples become arrays, which
+/// must hold items of one type.
+fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if value.is_none() {
+        return Ok(Object::Null);
+    }
+    // bools are ints too in Python
+    if let Ok(val) = value.downcast::<PyBool>() {
+        return Ok(Object::Boolean(val.is_true()));
+    }
+    if value.is_instance_of::<PyInt>() {
+        return Ok(Object::Int(value.extract()?));
+    }
+    if value.is_instance_of::<PyFloat>() {
+        return Ok(Object::Float(value.extract()?));
+    }
+    if let Ok(val) = value.downcast::<PyString>() {
+        return Ok(Object::String(val.to_str()?.into()));
+    }
+    if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        let items = value
+            .try_iter()?
+            .map(|item| from_python(&item?))
+            .collect::<PyResult<Vec<Value>>>()?;
+        let items_type = items.first().map(object_to_type);
+        if items
+            .iter()
+            .any(|item| Some(object_to_type(item)) != items_type)
+        {
+            return Err(PyTypeError::new_err("filipe arrays hold items of one type"));
+        }
+        return Ok(Object::new_array(items, items_type));
+    }
+    let type_name = value.get_type().name()?.to_string();
+    Err(PyTypeError::new_err(format!(
+        "a '{type_name}' can't be given to filipe"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+    use pyo3::types::PyDict;
+
+    /// Runs the Python `code` with the module imported, returning `result`.
+    fn python<'py>(py: Python<'py>, code: &str) -> PyResult<Bound<'py, PyAny>> {
+        let module = PyModule::new(py, "filipe")?;
+        filipe(&module)?;
+        let locals = PyDict::new(py);
+        locals.set_item("filipe", module)?;
+        py.run(&CString::new(code).unwrap(), None, Some(&locals))?;
+        locals.as_any().get_item("result")
+    }
+
+    #[test]
+    fn test_python_runs_sandboxed_scripts() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let result = python(py, "result = filipe.run('[1, 2, 3]')").unwrap();
+            assert_eq!(result.extract::<Vec<i64>>().unwrap(), [1, 2, 3]);
+            let result = python(
+                py,
+                "runtime = filipe.Runtime()\n\
+                 runtime.register('mean', lambda xs: sum(xs) / len(xs))\n\
+                 result = runtime.run('mean([1.0, 2.0, 6.0])')",
+            )
+            .unwrap();
+            assert_eq!(result.extract::<f64>().unwrap(), 3.0);
+
+            for script in ["exit(1)", "import \\\"./lib\\\""] {
+                let code = format!("result = filipe.run('{script}')");
+                let error = python(py, &code).unwrap_err();
+                assert!(error.is_instance_of::<FilipeError>(py));
+                assert!(error.to_string().contains("E021"), "{error}");
+            }
+            let code = "result = filipe.Runtime(trusted=True).run('exit(1)')";
+            let error = python(py, code).unwrap_err();
+            assert!(error.to_string().contains("E022"), "{error}");
+            let code = "result = filipe.Runtime(max_steps=1000).run('for i in range(0, 100000) {\\n  i\\n}')";
+            let error = python(py, code).unwrap_err();
+            assert!(error.to_string().contains("E014"), "{error}");
+        });
+    }
+}
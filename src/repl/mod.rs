@@ -1,15 +1,21 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::path::PathBuf;
 
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 
+use crate::ast_json::{self, eval_json_ast, parse_to_json};
+use crate::commands::ctrl_c_handle;
+use crate::diagnostics::{stderr_supports_color, Diagnostic, Renderer};
+use crate::frontend::ast::{Program, StmtKind};
+use crate::frontend::checker::infer_type;
 use crate::frontend::lexer::Lexer;
 use crate::frontend::parser::Parser;
 use crate::runtime::context::Context;
-use crate::runtime::flstdlib::builtins;
-use crate::runtime::object::Object;
-use crate::runtime::Runtime;
+use crate::runtime::flstdlib::{builtins, is_builtin, BUILTIN_DOCS};
+use crate::runtime::object::{Object, ObjectInfo};
+use crate::runtime::runtime_error::ErrorKind;
+use crate::runtime::{Runtime, Snapshot};
+use crate::shared::{Lock, Shared};
 
 const REPL_HELPER: &str = r#"
 Helper
@@ -51,16 +57,37 @@ Helper
         print(counter) 
     }
 
+    // errors
+    try {
+        raise(error("MyError", "something failed"))
+    } catch e {
+        print(errorKind(e), ": ", errorMessage(e))
+    }
+
     // Built-in functions
 
-    len("Hello")
-    typeof(10)
+{builtins}
 
     // More: ...
     // arthimetics: +, -, /, *
     // postfix: x++, x--
     // prefix: !x, -x
     
+    // REPL commands
+    :help          show this message
+    :type <expr>   show the type of an expression without running it
+    :ast <code>    show the syntax tree of some code as JSON
+    :env           show the variables and functions defined so far
+    :load <file>   run a script, or a JSON syntax tree, in this session
+    :save <file>   write the code run without errors so far to a script
+    :replay <file> run a script one statement at a time, as if typed
+    :reset         forget everything defined so far
+    :undo          forget what the last line or command did
+
+    Lines with unclosed '{', '(' or '[' continue on the next line, and the
+    value of an expression is printed without calling 'print'. History is
+    kept in ~/.filipe_history.
+
     Note: in case of bug:
        report to: dev.258.edilson@gmail.com
        open issue: https://github.com/edilson258/filipe
@@ -68,70 +95,351 @@ Helper
     Happy Hacking!
 "#;
 
-fn eval_repl_line(line: String, env: Rc<RefCell<Context>>) {
-    if line == String::from(".help") {
-        println!("{}", REPL_HELPER);
-        return;
+const PROMPT: &str = "|> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// What a session keeps besides its global scope.
+#[derive(Default)]
+struct Session {
+    /// How the session was before each change, for `:undo`.
+    history: Vec<(Snapshot, Vec<String>)>,
+    /// The code that ran without errors, in order, for `:save`.
+    inputs: Vec<String>,
+}
+
+impl Session {
+    /// Remembers the session as it is, before a change.
+    fn checkpoint(&mut self, env: &Shared<Lock<Context>>) {
+        let snapshot = Runtime::new(Shared::clone(env)).snapshot();
+        self.history.push((snapshot, self.inputs.clone()));
     }
 
-    if line == String::from("exit()") {
+    fn undo(&mut self, env: Shared<Lock<Context>>) {
+        match self.history.pop() {
+            Some((snapshot, inputs)) => {
+                Runtime::new(env).restore(&snapshot);
+                self.inputs = inputs;
+            }
+            None => println!("Nothing to undo"),
+        }
+    }
+}
+
+/// Evaluates one complete input, returns false once the user asked to exit.
+fn eval_repl_line(line: String, env: Shared<Lock<Context>>, session: &mut Session) -> bool {
+    let input = line.trim();
+    // `.help` and `.undo` are the old spellings of the commands
+    if let Some(command) = input.strip_prefix(':').or_else(|| input.strip_prefix('.')) {
+        run_command(command, env, session);
+        return true;
+    }
+
+    if input == "exit()" {
         println!("Exiting...");
-        std::process::exit(0);
+        return false;
+    }
+
+    if eval_source(&line, None, env, session) {
+        session.inputs.push(line);
+    }
+    true
+}
+
+fn run_command(command: &str, env: Shared<Lock<Context>>, session: &mut Session) {
+    let (name, arg) = match command.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (command, ""),
+    };
+    match name {
+        "help" => println!("{}", REPL_HELPER.replace("{builtins}", &builtin_list())),
+        "undo" => session.undo(env),
+        "type" => show_type(arg, &env),
+        "ast" if arg.is_empty() => println!("Usage: :ast <code>"),
+        "ast" => match parse_to_json(arg) {
+            Ok(json) => println!("{}", json),
+            Err(errors) => {
+                let renderer = Renderer::new(stderr_supports_color());
+                for error in &errors {
+                    eprint!(
+                        "{}",
+                        renderer.render(&Diagnostic::from(error), Some(arg), None)
+                    );
+                }
+            }
+        },
+        "env" => show_env(&env),
+        "load" if arg.is_empty() => println!("Usage: :load <file>"),
+        "load" => match std::fs::read_to_string(arg) {
+            Ok(json) if arg.ends_with(&format!(".{}", ast_json::EXTENSION)) => {
+                session.checkpoint(&env);
+                match eval_json_ast(&json, env) {
+                    Ok(Object::Null) => {}
+                    Ok(evaluated) => println!("{}", evaluated),
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            Ok(source) => {
+                if eval_source(&source, Some(arg), env, session) {
+                    session.inputs.push(source.trim_end().to_string());
+                }
+            }
+            Err(err) => eprintln!("Couldn't read '{}': {}", arg, err),
+        },
+        "save" if arg.is_empty() => println!("Usage: :save <file>"),
+        "save" => {
+            let mut script = session.inputs.join("\n");
+            script.push('\n');
+            match std::fs::write(arg, script) {
+                Ok(()) => println!("Saved {} inputs to {}", session.inputs.len(), arg),
+                Err(err) => eprintln!("Couldn't write '{}': {}", arg, err),
+            }
+        }
+        "replay" if arg.is_empty() => println!("Usage: :replay <file>"),
+        "replay" => match std::fs::read_to_string(arg) {
+            Ok(source) => replay(&source, arg, env, session),
+            Err(err) => eprintln!("Couldn't read '{}': {}", arg, err),
+        },
+        "reset" => {
+            session.checkpoint(&env);
+            *env.borrow_mut() = Context::make_global(builtins());
+            session.inputs.clear();
+        }
+        _ => println!("Unknown command ':{}', type ':help' for the list", name),
     }
+}
 
-    let input = line.chars().collect::<Vec<char>>();
+/// Runs `source` in the session, echoing the value it ends with. Returns
+/// whether it ran without errors.
+fn eval_source(
+    source: &str,
+    path: Option<&str>,
+    env: Shared<Lock<Context>>,
+    session: &mut Session,
+) -> bool {
+    let Some(program) = parse_source(source, path) else {
+        return false;
+    };
+    eval_program(program, source, path, env, session)
+}
+
+fn parse_source(source: &str, path: Option<&str>) -> Option<Program> {
+    let input = source.chars().collect::<Vec<char>>();
     let mut l = Lexer::new(&input);
     let mut p = Parser::new(&mut l);
     let program = p.parse();
 
-    if p.has_error() {
-        println!("{}", p.get_error().unwrap());
+    let renderer = Renderer::new(stderr_supports_color());
+    for error in p.errors() {
+        eprint!(
+            "{}",
+            renderer.render(&Diagnostic::from(error), Some(source), path)
+        );
+    }
+    (!p.has_error()).then_some(program)
+}
+
+fn eval_program(
+    program: Program,
+    source: &str,
+    path: Option<&str>,
+    env: Shared<Lock<Context>>,
+    session: &mut Session,
+) -> bool {
+    session.checkpoint(&env);
+    let mut runtime = Runtime::new(env);
+    runtime.set_interrupt_handle(ctrl_c_handle());
+    match runtime.eval(program) {
+        Ok(Object::Null) => true,
+        Ok(evaluated) => {
+            println!("{}", evaluated);
+            true
+        }
+        Err(error) => {
+            if let ErrorKind::Exit(code) = error.kind {
+                std::process::exit(code);
+            }
+            let renderer = Renderer::new(stderr_supports_color());
+            eprint!(
+                "{}",
+                renderer.render(&Diagnostic::from(&error), Some(source), path)
+            );
+            false
+        }
+    }
+}
+
+/// `:replay <file>`, runs a script, e.g. one written by `:save`, a
+/// statement at a time, showing each after the prompt like it was typed.
+/// Each can be undone on its own. It stops at the first error.
+fn replay(source: &str, path: &str, env: Shared<Lock<Context>>, session: &mut Session) {
+    let Some(program) = parse_source(source, Some(path)) else {
         return;
     };
+    let starts = program
+        .iter()
+        .map(|stmt| stmt.span.offset)
+        .chain([source.len()])
+        .collect::<Vec<_>>();
+    for (stmt, bounds) in program.into_iter().zip(starts.windows(2)) {
+        let input = source[bounds[0]..bounds[1]].trim_end();
+        for (index, line) in input.lines().enumerate() {
+            let prompt = if index == 0 {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            println!("{}{}", prompt, line);
+        }
+        if !eval_program(vec![stmt], source, Some(path), Shared::clone(&env), session) {
+            return;
+        }
+        session.inputs.push(input.to_string());
+    }
+}
 
-    let mut evaltr = Runtime::new(env);
-    let evaluated = evaltr.eval(program);
+/// `:type <expr>`, checks the expression against the session's bindings
+/// without running it.
+fn show_type(source: &str, env: &Shared<Lock<Context>>) {
+    let input = source.chars().collect::<Vec<char>>();
+    let mut l = Lexer::new(&input);
+    let mut p = Parser::new(&mut l);
+    let program = p.parse();
 
-    if evaluated.is_none() {
+    let renderer = Renderer::new(stderr_supports_color());
+    if p.has_error() {
+        for error in p.errors() {
+            eprint!(
+                "{}",
+                renderer.render(&Diagnostic::from(error), Some(source), None)
+            );
+        }
         return;
     }
 
-    match evaluated.clone().unwrap() {
-        Object::Null => {}
-        _ => println!("{}", evaluated.unwrap()),
+    let expr = match program.as_slice() {
+        [stmt] => match &stmt.kind {
+            StmtKind::Expr(expr) => Some(expr),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(expr) = expr else {
+        println!("Usage: :type <expr>");
+        return;
+    };
+    match infer_type(expr, env.borrow().globals()) {
+        Ok(Some(type_)) => println!("{}", type_),
+        Ok(None) => println!("unknown until it runs"),
+        Err(errors) => {
+            for error in &errors {
+                eprint!(
+                    "{}",
+                    renderer.render(&Diagnostic::from(error), Some(source), None)
+                );
+            }
+        }
     }
 }
 
+/// `:env`, the variables and functions defined in the session.
+fn show_env(env: &Shared<Lock<Context>>) {
+    let globals = env.borrow().globals();
+    let mut defined = globals
+        .iter()
+        .filter(|(name, _)| !is_builtin(name))
+        .peekable();
+    if defined.peek().is_none() {
+        println!("Nothing defined yet");
+    }
+    for (name, info) in defined {
+        println!("{}", describe_binding(name, info));
+    }
+}
+
+fn describe_binding(name: &str, info: &ObjectInfo) -> String {
+    match &info.value {
+        Object::UserDefinedFunction(function) => {
+            let params = function
+                .params
+                .iter()
+                .map(|param| format!("{}: {}", param.name, param.type_))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("define {}({}): {}", name, params, function.return_type)
+        }
+        value => format!("let {}: {} = {}", name, info.type_, value),
+    }
+}
+
+/// The builtin functions with how they're called, for `:help`.
+fn builtin_list() -> String {
+    BUILTIN_DOCS
+        .iter()
+        .map(|(_, usage, description)| format!("    {:<26}// {}", usage, description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub fn repl() {
+    repl_in(Shared::new(Lock::new(Context::make_global(builtins()))));
+}
+
+/// Starts a session in the global scope `env`, e.g. one a script ran in.
+pub fn repl_in(env: Shared<Lock<Context>>) {
     println!("Welcome to filipe v0.1.");
     println!("Type \".help\" for more information.");
 
     let mut rl = DefaultEditor::new().unwrap();
-    let env = Rc::new(RefCell::new(Context::make_global(builtins())));
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        // there is no history yet on the first run
+        let _ = rl.load_history(path);
+    }
+    let mut session = Session::default();
 
     loop {
-        let readline = read_line(&mut rl, "|> ");
+        let readline = read_line(&mut rl, PROMPT);
         match readline {
             Some(line) => {
                 let mut state = String::new();
                 state.push_str(&line);
-                if !balance_and_eval(&mut rl, state, Rc::clone(&env)) {
+                if !balance_and_eval(&mut rl, state, Shared::clone(&env), &mut session) {
                     break;
                 }
-            },
-            None => break
+            }
+            None => break,
+        }
+    }
+
+    if let Some(path) = &history_path {
+        if let Err(err) = rl.save_history(path) {
+            eprintln!("Couldn't save history to {}: {}", path.display(), err);
         }
     }
 }
 
-fn balance_and_eval(rl: &mut DefaultEditor, mut state: String, env: Rc<RefCell<Context>>) -> bool {
+/// `~/.filipe_history`, or nothing when the home directory is unknown.
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".filipe_history"))
+}
+
+fn balance_and_eval(
+    rl: &mut DefaultEditor,
+    mut state: String,
+    env: Shared<Lock<Context>>,
+    session: &mut Session,
+) -> bool {
     loop {
         if is_buf_balanced(&state) {
-            eval_repl_line(state.to_string(), env);
-            return true;
+            return eval_repl_line(state, env, session);
         }
-        match read_line(rl, "...") {
-            Some(line) => state.push_str(&line),
+        match read_line(rl, CONTINUATION_PROMPT) {
+            Some(line) => {
+                state.push('\n');
+                state.push_str(&line);
+            }
             None => return false,
         }
     }
@@ -155,8 +463,73 @@ fn read_line(rl: &mut DefaultEditor, prompt: &str) -> Option<String> {
     }
 }
 
+/// Whether every '{', '(' and '[' outside of strings is closed, so the input
+/// can be evaluated. Extra closing ones are left for the parser to report.
 fn is_buf_balanced(buf: &str) -> bool {
-    let open_brace_count = buf.chars().filter(|c| *c == '{').count();
-    let close_brace_count = buf.chars().filter(|c| *c == '}').count();
-    open_brace_count <= close_brace_count
+    let mut depth = 0;
+    let mut in_string = false;
+    for ch in buf.chars() {
+        match ch {
+            '"' => in_string = !in_string,
+            '{' | '(' | '[' if !in_string => depth += 1,
+            '}' | ')' | ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unclosed_brackets_continue_the_input() {
+        assert!(is_buf_balanced("let x = 1"));
+        assert!(!is_buf_balanced("define f(x: int): int {"));
+        assert!(!is_buf_balanced("print(1,"));
+        assert!(!is_buf_balanced("let xs = [1, 2,"));
+        assert!(is_buf_balanced("if x {\n  print(\"{\")\n}"));
+        assert!(is_buf_balanced("print(\"(\")"));
+        assert!(is_buf_balanced("}"));
+    }
+
+    #[test]
+    fn test_session_keeps_the_inputs_that_ran() {
+        let env = Shared::new(Lock::new(Context::make_global(builtins())));
+        let mut session = Session::default();
+        for line in ["let x = 1", "x +", "y", "let z = x * 2", ":undo", "x++"] {
+            eval_repl_line(line.to_string(), Shared::clone(&env), &mut session);
+        }
+        assert_eq!(session.inputs, ["let x = 1", "x++"]);
+
+        replay(
+            "let a = x\n\ndefine f(): int {\n  return a\n}\nf()",
+            "s.fl",
+            env,
+            &mut session,
+        );
+        assert_eq!(
+            session.inputs,
+            [
+                "let x = 1",
+                "x++",
+                "let a = x",
+                "define f(): int {\n  return a\n}",
+                "f()"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_builtin_function_is_documented() {
+        for (name, info) in builtins() {
+            if let Object::BuiltInFunction(_) = info.value {
+                assert!(
+                    BUILTIN_DOCS.iter().any(|(builtin, ..)| *builtin == name),
+                    "{name} has no description"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,196 @@
+//! Running scripts from async code without stalling the executor, with the
+//! `async` feature. The evaluation moves to a thread of its own, with as
+//! much stack as the main thread, which one of tokio's blocking threads
+//! waits for, and the future resolves once it is done, so it needs a tokio
+//! runtime:
+//!
+//! ```
+//! # async fn example() {
+//! let runtime = filipe::Runtime::builder().max_steps(1_000_000).build();
+//! let (runtime, result) = runtime.run_async("let x = 20\nx * 2 + 2").await;
+//! assert_eq!(result.unwrap().to_string(), "42");
+//! # }
+//! ```
+//!
+//! Native functions can be async too: see `Runtime::register_async_fn`.
+//! The runtime takes its streams, e.g. from `Runtime::set_stdout`, along.
+
+use std::future::Future;
+use std::panic;
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use tokio::runtime::Handle;
+use tokio::task::{self, JoinHandle};
+
+use super::interrupt::InterruptHandle;
+use super::native::Args;
+use super::object::Value;
+use super::runtime_error::RuntimeError;
+use super::Runtime;
+use crate::diagnostics::Diagnostic;
+
+/// Of the thread running the script, as much as the main thread has, so
+/// calls nest as deep as they would there rather than in the 2 MiB of
+/// tokio's blocking threads.
+const STACK_SIZE: usize = 8 << 20;
+
+/// What a finished `run_async` gives back: the runtime, to run more
+/// scripts with, and what the script evaluated to.
+pub type RunOutput = (Runtime, Result<Value, Diagnostic>);
+
+/// The future of `Runtime::run_async`. Dropping it before it is done
+/// interrupts the script.
+pub struct RunAsync {
+    task: JoinHandle<RunOutput>,
+    interrupt: InterruptHandle,
+    done: bool,
+}
+
+impl Future for RunAsync {
+    type Output = RunOutput;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<RunOutput> {
+        let output = match Pin::new(&mut self.task).poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.done = true;
+        match output {
+            Ok(output) => Poll::Ready(output),
+            // a native function panicked, which the caller gets to see
+            Err(err) => panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+impl Drop for RunAsync {
+    fn drop(&mut self) {
+        if !self.done {
+            self.interrupt.interrupt();
+        }
+    }
+}
+
+impl Runtime {
+    /// Runs `source` like `run_source`, on a thread of its own, and hands
+    /// the runtime back with the result.
+    ///
+    /// # Panics
+    ///
+    /// When called outside of a tokio runtime.
+    pub fn run_async(mut self, source: &str) -> RunAsync {
+        let interrupt = self.interrupt_handle();
+        let source = source.to_string();
+        let handle = Handle::current();
+        let task = task::spawn_blocking(move || {
+            let script = thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn(move || {
+                    // async native functions are driven by the caller's runtime
+                    let _guard = handle.enter();
+                    let result = self.run_source(&source);
+                    (self, result)
+                })
+                .expect("failed to spawn a thread for the script");
+            script
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err))
+        });
+        RunAsync {
+            task,
+            interrupt,
+            done: false,
+        }
+    }
+
+    /// Like `register_fn`, for functions that wait on async work. `function`
+    /// reads its arguments and returns the future giving the result, which
+    /// the script waits for. Run such scripts with `run_async`, whose tokio
+    /// runtime then drives the future, so it can use tokio's timers and IO.
+    /// Elsewhere the future is polled on the thread running the script, as
+    /// the waiting blocks it.
+    pub fn register_async_fn<F, Fut>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&Args) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, RuntimeError>>,
+    {
+        self.register_fn(name, move |args: &Args| match Handle::try_current() {
+            Ok(handle) => handle.block_on(function(args)),
+            Err(_) => block_on(function(args)),
+        });
+    }
+}
+
+/// Wakes the thread waiting in `block_on`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` on this thread until it is done, sleeping in between.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_async() {
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut runtime = Runtime::builder().build();
+        runtime.register_async_fn("slowDouble", |args| {
+            let n = args.int(0);
+            async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(Value::Int(n? * 2))
+            }
+        });
+        let run = async { runtime.run_async("let x = slowDouble(21)\nx").await };
+        let (mut runtime, result) = tokio.block_on(run);
+        assert_eq!(result.unwrap().to_string(), "42");
+        assert_eq!(runtime.run_source("x + 1").unwrap().to_string(), "43");
+
+        // interrupted, it stops and can run more scripts
+        let interrupt = runtime.interrupt_handle();
+        let run = async {
+            let looping = runtime.run_async("for i in range(0, 1000000000) {\n  i\n}");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            interrupt.interrupt();
+            looping.await
+        };
+        let (mut runtime, result) = tokio.block_on(run);
+        assert_eq!(result.unwrap_err().code, "E023");
+        assert_eq!(runtime.run_source("x").unwrap().to_string(), "42");
+
+        // with as much stack as the main thread
+        runtime.set_max_call_depth(Some(crate::runtime::DEFAULT_MAX_CALL_DEPTH));
+        let run = async {
+            runtime
+                .run_async("define f(n: int): int {\n  return f(n + 1)\n}\nf(0)")
+                .await
+        };
+        let (_, result) = tokio.block_on(run);
+        assert_eq!(result.unwrap_err().code, "E014");
+    }
+}
@@ -0,0 +1,25 @@
+use core::fmt;
+
+use serde::Serialize;
+
+use crate::frontend::token::Span;
+use crate::messages::msg;
+
+/// A user-defined function call that has not returned yet.
+#[derive(Clone, Debug, Serialize)]
+pub struct Frame {
+    pub function: String,
+    /// Where the call expression appears in the caller.
+    pub call_site: Span,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let called_at = msg!(
+            "trace.called_at",
+            function = self.function,
+            span = self.call_site
+        );
+        write!(f, "{}", called_at)
+    }
+}
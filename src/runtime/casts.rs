@@ -0,0 +1,115 @@
+//! Casts: `value as type` converts a value to `int`, `float`, `string` or
+//! `boolean`, the types the parser allows after `as`:
+//!
+//! ```text
+//! let half = total as float / 2.0
+//! let count = "42" as int
+//! let label = count as string + " items"
+//! ```
+//!
+//! Ints and floats convert to each other, a float to an int by dropping its
+//! fraction. Numbers convert to strings as `print` shows them and strings
+//! that hold a number convert back. Any value converts to a boolean, by
+//! whether a condition holds for it, in strict mode too. Other casts are
+//! type errors, and strings that aren't numbers or floats out of the range
+//! of int are value errors.
+
+use super::flstdlib::to_text;
+use super::object::Object;
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::type_system::{object_to_type, Type};
+use crate::messages::msg;
+
+/// Whether a condition holds for `value`: anything but `false`, `null`
+/// and zero.
+pub fn truthy(value: &Object) -> bool {
+    match value {
+        Object::Null | Object::Boolean(false) => false,
+        Object::Int(val) => *val != 0,
+        Object::Float(val) => *val != 0.0,
+        _ => true,
+    }
+}
+
+/// Whether a value of type `from` may be cast to `to`. A value that may be
+/// of any type of a union may be cast when one of them can.
+pub fn can_cast(from: &Type, to: &Type) -> bool {
+    match (from, to) {
+        (Type::Union(members), _) => members.iter().any(|member| can_cast(member, to)),
+        (_, Type::Boolean) => true,
+        (Type::Int | Type::Float | Type::String, Type::Int | Type::Float | Type::String) => true,
+        _ => from == to,
+    }
+}
+
+/// `value as to`.
+pub fn cast(value: Object, to: &Type) -> Result<Object, RuntimeError> {
+    let converted = match (&value, to) {
+        (_, Type::Boolean) => Some(Object::Boolean(truthy(&value))),
+        (Object::Int(val), Type::Int) => Some(Object::Int(*val)),
+        (Object::Int(val), Type::Float) => Some(Object::Float(*val as f64)),
+        // i64::MAX as f64 rounds up to 2^63, which is out of range
+        (Object::Float(val), Type::Int) => {
+            (val.is_finite() && *val >= i64::MIN as f64 && *val < i64::MAX as f64)
+                .then(|| Object::Int(val.trunc() as i64))
+        }
+        (Object::Float(val), Type::Float) => Some(Object::Float(*val)),
+        (Object::String(val), Type::Int) => val.trim().parse().ok().map(Object::Int),
+        (Object::String(val), Type::Float) => val.trim().parse().ok().map(Object::Float),
+        (Object::Int(_) | Object::Float(_) | Object::String(_), Type::String) => {
+            Some(Object::String(to_text(&value).as_str().into()))
+        }
+        _ => {
+            return Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                msg!(
+                    "E011.cast_type",
+                    found = object_to_type(&value),
+                    expected = to
+                ),
+            ))
+        }
+    };
+    converted.ok_or_else(|| {
+        RuntimeError::new(
+            ErrorKind::ValueError,
+            msg!("E013.cast_value", value = value, expected = to),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_casts_convert_numbers_strings_and_booleans() {
+        let mut runtime = Runtime::builder().build();
+        let cases = [
+            ("7 as float / 2.0", "3.5"),
+            ("-2.9 as int", "-2"),
+            ("\" 42 \" as int + 1", "43"),
+            ("\"2.5\" as float", "2.5"),
+            ("3 as string + \"!\"", "'3!'"),
+            ("1.5 as string", "'1.5'"),
+            ("0 as boolean", "false"),
+            ("\"\" as boolean", "true"),
+            ("null as boolean", "false"),
+        ];
+        for (source, expected) in cases {
+            let value = runtime.run_source(source).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", source);
+        }
+
+        let error = runtime.run_source("\"abc\" as int").unwrap_err();
+        assert_eq!(error.code, "E013");
+        let error = runtime
+            .run_source("10000000000000000000.0 as int")
+            .unwrap_err();
+        assert_eq!(error.code, "E013");
+        let error = runtime.run_source("true as int").unwrap_err();
+        assert_eq!(error.code, "E011");
+        let error = runtime.run_source("[1] as Array<int>").unwrap_err();
+        assert_eq!(error.code, "E001");
+    }
+}
@@ -0,0 +1,180 @@
+//! Channels, for tasks to hand each other values, see `tasks`. `channel()`
+//! makes one, which is both ends: any task that can see it can `send` to it
+//! and `recv` from it. As there is no type to give parameters for it,
+//! tasks see it in the scope they are spawned from:
+//!
+//! ```text
+//! let numbers = channel()
+//! define produce(n: int): void {
+//!   for x in range(0, n) {
+//!     send(numbers, x * x)
+//!   }
+//!   close(numbers)
+//! }
+//! spawn(produce, 3)
+//! for x in range(0, 3) {
+//!   print(recv(numbers))
+//! }
+//! ```
+//!
+//! `recv` waits for a value and `tryRecv` doesn't, returning `null` when
+//! there is none. Once a channel is closed, sending to it raises an error
+//! and receiving returns what is left, then `null`. Without the `sync`
+//! feature nothing else runs while the script waits, so `recv` on an empty
+//! channel that is open raises an error instead of waiting forever.
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use super::memory;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo, Value};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::userdata::{UserData, UserType};
+use crate::messages::msg;
+
+#[derive(Default)]
+struct Queue {
+    values: VecDeque<Value>,
+    closed: bool,
+}
+
+/// What `channel` returns.
+#[derive(Default)]
+struct Channel {
+    queue: Mutex<Queue>,
+    /// Signalled when a value is sent or the channel is closed.
+    changed: Condvar,
+}
+
+impl UserType for Channel {
+    fn type_name() -> &'static str {
+        "channel"
+    }
+}
+
+impl Channel {
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The values waiting in `data`, if it is a channel, for measuring its
+/// memory.
+pub(super) fn channel_values(data: &UserData) -> Option<Vec<Value>> {
+    data.downcast_ref::<Channel>()
+        .map(|channel| channel.queue().values.iter().cloned().collect())
+}
+
+/// Calls `f` with the channel that is the first of `args`, which must be
+/// `count` long.
+fn with_channel(
+    name: &str,
+    args: &[ObjectInfo],
+    count: usize,
+    f: impl FnOnce(&Channel, &Args) -> Result<Value, RuntimeError>,
+) -> BuiltInFuncReturnValue {
+    let args = Args::new(name, args);
+    let result = args
+        .expect_count(count)
+        .and_then(|_| args.user_data::<Channel>(0))
+        .and_then(|channel| f(channel, &args));
+    match result {
+        Ok(value) => BuiltInFuncReturnValue::Object(value),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_channel(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    match Args::new("channel", args).expect_count(0) {
+        Ok(()) => BuiltInFuncReturnValue::Object(Object::user_data(Channel::default())),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_send(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_channel("send", args, 2, |channel, args| {
+        let mut queue = channel.queue();
+        if queue.closed {
+            return Err(RuntimeError::new(
+                ErrorKind::ValueError,
+                msg!("E013.channel_closed"),
+            ));
+        }
+        queue.values.push_back(args.value(1)?.clone());
+        memory::charge(size_of::<Object>());
+        channel.changed.notify_one();
+        Ok(Object::Null)
+    })
+}
+
+pub fn filipe_recv(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_channel("recv", args, 1, |channel, _| {
+        let mut queue = channel.queue();
+        loop {
+            if let Some(value) = queue.values.pop_front() {
+                return Ok(value);
+            }
+            if queue.closed {
+                return Ok(Object::Null);
+            }
+            if cfg!(not(feature = "sync")) {
+                return Err(RuntimeError::new(
+                    ErrorKind::ValueError,
+                    msg!("E013.channel_empty"),
+                ));
+            }
+            queue = channel
+                .changed
+                .wait(queue)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+    })
+}
+
+pub fn filipe_try_recv(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_channel("tryRecv", args, 1, |channel, _| {
+        Ok(channel.queue().values.pop_front().unwrap_or(Object::Null))
+    })
+}
+
+pub fn filipe_close(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_channel("close", args, 1, |channel, _| {
+        channel.queue().closed = true;
+        channel.changed.notify_all();
+        Ok(Object::Null)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_tasks_talk_through_channels() {
+        let mut runtime = Runtime::builder().build();
+        let source = "let numbers = channel()\n\
+                      define produce(n: int): void {\n\
+                      \x20 for x in range(1, n + 1) {\n\
+                      \x20   send(numbers, x * 10)\n\
+                      \x20 }\n\
+                      \x20 close(numbers)\n\
+                      }\n\
+                      let producer = spawn(produce, 3)\n\
+                      let total = 0\n\
+                      for x in range(0, 3) {\n\
+                      \x20 total = total + recv(numbers)\n\
+                      }\n\
+                      join(producer)\n\
+                      total";
+        assert_eq!(runtime.run_source(source).unwrap().to_string(), "60");
+        let value = runtime.run_source("[recv(numbers), tryRecv(numbers)]");
+        assert_eq!(value.unwrap().to_string(), "[null, null]");
+
+        let error = runtime.run_source("send(numbers, 1)").unwrap_err();
+        assert_eq!(error.msg, "the channel is closed");
+        let error = runtime.run_source("recv(1)").unwrap_err();
+        assert_eq!(error.code, "E012");
+    }
+}
@@ -1,8 +1,12 @@
 use super::{
+    memory::object_size,
     object::{Object, ObjectInfo},
     type_system::Type,
 };
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use crate::frontend::token::Span;
+use crate::shared::{Lock, Shared};
+use crate::utils::closest_name;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub enum ContextType {
@@ -10,20 +14,29 @@ pub enum ContextType {
     Function,
     Loop,
     IfElse,
+    Try,
 }
 
+/// A scope. The global scope keeps its bindings in a map; every other scope
+/// is a frame whose locals are stored in declaration order, so the resolver's
+/// slots can index them directly.
 #[derive(Debug, Clone)]
 pub struct Context {
     type_: ContextType,
     store: HashMap<String, ObjectInfo>,
-    parent: Option<Rc<RefCell<Context>>>,
+    locals: Vec<(String, ObjectInfo)>,
+    /// Where the bindings of this scope declared by `let` or `define` are.
+    declared_at: HashMap<String, Span>,
+    parent: Option<Shared<Lock<Context>>>,
 }
 
 impl Context {
-    pub fn make_from(parent: Rc<RefCell<Context>>, type_: ContextType) -> Self {
+    pub fn make_from(parent: Shared<Lock<Context>>, type_: ContextType) -> Self {
         Self {
             type_,
             store: HashMap::new(),
+            locals: vec![],
+            declared_at: HashMap::new(),
             parent: Some(parent),
         }
     }
@@ -32,28 +45,35 @@ impl Context {
         Self {
             type_: ContextType::Global,
             store,
+            locals: vec![],
+            declared_at: HashMap::new(),
             parent: None,
         }
     }
 
+    /// Adds a binding to this scope. Returns `false`, and changes nothing,
+    /// when the scope already has one with that name; bindings of enclosing
+    /// scopes can be shadowed.
     pub fn set(&mut self, name: String, type_: Type, value: Object, is_assignable: bool) -> bool {
-        if self.store.contains_key(&name) {
+        if self.has(&name) {
             return false;
         }
-        self.store.insert(
-            name,
-            ObjectInfo {
-                value,
-                is_assignable,
-                type_,
-            },
-        );
+        let object_info = ObjectInfo {
+            value,
+            is_assignable,
+            type_,
+        };
+        match self.type_ {
+            ContextType::Global => {
+                self.store.insert(name, object_info);
+            }
+            _ => self.locals.push((name, object_info)),
+        }
         true
     }
 
-    pub fn mutate(&mut self, name: String, value: Object) -> bool {
-        if self.store.contains_key(&name) {
-            let old = self.store.get_mut(&name).unwrap();
+    pub fn mutate(&mut self, name: &str, value: Object) -> bool {
+        if let Some(old) = self.lookup_mut(name) {
             if !old.is_assignable {
                 return false;
             }
@@ -66,13 +86,73 @@ impl Context {
         }
     }
 
+    /// Runs `f` on the binding called `name`, wherever it lives in the scope
+    /// chain, so values can be updated in place.
+    pub fn with_mut<R>(&mut self, name: &str, f: impl FnOnce(&mut ObjectInfo) -> R) -> Option<R> {
+        if let Some(obj) = self.lookup_mut(name) {
+            return Some(f(obj));
+        }
+        match self.parent {
+            Some(ref p) => p.borrow_mut().with_mut(name, f),
+            None => None,
+        }
+    }
+
+    /// The bindings of the global scope, builtins included, sorted by name.
+    pub fn globals(&self) -> Vec<(String, ObjectInfo)> {
+        let mut globals = self
+            .store
+            .iter()
+            .map(|(name, obj)| (name.clone(), obj.clone()))
+            .collect::<Vec<_>>();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+        globals
+    }
+
+    /// `globals` of the global scope this one is nested in.
+    pub fn visible_globals(&self) -> Vec<(String, ObjectInfo)> {
+        match self.parent {
+            Some(ref p) => p.borrow().visible_globals(),
+            None => self.globals(),
+        }
+    }
+
+    /// The bindings of this scope and the enclosing ones below the global
+    /// scope, innermost first. Shadowed ones are left out.
+    pub fn visible_locals(&self) -> Vec<(String, ObjectInfo)> {
+        let mut locals: Vec<(String, ObjectInfo)> = vec![];
+        for (name, obj) in self.locals.iter().rev() {
+            if !locals.iter().any(|(seen, _)| seen == name) {
+                locals.push((name.clone(), obj.clone()));
+            }
+        }
+        if let Some(ref p) = self.parent {
+            for (name, obj) in p.borrow().visible_locals() {
+                if !locals.iter().any(|(seen, _)| *seen == name) {
+                    locals.push((name, obj));
+                }
+            }
+        }
+        locals
+    }
+
+    /// Records where the binding `name` of this scope was declared.
+    pub fn set_declared_at(&mut self, name: &str, span: Span) {
+        self.declared_at.insert(name.to_string(), span);
+    }
+
+    /// Where the binding `name` of this scope was declared, if it was
+    /// recorded.
+    pub fn declared_at(&self, name: &str) -> Option<Span> {
+        self.declared_at.get(name).copied()
+    }
+
     pub fn has(&self, name: &str) -> bool {
-        self.store.contains_key(name)
+        self.lookup(name).is_some()
     }
 
     pub fn resolve(&self, name: &str) -> Option<ObjectInfo> {
-        if self.store.contains_key(name) {
-            let obj = self.store.get(name).unwrap();
+        if let Some(obj) = self.lookup(name) {
             return Some(obj.clone());
         }
         match self.parent {
@@ -80,4 +160,93 @@ impl Context {
             None => None,
         }
     }
+
+    /// The visible name closest to `name`, see `utils::closest_name`.
+    pub fn closest_name(&self, name: &str) -> Option<String> {
+        let mut names = Vec::new();
+        self.visible_names(&mut names);
+        closest_name(name, names.iter().map(String::as_str)).map(str::to_string)
+    }
+
+    fn visible_names(&self, names: &mut Vec<String>) {
+        names.extend(self.store.keys().cloned());
+        names.extend(self.locals.iter().map(|(name, _)| name.clone()));
+        if let Some(ref p) = self.parent {
+            p.borrow().visible_names(names);
+        }
+    }
+
+    /// Overwrites the local at `index` of this scope.
+    pub fn replace_local(&mut self, index: usize, type_: Type, value: Object) {
+        let (_, local) = &mut self.locals[index];
+        local.type_ = type_;
+        local.value = value;
+    }
+
+    pub fn resolve_slot(&self, depth: usize, index: usize) -> Option<ObjectInfo> {
+        if depth == 0 {
+            return self.locals.get(index).map(|(_, obj)| obj.clone());
+        }
+        match self.parent {
+            Some(ref p) => p.borrow().resolve_slot(depth - 1, index),
+            None => None,
+        }
+    }
+
+    /// Approximate memory held by the values visible from this scope.
+    pub fn live_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        let store = self.store.values();
+        let locals = self.locals.iter().map(|(_, obj)| obj);
+        let own = store
+            .chain(locals)
+            .map(|obj| object_size(&obj.value, seen))
+            .sum::<usize>();
+        match self.parent {
+            Some(ref p) => own + p.borrow().live_bytes(seen),
+            None => own,
+        }
+    }
+
+    /// Copy of this scope that shares no mutable state with it. Most values
+    /// are immutable or copy-on-write and can be shared as is; iterators get
+    /// their own cursor.
+    pub fn detached(&self) -> Self {
+        let mut copy = self.clone();
+        let store = copy.store.values_mut();
+        let locals = copy.locals.iter_mut().map(|(_, obj)| obj);
+        for obj in store.chain(locals) {
+            if let Object::Iterator(iterator) = &obj.value {
+                let cursor = iterator.borrow().clone();
+                obj.value = Object::Iterator(Shared::new(Lock::new(cursor)));
+            }
+        }
+        copy
+    }
+
+    /// Drops every local declared after the first `len` ones.
+    pub fn truncate_locals(&mut self, len: usize) {
+        self.locals.truncate(len);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&ObjectInfo> {
+        match self.type_ {
+            ContextType::Global => self.store.get(name),
+            _ => self
+                .locals
+                .iter()
+                .find(|(local, _)| local == name)
+                .map(|(_, obj)| obj),
+        }
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut ObjectInfo> {
+        match self.type_ {
+            ContextType::Global => self.store.get_mut(name),
+            _ => self
+                .locals
+                .iter_mut()
+                .find(|(local, _)| local == name)
+                .map(|(_, obj)| obj),
+        }
+    }
 }
@@ -0,0 +1,292 @@
+//! Conversions between `Value`s and Rust types, so programs embedding
+//! filipe and native functions don't have to match on `Object` variants:
+//!
+//! ```
+//! use filipe::{FromFilipe, ToFilipe, Value};
+//!
+//! let value: Value = vec![1i64, 2, 3].into();
+//! assert_eq!(value.to_string(), "[1, 2, 3]");
+//! let items: Vec<i64> = value.try_into().unwrap();
+//! assert_eq!(items, [1, 2, 3]);
+//! assert_eq!(Option::<String>::from_filipe(&().to_filipe()), Ok(None));
+//! ```
+//!
+//! Types of the embedding program can take part by implementing
+//! `ToFilipe` and `FromFilipe`.
+
+use std::fmt;
+
+use super::object::{Object, Value};
+use super::type_system::{object_to_type, Type};
+
+/// A Rust value that can be given to scripts.
+pub trait ToFilipe {
+    fn to_filipe(self) -> Value;
+
+    /// The type of every value `to_filipe` gives, if there is one. Arrays
+    /// made from a `Vec` have it as the type of their items.
+    fn filipe_type() -> Option<Type>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+/// A Rust value that can be read from a value of a script.
+pub trait FromFilipe: Sized {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError>;
+}
+
+/// A value that isn't of the type it was converted to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversionError {
+    pub expected: Type,
+    pub found: Type,
+}
+
+impl ConversionError {
+    pub fn new(expected: Type, found: &Value) -> Self {
+        Self {
+            expected,
+            found: object_to_type(found),
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected '{}' but found '{}'", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl ToFilipe for Value {
+    fn to_filipe(self) -> Value {
+        self
+    }
+}
+
+impl FromFilipe for Value {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        Ok(value.clone())
+    }
+}
+
+impl ToFilipe for () {
+    fn to_filipe(self) -> Value {
+        Object::Null
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::Null)
+    }
+}
+
+impl ToFilipe for i64 {
+    fn to_filipe(self) -> Value {
+        Object::Int(self)
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::Int)
+    }
+}
+
+impl FromFilipe for i64 {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Object::Int(val) => Ok(*val),
+            other => Err(ConversionError::new(Type::Int, other)),
+        }
+    }
+}
+
+impl ToFilipe for f64 {
+    fn to_filipe(self) -> Value {
+        Object::Float(self)
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::Float)
+    }
+}
+
+/// Ints are converted too, like they are where scripts expect a float.
+impl FromFilipe for f64 {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Object::Float(val) => Ok(*val),
+            Object::Int(val) => Ok(*val as f64),
+            other => Err(ConversionError::new(Type::Float, other)),
+        }
+    }
+}
+
+impl ToFilipe for bool {
+    fn to_filipe(self) -> Value {
+        Object::Boolean(self)
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::Boolean)
+    }
+}
+
+impl FromFilipe for bool {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Object::Boolean(val) => Ok(*val),
+            other => Err(ConversionError::new(Type::Boolean, other)),
+        }
+    }
+}
+
+impl ToFilipe for String {
+    fn to_filipe(self) -> Value {
+        Object::String(self.into())
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::String)
+    }
+}
+
+impl ToFilipe for &str {
+    fn to_filipe(self) -> Value {
+        Object::String(self.into())
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::String)
+    }
+}
+
+impl FromFilipe for String {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Object::String(val) => Ok(val.to_string()),
+            other => Err(ConversionError::new(Type::String, other)),
+        }
+    }
+}
+
+impl<T: ToFilipe> ToFilipe for Vec<T> {
+    fn to_filipe(self) -> Value {
+        let items = self.into_iter().map(ToFilipe::to_filipe).collect();
+        Object::new_array(items, T::filipe_type())
+    }
+
+    fn filipe_type() -> Option<Type> {
+        Some(Type::Array(T::filipe_type().map(Box::new)))
+    }
+}
+
+impl<T: FromFilipe> FromFilipe for Vec<T> {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Object::Array(items) => items.iter().map(T::from_filipe).collect(),
+            other => Err(ConversionError::new(Type::Array(None), other)),
+        }
+    }
+}
+
+/// `None` is `null`.
+impl<T: ToFilipe> ToFilipe for Option<T> {
+    fn to_filipe(self) -> Value {
+        self.map_or(Object::Null, ToFilipe::to_filipe)
+    }
+}
+
+impl<T: FromFilipe> FromFilipe for Option<T> {
+    fn from_filipe(value: &Value) -> Result<Self, ConversionError> {
+        match value {
+            Object::Null => Ok(None),
+            other => T::from_filipe(other).map(Some),
+        }
+    }
+}
+
+/// `From` and `TryFrom` for types that implement both traits. `Option`
+/// only gets `From`: the standard library already turns any value into one.
+macro_rules! impl_conversions {
+    ($($type:ty),*) => {$(
+        impl From<$type> for Value {
+            fn from(value: $type) -> Self {
+                value.to_filipe()
+            }
+        }
+
+        impl TryFrom<Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(value: Value) -> Result<Self, ConversionError> {
+                <$type>::from_filipe(&value)
+            }
+        }
+    )*};
+}
+
+impl_conversions!(i64, f64, bool, String);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        value.to_filipe()
+    }
+}
+
+impl<T: ToFilipe> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        value.to_filipe()
+    }
+}
+
+impl<T: FromFilipe> TryFrom<Value> for Vec<T> {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, ConversionError> {
+        Self::from_filipe(&value)
+    }
+}
+
+impl<T: ToFilipe> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.to_filipe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_round_trip() {
+        let value = Value::from(vec![Some(1.5), None]);
+        assert_eq!(value.to_string(), "[1.5, null]");
+        assert_eq!(
+            Vec::<Option<f64>>::try_from(value).unwrap(),
+            [Some(1.5), None]
+        );
+        assert_eq!(Option::<bool>::from_filipe(&Value::Null), Ok(None));
+
+        let value = Value::from(vec![vec!["a"], vec![]]);
+        assert_eq!(
+            object_to_type(&value),
+            Type::Array(Some(Box::new(Type::Array(Some(Box::new(Type::String))))))
+        );
+        assert_eq!(
+            Vec::<Vec<String>>::try_from(value).unwrap(),
+            [vec!["a".to_string()], vec![]]
+        );
+
+        assert_eq!(
+            Vec::<i64>::try_from(Value::from(vec![true])),
+            Err(ConversionError {
+                expected: Type::Int,
+                found: Type::Boolean
+            })
+        );
+        assert_eq!(f64::try_from(Value::from(2i64)), Ok(2.0));
+    }
+}
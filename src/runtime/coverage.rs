@@ -0,0 +1,209 @@
+//! Which lines a run executed, for `--coverage`, and the lcov and HTML
+//! reports made from it.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::frontend::ast::*;
+use crate::utils::escape_html;
+
+/// How many times a statement starting on each line was run.
+#[derive(Default, Clone)]
+pub struct Coverage {
+    hits: HashMap<usize, u64>,
+}
+
+impl Coverage {
+    pub fn record(&mut self, line: usize) {
+        *self.hits.entry(line).or_default() += 1;
+    }
+
+    /// Adds the hits of another run of the same script.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (line, hits) in &other.hits {
+            *self.hits.entry(*line).or_default() += hits;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Lcov,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Lcov => "lcov.info",
+            Self::Html => "coverage.html",
+        }
+    }
+}
+
+/// Coverage of one script.
+pub struct FileCoverage {
+    pub path: String,
+    pub source: Option<String>,
+    /// Lines that have a statement.
+    pub lines: BTreeSet<usize>,
+    pub coverage: Coverage,
+}
+
+impl FileCoverage {
+    pub fn new(path: &str, source: Option<&str>, program: &Program) -> Self {
+        let mut lines = BTreeSet::new();
+        collect_lines(program, &mut lines);
+        Self {
+            path: path.to_string(),
+            source: source.map(str::to_string),
+            lines,
+            coverage: Coverage::default(),
+        }
+    }
+
+    fn hits(&self, line: usize) -> u64 {
+        self.coverage.hits.get(&line).copied().unwrap_or_default()
+    }
+
+    fn lines_hit(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| self.hits(**line) > 0)
+            .count()
+    }
+
+    /// "3/4 lines (75.0%)"
+    pub fn summary(&self) -> String {
+        let percent = match self.lines.len() {
+            0 => 100.0,
+            lines => self.lines_hit() as f64 * 100.0 / lines as f64,
+        };
+        format!(
+            "{}/{} lines ({:.1}%)",
+            self.lines_hit(),
+            self.lines.len(),
+            percent
+        )
+    }
+}
+
+fn collect_lines(block: &BlockStmt, lines: &mut BTreeSet<usize>) {
+    for stmt in block {
+        lines.insert(stmt.span.line);
+        match &stmt.kind {
+            StmtKind::Func(_, _, body, ..) => collect_lines(body, lines),
+            StmtKind::If {
+                consequence,
+                alternative,
+                ..
+            } => {
+                collect_lines(consequence, lines);
+                if let Some(alternative) = alternative {
+                    collect_lines(alternative, lines);
+                }
+            }
+            StmtKind::ForLoop { block, .. } => collect_lines(block, lines),
+            StmtKind::Try { block, handler, .. } => {
+                collect_lines(block, lines);
+                collect_lines(handler, lines);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn report(format: ReportFormat, files: &[FileCoverage]) -> String {
+    match format {
+        ReportFormat::Lcov => lcov(files),
+        ReportFormat::Html => html(files),
+    }
+}
+
+fn lcov(files: &[FileCoverage]) -> String {
+    let mut out = String::new();
+    for file in files {
+        let _ = writeln!(out, "TN:\nSF:{}", file.path);
+        for line in &file.lines {
+            let _ = writeln!(out, "DA:{},{}", line, file.hits(*line));
+        }
+        let _ = writeln!(
+            out,
+            "LF:{}\nLH:{}\nend_of_record",
+            file.lines.len(),
+            file.lines_hit()
+        );
+    }
+    out
+}
+
+fn html(files: &[FileCoverage]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Coverage</title>\n\
+         <style>\n.hit { background: #dfd; }\n.missed { background: #fdd; }\n\
+         td { font-family: monospace; white-space: pre; padding: 0 8px; }\n</style>\n\
+         </head>\n<body>\n",
+    );
+    for file in files {
+        let _ = writeln!(
+            out,
+            "<h2>{}</h2>\n<p>{}</p>\n<table>",
+            escape_html(&file.path),
+            file.summary()
+        );
+        let source = file.source.as_deref().unwrap_or_default();
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let (class, hits) = match file.lines.contains(&line) {
+                false => ("", String::new()),
+                true if file.hits(line) > 0 => (" class=\"hit\"", file.hits(line).to_string()),
+                true => (" class=\"missed\"", "0".to_string()),
+            };
+            let _ = writeln!(
+                out,
+                "<tr{}><td>{}</td><td>{}</td><td>{}</td></tr>",
+                class,
+                line,
+                hits,
+                escape_html(text)
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+    use crate::runtime::{context::Context, flstdlib::builtins, Runtime};
+    use crate::shared::{Lock, Shared};
+
+    #[test]
+    fn test_coverage_counts_executed_lines() {
+        let source = "define sign(n: int): int {\n\
+                      \x20 if n < 0 {\n\
+                      \x20   return 0 - 1\n\
+                      \x20 }\n\
+                      \x20 return 1\n\
+                      }\n\
+                      for i in range(0, 3) { sign(i) }";
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+        let mut file = FileCoverage::new("sign.fl", Some(source), &program);
+
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.enable_coverage();
+        assert!(runtime.eval(program).is_ok());
+        file.coverage.merge(runtime.coverage().unwrap());
+
+        assert_eq!(
+            report(ReportFormat::Lcov, &[file]),
+            "TN:\nSF:sign.fl\nDA:1,1\nDA:2,3\nDA:3,0\nDA:5,3\nDA:7,4\nLF:5\nLH:4\nend_of_record\n"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Pausing a running program. The runtime asks the `Debugger` before every
+//! statement whether to stop; when it does, a `DebugFrontend`, like the
+//! `--debug` prompt or an editor, looks around and says how to go on.
+
+use std::collections::HashSet;
+
+use super::call_stack::Frame;
+use super::object::ObjectInfo;
+use super::Runtime;
+use crate::frontend::token::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PauseReason {
+    /// Before the first statement.
+    Entry,
+    /// A line breakpoint or a `breakpoint()` call.
+    Breakpoint,
+    Step,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resume {
+    /// Run to the next breakpoint.
+    Continue,
+    /// Stop at the next statement, inside calls too.
+    Step,
+    /// Stop at the next statement of the current function or its callers.
+    Next,
+    /// Stop once the current function has returned.
+    Out,
+}
+
+/// Where and why the program stopped.
+pub struct Pause<'d> {
+    pub reason: PauseReason,
+    pub span: Span,
+    /// Lines to stop at, which can be changed while paused.
+    pub breakpoints: &'d mut HashSet<usize>,
+}
+
+/// `Send` because the runtime holding it is with the `sync` feature.
+pub trait DebugFrontend: Send {
+    /// Called while the program is stopped. The runtime can be inspected
+    /// and used to evaluate code in the paused scope.
+    fn paused(&mut self, runtime: &mut Runtime, pause: Pause) -> Resume;
+}
+
+/// Where to stop next, besides breakpoints.
+enum StopAt {
+    Breakpoints,
+    Entry,
+    NextStmt,
+    /// The next statement run this many calls deep or less.
+    NextStmtWithin(usize),
+}
+
+pub struct Debugger {
+    breakpoints: HashSet<usize>,
+    stop_at: StopAt,
+    /// Line of the last statement run, so a breakpoint on a line with
+    /// nested statements stops once.
+    last_line: usize,
+    /// Whether it stopped before the statement being run, so a
+    /// `breakpoint()` call in it doesn't stop again.
+    stopped_at_stmt: bool,
+    frontend: Box<dyn DebugFrontend>,
+}
+
+impl Debugger {
+    /// Stops before the first statement when `stop_on_entry`, and then at
+    /// the given lines.
+    pub fn new(
+        frontend: Box<dyn DebugFrontend>,
+        breakpoints: HashSet<usize>,
+        stop_on_entry: bool,
+    ) -> Self {
+        Self {
+            breakpoints,
+            stop_at: match stop_on_entry {
+                true => StopAt::Entry,
+                false => StopAt::Breakpoints,
+            },
+            last_line: 0,
+            stopped_at_stmt: false,
+            frontend,
+        }
+    }
+
+    /// Why to stop before a statement at `span` run `depth` calls deep.
+    fn should_pause(&mut self, span: Span, depth: usize) -> Option<PauseReason> {
+        let new_line = span.line != self.last_line;
+        self.last_line = span.line;
+        self.stopped_at_stmt = false;
+        let reason = match self.stop_at {
+            StopAt::Entry => Some(PauseReason::Entry),
+            StopAt::NextStmt => Some(PauseReason::Step),
+            StopAt::NextStmtWithin(max) if depth <= max => Some(PauseReason::Step),
+            _ => None,
+        };
+        reason.or_else(|| {
+            (new_line && self.breakpoints.contains(&span.line)).then_some(PauseReason::Breakpoint)
+        })
+    }
+}
+
+impl Runtime {
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(debugger);
+    }
+
+    /// The user-defined calls that haven't returned, outermost first.
+    pub fn call_stack(&self) -> &[Frame] {
+        &self.call_stack
+    }
+
+    /// The bindings of the global scope, builtins included, sorted by name.
+    pub fn globals(&self) -> Vec<(String, ObjectInfo)> {
+        self.env.borrow().visible_globals()
+    }
+
+    /// The variables of the scope being run and the ones enclosing it, up
+    /// to the global scope, innermost first.
+    pub fn locals(&self) -> Vec<(String, ObjectInfo)> {
+        self.env.borrow().visible_locals()
+    }
+
+    /// Stops before a statement at `span` if a breakpoint or step says so.
+    pub(super) fn debug_stmt(&mut self, span: Span) {
+        let depth = self.call_stack.len();
+        let reason = match self.debugger.as_mut() {
+            Some(debugger) => debugger.should_pause(span, depth),
+            None => return,
+        };
+        if let Some(reason) = reason {
+            self.pause(reason, span);
+            if let Some(debugger) = self.debugger.as_mut() {
+                debugger.stopped_at_stmt = true;
+            }
+        }
+    }
+
+    /// Stops at a `breakpoint()` call.
+    pub(super) fn debug_breakpoint_call(&mut self, span: Span) {
+        if self
+            .debugger
+            .as_ref()
+            .is_some_and(|debugger| !debugger.stopped_at_stmt)
+        {
+            self.pause(PauseReason::Breakpoint, span);
+        }
+    }
+
+    fn pause(&mut self, reason: PauseReason, span: Span) {
+        // taken out, so code evaluated while paused doesn't stop again
+        let Some(mut debugger) = self.debugger.take() else {
+            return;
+        };
+        let pause = Pause {
+            reason,
+            span,
+            breakpoints: &mut debugger.breakpoints,
+        };
+        let resume = debugger.frontend.paused(self, pause);
+        debugger.stop_at = match resume {
+            Resume::Continue => StopAt::Breakpoints,
+            Resume::Step => StopAt::NextStmt,
+            Resume::Next => StopAt::NextStmtWithin(self.call_stack.len()),
+            Resume::Out => match self.call_stack.len().checked_sub(1) {
+                Some(depth) => StopAt::NextStmtWithin(depth),
+                None => StopAt::Breakpoints,
+            },
+        };
+        debugger.last_line = span.line;
+        self.debugger = Some(debugger);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+    use crate::runtime::{context::Context, flstdlib::builtins};
+    use crate::shared::{Lock, Shared};
+
+    /// Line, reason and names of the locals.
+    type Stop = (usize, PauseReason, Vec<String>);
+
+    /// Resumes as told and records where it stopped.
+    struct Scripted {
+        resumes: Vec<Resume>,
+        stops: Arc<Mutex<Vec<Stop>>>,
+    }
+
+    impl DebugFrontend for Scripted {
+        fn paused(&mut self, runtime: &mut Runtime, pause: Pause) -> Resume {
+            let locals = runtime.locals().into_iter().map(|(name, _)| name);
+            self.stops
+                .lock()
+                .unwrap()
+                .push((pause.span.line, pause.reason, locals.collect()));
+            self.resumes.pop().unwrap_or(Resume::Continue)
+        }
+    }
+
+    #[test]
+    fn test_debugger_stops_at_breakpoints_and_steps() {
+        let source = "define double(n: int): int {\n\
+                      \x20 let twice = n * 2\n\
+                      \x20 return twice\n\
+                      }\n\
+                      let y = double(5)\n\
+                      breakpoint()\n\
+                      print(y)";
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+
+        let stops = Arc::new(Mutex::new(vec![]));
+        let frontend = Scripted {
+            // popped from the end
+            resumes: vec![Resume::Continue, Resume::Next, Resume::Step],
+            stops: Arc::clone(&stops),
+        };
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        let breakpoints = HashSet::from([5]);
+        runtime.set_debugger(Debugger::new(Box::new(frontend), breakpoints, false));
+        assert!(runtime.eval(program).is_ok());
+
+        let stops = stops.lock().unwrap();
+        assert_eq!(
+            *stops,
+            [
+                (5, PauseReason::Breakpoint, vec![]),
+                (2, PauseReason::Step, vec!["n".to_string()]),
+                (
+                    3,
+                    PauseReason::Step,
+                    vec!["twice".to_string(), "n".to_string()]
+                ),
+                (6, PauseReason::Breakpoint, vec![]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Double-ended queues, for breadth-first searches, stacks and work lists
+//! that an array would have to shift. `deque()` makes an empty one and
+//! `deque(array)` one with the items of the array; `pushBack` and
+//! `pushFront` add to either end and `popBack` and `popFront` take from
+//! it, all without moving the other items. A for-loop over a deque takes
+//! its items from the front until it is empty, those pushed by the loop
+//! too, so a breadth-first walk is:
+//!
+//! ```text
+//! let todo = deque([1])
+//! for n in todo {
+//!   print(n)
+//!   if n < 8 {
+//!     pushBack(todo, n * 2)
+//!     pushBack(todo, n * 2 + 1)
+//!   }
+//! }
+//! ```
+//!
+//! Popping or peeking at an empty deque returns `null`. Like channels,
+//! deques aren't copied: every variable holding one sees what is pushed.
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::sync::{Mutex, MutexGuard};
+
+use super::memory;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo, Value};
+use super::runtime_error::RuntimeError;
+use super::userdata::{UserData, UserType};
+
+/// What `deque` returns.
+struct Deque {
+    items: Mutex<VecDeque<Value>>,
+}
+
+impl UserType for Deque {
+    fn type_name() -> &'static str {
+        "deque"
+    }
+}
+
+impl Deque {
+    fn items(&self) -> MutexGuard<'_, VecDeque<Value>> {
+        self.items.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// How many items `data` has, if it is a deque, for `len`.
+pub(super) fn deque_len(data: &UserData) -> Option<usize> {
+    data.downcast_ref::<Deque>()
+        .map(|deque| deque.items().len())
+}
+
+/// The items of `data`, if it is a deque, for measuring its memory.
+pub(super) fn deque_values(data: &UserData) -> Option<Vec<Value>> {
+    data.downcast_ref::<Deque>()
+        .map(|deque| deque.items().iter().cloned().collect())
+}
+
+/// Takes the item at the front of `data`, if it is a deque, for for-loops.
+pub(super) fn deque_pop_front(data: &UserData) -> Option<Value> {
+    data.downcast_ref::<Deque>()?.items().pop_front()
+}
+
+/// Calls `f` with the deque that is the first of `args`, which must be
+/// `count` long.
+fn with_deque(
+    name: &str,
+    args: &[ObjectInfo],
+    count: usize,
+    f: impl FnOnce(&mut VecDeque<Value>, &Args) -> Result<Value, RuntimeError>,
+) -> BuiltInFuncReturnValue {
+    let args = Args::new(name, args);
+    let result = args
+        .expect_count(count)
+        .and_then(|_| args.user_data::<Deque>(0))
+        .and_then(|deque| f(&mut deque.items(), &args));
+    match result {
+        Ok(value) => BuiltInFuncReturnValue::Object(value),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_deque(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("deque", args);
+    let items = args.expect_count_between(0, Some(1)).and_then(|_| {
+        Ok(match args.is_empty() {
+            true => VecDeque::new(),
+            false => args.array(0)?.iter().cloned().collect(),
+        })
+    });
+    if let Ok(items) = &items {
+        memory::charge(items.len() * size_of::<Object>());
+    }
+    match items {
+        Ok(items) => BuiltInFuncReturnValue::Object(Object::user_data(Deque {
+            items: Mutex::new(items),
+        })),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_push_back(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_deque("pushBack", args, 2, |items, args| {
+        items.push_back(args.value(1)?.clone());
+        memory::charge(size_of::<Object>());
+        Ok(Object::Null)
+    })
+}
+
+pub fn filipe_push_front(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_deque("pushFront", args, 2, |items, args| {
+        items.push_front(args.value(1)?.clone());
+        memory::charge(size_of::<Object>());
+        Ok(Object::Null)
+    })
+}
+
+pub fn filipe_pop_back(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_deque("popBack", args, 1, |items, _| {
+        Ok(items.pop_back().unwrap_or(Object::Null))
+    })
+}
+
+pub fn filipe_pop_front(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_deque("popFront", args, 1, |items, _| {
+        Ok(items.pop_front().unwrap_or(Object::Null))
+    })
+}
+
+pub fn filipe_peek_back(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_deque("peekBack", args, 1, |items, _| {
+        Ok(items.back().cloned().unwrap_or(Object::Null))
+    })
+}
+
+pub fn filipe_peek_front(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    with_deque("peekFront", args, 1, |items, _| {
+        Ok(items.front().cloned().unwrap_or(Object::Null))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_deques_work_at_both_ends() {
+        let mut runtime = Runtime::builder().build();
+        let source = "let todo = deque([1])\n\
+                      let order = 0\n\
+                      for n in todo {\n\
+                      \x20 order = order * 10 + n\n\
+                      \x20 if n < 4 {\n\
+                      \x20   pushBack(todo, n * 2)\n\
+                      \x20   pushBack(todo, n * 2 + 1)\n\
+                      \x20 }\n\
+                      }\n\
+                      order";
+        let order = runtime.run_source(source).unwrap();
+        assert_eq!(order.to_string(), "1234567");
+        assert_eq!(runtime.run_source("len(todo)").unwrap().to_string(), "0");
+
+        let source = "let stack = deque()\n\
+                      let alias = stack\n\
+                      pushBack(stack, 1)\n\
+                      pushFront(alias, 0)\n\
+                      pushBack(stack, 2)\n\
+                      let ends = [peekFront(stack), peekBack(stack), popBack(alias), len(stack)]\n\
+                      ends";
+        let ends = runtime.run_source(source).unwrap();
+        assert_eq!(ends.to_string(), "[0, 2, 2, 2]");
+        let empty = runtime.run_source("[popFront(deque()), peekBack(deque())]");
+        assert_eq!(empty.unwrap().to_string(), "[null, null]");
+
+        let error = runtime.run_source("pushBack([1], 2)").unwrap_err();
+        assert_eq!(error.code, "E012");
+    }
+
+    #[test]
+    fn test_deques_count_against_the_memory_limit() {
+        let mut runtime = Runtime::builder().max_memory(100_000).build();
+        let error = runtime
+            .run_source("let d = deque()\nfor i in range(0, 1000000) {\n  pushBack(d, i)\n}")
+            .unwrap_err();
+        assert_eq!(error.code, "E015");
+
+        // what they hold is still there when the live memory is recounted
+        let source = "let held = deque()\n\
+                      let chunk = \"x\"\n\
+                      for i in range(0, 16) { chunk = chunk + chunk }\n\
+                      for i in range(0, 40) { pushBack(held, chunk + \"y\") }";
+        let mut runtime = Runtime::builder().max_memory(1 << 20).build();
+        assert_eq!(runtime.run_source(source).unwrap_err().code, "E015");
+    }
+}
@@ -0,0 +1,371 @@
+//! The API for Rust programs that embed filipe as a scripting layer:
+//!
+//! ```
+//! use filipe::Runtime;
+//!
+//! let mut runtime = Runtime::builder().max_steps(10_000).strict(true).build();
+//! let answer = runtime.run_source("let x = 20\nx * 2 + 2").unwrap();
+//! assert_eq!(answer.to_string(), "42");
+//!
+//! let error = runtime.run_source("x + \"!\"").unwrap_err();
+//! assert_eq!(error.code, "E011");
+//! ```
+//!
+//! A runtime keeps its globals between runs, so a script can define what
+//! later ones use.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::context::Context;
+use super::convert::ToFilipe;
+use super::evaluators::func_call_evaluator::call_function;
+use super::flstdlib::builtins;
+use super::object::{Object, ObjectInfo, Value};
+use super::permissions::RuntimePermissions;
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::streams::{Input, Output, Streams};
+use super::type_system::object_to_type;
+use super::Runtime;
+use crate::diagnostics::Diagnostic;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::messages::{self, msg, Lang};
+use crate::shared::{Lock, Shared};
+
+/// Sets up a `Runtime` with the builtins in a fresh global scope. Nothing
+/// is limited unless asked for.
+#[derive(Default)]
+pub struct RuntimeBuilder {
+    max_steps: Option<u64>,
+    max_memory: Option<usize>,
+    max_call_depth: Option<usize>,
+    max_timer_wait: Option<Duration>,
+    wrapping_arithmetic: bool,
+    strict: bool,
+    permissions: RuntimePermissions,
+    seed: Option<u64>,
+    lang: Option<Lang>,
+    script_path: Option<String>,
+    import_paths: Vec<PathBuf>,
+    streams: Streams,
+    globals: Vec<(String, Value)>,
+}
+
+impl RuntimeBuilder {
+    /// Stops each run with an `ExecutionLimitExceeded` error after `steps`
+    /// statements and loop iterations, see `Runtime::set_max_steps`.
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Caps the approximate memory, in bytes, held by strings, arrays
+    /// and the sets, deques, channels and mutexes holding values.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// See `Runtime::set_max_timer_wait`.
+    pub fn max_timer_wait(mut self, wait: Duration) -> Self {
+        self.max_timer_wait = Some(wait);
+        self
+    }
+
+    /// Stops calls nesting deeper than `depth` with an
+    /// `ExecutionLimitExceeded` error, see `DEFAULT_MAX_CALL_DEPTH` for one
+    /// that fits the stack of the main thread.
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = Some(depth);
+        self
+    }
+
+    /// See `Runtime::set_wrapping_arithmetic`.
+    pub fn wrapping_arithmetic(mut self, wrapping: bool) -> Self {
+        self.wrapping_arithmetic = wrapping;
+        self
+    }
+
+    /// See `Runtime::set_strict`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// See `RuntimePermissions`.
+    pub fn permissions(mut self, permissions: RuntimePermissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Makes `random` yield the same sequence on every run.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// See `Runtime::set_lang`.
+    pub fn lang(mut self, lang: Lang) -> Self {
+        self.lang = Some(lang);
+        self
+    }
+
+    /// Makes imports relative to the script at `path` instead of the
+    /// current directory.
+    pub fn script_path(mut self, path: &str) -> Self {
+        self.script_path = Some(path.to_string());
+        self
+    }
+
+    /// Directories to look for packages in after `fl_modules`.
+    pub fn import_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.import_paths = paths;
+        self
+    }
+
+    /// See `Runtime::set_stdout`.
+    pub fn stdout(mut self, stdout: impl Output + 'static) -> Self {
+        self.streams.set_stdout(Box::new(stdout));
+        self
+    }
+
+    /// See `Runtime::set_stderr`.
+    pub fn stderr(mut self, stderr: impl Output + 'static) -> Self {
+        self.streams.set_stderr(Box::new(stderr));
+        self
+    }
+
+    /// See `Runtime::set_stdin`.
+    pub fn stdin(mut self, stdin: impl Input + 'static) -> Self {
+        self.streams.set_stdin(Box::new(stdin));
+        self
+    }
+
+    /// Starts scripts with the global `name` set to `value`, see
+    /// `Runtime::set_global`.
+    pub fn with_global(mut self, name: &str, value: impl ToFilipe) -> Self {
+        self.globals.push((name.to_string(), value.to_filipe()));
+        self
+    }
+
+    /// Starts scripts with a global for each of `globals`, e.g. the entries
+    /// of a `HashMap` of settings.
+    pub fn with_globals<K: Into<String>, V: ToFilipe>(
+        mut self,
+        globals: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        let globals = globals
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.to_filipe()));
+        self.globals.extend(globals);
+        self
+    }
+
+    pub fn build(self) -> Runtime {
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_max_steps(self.max_steps);
+        runtime.set_memory_limit(self.max_memory);
+        runtime.set_max_call_depth(self.max_call_depth);
+        runtime.set_max_timer_wait(self.max_timer_wait);
+        runtime.set_wrapping_arithmetic(self.wrapping_arithmetic);
+        runtime.set_strict(self.strict);
+        runtime.set_permissions(self.permissions);
+        if let Some(seed) = self.seed {
+            runtime.set_random_seed(seed);
+        }
+        runtime.lang = self.lang;
+        if let Some(path) = &self.script_path {
+            runtime.set_script_path(path);
+        }
+        runtime.set_import_paths(self.import_paths);
+        runtime.streams = self.streams;
+        for (name, value) in self.globals {
+            runtime.set_global(&name, value);
+        }
+        runtime
+    }
+}
+
+impl Runtime {
+    pub fn builder() -> RuntimeBuilder {
+        RuntimeBuilder::default()
+    }
+
+    /// Runs `source` in this runtime's global scope and returns the value
+    /// of its last statement. A syntax error is reported before anything
+    /// runs; only the first one is returned. A script calling `exit` stops
+    /// with an `E022` diagnostic instead of ending the process.
+    pub fn run_source(&mut self, source: &str) -> Result<Value, Diagnostic> {
+        messages::with_runtime_lang(self.lang, || {
+            let input = source.chars().collect::<Vec<char>>();
+            let mut l = Lexer::new(&input);
+            let mut p = Parser::new(&mut l);
+            let program = p.parse();
+            if let Some(error) = p.errors().first() {
+                return Err(Diagnostic::from(error));
+            }
+            self.eval(program).map_err(|error| Diagnostic::from(&error))
+        })
+    }
+
+    /// Sets the global `name` to `value`, declaring it if scripts haven't.
+    /// Its type is that of `value`, like a `let` without one, so scripts
+    /// can reassign it with values of that type.
+    pub fn set_global(&mut self, name: &str, value: impl ToFilipe) {
+        let value = value.to_filipe();
+        let type_ = object_to_type(&value);
+        let mut env = self.env.borrow_mut();
+        let replaced = env.with_mut(name, |info| {
+            info.value = value.clone();
+            info.type_ = type_.clone();
+            info.is_assignable = true;
+        });
+        if replaced.is_none() {
+            env.set(name.to_string(), type_, value, true);
+        }
+    }
+
+    /// A handle to call the global function `name` with, e.g. one a script
+    /// defines for the host to call back:
+    ///
+    /// ```
+    /// # let mut runtime = filipe::Runtime::builder().build();
+    /// runtime.run_source("define onEvent(name: string, n: int): int {\n  return len(name) * n\n}").unwrap();
+    /// let mut on_event = runtime.get_function("onEvent").unwrap();
+    /// assert_eq!(on_event.call(("click", 2)).unwrap().to_string(), "10");
+    /// ```
+    pub fn get_function(&mut self, name: &str) -> Result<ScriptFunction<'_>, RuntimeError> {
+        let resolved = self.env.borrow().resolve(name);
+        let Some(function) = resolved.map(|info| info.value) else {
+            let msg = self.undeclared_msg(name);
+            return Err(RuntimeError::new(ErrorKind::NameError, msg));
+        };
+        if !matches!(
+            function,
+            Object::UserDefinedFunction(_) | Object::BuiltInFunction(_) | Object::NativeFunction(_)
+        ) {
+            return Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                msg!("E011.not_callable", name = name),
+            ));
+        }
+        Ok(ScriptFunction {
+            runtime: self,
+            name: name.to_string(),
+            function,
+        })
+    }
+}
+
+/// A function of a runtime's global scope, see `Runtime::get_function`.
+pub struct ScriptFunction<'r> {
+    runtime: &'r mut Runtime,
+    name: String,
+    function: Value,
+}
+
+impl ScriptFunction<'_> {
+    /// Calls the function with `args`, a tuple of values that convert with
+    /// `ToFilipe`, and returns what it returned. Arguments are checked
+    /// against its parameters like in a call from a script.
+    pub fn call(&mut self, args: impl IntoArgs) -> Result<Value, RuntimeError> {
+        let args = args
+            .into_args()
+            .into_iter()
+            .map(|value| ObjectInfo {
+                is_assignable: true,
+                type_: object_to_type(&value),
+                value,
+            })
+            .collect();
+        let runtime = &mut *self.runtime;
+        let function = self.function.clone();
+        runtime.reset_steps();
+        let returned =
+            runtime.isolated(|runtime| call_function(runtime, &self.name, function, args));
+        runtime.returning = false;
+        match runtime.error_handler.take_error() {
+            Some(error) => {
+                runtime.hook_error(&error);
+                Err(error)
+            }
+            None => Ok(returned.unwrap_or(Object::Null)),
+        }
+    }
+}
+
+/// Arguments of `ScriptFunction::call`: `()`, tuples of up to six values
+/// that convert with `ToFilipe`, or a `Vec<Value>`.
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+impl IntoArgs for Vec<Value> {
+    fn into_args(self) -> Vec<Value> {
+        self
+    }
+}
+
+macro_rules! impl_into_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: ToFilipe),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Value> {
+                let ($($arg,)*) = self;
+                vec![$($arg.to_filipe()),*]
+            }
+        }
+    };
+}
+
+impl_into_args!();
+impl_into_args!(A);
+impl_into_args!(A, B);
+impl_into_args!(A, B, C);
+impl_into_args!(A, B, C, D);
+impl_into_args!(A, B, C, D, E);
+impl_into_args!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_script_functions() {
+        let mut runtime = Runtime::builder().build();
+        runtime
+            .run_source(
+                "let calls = 0\ndefine tick(): void {\n  calls = calls + 1\n}\nlet limit = 3",
+            )
+            .unwrap();
+        let mut tick = runtime.get_function("tick").unwrap();
+        tick.call(()).unwrap();
+        tick.call(()).unwrap();
+        let error = tick.call((1i64,)).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::TypeError));
+        assert_eq!(runtime.run_source("calls").unwrap().to_string(), "2");
+
+        let error = runtime.get_function("tik").err().unwrap();
+        assert_eq!(error.msg, "'tik' is not declared; did you mean 'tick'?");
+        let error = runtime.get_function("limit").err().unwrap();
+        assert!(matches!(error.kind, ErrorKind::TypeError));
+    }
+
+    #[test]
+    fn test_globals_given_by_the_host() {
+        let settings = std::collections::HashMap::from([("retries", 3i64), ("timeout", 30)]);
+        let mut runtime = Runtime::builder()
+            .with_global("region", "eu")
+            .with_global("ports", vec![80i64, 443])
+            .with_globals(settings)
+            .build();
+        let value = runtime
+            .run_source("retries = retries + timeout\nlen(region) + ports[1] + retries")
+            .unwrap();
+        assert_eq!(value.to_string(), "478");
+        let error = runtime.run_source("region = 1").unwrap_err();
+        assert_eq!(error.code, "E011");
+    }
+}
@@ -1,35 +1,75 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::time::Instant;
 
+use super::super::call_stack::Frame;
+use super::super::memory;
+use super::super::native::Args;
 use super::super::object::*;
+use crate::frontend::token::Span;
+use crate::messages::{count_args, msg};
 use crate::runtime::context::{Context, ContextType};
+use crate::runtime::runtime_error::ErrorKind;
 use crate::runtime::type_system::{object_to_type, Type};
-use crate::runtime::{Expr, Identifier, Runtime};
+use crate::runtime::{Expr, ExprKind, Identifier, Runtime};
+use crate::shared::{Lock, Shared};
 
 pub fn eval_call_expr(
     e: &mut Runtime,
-    func_ident: Expr,
-    provided_args: Vec<Expr>,
+    func_ident: &Expr,
+    provided_args: &[Expr],
 ) -> Option<Object> {
-    let fn_name = match func_ident {
-        Expr::Identifier(Identifier(name)) => name,
+    let fn_name = match &func_ident.kind {
+        ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => name,
         _ => {
-            e.error_handler
-                .set_name_error(format!("Function name must be an identifier"));
+            e.error_handler.set_name_error(msg!("E010.function_name"));
             return None;
         }
     };
+    call_named(e, fn_name, func_ident.span, None, provided_args)
+}
+
+/// `receiver.fn_name(provided_args)`
+pub fn eval_method_expr(
+    e: &mut Runtime,
+    receiver: &Expr,
+    fn_name: &str,
+    provided_args: &[Expr],
+    optional: bool,
+) -> Option<Object> {
+    // the call starts where its receiver does
+    let call_site = receiver.span;
+    let receiver = e.eval_expr(receiver)?;
+    if optional && matches!(receiver, Object::Null) {
+        return Some(Object::Null);
+    }
+    call_named(e, fn_name, call_site, Some(receiver), provided_args)
+}
 
-    let fn_object = match e.env.borrow().resolve(&fn_name) {
+/// Calls the function bound to `fn_name` with `receiver`, if any, followed
+/// by `provided_args`.
+fn call_named(
+    e: &mut Runtime,
+    fn_name: &str,
+    call_site: Span,
+    receiver: Option<Object>,
+    provided_args: &[Expr],
+) -> Option<Object> {
+    let resolved = e.env.borrow().resolve(fn_name);
+    let fn_object = match resolved {
         Some(object) => object.value,
         None => {
-            e.error_handler
-                .set_name_error(format!("'{}' is not declared", fn_name));
+            e.set_undeclared_error(fn_name);
             return None;
         }
     };
 
-    let mut checked_args: Vec<ObjectInfo> = vec![];
+    let mut checked_args: Vec<ObjectInfo> = Vec::with_capacity(provided_args.len() + 1);
+    if let Some(receiver) = receiver {
+        checked_args.push(ObjectInfo {
+            is_assignable: true,
+            type_: object_to_type(&receiver),
+            value: receiver,
+        });
+    }
     for arg in provided_args {
         let arg = match e.eval_expr(arg) {
             Some(object) => ObjectInfo {
@@ -42,68 +82,179 @@ pub fn eval_call_expr(
         checked_args.push(arg);
     }
 
-    let (params, body, expected_ret_type) = match fn_object {
-        Object::BuiltInFunction(builtin_fn) => match builtin_fn(checked_args) {
-            BuiltInFuncReturnValue::Object(object) => return Some(object),
+    if fn_name == "breakpoint" && matches!(fn_object, Object::BuiltInFunction(_)) {
+        e.debug_breakpoint_call(call_site);
+    }
+
+    let is_user_defined = matches!(fn_object, Object::UserDefinedFunction(_));
+    if is_user_defined {
+        e.call_stack.push(Frame {
+            function: fn_name.to_string(),
+            call_site,
+        });
+    }
+
+    let started = e.profiler.is_some().then(Instant::now);
+    let returned_value = call_function(e, fn_name, fn_object, checked_args);
+    if is_user_defined {
+        if e.error_handler.has_error() {
+            e.error_handler.attach_trace(&e.call_stack);
+        }
+        e.call_stack.pop();
+    }
+    if let (Some(started), Some(profiler)) = (started, e.profiler.as_mut()) {
+        profiler.record(fn_name, started.elapsed());
+    }
+    returned_value
+}
+
+pub fn call_function(
+    e: &mut Runtime,
+    fn_name: &str,
+    fn_object: Object,
+    checked_args: Vec<ObjectInfo>,
+) -> Option<Object> {
+    e.hook_call(fn_name);
+    let function = match fn_object {
+        Object::BuiltInFunction(_) if fn_name == "spawn" => return e.spawn_task(checked_args),
+        Object::BuiltInFunction(_) if fn_name == "withLock" => return e.with_lock(checked_args),
+        Object::BuiltInFunction(_) if matches!(fn_name, "next" | "collect" | "set") => {
+            return e.advance_sequence(fn_name, checked_args)
+        }
+        Object::BuiltInFunction(_) if matches!(fn_name, "methods" | "globals") => {
+            return e.reflect(fn_name, checked_args)
+        }
+        Object::BuiltInFunction(_) if fn_name == "setTimeout" || fn_name == "setInterval" => {
+            return e.schedule_timer(fn_name, checked_args)
+        }
+        Object::BuiltInFunction(builtin_fn) => match builtin_fn(&checked_args) {
+            BuiltInFuncReturnValue::Object(object) => {
+                let charged = memory::take_charged();
+                if charged > 0 && !e.track_allocation(charged) {
+                    return None;
+                }
+                return Some(object);
+            }
             BuiltInFuncReturnValue::Error(err) => {
                 e.error_handler.set_error(err.kind, err.msg);
                 return None;
             }
         },
-        Object::UserDefinedFunction {
-            params,
-            body,
-            return_type,
-        } => (params, body, return_type),
+        Object::NativeFunction(native) => {
+            return match (native.function)(&Args::new(fn_name, &checked_args)) {
+                Ok(object) => Some(object),
+                Err(err) => {
+                    e.error_handler.set_error(err.kind, err.msg);
+                    None
+                }
+            };
+        }
+        Object::UserDefinedFunction(function) => function,
         _ => {
             e.error_handler
-                .set_type_error(format!("'{}' is not callable", fn_name));
+                .set_type_error(msg!("E011.not_callable", name = fn_name));
             return None;
         }
     };
 
-    if params.len() != checked_args.len() {
-        e.error_handler.set_type_error(format!(
-            "Function '{}' expecteds {} args but provided {}",
-            fn_name,
-            params.len(),
-            checked_args.len()
+    if function.params.len() != checked_args.len() {
+        let params = function.params.len();
+        e.error_handler.set_type_error(msg!(
+            "E011.function_arg_count",
+            name = fn_name,
+            expected = count_args(params, Some(params)),
+            found = checked_args.len()
         ));
         return None;
     }
 
-    let global_scope = Rc::clone(&e.env);
-    let mut fn_scope = Context::make_from(Rc::clone(&global_scope), ContextType::Function);
+    let mut fn_scope = Context::make_from(Shared::clone(&e.env), ContextType::Function);
 
-    for (_, (FunctionParam { name, type_ }, object_info)) in
-        params.into_iter().zip(checked_args).enumerate()
-    {
-        if type_ != object_info.type_ {
-            e.error_handler.set_type_error(format!(
-                "Passing argument of type '{}' to parameter of type '{}'",
-                object_info.type_, type_
+    for (FunctionParam { name, type_ }, object_info) in function.params.iter().zip(checked_args) {
+        if !type_.accepts(&object_info.type_) {
+            e.error_handler.set_type_error(msg!(
+                "E011.param_type",
+                found = object_info.type_,
+                expected = type_
             ));
             return None;
         }
 
-        if !fn_scope.set(name.clone(), object_info.type_, object_info.value, true) {
+        if !fn_scope.set(name.clone(), type_.clone(), object_info.value, true) {
             e.error_handler
-                .set_name_error(format!("Param '{}' already declared", &name));
+                .set_name_error(msg!("E010.param_declared", name = name));
             return None;
         }
     }
 
-    e.env = Rc::new(RefCell::new(fn_scope));
-    let returned_value = e.eval_block_stmt(&body);
+    if function.is_async {
+        let fn_name = fn_name.to_string();
+        return Some(e.start_task(move |e| run_function(e, &fn_name, &function, fn_scope)));
+    }
+    run_function(e, fn_name, &function, fn_scope)
+}
+
+/// Runs the body of `function` in `fn_scope`, which has its arguments,
+/// unless calls already nest as deep as they may.
+fn run_function(
+    e: &mut Runtime,
+    fn_name: &str,
+    function: &FilipeFunction,
+    fn_scope: Context,
+) -> Option<Object> {
+    if let Some(max) = e.max_call_depth.filter(|max| e.call_depth >= *max) {
+        e.error_handler.set_error(
+            ErrorKind::ExecutionLimitExceeded,
+            msg!("E014.call_depth", max = max),
+        );
+        return None;
+    }
+    e.call_depth += 1;
+    let returned_value = run_body(e, fn_name, function, fn_scope);
+    e.call_depth -= 1;
+    returned_value
+}
+
+fn run_body(
+    e: &mut Runtime,
+    fn_name: &str,
+    function: &FilipeFunction,
+    fn_scope: Context,
+) -> Option<Object> {
+    let global_scope = Shared::clone(&e.env);
+    e.env = Shared::new(Lock::new(fn_scope));
+    let returned_value = e.eval_block_stmt(&function.body);
+    e.returning = false;
+    if e.error_handler.has_error() {
+        e.env = global_scope;
+        return None;
+    }
+    let returned_value = match returned_value {
+        Some(object) => object,
+        None if function.return_type == Type::Void => Object::Null,
+        None => {
+            e.error_handler.set_type_error(msg!(
+                "E011.missing_return",
+                name = fn_name,
+                expected = function.return_type
+            ));
+            e.env = global_scope;
+            return None;
+        }
+    };
     let provided_type = object_to_type(&returned_value);
 
-    if (expected_ret_type != provided_type)
-        && !is_types_equivalents(&expected_ret_type, &provided_type)
+    let expected_ret_type = &function.return_type;
+    if !expected_ret_type.accepts(&provided_type)
+        && !is_types_equivalents(expected_ret_type, &provided_type)
     {
-        e.error_handler.set_type_error(format!(
-            "Function '{}' must return '{}' but found '{}'",
-            fn_name, expected_ret_type, provided_type
+        e.error_handler.set_type_error(msg!(
+            "E011.return_type",
+            name = fn_name,
+            expected = expected_ret_type,
+            found = provided_type
         ));
+        e.env = global_scope;
         return None;
     }
 
@@ -113,14 +264,8 @@ pub fn eval_call_expr(
 
 fn is_types_equivalents(lhs: &Type, rhs: &Type) -> bool {
     match lhs {
-        Type::Void => match rhs {
-            Type::Null => true,
-            _ => false,
-        },
-        Type::Null => match rhs {
-            Type::Void => true,
-            _ => false,
-        },
+        Type::Void => matches!(rhs, Type::Null),
+        Type::Null => matches!(rhs, Type::Void),
         _ => false,
     }
 }
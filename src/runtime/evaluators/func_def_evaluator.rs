@@ -1,19 +1,29 @@
-use crate::runtime::object::{FunctionParam, FunctionParams, Object};
+use crate::frontend::token::Span;
+use crate::messages::msg;
+use crate::runtime::object::{FilipeFunction, FunctionParam, FunctionParams, Object};
 use crate::runtime::type_system::{expr_type_to_object_type, Type};
 use crate::runtime::{BlockStmt, ExprType, Identifier, Runtime};
+use crate::shared::Shared;
 
 pub fn eval_func_def(
     e: &mut Runtime,
-    name: String,
-    params: &Vec<(Identifier, ExprType)>,
-    body: &BlockStmt,
-    ret_type: &ExprType,
+    name: &str,
+    params: &[(Identifier, ExprType)],
+    body: &Shared<BlockStmt>,
+    ret_type: Option<&ExprType>,
+    is_async: bool,
+    span: Span,
 ) {
-    if e.env.borrow().has(&name) {
-        e.error_handler
-            .set_name_error(format!("'{}' is already declared", name));
+    if e.env.borrow().has(name) {
+        e.set_redeclared_error(name);
         return;
     }
+    // left out and not inferred by the checker, see `Runtime::eval`
+    let Some(ret_type) = ret_type else {
+        e.error_handler
+            .set_type_error(msg!("E011.infer_return", name = name));
+        return;
+    };
 
     let params = params
         .iter()
@@ -27,13 +37,14 @@ pub fn eval_func_def(
         })
         .collect::<FunctionParams>();
     let return_type = expr_type_to_object_type(ret_type);
-    let function_object = Object::UserDefinedFunction {
+    let function_object = Object::UserDefinedFunction(Shared::new(FilipeFunction {
         params,
-        body: body.clone(),
+        body: Shared::clone(body),
         return_type,
-    };
+        is_async,
+    }));
 
-    e.env
-        .borrow_mut()
-        .set(name, Type::Function, function_object, false);
+    let mut env = e.env.borrow_mut();
+    env.set(name.to_string(), Type::Function, function_object, false);
+    env.set_declared_at(name, span);
 }
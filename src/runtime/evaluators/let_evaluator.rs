@@ -1,57 +1,55 @@
+use crate::frontend::token::Span;
+use crate::messages::msg;
 use crate::runtime::{
-    object_to_type, stdlib::FilipeArray, type_system::expr_type_to_object_type, Expr, ExprType,
-    Object, Runtime, Type,
+    object_to_type, type_system::expr_type_to_object_type, Expr, ExprType, Object, Runtime, Type,
 };
 
 pub fn eval_let_stmt(
     rt: &mut Runtime,
-    name: String,
-    expr_type: Option<ExprType>,
-    expr: Option<Expr>,
+    name: &str,
+    expr_type: Option<&ExprType>,
+    expr: Option<&Expr>,
+    span: Span,
 ) {
-    if rt.env.borrow().has(&name) {
-        rt.error_handler
-            .set_name_error(format!("'{}' already declared", name));
+    if rt.env.borrow().has(name) {
+        rt.set_redeclared_error(name);
         return;
     }
 
     if expr_type.is_none() && expr.is_none() {
-        rt.error_handler.set_type_error(format!(
-            "Can't infer type of '{}', define it's type or initialize it",
-            &name
-        ));
+        rt.error_handler
+            .set_type_error(msg!("E011.infer_type", name = name));
         return;
     }
 
-    if expr_type.is_none() {
-        eval_let_by_type_inference(rt, name, expr.unwrap());
-        return;
+    match expr_type {
+        Some(expr_type) => eval_typed_let(rt, name, expr_type, expr),
+        None => eval_let_by_type_inference(rt, name, expr.unwrap()),
     }
+    if !rt.error_handler.has_error() {
+        rt.env.borrow_mut().set_declared_at(name, span);
+    }
+}
 
-    let expected_type = expr_type_to_object_type(&expr_type.clone().unwrap());
+fn eval_typed_let(rt: &mut Runtime, name: &str, expr_type: &ExprType, expr: Option<&Expr>) {
+    let expected_type = expr_type_to_object_type(expr_type);
 
     if Type::Void == expected_type {
-        rt.error_handler
-            .set_type_error(format!("Can't declared var of type 'void'"));
+        rt.error_handler.set_type_error(msg!("E011.void_var"));
         return;
     }
 
     if let Type::Array(Some(generic)) = expected_type.clone() {
-
         if Type::Void == *generic {
-            rt.error_handler
-                .set_type_error(format!("Can't declared array of type 'void'"));
+            rt.error_handler.set_type_error(msg!("E011.void_array"));
             return;
         }
 
         if expr.is_none() {
             add_to_env(
                 rt,
-                &name,
-                Object::Array {
-                    inner: FilipeArray::new(vec![]),
-                    items_type: Some(*generic),
-                },
+                name,
+                Object::new_array(vec![], Some(*generic)),
                 expected_type,
             );
             return;
@@ -67,21 +65,23 @@ pub fn eval_let_stmt(
         if let Type::Array(None) = evaluated_expr_type {
             add_to_env(
                 rt,
-                &name,
-                Object::Array {
-                    inner: FilipeArray::new(vec![]),
-                    items_type: Some(*generic),
-                },
+                name,
+                Object::new_array(vec![], Some(*generic)),
                 expected_type,
             );
             return;
         }
-        add_to_env(rt, &name, evaluated_expr, evaluated_expr_type);
+        add_to_env(rt, name, evaluated_expr, evaluated_expr_type);
         return;
     }
 
     if expr.is_none() {
-        add_to_env(rt, &name, Object::Null, expected_type);
+        if rt.strict {
+            rt.error_handler
+                .set_type_error(msg!("E011.strict_uninitialized", name = name));
+            return;
+        }
+        add_to_env(rt, name, Object::Null, expected_type);
         return;
     }
 
@@ -92,38 +92,42 @@ pub fn eval_let_stmt(
 
     let evaluated_expr_type = object_to_type(&evaluated_expr);
 
-    if expected_type != evaluated_expr_type {
-        rt.error_handler.set_type_error(format!(
-            "Assigning value of type {} to variable '{name}' which has type {}",
-            expected_type,
-            object_to_type(&evaluated_expr)
+    if !expected_type.accepts(&evaluated_expr_type) {
+        rt.error_handler.set_type_error(msg!(
+            "E011.let_mismatch",
+            found = evaluated_expr_type,
+            name = name,
+            expected = expected_type
         ));
         return;
     }
 
-    add_to_env(rt, &name, evaluated_expr, expected_type);
+    add_to_env(rt, name, evaluated_expr, expected_type);
 }
 
-fn eval_let_by_type_inference(e: &mut Runtime, name: String, expr: Expr) {
+fn eval_let_by_type_inference(e: &mut Runtime, name: &str, expr: &Expr) {
     let evaluated_expr = match e.eval_expr(expr) {
         Some(evaluated_expr) => evaluated_expr,
         None => return,
     };
 
-    if let Object::Array {
-        inner: _,
-        items_type: None,
-    } = evaluated_expr
-    {
+    if let Object::Array(ref inner) = evaluated_expr {
+        if inner.items_type.is_some() {
+            let infered_type = object_to_type(&evaluated_expr);
+            add_to_env(e, name, evaluated_expr, infered_type);
+            return;
+        }
         e.error_handler
-            .set_type_error(format!("Can't infer type of array '{}'", name));
+            .set_type_error(msg!("E011.infer_array", name = name));
         return;
     }
 
     let infered_type = object_to_type(&evaluated_expr);
-    add_to_env(e, &name, evaluated_expr, infered_type);
+    add_to_env(e, name, evaluated_expr, infered_type);
 }
 
-fn add_to_env(e: &mut Runtime, name: &String, object: Object, type_: Type) {
-    e.env.borrow_mut().set(name.clone(), type_, object, true);
+fn add_to_env(e: &mut Runtime, name: &str, object: Object, type_: Type) {
+    e.env
+        .borrow_mut()
+        .set(name.to_string(), type_, object, true);
 }
@@ -1,3 +1,3 @@
-pub mod let_evaluator;
 pub mod func_call_evaluator;
 pub mod func_def_evaluator;
+pub mod let_evaluator;
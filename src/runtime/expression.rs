@@ -0,0 +1,275 @@
+//! Evaluating a single expression against values the caller provides, for
+//! spreadsheet-like formulas, feature flag rules or config values:
+//!
+//! ```
+//! use filipe::{Bindings, Runtime};
+//!
+//! let mut runtime = Runtime::builder().build();
+//! let bindings = Bindings::new().set("price", 12.5).set("quantity", 4.0);
+//! let total = runtime.eval_expression("price * quantity > 40.0", &bindings);
+//! assert_eq!(total.unwrap().to_string(), "true");
+//!
+//! let error = runtime.eval_expression("print(price)", &bindings).unwrap_err();
+//! assert_eq!(error.code, "E021");
+//! ```
+//!
+//! Statements aren't allowed, and neither are assignments or calls to
+//! builtins other than those that only compute a value, like `len`; the
+//! expression runs without any of the permissions too. Globals of the
+//! runtime, e.g. functions the host registered, can be used; functions
+//! scripts defined run as usual.
+
+use super::context::{Context, ContextType};
+use super::convert::ToFilipe;
+use super::object::{Object, Value};
+use super::permissions::RuntimePermissions;
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::type_system::object_to_type;
+use super::Runtime;
+use crate::diagnostics::Diagnostic;
+use crate::frontend::ast::*;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::{Parser, ParserError, ParserErrorKind};
+use crate::frontend::token::Span;
+use crate::messages::msg;
+use crate::shared::{Lock, Shared};
+
+/// Builtins that do nothing but return a value, the only ones expressions
+/// may call. Others, and new ones until they are added here, change
+/// something: print, raise, advance an iterator or the random generator.
+const PURE_BUILTINS: &[&str] = &[
+    "len",
+    "typeof",
+    "range",
+    "iter",
+    "error",
+    "errorKind",
+    "errorMessage",
+    "atomicGet",
+    "map",
+    "filter",
+    "take",
+    "set",
+    "union",
+    "intersection",
+    "difference",
+    "peekBack",
+    "peekFront",
+    "arity",
+    "methods",
+    "globals",
+];
+
+/// The variables an expression is evaluated with.
+#[derive(Clone, Debug, Default)]
+pub struct Bindings {
+    values: Vec<(String, Value)>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `name` to `value`.
+    pub fn set(mut self, name: &str, value: impl ToFilipe) -> Self {
+        self.values.push((name.to_string(), value.to_filipe()));
+        self
+    }
+}
+
+impl Runtime {
+    /// Evaluates `source`, which must be a single expression, with the
+    /// variables of `bindings`, which can't be reassigned.
+    pub fn eval_expression(
+        &mut self,
+        source: &str,
+        bindings: &Bindings,
+    ) -> Result<Value, Diagnostic> {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        if let Some(error) = p.errors().first() {
+            return Err(Diagnostic::from(error));
+        }
+        let expr = match program.as_slice() {
+            [Stmt {
+                kind: StmtKind::Expr(expr),
+                ..
+            }] => expr,
+            _ => {
+                let span = program.get(1).or(program.first()).map(|stmt| stmt.span);
+                return Err(Diagnostic::from(&ParserError {
+                    kind: ParserErrorKind::SyntaxError,
+                    msg: msg!("E001.not_an_expression"),
+                    span: span.unwrap_or_default(),
+                }));
+            }
+        };
+        // by what the name is bound to, so `let say = print` doesn't help
+        let is_impure = |name: &str| {
+            let resolved = self.env.borrow().resolve(name);
+            matches!(
+                resolved.map(|info| info.value),
+                Some(Object::BuiltInFunction(_))
+            ) && !PURE_BUILTINS.contains(&name)
+        };
+        if let Some(error) = side_effect(expr, &is_impure) {
+            return Err(Diagnostic::from(&error));
+        }
+
+        let global_scope = Shared::clone(&self.env);
+        let mut scope = Context::make_from(Shared::clone(&global_scope), ContextType::Function);
+        for (name, value) in &bindings.values {
+            scope.set(name.clone(), object_to_type(value), value.clone(), false);
+        }
+        self.env = Shared::new(Lock::new(scope));
+        let permissions = self.permissions.clone();
+        self.set_permissions(RuntimePermissions::sandboxed());
+        self.reset_steps();
+        let value = self.isolated(|runtime| runtime.eval_expr(expr));
+        self.set_permissions(permissions);
+        self.env = global_scope;
+        self.returning = false;
+
+        if let Some(mut error) = self.error_handler.take_error() {
+            error.span.get_or_insert(expr.span);
+            self.hook_error(&error);
+            return Err(Diagnostic::from(&error));
+        }
+        Ok(value.unwrap_or(Value::Null))
+    }
+}
+
+/// The error for the first part of `expr` that would change something,
+/// calling a function `is_impure` says has side effects among them.
+fn side_effect(expr: &Expr, is_impure: &dyn Fn(&str) -> bool) -> Option<RuntimeError> {
+    let changed = |name: &str, span: Span| {
+        let mut error = RuntimeError::new(
+            ErrorKind::PermissionError,
+            msg!("E021.expression_assign", name = name),
+        );
+        error.span = Some(span);
+        Some(error)
+    };
+    let called = |name: &str| {
+        let mut error = RuntimeError::new(
+            ErrorKind::PermissionError,
+            msg!("E021.expression_call", name = name),
+        );
+        error.span = Some(expr.span);
+        Some(error)
+    };
+    let side_effect = |expr: &Expr| side_effect(expr, is_impure);
+    match &expr.kind {
+        ExprKind::Literal(Literal::Array(items)) => items.iter().find_map(side_effect),
+        ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::Local(..) => None,
+        ExprKind::Call(function, args) => {
+            if let ExprKind::Identifier(Identifier(name)) = &function.kind {
+                if is_impure(name) {
+                    return called(name);
+                }
+            }
+            side_effect(function).or_else(|| args.iter().find_map(side_effect))
+        }
+        ExprKind::Method(receiver, Identifier(name), args, _) => {
+            if is_impure(name) {
+                return called(name);
+            }
+            side_effect(receiver).or_else(|| args.iter().find_map(side_effect))
+        }
+        ExprKind::Infix(lhs, _, rhs) | ExprKind::Index(lhs, rhs) => {
+            side_effect(lhs).or_else(|| side_effect(rhs))
+        }
+        ExprKind::Prefix(_, operand) | ExprKind::Await(operand) | ExprKind::Cast(operand, _) => {
+            side_effect(operand)
+        }
+        ExprKind::Postfix(operand, _) => match &operand.kind {
+            ExprKind::Identifier(Identifier(name)) | ExprKind::Local(Identifier(name), _) => {
+                changed(name, expr.span)
+            }
+            _ => side_effect(operand),
+        },
+        ExprKind::Assign(Identifier(name), _) | ExprKind::IndexAssign(Identifier(name), ..) => {
+            changed(name, expr.span)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::flstdlib::is_builtin;
+
+    #[test]
+    fn test_expressions_cant_change_anything() {
+        let mut runtime = Runtime::builder().build();
+        runtime.run_source("let limit = 10").unwrap();
+        let bindings = Bindings::new().set("xs", vec![1i64, 2, 3]);
+        let value = runtime.eval_expression("len(xs) + xs[2] < limit", &bindings);
+        assert_eq!(value.unwrap().to_string(), "true");
+
+        let error = runtime.eval_expression("[1, limit = 2]", &bindings);
+        assert_eq!(
+            error.unwrap_err().msg,
+            "'limit' can't be changed in an expression"
+        );
+        let error = runtime.eval_expression("let y = 1", &bindings);
+        assert_eq!(error.unwrap_err().code, "E001");
+        let error = runtime.eval_expression("1\n2", &bindings);
+        assert_eq!(error.unwrap_err().span.map(|span| span.line), Some(2));
+        assert!(runtime.eval_expression("xs", &Bindings::new()).is_err());
+
+        runtime.run_source("let say = print").unwrap();
+        runtime.run_source("let d = deque()").unwrap();
+        runtime.run_source("let m = mutex(1)").unwrap();
+        runtime.run_source("let a = atomic(0)").unwrap();
+        runtime.run_source("let c = channel()").unwrap();
+        let calls = [
+            "print(1)",
+            "say(1)",
+            "exit()",
+            "random(10)",
+            "next(iter(xs))",
+            "raise(error(\"E\", \"m\"))",
+            "breakpoint()",
+            "spawn(len, xs)",
+            "join(spawn(len, xs))",
+            "send(c, 1)",
+            "recv(c)",
+            "close(c)",
+            "lock(m)",
+            "unlock(m, 2)",
+            "withLock(m, len)",
+            "atomicAdd(a, 1)",
+            "atomicSet(a, 1)",
+            "setTimeout(len, 0)",
+            "setInterval(len, 0)",
+            "collect(iter(xs))",
+            "pushBack(d, 1)",
+            "d.pushBack(1)",
+            "popFront(d)",
+            "len([print(1)])",
+        ];
+        for call in calls {
+            let error = runtime.eval_expression(call, &bindings).unwrap_err();
+            assert_eq!(error.code, "E021", "{call}");
+        }
+        assert_eq!(runtime.run_source("len(d)").unwrap().to_string(), "0");
+        let error = crate::template::render("${print(1)}", &bindings).unwrap_err();
+        assert_eq!(error.code, "E021");
+        let values = [
+            "atomicGet(a)",
+            "typeof(take(map(xs, typeof), 1))",
+            "len(union(set(xs), difference(set(xs), set([1]))))",
+            "peekFront(d)",
+            "arity(len)",
+        ];
+        for value in values {
+            let result = runtime.eval_expression(value, &bindings);
+            assert!(result.is_ok(), "{value}");
+        }
+        assert!(PURE_BUILTINS.iter().all(|name| is_builtin(name)));
+    }
+}
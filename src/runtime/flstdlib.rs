@@ -1,8 +1,277 @@
-use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo};
+use super::channels::{filipe_channel, filipe_close, filipe_recv, filipe_send, filipe_try_recv};
+use super::deques::{
+    deque_len, filipe_deque, filipe_peek_back, filipe_peek_front, filipe_pop_back,
+    filipe_pop_front, filipe_push_back, filipe_push_front,
+};
+use super::locks::{
+    filipe_atomic, filipe_atomic_add, filipe_atomic_get, filipe_atomic_set, filipe_compare_and_set,
+    filipe_lock, filipe_mutex, filipe_unlock, filipe_with_lock,
+};
+use super::object::{BuiltInFuncReturnValue, FilipeError, Object, ObjectInfo};
+use super::permissions::{self, Capability};
+use super::reflection::{filipe_arity, filipe_globals, filipe_methods};
 use super::runtime_error::{ErrorKind, RuntimeError};
+use super::sequences::{
+    filipe_collect, filipe_filter, filipe_map, filipe_next, filipe_set, filipe_take,
+};
+use super::sets::{filipe_difference, filipe_intersection, filipe_union};
+use super::stdlib::iterator::FilipeIterator;
+use super::streams;
+use super::tasks::{filipe_join, filipe_spawn};
+use super::timers::{filipe_cancel, filipe_set_timer};
 use super::type_system::Type;
+use crate::messages::{count_args, msg};
+use crate::shared::{Lock, Shared};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
 use std::collections::HashMap;
-use rand::Rng;
+
+thread_local! {
+    /// Source for `random`: the generator of the runtime running on this
+    /// thread, see `Runtime::isolated`.
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// A generator seeded from the one `random` uses on this thread.
+#[cfg(feature = "sync")]
+pub fn fork_rng() -> StdRng {
+    RNG.with(|rng| {
+        StdRng::from_rng(&mut *rng.borrow_mut()).unwrap_or_else(|_| StdRng::from_entropy())
+    })
+}
+
+/// Swaps `rng` with the generator `random` uses on this thread.
+pub fn swap_rng(rng: &mut StdRng) {
+    RNG.with(|current| std::mem::swap(&mut *current.borrow_mut(), rng));
+}
+
+/// How each builtin function is called and what it does, listed by the
+/// REPL's `:help`.
+pub const BUILTIN_DOCS: &[(&str, &str, &str)] = &[
+    ("print", "print(values...)", "prints the values on one line"),
+    (
+        "exit",
+        "exit(code?)",
+        "stops the program with the exit code, 0 by default",
+    ),
+    (
+        "len",
+        "len(value)",
+        "number of items of an array or chars of a string",
+    ),
+    (
+        "random",
+        "random(min?, max?)",
+        "random int, up to max or between min and max",
+    ),
+    ("typeof", "typeof(value)", "type of the value"),
+    (
+        "range",
+        "range(start, end, step?)",
+        "ints from start up to, not including, end",
+    ),
+    (
+        "iter",
+        "iter(value)",
+        "iterator over an array, string or range",
+    ),
+    (
+        "next",
+        "next(iterator)",
+        "next item of the iterator, null once it is done",
+    ),
+    (
+        "map",
+        "map(iterable, function)",
+        "iterator over what the function returns for each item, called as they are asked for",
+    ),
+    (
+        "filter",
+        "filter(iterable, function)",
+        "iterator over the items the function holds for",
+    ),
+    (
+        "take",
+        "take(iterable, n)",
+        "iterator over the first n items",
+    ),
+    (
+        "collect",
+        "collect(iterable)",
+        "array of the items left in the iterable",
+    ),
+    (
+        "set",
+        "set(iterable?)",
+        "set of the distinct items of the iterable, empty without one",
+    ),
+    ("union", "union(a, b)", "set of the items of either set"),
+    (
+        "intersection",
+        "intersection(a, b)",
+        "set of the items of a that b has too",
+    ),
+    (
+        "difference",
+        "difference(a, b)",
+        "set of the items of a that b doesn't have",
+    ),
+    (
+        "error",
+        "error(kind, message)",
+        "makes an error value to be raised",
+    ),
+    (
+        "raise",
+        "raise(error)",
+        "raises the error, so it unwinds to the closest catch",
+    ),
+    ("errorKind", "errorKind(error)", "kind of a caught error"),
+    (
+        "errorMessage",
+        "errorMessage(error)",
+        "message of a caught error",
+    ),
+    (
+        "breakpoint",
+        "breakpoint()",
+        "pauses the program when it runs with --debug",
+    ),
+    (
+        "spawn",
+        "spawn(function, args...)",
+        "calls the function on another thread, returns its task",
+    ),
+    (
+        "join",
+        "join(task)",
+        "waits for the task, returns what its function returned",
+    ),
+    (
+        "channel",
+        "channel()",
+        "makes a channel for tasks to send values through",
+    ),
+    (
+        "send",
+        "send(channel, value)",
+        "adds the value to the channel",
+    ),
+    (
+        "recv",
+        "recv(channel)",
+        "waits for a value of the channel, null once it is closed",
+    ),
+    (
+        "tryRecv",
+        "tryRecv(channel)",
+        "a value of the channel, null when there is none",
+    ),
+    ("close", "close(channel)", "stops the channel taking values"),
+    (
+        "deque",
+        "deque(array?)",
+        "makes a double-ended queue, with the items of the array if given",
+    ),
+    (
+        "pushBack",
+        "pushBack(deque, value)",
+        "adds the value at the back of the deque",
+    ),
+    (
+        "pushFront",
+        "pushFront(deque, value)",
+        "adds the value at the front of the deque",
+    ),
+    (
+        "popBack",
+        "popBack(deque)",
+        "takes the value at the back of the deque, null when it is empty",
+    ),
+    (
+        "popFront",
+        "popFront(deque)",
+        "takes the value at the front of the deque, null when it is empty",
+    ),
+    (
+        "peekBack",
+        "peekBack(deque)",
+        "the value at the back of the deque, without taking it",
+    ),
+    (
+        "peekFront",
+        "peekFront(deque)",
+        "the value at the front of the deque, without taking it",
+    ),
+    (
+        "mutex",
+        "mutex(value)",
+        "makes a mutex keeping the value for one task at a time",
+    ),
+    (
+        "lock",
+        "lock(mutex)",
+        "waits until the mutex is unlocked, locks it and returns its value",
+    ),
+    (
+        "unlock",
+        "unlock(mutex, value?)",
+        "stores the value, if given, and unlocks the mutex",
+    ),
+    (
+        "withLock",
+        "withLock(mutex, function)",
+        "locks the mutex, stores what the function returns for its value",
+    ),
+    (
+        "atomic",
+        "atomic(n)",
+        "makes an int tasks can change at once",
+    ),
+    ("atomicGet", "atomicGet(atomic)", "the value of the atomic"),
+    (
+        "atomicSet",
+        "atomicSet(atomic, n)",
+        "sets the value of the atomic",
+    ),
+    (
+        "atomicAdd",
+        "atomicAdd(atomic, n)",
+        "adds n to the atomic, returns the new value",
+    ),
+    (
+        "compareAndSet",
+        "compareAndSet(atomic, expected, n)",
+        "sets the atomic to n if it is expected, returns whether it did",
+    ),
+    (
+        "setTimeout",
+        "setTimeout(function, ms)",
+        "calls the function once, ms milliseconds from now, returns a timer",
+    ),
+    (
+        "setInterval",
+        "setInterval(function, ms)",
+        "calls the function every ms milliseconds, returns a timer",
+    ),
+    ("cancel", "cancel(timer)", "stops the timer"),
+    (
+        "arity",
+        "arity(function)",
+        "how many arguments the function takes, null for builtins",
+    ),
+    (
+        "methods",
+        "methods(value)",
+        "names of the functions value.name() can call",
+    ),
+    (
+        "globals",
+        "globals()",
+        "names of the globals the program declared",
+    ),
+];
 
 pub fn builtins() -> HashMap<String, ObjectInfo> {
     let mut builtin_list: HashMap<String, ObjectInfo> = HashMap::new();
@@ -41,7 +310,7 @@ pub fn builtins() -> HashMap<String, ObjectInfo> {
             type_: Type::Function,
             value: Object::BuiltInFunction(filipe_random),
         },
-    );  
+    );
     builtin_list.insert(
         "typeof".to_string(),
         ObjectInfo {
@@ -60,6 +329,375 @@ pub fn builtins() -> HashMap<String, ObjectInfo> {
         },
     );
 
+    builtin_list.insert(
+        "iter".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_iter),
+        },
+    );
+
+    builtin_list.insert(
+        "next".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_next),
+        },
+    );
+
+    builtin_list.insert(
+        "map".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_map),
+        },
+    );
+
+    builtin_list.insert(
+        "filter".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_filter),
+        },
+    );
+
+    builtin_list.insert(
+        "take".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_take),
+        },
+    );
+
+    builtin_list.insert(
+        "collect".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_collect),
+        },
+    );
+
+    builtin_list.insert(
+        "set".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_set),
+        },
+    );
+
+    builtin_list.insert(
+        "union".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_union),
+        },
+    );
+
+    builtin_list.insert(
+        "intersection".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_intersection),
+        },
+    );
+
+    builtin_list.insert(
+        "difference".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_difference),
+        },
+    );
+
+    builtin_list.insert(
+        "error".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_error),
+        },
+    );
+
+    builtin_list.insert(
+        "raise".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_raise),
+        },
+    );
+
+    builtin_list.insert(
+        "errorKind".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_error_kind),
+        },
+    );
+
+    builtin_list.insert(
+        "errorMessage".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_error_message),
+        },
+    );
+
+    builtin_list.insert(
+        "breakpoint".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_breakpoint),
+        },
+    );
+
+    builtin_list.insert(
+        "spawn".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_spawn),
+        },
+    );
+
+    builtin_list.insert(
+        "join".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_join),
+        },
+    );
+
+    builtin_list.insert(
+        "channel".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_channel),
+        },
+    );
+
+    builtin_list.insert(
+        "send".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_send),
+        },
+    );
+
+    builtin_list.insert(
+        "recv".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_recv),
+        },
+    );
+
+    builtin_list.insert(
+        "tryRecv".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_try_recv),
+        },
+    );
+
+    builtin_list.insert(
+        "close".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_close),
+        },
+    );
+
+    builtin_list.insert(
+        "deque".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_deque),
+        },
+    );
+
+    builtin_list.insert(
+        "pushBack".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_push_back),
+        },
+    );
+
+    builtin_list.insert(
+        "pushFront".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_push_front),
+        },
+    );
+
+    builtin_list.insert(
+        "popBack".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_pop_back),
+        },
+    );
+
+    builtin_list.insert(
+        "popFront".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_pop_front),
+        },
+    );
+
+    builtin_list.insert(
+        "peekBack".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_peek_back),
+        },
+    );
+
+    builtin_list.insert(
+        "peekFront".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_peek_front),
+        },
+    );
+
+    builtin_list.insert(
+        "mutex".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_mutex),
+        },
+    );
+
+    builtin_list.insert(
+        "lock".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_lock),
+        },
+    );
+
+    builtin_list.insert(
+        "unlock".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_unlock),
+        },
+    );
+
+    builtin_list.insert(
+        "withLock".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_with_lock),
+        },
+    );
+
+    builtin_list.insert(
+        "atomic".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_atomic),
+        },
+    );
+
+    builtin_list.insert(
+        "atomicGet".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_atomic_get),
+        },
+    );
+
+    builtin_list.insert(
+        "atomicSet".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_atomic_set),
+        },
+    );
+
+    builtin_list.insert(
+        "atomicAdd".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_atomic_add),
+        },
+    );
+
+    builtin_list.insert(
+        "compareAndSet".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_compare_and_set),
+        },
+    );
+
+    builtin_list.insert(
+        "setTimeout".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_set_timer),
+        },
+    );
+
+    builtin_list.insert(
+        "setInterval".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_set_timer),
+        },
+    );
+
+    builtin_list.insert(
+        "cancel".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_cancel),
+        },
+    );
+
     builtin_list.insert(
         "true".to_string(),
         ObjectInfo {
@@ -78,6 +716,33 @@ pub fn builtins() -> HashMap<String, ObjectInfo> {
         },
     );
 
+    builtin_list.insert(
+        "arity".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_arity),
+        },
+    );
+
+    builtin_list.insert(
+        "methods".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_methods),
+        },
+    );
+
+    builtin_list.insert(
+        "globals".to_string(),
+        ObjectInfo {
+            is_assignable: false,
+            type_: Type::Function,
+            value: Object::BuiltInFunction(filipe_globals),
+        },
+    );
+
     builtin_list.insert(
         "null".to_string(),
         ObjectInfo {
@@ -90,163 +755,198 @@ pub fn builtins() -> HashMap<String, ObjectInfo> {
     builtin_list
 }
 
-fn filipe_print(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
+/// Whether `name` is predefined, like `print` or `true`.
+pub fn is_builtin(name: &str) -> bool {
+    builtins().contains_key(name)
+}
+
+/// `value` as `print` shows it: strings without quotes.
+pub fn to_text(value: &Object) -> String {
+    match value {
+        Object::Int(val) => val.to_string(),
+        Object::Float(val) => val.to_string(),
+        Object::String(val) => val.to_string(),
+        Object::Null => "null".to_string(),
+        Object::BuiltInFunction(_) | Object::NativeFunction(_) => "[Builtin Function]".to_string(),
+        Object::UserDefinedFunction(_) => value.to_string(),
+        Object::Boolean(val) => val.to_string(),
+        Object::Type(val) => val.to_string(),
+        Object::Range {
+            start: _,
+            end: _,
+            step: _,
+        } => value.to_string(),
+        Object::Array(inner) => inner.to_string(),
+        Object::Set(inner) => inner.to_string(),
+        Object::Iterator(_) | Object::UserData(_) => value.to_string(),
+        Object::Error(error) => error.to_string(),
+    }
+}
+
+fn filipe_print(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let mut line = String::new();
     for arg in args {
-        match &arg.value {
-            Object::Int(val) => print!("{}", val),
-            Object::Float(val) => print!("{}", val),
-            Object::String(val) => print!("{}", val),
-            Object::Null => print!("null"),
-            Object::BuiltInFunction(_) => print!("[Builtin Function]"),
-            Object::UserDefinedFunction {
-                params: _,
-                body: _,
-                return_type: _,
-            } => print!("{}", arg.value),
-            Object::RetVal(val) => print!("{}", val),
-            Object::Boolean(val) => print!("{}", val),
-            Object::Type(val) => print!("{}", val),
-            Object::Range {
-                start: _,
-                end: _,
-                step: _,
-            } => print!("{}", arg.value),
-            Object::Array {
-                inner,
-                items_type: _,
-            } => print!("{}", inner),
-        }
+        line.push_str(&to_text(&arg.value));
     }
-    println!();
+    line.push('\n');
+    // like `print!`, but a closed stdout doesn't stop the program
+    let _ = streams::write_stdout(&line);
     BuiltInFuncReturnValue::Object(Object::Null)
 }
 
-fn filipe_random(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
+fn filipe_random(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
     match args.len() {
         0 => {
-            let num = rand::thread_rng().gen::<f64>();
+            let num = RNG.with(|rng| rng.borrow_mut().gen::<f64>());
             BuiltInFuncReturnValue::Object(Object::Float(num))
         }
         1 => {
-            if let Object::Int(max) = args[0].value.clone() {
+            if let Object::Int(max) = args[0].value {
                 if max < 0 {
-                    return BuiltInFuncReturnValue::Error(RuntimeError {
-                        kind: ErrorKind::ValueError,
-                        msg: "Argument for 'random' must be a non-negative integer".to_string(),
-                    });
+                    return BuiltInFuncReturnValue::Error(RuntimeError::new(
+                        ErrorKind::ValueError,
+                        msg!("E012.random_negative"),
+                    ));
                 }
-                let num = rand::thread_rng().gen_range(0..=max);
+                let num = RNG.with(|rng| rng.borrow_mut().gen_range(0..=max));
                 BuiltInFuncReturnValue::Object(Object::Int(num))
             } else {
-                BuiltInFuncReturnValue::Error(RuntimeError {
-                    kind: ErrorKind::TypeError,
-                    msg: "'random' expects an integer argument".to_string(),
-                })
+                BuiltInFuncReturnValue::Error(RuntimeError::new(
+                    ErrorKind::TypeError,
+                    msg!("E012.random_int"),
+                ))
             }
         }
         2 => {
-            if let (Object::Int(min), Object::Int(max)) = (args[0].value.clone(), args[1].value.clone()) {
+            if let (&Object::Int(min), &Object::Int(max)) = (&args[0].value, &args[1].value) {
                 if min < 0 || max < 0 {
-                    return BuiltInFuncReturnValue::Error(RuntimeError {
-                        kind: ErrorKind::ValueError,
-                        msg: "Arguments for 'random' must be non-negative integers".to_string(),
-                    });
+                    return BuiltInFuncReturnValue::Error(RuntimeError::new(
+                        ErrorKind::ValueError,
+                        msg!("E012.random_negatives"),
+                    ));
                 }
                 if min > max {
-                    return BuiltInFuncReturnValue::Error(RuntimeError {
-                        kind: ErrorKind::ValueError,
-                        msg: "The first argument for 'random' must be less than or equal to the second argument".to_string(),
-                    });
+                    return BuiltInFuncReturnValue::Error(RuntimeError::new(
+                        ErrorKind::ValueError,
+                        msg!("E013.random_order"),
+                    ));
                 }
-                let num = rand::thread_rng().gen_range(min..=max);
+                let num = RNG.with(|rng| rng.borrow_mut().gen_range(min..=max));
                 BuiltInFuncReturnValue::Object(Object::Int(num))
             } else {
-                BuiltInFuncReturnValue::Error(RuntimeError {
-                    kind: ErrorKind::TypeError,
-                    msg: "'random' expects two integer arguments".to_string(),
-                })
+                BuiltInFuncReturnValue::Error(RuntimeError::new(
+                    ErrorKind::TypeError,
+                    msg!("E012.random_ints"),
+                ))
             }
         }
-        _ => BuiltInFuncReturnValue::Error(RuntimeError {
-            kind: ErrorKind::ArgumentError,
-            msg: "'random' expects 0, 1, or 2 arguments".to_string(),
-        }),
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!("E012.random_count"),
+        )),
     }
 }
 
-fn filipe_exit(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
+fn filipe_exit(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    if let Err(error) = permissions::require(Capability::Exit) {
+        return BuiltInFuncReturnValue::Error(error);
+    }
+
     if args.is_empty() {
-        std::process::exit(0);
+        return BuiltInFuncReturnValue::Error(RuntimeError::exit(0));
     }
 
     if args.len() != 1 {
-        return BuiltInFuncReturnValue::Error(RuntimeError {
-            kind: ErrorKind::ArgumentError,
-            msg: format!(
-                "'exit' expects 0 or 1 argument but {} were provided",
-                args.len()
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!(
+                "E012.arg_count",
+                name = "exit",
+                expected = count_args(0, Some(1)),
+                found = args.len()
             ),
-        });
+        ));
     }
 
-    match args[0].value.clone() {
-        Object::Int(val) => std::process::exit(val as i32),
-        _ => BuiltInFuncReturnValue::Error(RuntimeError {
-            kind: ErrorKind::ArgumentError,
-            msg: "'exit' only accepts an integer argument".to_string(),
-        }),
+    match &args[0].value {
+        // only the low byte of a larger code reaches the shell, so 256 would
+        // look like success
+        Object::Int(val) if (0..=255).contains(val) => {
+            BuiltInFuncReturnValue::Error(RuntimeError::exit(*val as i32))
+        }
+        Object::Int(val) => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ValueError,
+            msg!("E013.exit_code", code = val),
+        )),
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!("E012.exit_type"),
+        )),
     }
 }
 
-fn filipe_len(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
+fn filipe_len(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
     if args.len() != 1 {
-        return BuiltInFuncReturnValue::Error(RuntimeError {
-            kind: ErrorKind::TypeError,
-            msg: format!("'len' expects 1 arg but {} were provided", args.len()),
-        });
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!(
+                "E011.builtin_arg_count",
+                name = "len",
+                expected = count_args(1, Some(1)),
+                found = args.len()
+            ),
+        ));
     }
 
-    match args[0].value.clone() {
+    match &args[0].value {
         Object::String(val) => BuiltInFuncReturnValue::Object(Object::Int(val.len() as i64)),
-        _ => BuiltInFuncReturnValue::Error(RuntimeError {
-            kind: ErrorKind::TypeError,
-            msg: format!("'len' only accepts iterable types"),
-        }),
+        Object::Array(inner) => BuiltInFuncReturnValue::Object(Object::Int(inner.len() as i64)),
+        Object::Set(inner) => BuiltInFuncReturnValue::Object(Object::Int(inner.len() as i64)),
+        Object::UserData(data) if deque_len(data).is_some() => {
+            BuiltInFuncReturnValue::Object(Object::Int(deque_len(data).unwrap_or(0) as i64))
+        }
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.len_type"),
+        )),
     }
 }
 
-fn filipe_typeof(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
+fn filipe_typeof(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
     if args.len() != 1 {
-        return BuiltInFuncReturnValue::Error(RuntimeError {
-            kind: ErrorKind::TypeError,
-            msg: format!("'typeof' expects 1 arg but {} were provided", args.len()),
-        });
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!(
+                "E011.builtin_arg_count",
+                name = "typeof",
+                expected = count_args(1, Some(1)),
+                found = args.len()
+            ),
+        ));
     }
 
     BuiltInFuncReturnValue::Object(Object::Type(args[0].type_.clone()))
 }
 
-fn filipe_range(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
+fn filipe_range(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
     if args.len() > 3 || args.len() < 2 {
-        return BuiltInFuncReturnValue::Error({
-            RuntimeError {
-                kind: ErrorKind::TypeError,
-                msg: format!(
-                    "function 'range' takes 2 or 3 argus but {} were provided",
-                    args.len()
-                ),
-            }
-        });
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!(
+                "E011.builtin_arg_count",
+                name = "range",
+                expected = count_args(2, Some(3)),
+                found = args.len()
+            ),
+        ));
     }
 
-    for item in args.clone().into_iter() {
+    for item in args {
         if item.type_ != Type::Int {
-            return BuiltInFuncReturnValue::Error({
-                RuntimeError {
-                    kind: ErrorKind::TypeError,
-                    msg: format!("args for function 'range' must be of type number"),
-                }
-            });
+            return BuiltInFuncReturnValue::Error(RuntimeError::new(
+                ErrorKind::TypeError,
+                msg!("E011.range_args"),
+            ));
         }
     }
 
@@ -263,6 +963,12 @@ fn filipe_range(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
     if built_args.len() < 3 {
         built_args.push(1)
     };
+    if built_args[2] == 0 {
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ValueError,
+            msg!("E013.range_step"),
+        ));
+    }
 
     BuiltInFuncReturnValue::Object(Object::Range {
         start: built_args[0],
@@ -270,3 +976,111 @@ fn filipe_range(args: Vec<ObjectInfo>) -> BuiltInFuncReturnValue {
         step: built_args[2],
     })
 }
+
+fn filipe_iter(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    if args.len() != 1 {
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!(
+                "E012.arg_count",
+                name = "iter",
+                expected = count_args(1, Some(1)),
+                found = args.len()
+            ),
+        ));
+    }
+
+    if let Object::Iterator(_) = args[0].value {
+        return BuiltInFuncReturnValue::Object(args[0].value.clone());
+    }
+
+    match FilipeIterator::new(&args[0].value) {
+        Some(iterator) => {
+            BuiltInFuncReturnValue::Object(Object::Iterator(Shared::new(Lock::new(iterator))))
+        }
+        None => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.not_iterable", found = args[0].type_),
+        )),
+    }
+}
+
+/// Makes an error value from a kind and a message, to be raised later.
+fn filipe_error(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    match args {
+        [ObjectInfo {
+            value: Object::String(kind),
+            ..
+        }, ObjectInfo {
+            value: Object::String(message),
+            ..
+        }] => BuiltInFuncReturnValue::Object(Object::Error(Shared::new(FilipeError {
+            kind: kind.to_string(),
+            message: message.to_string(),
+        }))),
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!("E012.error_args"),
+        )),
+    }
+}
+
+/// Raises an error value, so it unwinds to the closest `catch`.
+fn filipe_raise(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    match args {
+        [ObjectInfo {
+            value: Object::Error(error),
+            ..
+        }] => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::from_name(&error.kind),
+            error.message.clone(),
+        )),
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!("E012.error_value", name = "raise"),
+        )),
+    }
+}
+
+fn filipe_error_kind(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    match args {
+        [ObjectInfo {
+            value: Object::Error(error),
+            ..
+        }] => BuiltInFuncReturnValue::Object(Object::String(error.kind.as_str().into())),
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!("E012.error_value", name = "errorKind"),
+        )),
+    }
+}
+
+/// Does nothing by itself: the runtime pauses at the call when a debugger
+/// is attached.
+fn filipe_breakpoint(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    if !args.is_empty() {
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!(
+                "E012.arg_count",
+                name = "breakpoint",
+                expected = count_args(0, Some(0)),
+                found = args.len()
+            ),
+        ));
+    }
+    BuiltInFuncReturnValue::Object(Object::Null)
+}
+
+fn filipe_error_message(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    match args {
+        [ObjectInfo {
+            value: Object::Error(error),
+            ..
+        }] => BuiltInFuncReturnValue::Object(Object::String(error.message.as_str().into())),
+        _ => BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!("E012.error_value", name = "errorMessage"),
+        )),
+    }
+}
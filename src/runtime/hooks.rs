@@ -0,0 +1,100 @@
+//! Callbacks the program embedding filipe can have called as scripts run,
+//! to trace them, log what they do or audit them without changing the
+//! evaluator:
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use filipe::Runtime;
+//!
+//! let calls = Arc::new(Mutex::new(Vec::new()));
+//! let seen = Arc::clone(&calls);
+//! let mut runtime = Runtime::builder().build();
+//! runtime.on_call(move |name| seen.lock().unwrap().push(name.to_string()));
+//! runtime.run_source("define double(n: int): int {\n  return n * 2\n}\nlen(\"ab\") + double(2)").unwrap();
+//! assert_eq!(*calls.lock().unwrap(), ["len", "double"]);
+//! ```
+//!
+//! Like the tracer's output, hooks must be `Send`.
+
+use super::runtime_error::RuntimeError;
+use super::Runtime;
+use crate::frontend::token::Span;
+
+type StatementHook = Box<dyn FnMut(Span) + Send>;
+type CallHook = Box<dyn FnMut(&str) + Send>;
+type ErrorHook = Box<dyn FnMut(&RuntimeError) + Send>;
+
+#[derive(Default)]
+pub struct Hooks {
+    statement: Option<StatementHook>,
+    call: Option<CallHook>,
+    error: Option<ErrorHook>,
+}
+
+impl Runtime {
+    /// Calls `hook` with where each statement is before it runs, those of
+    /// imported modules included.
+    pub fn on_statement(&mut self, hook: impl FnMut(Span) + Send + 'static) {
+        self.hooks.statement = Some(Box::new(hook));
+    }
+
+    /// Calls `hook` with the name of each function called, whether the
+    /// script defined it, it is a builtin or the host registered it.
+    pub fn on_call(&mut self, hook: impl FnMut(&str) + Send + 'static) {
+        self.hooks.call = Some(Box::new(hook));
+    }
+
+    /// Calls `hook` with each error raised, once: when a `try` catches it
+    /// or when it ends the run.
+    pub fn on_error(&mut self, hook: impl FnMut(&RuntimeError) + Send + 'static) {
+        self.hooks.error = Some(Box::new(hook));
+    }
+
+    pub(super) fn hook_statement(&mut self, span: Span) {
+        if let Some(hook) = self.hooks.statement.as_mut() {
+            hook(span);
+        }
+    }
+
+    pub(super) fn hook_call(&mut self, name: &str) {
+        if let Some(hook) = self.hooks.call.as_mut() {
+            hook(name);
+        }
+    }
+
+    pub(super) fn hook_error(&mut self, error: &RuntimeError) {
+        if let Some(hook) = self.hooks.error.as_mut() {
+            hook(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::runtime::runtime_error::ErrorKind;
+
+    #[test]
+    fn test_hooks_see_statements_and_errors() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let mut runtime = Runtime::builder().build();
+        let seen = Arc::clone(&lines);
+        runtime.on_statement(move |span| seen.lock().unwrap().push(span.line));
+        let seen = Arc::clone(&errors);
+        runtime.on_error(move |error| seen.lock().unwrap().push(error.kind.name().to_string()));
+
+        let source = "try {\n  1 / 0\n} catch e {\n  e\n}\nundefinedName";
+        runtime.run_source(source).unwrap_err();
+        assert_eq!(*lines.lock().unwrap(), [1, 2, 4, 6]);
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [
+                ErrorKind::DivisionByZero.name(),
+                ErrorKind::NameError.name()
+            ]
+        );
+    }
+}
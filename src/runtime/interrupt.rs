@@ -0,0 +1,48 @@
+//! Stopping a running program from outside, e.g. from another thread or on
+//! Ctrl-C:
+//!
+//! ```
+//! use std::time::Duration;
+//! use filipe::Runtime;
+//!
+//! let mut runtime = Runtime::builder().build();
+//! let handle = runtime.interrupt_handle();
+//! std::thread::spawn(move || {
+//!     std::thread::sleep(Duration::from_millis(50));
+//!     handle.interrupt();
+//! });
+//! let error = runtime.run_source("for i in range(0, 1000000000) {\n  i\n}").unwrap_err();
+//! assert_eq!(error.code, "E023");
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Makes the runtime it came from stop before its next statement with an
+/// `Interrupted` error. Clones interrupt the same runtime.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the runtime to stop. While nothing runs, the next program it
+    /// runs stops right away.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether an interrupt is waiting for the runtime to pick it up.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Picks up the interrupt, so the runtime can run again afterwards.
+    pub(super) fn take(&self) -> bool {
+        self.interrupted.swap(false, Ordering::Relaxed)
+    }
+}
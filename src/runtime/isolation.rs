@@ -0,0 +1,98 @@
+//! What keeps runtimes from seeing each other's state, so a process can run
+//! many of them, on one thread or several, without them interfering.
+//!
+//! Each runtime owns its globals, limits, permissions, streams, language
+//! and the generator of `random`. Builtins are plain functions that don't
+//! get the runtime, so the generator, permissions, streams and language are
+//! put in thread locals while it runs and taken back afterwards; a runtime
+//! called into from another one's native function puts the outer one's
+//! back when it is done. `exit` stops only the script, see
+//! `ErrorKind::Exit`.
+//!
+//! A runtime that wasn't given a language uses the process' one, see
+//! `messages::set_lang`, which is also what rendering a `Diagnostic` uses.
+
+use super::flstdlib;
+use super::permissions;
+use super::streams;
+use super::Runtime;
+use crate::messages::{self, Lang};
+
+impl Runtime {
+    /// Messages of the errors this runtime raises are in `lang`, instead of
+    /// the process' language.
+    pub fn set_lang(&mut self, lang: Lang) {
+        self.lang = Some(lang);
+    }
+
+    /// Runs `f` with this runtime's generator, permissions, streams and
+    /// language in place of the thread's.
+    pub(super) fn isolated<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        flstdlib::swap_rng(&mut self.rng);
+        streams::swap(&mut self.streams);
+        let outer = permissions::replace_current(self.permissions.clone());
+        let result = messages::with_runtime_lang(self.lang, || f(self));
+        permissions::replace_current(outer);
+        streams::swap(&mut self.streams);
+        flstdlib::swap_rng(&mut self.rng);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::runtime::permissions::RuntimePermissions;
+
+    const SCRIPT: &str = "define fib(n: int): int {\n\
+                          \x20 if n < 2 {\n\
+                          \x20   return n\n\
+                          \x20 }\n\
+                          \x20 return fib(n - 1) + fib(n - 2)\n\
+                          }\n\
+                          fib(15) + random(0, 1000)";
+
+    fn run(seed: u64) -> String {
+        let mut runtime = Runtime::builder().seed(seed).max_steps(1_000_000).build();
+        runtime.run_source(SCRIPT).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_runtimes_dont_share_state() {
+        let expected = (0..32).map(run).collect::<Vec<_>>();
+        let threads = (0..32)
+            .map(|seed| thread::spawn(move || run(seed)))
+            .collect::<Vec<_>>();
+        let results = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results, expected);
+
+        // runtimes taking turns on one thread
+        let mut sandboxed = Runtime::builder()
+            .seed(1)
+            .permissions(RuntimePermissions::sandboxed())
+            .build();
+        let mut open = Runtime::builder().seed(1).build();
+        let first = sandboxed.run_source("random(0, 1000)").unwrap().to_string();
+        assert_eq!(
+            open.run_source("random(0, 1000)").unwrap().to_string(),
+            first
+        );
+        let error = sandboxed.run_source("exit(1)").unwrap_err();
+        assert_eq!(error.code, "E021");
+        let error = open.run_source("exit(1)").unwrap_err();
+        assert_eq!(error.code, "E022");
+
+        sandboxed.set_lang(Lang::Pt);
+        let error = sandboxed.run_source("x").unwrap_err();
+        assert_eq!(error.msg, "'x' não foi declarado");
+        let error = sandboxed.run_source("let").unwrap_err();
+        assert_eq!(error.kind, "[Erro de Sintaxe]");
+        let error = open.run_source("x").unwrap_err();
+        assert_eq!(error.msg, "'x' is not declared");
+    }
+}
@@ -0,0 +1,301 @@
+//! Mutexes and atomic counters, for tasks sharing state, see `tasks`.
+//!
+//! `mutex(value)` keeps a value that one task at a time can have: `lock`
+//! waits until no task has it and returns the value, and `unlock` stores
+//! the new one and lets the next task have it. `withLock(mutex, function)`
+//! does both around a call, storing what the function returns, and unlocks
+//! it even when the function raises:
+//!
+//! ```text
+//! let total = mutex(0)
+//! define add(n: int): int {
+//!   return n + 1
+//! }
+//! define work(): void {
+//!   for x in range(0, 100) {
+//!     withLock(total, add)
+//!   }
+//! }
+//! let tasks = [spawn(work), spawn(work)]
+//! join(tasks[0])
+//! join(tasks[1])
+//! print(lock(total))
+//! ```
+//!
+//! `atomic(n)` is an int that `atomicAdd`, `atomicSet`, `atomicGet` and
+//! `compareAndSet` change and read at once, without a lock.
+//!
+//! As with every value, what is stored in a mutex and what `lock` returns
+//! are copies: changing the array `lock` returned doesn't change the mutex
+//! until it is given to `unlock`. Mutexes and atomics themselves aren't
+//! copied, every variable and task holding one sees the same. Without the
+//! `sync` feature nothing else runs while the script waits, so locking a
+//! mutex that is locked raises an error instead of waiting forever.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Condvar, Mutex as StdMutex, MutexGuard};
+
+use super::evaluators::func_call_evaluator::call_function;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo, Value};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::type_system::object_to_type;
+use super::userdata::{UserData, UserType};
+use super::Runtime;
+use crate::messages::msg;
+
+struct Guarded {
+    value: Value,
+    locked: bool,
+}
+
+/// What `mutex` returns.
+struct Mutex {
+    guarded: StdMutex<Guarded>,
+    /// Signalled when it is unlocked.
+    unlocked: Condvar,
+}
+
+impl UserType for Mutex {
+    fn type_name() -> &'static str {
+        "mutex"
+    }
+}
+
+impl Mutex {
+    fn guarded(&self) -> MutexGuard<'_, Guarded> {
+        self.guarded.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn lock(&self) -> Result<Value, RuntimeError> {
+        let mut guarded = self.guarded();
+        while guarded.locked {
+            if cfg!(not(feature = "sync")) {
+                return Err(RuntimeError::new(
+                    ErrorKind::ValueError,
+                    msg!("E013.mutex_locked"),
+                ));
+            }
+            guarded = self
+                .unlocked
+                .wait(guarded)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        guarded.locked = true;
+        Ok(guarded.value.clone())
+    }
+
+    fn unlock(&self, value: Option<Value>) -> Result<(), RuntimeError> {
+        let mut guarded = self.guarded();
+        if !guarded.locked {
+            return Err(RuntimeError::new(
+                ErrorKind::ValueError,
+                msg!("E013.mutex_unlocked"),
+            ));
+        }
+        if let Some(value) = value {
+            guarded.value = value;
+        }
+        guarded.locked = false;
+        self.unlocked.notify_one();
+        Ok(())
+    }
+}
+
+/// The value `data` guards, if it is a mutex, for measuring its memory.
+pub(super) fn mutex_value(data: &UserData) -> Option<Value> {
+    data.downcast_ref::<Mutex>()
+        .map(|mutex| mutex.guarded().value.clone())
+}
+
+/// What `atomic` returns.
+struct Atomic(AtomicI64);
+
+impl UserType for Atomic {
+    fn type_name() -> &'static str {
+        "atomic"
+    }
+}
+
+fn returned(result: Result<Value, RuntimeError>) -> BuiltInFuncReturnValue {
+    match result {
+        Ok(value) => BuiltInFuncReturnValue::Object(value),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_mutex(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("mutex", args);
+    returned(args.expect_count(1).and_then(|_| {
+        Ok(Object::user_data(Mutex {
+            guarded: StdMutex::new(Guarded {
+                value: args.value(0)?.clone(),
+                locked: false,
+            }),
+            unlocked: Condvar::new(),
+        }))
+    }))
+}
+
+pub fn filipe_lock(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("lock", args);
+    returned(
+        args.expect_count(1)
+            .and_then(|_| args.user_data::<Mutex>(0))
+            .and_then(Mutex::lock),
+    )
+}
+
+/// `unlock(mutex)` keeps the value, `unlock(mutex, value)` replaces it.
+pub fn filipe_unlock(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("unlock", args);
+    returned(args.expect_count_between(1, Some(2)).and_then(|_| {
+        let mutex = args.user_data::<Mutex>(0)?;
+        mutex.unlock(args.get(1).cloned())?;
+        Ok(Object::Null)
+    }))
+}
+
+/// Stands in for `withLock` when it isn't called by its name, as the
+/// runtime only runs it then.
+pub fn filipe_with_lock(_: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    BuiltInFuncReturnValue::Error(RuntimeError::new(
+        ErrorKind::TypeError,
+        msg!("E011.call_by_name", name = "withLock"),
+    ))
+}
+
+impl Runtime {
+    /// `withLock`, which unlike other builtins needs the runtime calling it.
+    pub(super) fn with_lock(&mut self, args: Vec<ObjectInfo>) -> Option<Object> {
+        let call = Args::new("withLock", &args);
+        let locked = call
+            .expect_count(2)
+            .and_then(|_| call.user_data::<Mutex>(0))
+            .and_then(|mutex| Ok((mutex, mutex.lock()?)));
+        let (mutex, value) = match locked {
+            Ok(locked) => locked,
+            Err(error) => {
+                self.error_handler.set_error(error.kind, error.msg);
+                return None;
+            }
+        };
+        let argument = ObjectInfo {
+            is_assignable: true,
+            type_: object_to_type(&value),
+            value,
+        };
+        let returned = call_function(self, "withLock", args[1].value.clone(), vec![argument]);
+        // what it had stays when the function raises
+        let _ = mutex.unlock(returned.clone());
+        returned
+    }
+}
+
+pub fn filipe_atomic(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("atomic", args);
+    returned(
+        args.expect_count(1)
+            .and_then(|_| args.int(0))
+            .map(|n| Object::user_data(Atomic(AtomicI64::new(n)))),
+    )
+}
+
+pub fn filipe_atomic_get(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("atomicGet", args);
+    returned(
+        args.expect_count(1)
+            .and_then(|_| args.user_data::<Atomic>(0))
+            .map(|atomic| Object::Int(atomic.0.load(Ordering::SeqCst))),
+    )
+}
+
+pub fn filipe_atomic_set(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("atomicSet", args);
+    returned(args.expect_count(2).and_then(|_| {
+        args.user_data::<Atomic>(0)?
+            .0
+            .store(args.int(1)?, Ordering::SeqCst);
+        Ok(Object::Null)
+    }))
+}
+
+/// Returns the new value, which wraps around on overflow.
+pub fn filipe_atomic_add(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("atomicAdd", args);
+    returned(args.expect_count(2).and_then(|_| {
+        let n = args.int(1)?;
+        let previous = args
+            .user_data::<Atomic>(0)?
+            .0
+            .fetch_add(n, Ordering::SeqCst);
+        Ok(Object::Int(previous.wrapping_add(n)))
+    }))
+}
+
+/// Sets it to `new` if it is `expected`, returning whether it did.
+pub fn filipe_compare_and_set(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("compareAndSet", args);
+    returned(args.expect_count(3).and_then(|_| {
+        let (expected, new) = (args.int(1)?, args.int(2)?);
+        let swapped = args.user_data::<Atomic>(0)?.0.compare_exchange(
+            expected,
+            new,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+        Ok(Object::Boolean(swapped.is_ok()))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_tasks_share_state_through_locks() {
+        let mut runtime = Runtime::builder().build();
+        let source = "let total = mutex(0)\n\
+                      let count = atomic(0)\n\
+                      define add(n: int): int {\n\
+                      \x20 return n + 1\n\
+                      }\n\
+                      define work(): void {\n\
+                      \x20 for x in range(0, 50) {\n\
+                      \x20   withLock(total, add)\n\
+                      \x20   atomicAdd(count, 2)\n\
+                      \x20 }\n\
+                      }\n\
+                      let tasks = [spawn(work), spawn(work), spawn(work)]\n\
+                      for task in tasks {\n\
+                      \x20 join(task)\n\
+                      }\n\
+                      let result = [lock(total), atomicGet(count)]\n\
+                      unlock(total)\n\
+                      result";
+        let value = runtime.run_source(source).unwrap();
+        assert_eq!(value.to_string(), "[150, 300]");
+
+        // what lock returns is a copy, the mutex changes on unlock
+        let source = "let items = mutex([1])\n\
+                      let held = lock(items)\n\
+                      held[0] = 2\n\
+                      unlock(items)\n\
+                      let before = lock(items)\n\
+                      unlock(items, held)\n\
+                      let seen = [before[0], lock(items)[0], atomicGet(count)]\n\
+                      seen";
+        let value = runtime.run_source(source).unwrap();
+        assert_eq!(value.to_string(), "[1, 2, 300]");
+        let value =
+            runtime.run_source("[compareAndSet(count, 300, 1), compareAndSet(count, 300, 2)]");
+        assert_eq!(value.unwrap().to_string(), "[true, false]");
+        assert_eq!(
+            runtime.run_source("atomicGet(count)").unwrap().to_string(),
+            "1"
+        );
+
+        let error = runtime.run_source("unlock(mutex(1))").unwrap_err();
+        assert_eq!(error.msg, "the mutex isn't locked");
+    }
+}
@@ -0,0 +1,105 @@
+//! What the interpreter logs through the `log` crate, for programs
+//! embedding it to send to the logger they already use. Nothing is logged
+//! unless they install one, e.g. `env_logger`:
+//!
+//! - `debug`: each script parsed, with how many statements and syntax
+//!   errors it has, and each module imported, with its path;
+//! - `debug`: each recount of the live memory, see `set_memory_limit`;
+//! - `debug`: capabilities denied to scripts, `trace` those allowed;
+//! - `warn`: statements slower than `set_slow_statement_threshold`.
+//!
+//! Records have the target of the module logging them, e.g.
+//! `filipe::runtime::modules`, to filter on.
+
+use std::time::{Duration, Instant};
+
+use super::Runtime;
+use crate::frontend::token::Span;
+
+impl Runtime {
+    /// Logs a warning for each statement, a function call with all its
+    /// statements, taking longer than `threshold`. `None` turns it off.
+    /// Timing isn't available on `wasm32-unknown-unknown`.
+    pub fn set_slow_statement_threshold(&mut self, threshold: Option<Duration>) {
+        self.slow_statement = threshold;
+    }
+
+    pub(super) fn start_timing(&self) -> Option<Instant> {
+        self.slow_statement.is_some().then(Instant::now)
+    }
+
+    pub(super) fn log_if_slow(&self, started: Option<Instant>, span: Span) {
+        let (Some(started), Some(threshold)) = (started, self.slow_statement) else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        if elapsed > threshold {
+            log::warn!("statement at {} took {:?}", span, elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, Once};
+    use std::thread::{self, ThreadId};
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+
+    use super::*;
+
+    /// Keeps the records of every thread, as tests run side by side.
+    struct Recorder(Mutex<Vec<(ThreadId, Level, String)>>);
+
+    impl Log for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            let entry = (
+                thread::current().id(),
+                record.level(),
+                record.args().to_string(),
+            );
+            self.0.lock().unwrap().push(entry);
+        }
+
+        fn flush(&self) {}
+    }
+
+    static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+    static INSTALL: Once = Once::new();
+
+    fn records() -> Vec<(Level, String)> {
+        let current = thread::current().id();
+        RECORDER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(thread, _, _)| *thread == current)
+            .map(|(_, level, message)| (*level, message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_interpreter_internals_are_logged() {
+        INSTALL.call_once(|| {
+            log::set_logger(&RECORDER).unwrap();
+            log::set_max_level(LevelFilter::Trace);
+        });
+        let mut runtime = Runtime::builder().max_memory(1 << 14).build();
+        runtime.set_slow_statement_threshold(Some(Duration::ZERO));
+        runtime
+            .run_source("let xs = [1, 2, 3]\nfor x in range(0, 2000) {\n  xs = [x]\n}")
+            .unwrap();
+
+        let records = records();
+        assert!(records.contains(&(Level::Debug, "parsed 2 statements, 0 errors".to_string())));
+        assert!(records.iter().any(|(level, message)| *level == Level::Warn
+            && message.starts_with("statement at 1:1 took")));
+        assert!(records.iter().any(|(level, message)| *level == Level::Debug
+            && message.starts_with("recounted live memory")));
+    }
+}
@@ -0,0 +1,76 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::sync::atomic::AtomicUsize;
+
+use super::channels::channel_values;
+use super::deques::deque_values;
+use super::locks::mutex_value;
+use super::object::Object;
+use crate::shared::Shared;
+
+thread_local! {
+    /// Bytes builtins added to values since the runtime last took them,
+    /// see `charge`.
+    static CHARGED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// For builtins that grow values the runtime doesn't see them change, like
+/// `pushBack`: counts `bytes` towards the memory limit of the runtime on
+/// this thread once the builtin returns.
+pub fn charge(bytes: usize) {
+    CHARGED.with(|charged| charged.set(charged.get() + bytes));
+}
+
+/// What builtins charged since the last call, see `charge`.
+pub fn take_charged() -> usize {
+    CHARGED.with(|charged| charged.take())
+}
+
+/// Enforces a cap on the approximate memory held by runtime values.
+///
+/// Allocations only bump an estimate; the live size is recounted from the
+/// scopes when the estimate crosses the limit, so scripts that keep
+/// reassigning big values aren't charged for the garbage they drop. A
+/// runtime shares its limiter with the tasks it spawns, see `tasks`.
+pub struct MemoryLimiter {
+    pub limit: usize,
+    pub estimated: AtomicUsize,
+}
+
+impl MemoryLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            estimated: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// Approximate heap footprint of `object`. Shared data is only counted the
+/// first time it's seen.
+pub fn object_size(object: &Object, seen: &mut HashSet<usize>) -> usize {
+    size_of::<Object>()
+        + match object {
+            Object::String(val) if seen.insert(val.as_ptr() as usize) => val.len(),
+            Object::Array(inner) if seen.insert(Shared::as_ptr(inner) as usize) => {
+                inner.iter().map(|item| object_size(item, seen)).sum()
+            }
+            // and about as much again for the keys
+            Object::Set(inner) if seen.insert(Shared::as_ptr(inner) as usize) => {
+                2 * inner
+                    .iter()
+                    .map(|item| object_size(item, seen))
+                    .sum::<usize>()
+            }
+            // what deques, channels and mutexes hold
+            Object::UserData(data) if seen.insert(Shared::as_ptr(data) as usize) => {
+                let held = deque_values(data)
+                    .or_else(|| channel_values(data))
+                    .or_else(|| mutex_value(data).map(|value| vec![value]))
+                    .unwrap_or_default();
+                held.iter().map(|item| object_size(item, seen)).sum()
+            }
+            _ => 0,
+        }
+}
@@ -1,204 +1,696 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod call_stack;
+pub mod casts;
+mod channels;
 pub mod context;
+pub mod convert;
+pub mod coverage;
+pub mod debugger;
+mod deques;
+pub mod embedding;
 mod evaluators;
+pub mod expression;
 pub mod flstdlib;
+pub mod hooks;
+pub mod interrupt;
+mod isolation;
+mod locks;
+mod logging;
+mod memory;
+pub mod modules;
+pub mod native;
 pub mod object;
-mod runtime_error;
+pub mod permissions;
+pub mod plugins;
+mod profiler;
+mod reflection;
+pub mod runtime_error;
+mod sequences;
+mod sets;
 mod stdlib;
-mod type_system;
+pub mod streams;
+mod tasks;
+mod timers;
+mod tracer;
+pub mod type_system;
+pub mod userdata;
 
-use std::{cell::RefCell, rc::Rc};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 use crate::frontend::ast::*;
+use crate::frontend::checker;
+use crate::frontend::token::Span;
+use crate::messages::{msg, Lang};
+use crate::shared::{Lock, Shared};
+use call_stack::Frame;
 use context::{Context, ContextType};
-use evaluators::func_call_evaluator::eval_call_expr;
+use coverage::Coverage;
+use debugger::Debugger;
+pub use embedding::RuntimeBuilder;
+use evaluators::func_call_evaluator::{eval_call_expr, eval_method_expr};
 use evaluators::func_def_evaluator::eval_func_def;
 use evaluators::let_evaluator::eval_let_stmt;
-use object::Object;
-use runtime_error::RuntimeErrorHandler;
-use stdlib::FilipeArray;
-use type_system::{object_to_type, Type};
+use flstdlib::is_builtin;
+use hooks::Hooks;
+use interrupt::InterruptHandle;
+use memory::MemoryLimiter;
+use modules::Modules;
+use object::{FilipeError, Object};
+use permissions::RuntimePermissions;
+use profiler::Profiler;
+use runtime_error::{ErrorKind, RuntimeError, RuntimeErrorHandler};
+use stdlib::iterator::FilipeIterator;
+pub use stdlib::FilipeArray;
+use streams::{Input, Output, Streams};
+use tracer::Tracer;
+use type_system::{expr_type_to_object_type, object_to_type, Type};
+
+/// How deep calls of user-defined functions can nest in `serve` sessions
+/// and sandboxed Python runtimes before raising an `ExecutionLimitExceeded`
+/// error instead of overflowing the stack of the thread. Each call takes a
+/// few kilobytes of it, so this fits in the 8 MiB main thread with room to
+/// spare, not in the 2 MiB of a thread spawned with the defaults. Other
+/// runtimes recurse as deep as their stack goes unless given a limit.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 400;
+
+enum IndexAssignError {
+    NotAssignable,
+    NotAnArray(Type),
+    TypeMismatch(Type),
+    OutOfRange(usize),
+}
+
+/// Saved state of the global scope, see `Runtime::snapshot`.
+#[derive(Clone)]
+pub struct Snapshot {
+    globals: Context,
+    /// Modules imported so far, which go with the globals they defined.
+    loaded: modules::Loaded,
+}
 
 pub struct Runtime {
-    env: Rc<RefCell<Context>>,
-    pub error_handler: RuntimeErrorHandler,
+    env: Shared<Lock<Context>>,
+    error_handler: RuntimeErrorHandler,
+    max_steps: Option<u64>,
+    /// Shared with the tasks spawned during the run, which count against
+    /// the same budget.
+    steps: Shared<AtomicU64>,
+    max_call_depth: Option<usize>,
+    /// Calls of user-defined functions that haven't returned yet.
+    call_depth: usize,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    memory: Option<Shared<MemoryLimiter>>,
+    wrapping_arithmetic: bool,
+    strict: bool,
+    /// Set by `return` until the enclosing function call picks up the value.
+    returning: bool,
+    call_stack: Vec<Frame>,
+    debugger: Option<Debugger>,
+    modules: Modules,
+    permissions: RuntimePermissions,
+    interrupt: InterruptHandle,
+    tracer: Option<Tracer>,
+    hooks: Hooks,
+    /// See `logging`.
+    slow_statement: Option<Duration>,
+    /// The generator of `random`, see `isolation`.
+    rng: StdRng,
+    /// Where `print` writes, see `streams`.
+    streams: Streams,
+    /// Of messages, see `set_lang`.
+    lang: Option<Lang>,
+    /// Waiting for the event loop, see `timers`.
+    timers: Vec<timers::Scheduled>,
+    /// How long the event loop may wait for timers in a run.
+    max_timer_wait: Option<Duration>,
 }
 
 impl Runtime {
-    pub fn new(env: Rc<RefCell<Context>>) -> Self {
+    pub fn new(env: Shared<Lock<Context>>) -> Self {
         Self {
             env,
             error_handler: RuntimeErrorHandler::new(),
+            max_steps: None,
+            steps: Shared::new(AtomicU64::new(0)),
+            max_call_depth: None,
+            call_depth: 0,
+            profiler: None,
+            coverage: None,
+            memory: None,
+            wrapping_arithmetic: false,
+            strict: false,
+            returning: false,
+            call_stack: Vec::new(),
+            debugger: None,
+            modules: Modules::default(),
+            permissions: RuntimePermissions::default(),
+            interrupt: InterruptHandle::new(),
+            tracer: None,
+            hooks: Hooks::default(),
+            slow_statement: None,
+            rng: StdRng::from_entropy(),
+            streams: Streams::default(),
+            lang: None,
+            timers: Vec::new(),
+            max_timer_wait: None,
         }
     }
 
-    pub fn eval(&mut self, program: Program) -> Option<Object> {
-        let mut output: Option<Object> = None;
-        for stmt in program {
-            let object = self.eval_stmt(stmt);
-            if self.error_handler.has_error() {
-                eprintln!("{}", self.error_handler.get_error().unwrap());
-                return None;
+    /// Makes integer arithmetic wrap around on overflow instead of raising
+    /// an `OverflowError`. Dividing by zero is an error either way.
+    pub fn set_wrapping_arithmetic(&mut self, wrapping: bool) {
+        self.wrapping_arithmetic = wrapping;
+    }
+
+    /// Strict mode: conditions must be booleans and typed variables must be
+    /// initialized instead of starting out as `null`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Turns off what `permissions` doesn't allow; using it raises a
+    /// `PermissionError`.
+    pub fn set_permissions(&mut self, permissions: RuntimePermissions) {
+        self.permissions = permissions;
+    }
+
+    /// Limits how long the event loop waits for timers once a program is
+    /// done, see `timers`. A timer due later than that raises an
+    /// `ExecutionLimitExceeded` error instead of being waited for. `None`
+    /// means no limit.
+    pub fn set_max_timer_wait(&mut self, max_timer_wait: Option<Duration>) {
+        self.max_timer_wait = max_timer_wait;
+    }
+
+    /// Caps the approximate memory, in bytes, held by strings, arrays
+    /// and the sets, deques, channels and mutexes holding values.
+    /// Going over raises a `MemoryLimitExceeded` error. `None` means no limit.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory = limit.map(|limit| Shared::new(MemoryLimiter::new(limit)));
+    }
+
+    fn track_allocation(&mut self, bytes: usize) -> bool {
+        self.track_unscoped_allocation(bytes, bytes)
+    }
+
+    /// Like `track_allocation`, for a value still being built, which no
+    /// scope holds yet: `bytes` more of it, `held` in all, which a recount
+    /// of the live memory adds to what the scopes hold.
+    fn track_unscoped_allocation(&mut self, bytes: usize, held: usize) -> bool {
+        let Some(memory) = self.memory.as_ref() else {
+            return true;
+        };
+        if memory.estimated.fetch_add(bytes, Ordering::Relaxed) + bytes <= memory.limit {
+            return true;
+        }
+
+        let live = self.env.borrow().live_bytes(&mut HashSet::new()) + held;
+        let memory = self.memory.as_ref().unwrap();
+        log::debug!("recounted live memory: {} of {} bytes", live, memory.limit);
+        memory.estimated.store(live, Ordering::Relaxed);
+        if live <= memory.limit {
+            return true;
+        }
+        let limit = memory.limit;
+        self.error_handler.set_error(
+            ErrorKind::MemoryLimitExceeded,
+            msg!("E015.exceeded", max = limit),
+        );
+        false
+    }
+
+    /// Captures the global scope, and which modules were imported into it,
+    /// so it can be brought back with `restore`. Cheap: values are shared
+    /// with the live scope until either side changes them.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            globals: self.env.borrow().detached(),
+            loaded: self.modules.loaded.clone(),
+        }
+    }
+
+    /// Puts the global scope back to how it was when `snapshot` was taken.
+    /// Everyone holding this runtime's environment sees the restored state.
+    /// Modules imported since are run again by the next import of them.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        *self.env.borrow_mut() = snapshot.globals.detached();
+        self.modules.loaded = snapshot.loaded.clone();
+    }
+
+    /// Deterministic mode: seeds `random` so a script produces the same
+    /// output on every run.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Sends what `print` writes to `stdout`.
+    pub fn set_stdout(&mut self, stdout: impl Output + 'static) {
+        self.streams.set_stdout(Box::new(stdout));
+    }
+
+    /// Where errors meant for the user are written.
+    pub fn set_stderr(&mut self, stderr: impl Output + 'static) {
+        self.streams.set_stderr(Box::new(stderr));
+    }
+
+    /// Where builtins reading input read it from.
+    pub fn set_stdin(&mut self, stdin: impl Input + 'static) {
+        self.streams.set_stdin(Box::new(stdin));
+    }
+
+    /// Starts recording call counts and time spent per function.
+    pub fn enable_profiling(&mut self) {
+        self.profiler.get_or_insert_with(Profiler::default);
+    }
+
+    pub fn profile_report(&self) -> Option<String> {
+        self.profiler.as_ref().map(Profiler::report)
+    }
+
+    /// Starts counting how many times the statements of each line run.
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Coverage::default);
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Logs every statement run to `out`, see `tracer`.
+    pub fn set_tracer(&mut self, out: Box<dyn std::io::Write + Send>) {
+        self.tracer = Some(Tracer::new(out));
+    }
+
+    /// Limits how many statements and loop iterations each run may execute
+    /// before giving up with an `ExecutionLimitExceeded` error. `None`
+    /// means no limit. Every `eval`, `eval_expression` and call through
+    /// `get_function` starts with the whole budget.
+    pub fn set_max_steps(&mut self, max_steps: Option<u64>) {
+        self.max_steps = max_steps;
+        self.reset_steps();
+    }
+
+    /// Limits how deep calls can nest, see `DEFAULT_MAX_CALL_DEPTH`. `None`
+    /// means no limit.
+    pub fn set_max_call_depth(&mut self, max_call_depth: Option<usize>) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// A handle to stop the program this runtime runs from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Makes `handle` stop this runtime, e.g. so one Ctrl-C handler can
+    /// stop every runtime the REPL makes.
+    pub fn set_interrupt_handle(&mut self, handle: InterruptHandle) {
+        self.interrupt = handle;
+    }
+
+    /// Raises an `Interrupted` error if the handle was triggered.
+    fn check_interrupt(&mut self) -> bool {
+        if self.interrupt.take() {
+            self.error_handler
+                .set_error(ErrorKind::Interrupted, msg!("E023.interrupted"));
+            return false;
+        }
+        true
+    }
+
+    /// Gives the next run the whole step budget. Tasks still running from
+    /// the last one keep counting against what was left of theirs.
+    fn reset_steps(&mut self) {
+        self.steps = Shared::new(AtomicU64::new(0));
+    }
+
+    fn consume_step(&mut self) -> bool {
+        if !self.check_interrupt() {
+            return false;
+        }
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.max_steps {
+            Some(max_steps) if steps > max_steps => {
+                self.error_handler.set_error(
+                    ErrorKind::ExecutionLimitExceeded,
+                    msg!("E014.exceeded", max = max_steps),
+                );
+                false
             }
-            output = object;
+            _ => true,
+        }
+    }
+
+    fn set_undeclared_error(&mut self, name: &str) {
+        let msg = self.undeclared_msg(name);
+        self.error_handler.set_name_error(msg);
+    }
+
+    fn undeclared_msg(&self, name: &str) -> String {
+        match self.env.borrow().closest_name(name) {
+            Some(closest) => msg!("E010.did_you_mean", name = name, closest = closest),
+            None => msg!("E010.not_declared", name = name),
+        }
+    }
+
+    /// Reports that `name` is declared twice in the same scope, pointing at
+    /// the first declaration when it is known.
+    fn set_redeclared_error(&mut self, name: &str) {
+        self.error_handler
+            .set_name_error(msg!("E010.already_declared", name = name));
+        let first = self.env.borrow().declared_at(name);
+        match first {
+            Some(span) => {
+                self.error_handler
+                    .add_note(msg!("E010.first_declared", name = name, span = span))
+            }
+            None if is_builtin(name) => self
+                .error_handler
+                .add_note(msg!("E010.builtin", name = name)),
+            None => {}
+        }
+    }
+
+    /// Runs `program` and returns the value of its last statement, or `null`
+    /// when that statement has none, once its timers are done. Stops at the
+    /// first error, dropping the timers still waiting; the runtime can keep
+    /// running other programs afterwards.
+    pub fn eval(&mut self, mut program: Program) -> Result<Object, RuntimeError> {
+        // types left out run as the checker infers them
+        if checker::has_missing_annotations(&program) {
+            checker::annotate(&mut program, self.env.borrow().visible_globals());
+        }
+        self.reset_steps();
+        self.isolated(|runtime| {
+            let mut output = None;
+            for stmt in &program {
+                let object = runtime.eval_stmt(stmt);
+                runtime.returning = false;
+                if let Some(error) = runtime.error_handler.take_error() {
+                    runtime.timers.clear();
+                    runtime.hook_error(&error);
+                    return Err(error);
+                }
+                output = object;
+            }
+            runtime.run_timers();
+            if let Some(error) = runtime.error_handler.take_error() {
+                runtime.timers.clear();
+                runtime.hook_error(&error);
+                return Err(error);
+            }
+            Ok(output.unwrap_or(Object::Null))
+        })
+    }
+
+    fn eval_stmt(&mut self, stmt: &Stmt) -> Option<Object> {
+        self.debug_stmt(stmt.span);
+        self.hook_statement(stmt.span);
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record(stmt.span.line);
         }
-        output
+        self.trace_start(stmt);
+        let started = self.start_timing();
+        let object = self.eval_stmt_kind(&stmt.kind, stmt.span);
+        self.log_if_slow(started, stmt.span);
+        if object.is_none() && self.error_handler.has_error() {
+            self.error_handler.attach_span(stmt.span);
+        }
+        self.trace_end(stmt, object.as_ref());
+        object
     }
 
-    fn eval_stmt(&mut self, stmt: Stmt) -> Option<Object> {
+    fn eval_stmt_kind(&mut self, stmt: &StmtKind, span: Span) -> Option<Object> {
+        if !self.consume_step() {
+            return None;
+        }
+
         match stmt {
-            Stmt::Let(Identifier(name), type_, expr) => {
-                eval_let_stmt(self, name, type_, expr);
+            StmtKind::Let(Identifier(name), type_, expr) => {
+                eval_let_stmt(self, name, type_.as_ref(), expr.as_ref(), span);
                 None
             }
-            Stmt::Func(Identifier(name), params, body, ret_type) => {
-                eval_func_def(self, name, &params, &body, &ret_type);
+            StmtKind::Func(Identifier(name), params, body, ret_type, _, is_async) => {
+                eval_func_def(self, name, params, body, ret_type.as_ref(), *is_async, span);
                 None
             }
-            Stmt::Return(expr) => self.eval_return(expr),
-            Stmt::Expr(expr) => self.eval_expr(expr),
-            Stmt::If {
+            StmtKind::Return(expr) => self.eval_return(expr.as_ref()),
+            StmtKind::Expr(expr) => self.eval_expr(expr),
+            StmtKind::If {
                 condition,
                 consequence,
                 alternative,
-            } => self.eval_if_stmt(condition, consequence, alternative),
-            Stmt::ForLoop {
+            } => self.eval_if_stmt(condition, consequence, alternative.as_ref()),
+            StmtKind::ForLoop {
                 cursor,
                 iterable,
                 block,
             } => self.eval_forloop_stmt(cursor, iterable, block),
+            StmtKind::Try {
+                block,
+                binding,
+                handler,
+            } => self.eval_try_stmt(block, binding, handler),
+            StmtKind::Assert(condition) => self.eval_assert_stmt(condition),
+            StmtKind::Import(name) => self.eval_import_stmt(name, span),
         }
     }
 
-    fn eval_forloop_stmt(
+    /// Fails with the source of `condition`. For a comparison, the values
+    /// both sides evaluated to are shown too, unless they are literals.
+    fn eval_assert_stmt(&mut self, condition: &Expr) -> Option<Object> {
+        let (holds, operands) = match &condition.kind {
+            ExprKind::Infix(lhs, infix, rhs) => {
+                let lhs_value = self.eval_expr(lhs)?;
+                let rhs_value = self.eval_expr(rhs)?;
+                let operands = vec![(lhs, lhs_value.clone()), (rhs, rhs_value.clone())];
+                let Some(holds) = self.apply_infix(lhs_value, infix, rhs_value) else {
+                    self.error_handler.attach_expr(condition);
+                    return None;
+                };
+                (holds, operands)
+            }
+            _ => (self.eval_expr(condition)?, vec![]),
+        };
+        if self.is_truthy(holds)? {
+            return None;
+        }
+
+        let values = operands
+            .into_iter()
+            .filter(|(expr, _)| !matches!(expr.kind, ExprKind::Literal(_)))
+            .map(|(expr, value)| format!("{} = {}", expr, value))
+            .collect::<Vec<_>>();
+        let msg = match values.is_empty() {
+            true => msg!("E019.failed", condition = condition),
+            false => msg!(
+                "E019.failed_values",
+                condition = condition,
+                values = values.join(", ")
+            ),
+        };
+        self.error_handler.set_error(ErrorKind::AssertionError, msg);
+        None
+    }
+
+    fn eval_try_stmt(
         &mut self,
-        cursor: String,
-        iterable: Expr,
-        block: BlockStmt,
+        block: &BlockStmt,
+        binding: &str,
+        handler: &BlockStmt,
     ) -> Option<Object> {
-        let iterable_object = match self.eval_expr(iterable) {
-            Some(object) => object,
-            None => return None,
-        };
-        match iterable_object {
-            Object::Range { start, end, step } => {
-                self.eval_range_forloop(cursor, start, end, step, block)
-            }
-            _ => {
-                self.error_handler
-                    .set_type_error(format!("for loop works only with range (for now)"));
-                return None;
-            }
+        let parent_scope = Shared::clone(&self.env);
+        let try_scope = Context::make_from(Shared::clone(&parent_scope), ContextType::Try);
+        self.env = Shared::new(Lock::new(try_scope));
+        let returned = self.eval_block_stmt(block);
+        self.env = Shared::clone(&parent_scope);
+
+        let caught = self
+            .error_handler
+            .get_error()
+            .is_some_and(|error| error.kind.is_catchable());
+        if !caught {
+            return returned;
         }
+        let error = self.error_handler.take_error()?;
+        self.hook_error(&error);
+        let value = Object::Error(Shared::new(FilipeError {
+            kind: error.kind.name().to_string(),
+            message: error.msg,
+        }));
+
+        let mut catch_scope = Context::make_from(Shared::clone(&parent_scope), ContextType::Try);
+        catch_scope.set(binding.to_string(), Type::Error, value, true);
+        self.env = Shared::new(Lock::new(catch_scope));
+        let returned = self.eval_block_stmt(handler);
+        self.env = parent_scope;
+        returned
     }
 
-    fn eval_range_forloop(
+    fn eval_forloop_stmt(
         &mut self,
-        cursor: String,
-        start: i64,
-        end: i64,
-        step: i64,
-        block: BlockStmt,
+        cursor: &str,
+        iterable: &Expr,
+        block: &BlockStmt,
     ) -> Option<Object> {
-        let parent_scope = Rc::clone(&self.env);
-        let loop_scope = Context::make_from(Rc::clone(&parent_scope), ContextType::Loop);
-        self.env = Rc::new(RefCell::new(loop_scope));
-
-        self.env
-            .borrow_mut()
-            .set(cursor.clone(), Type::Int, Object::Int(start), true);
-
-        for _ in (start..end).step_by(step as usize) {
-            self.eval_block_stmt(&block);
-            let old_val = match self.env.borrow().resolve(&cursor).unwrap().value {
-                Object::Int(val) => val,
-                _ => return None,
+        let iterable_object = self.eval_expr(iterable)?;
+        let iterator = match iterable_object {
+            Object::Iterator(iterator) => iterator,
+            _ => match FilipeIterator::new(&iterable_object) {
+                Some(iterator) => Shared::new(Lock::new(iterator)),
+                None => {
+                    self.error_handler.set_type_error(msg!(
+                        "E011.not_iterable",
+                        found = object_to_type(&iterable_object)
+                    ));
+                    return None;
+                }
+            },
+        };
+
+        let parent_scope = Shared::clone(&self.env);
+        let loop_scope = Context::make_from(Shared::clone(&parent_scope), ContextType::Loop);
+        self.env = Shared::new(Lock::new(loop_scope));
+
+        let mut first = true;
+        loop {
+            let next = FilipeIterator::advance(&iterator, self);
+            let Some(item) = next else {
+                break;
             };
-            let incrementor = if step == 0 { 1 } else { step };
-            self.env
-                .borrow_mut()
-                .mutate(cursor.clone(), Object::Int(old_val + incrementor));
+            let item_type = object_to_type(&item);
+            let mut scope = self.env.borrow_mut();
+            if first {
+                scope.set(cursor.to_string(), item_type, item, true);
+                first = false;
+            } else {
+                // locals declared by the body don't survive the iteration
+                scope.truncate_locals(1);
+                scope.replace_local(0, item_type, item);
+            }
+            drop(scope);
+            // an empty body has no statements to stop at or count, so the
+            // iteration is a step of its own
+            if !self.consume_step() {
+                break;
+            }
+            let returned = self.eval_block_stmt(block);
+            if self.error_handler.has_error() {
+                break;
+            }
+            if returned.is_some() {
+                self.env = parent_scope;
+                return returned;
+            }
         }
 
         self.env = parent_scope;
         None
     }
 
-    fn is_truthy(&mut self, object: Object) -> bool {
-        match object {
-            Object::Null | Object::Boolean(false) => false,
-            Object::Int(val) => val != 0,
-            Object::Float(val) => val != 0.0,
-            _ => true,
+    /// Whether a condition holds. Strict mode only takes booleans.
+    fn is_truthy(&mut self, object: Object) -> Option<bool> {
+        if self.strict && !matches!(object, Object::Boolean(_)) {
+            self.error_handler.set_type_error(msg!(
+                "E011.strict_condition",
+                found = object_to_type(&object)
+            ));
+            return None;
         }
+        Some(casts::truthy(&object))
     }
 
     fn eval_if_stmt(
         &mut self,
-        condition: Expr,
-        consequence: BlockStmt,
-        alternative: Option<BlockStmt>,
+        condition: &Expr,
+        consequence: &BlockStmt,
+        alternative: Option<&BlockStmt>,
     ) -> Option<Object> {
-        let evaluated_cond = match self.eval_expr(condition) {
-            Some(object) => object,
-            None => return None,
-        };
+        let evaluated_cond = self.eval_expr(condition)?;
 
-        let parent_scope = Rc::clone(&self.env);
-        let ifelse_scope = Context::make_from(Rc::clone(&parent_scope), ContextType::IfElse);
-        self.env = Rc::new(RefCell::new(ifelse_scope));
+        let block = if self.is_truthy(evaluated_cond)? {
+            consequence
+        } else {
+            alternative?
+        };
 
-        if self.is_truthy(evaluated_cond) {
-            return Some(self.eval_block_stmt(&consequence));
-        }
+        let parent_scope = Shared::clone(&self.env);
+        let ifelse_scope = Context::make_from(Shared::clone(&parent_scope), ContextType::IfElse);
+        self.env = Shared::new(Lock::new(ifelse_scope));
+        let returned = self.eval_block_stmt(block);
+        self.env = parent_scope;
+        returned
+    }
 
-        if alternative.is_some() {
-            return Some(self.eval_block_stmt(&alternative.unwrap()));
+    fn eval_expr(&mut self, expr: &Expr) -> Option<Object> {
+        let object = self.eval_expr_kind(&expr.kind);
+        if object.is_none() && self.error_handler.has_error() {
+            self.error_handler.attach_expr(expr);
         }
-
-        self.env = parent_scope;
-        None
+        object
     }
 
-    fn eval_expr(&mut self, expr: Expr) -> Option<Object> {
+    fn eval_expr_kind(&mut self, expr: &ExprKind) -> Option<Object> {
         match expr {
-            Expr::Literal(literal) => self.eval_literal_expr(literal),
-            Expr::Identifier(identifier) => self.resolve_identfier(identifier),
-            Expr::Call(func, args) => eval_call_expr(self, *func, args),
-            Expr::Infix(lhs, infix, rhs) => self.eval_infix_expr(*lhs, infix, *rhs),
-            Expr::Prefix(prefix, expr) => self.eval_prefix_expr(prefix, *expr),
-            Expr::Postfix(expr, postfix) => self.eval_postfix_expr(*expr, postfix),
-            Expr::Assign(identifier, expr) => self.eval_assign_expr(identifier, *expr),
+            ExprKind::Literal(literal) => self.eval_literal_expr(literal),
+            ExprKind::Identifier(identifier) => self.resolve_identfier(identifier),
+            ExprKind::Local(identifier, slot) => self.resolve_local(identifier, slot),
+            ExprKind::Call(func, args) => eval_call_expr(self, func, args),
+            ExprKind::Method(receiver, Identifier(name), args, optional) => {
+                eval_method_expr(self, receiver, name, args, *optional)
+            }
+            ExprKind::Infix(lhs, infix, rhs) => self.eval_infix_expr(lhs, infix, rhs),
+            ExprKind::Prefix(prefix, expr) => self.eval_prefix_expr(prefix, expr),
+            ExprKind::Postfix(expr, postfix) => self.eval_postfix_expr(expr, postfix),
+            ExprKind::Assign(identifier, expr) => self.eval_assign_expr(identifier, expr),
+            ExprKind::Index(target, index) => self.eval_index_expr(target, index),
+            ExprKind::IndexAssign(identifier, index, expr) => {
+                self.eval_index_assign_expr(identifier, index, expr)
+            }
+            ExprKind::Await(expr) => {
+                let value = self.eval_expr(expr)?;
+                self.await_value(value)
+            }
+            ExprKind::Cast(expr, type_) => {
+                let value = self.eval_expr(expr)?;
+                match casts::cast(value, &expr_type_to_object_type(type_)) {
+                    Ok(value) => Some(value),
+                    Err(error) => {
+                        self.error_handler.set_error(error.kind, error.msg);
+                        None
+                    }
+                }
+            }
         }
     }
 
-    fn eval_postfix_expr(&mut self, expr: Expr, postfix: Postfix) -> Option<Object> {
-        let evaluated_expr = match self.eval_expr(expr) {
-            Some(object) => object,
-            None => return None,
-        };
+    fn eval_postfix_expr(&mut self, expr: &Expr, postfix: &Postfix) -> Option<Object> {
+        let evaluated_expr = self.eval_expr(expr)?;
 
         let old_value = match evaluated_expr {
             Object::Int(val) => val,
             _ => {
-                self.error_handler.set_type_error(format!(
-                    "'{}' operation is only allowed for type 'number'",
-                    postfix
-                ));
+                self.error_handler
+                    .set_type_error(msg!("E011.postfix_number", op = postfix));
                 return None;
             }
         };
 
-        match postfix {
-            Postfix::Increment => Some(Object::Int(old_value + 1)),
-            Postfix::Decrement => Some(Object::Int(old_value - 1)),
-        }
+        let new_value = match postfix {
+            Postfix::Increment => self.int_arithmetic(old_value, 1, &Infix::Plus)?,
+            Postfix::Decrement => self.int_arithmetic(old_value, 1, &Infix::Minus)?,
+        };
+        Some(Object::Int(new_value))
     }
 
-    fn eval_prefix_expr(&mut self, prefix: Prefix, expr: Expr) -> Option<Object> {
-        let evaluated_expr = match self.eval_expr(expr) {
-            Some(object) => object,
-            None => return None,
-        };
+    fn eval_prefix_expr(&mut self, prefix: &Prefix, expr: &Expr) -> Option<Object> {
+        let evaluated_expr = self.eval_expr(expr)?;
 
         match prefix {
             Prefix::Not => self.eval_not_prefix(evaluated_expr),
@@ -215,61 +707,57 @@ impl Runtime {
         }
     }
 
-    fn eval_plus_prefix(&mut self, prefix: Prefix, evaluated_expr: Object) -> Option<Object> {
+    fn eval_plus_prefix(&mut self, prefix: &Prefix, evaluated_expr: Object) -> Option<Object> {
         match evaluated_expr {
             Object::Int(val) => Some(Object::Int(val)),
             Object::Float(val) => Some(Object::Float(val)),
             _ => {
                 self.error_handler
-                    .set_type_error(format!("'{}' prefix is for type number", prefix));
-                return None;
+                    .set_type_error(msg!("E011.prefix_number", op = prefix));
+                None
             }
         }
     }
 
-    fn eval_minus_prefix(&mut self, prefix: Prefix, evaluated_expr: Object) -> Option<Object> {
+    fn eval_minus_prefix(&mut self, prefix: &Prefix, evaluated_expr: Object) -> Option<Object> {
         match evaluated_expr {
-            Object::Int(val) => Some(Object::Int(-val)),
+            Object::Int(val) => self.int_arithmetic(0, val, &Infix::Minus).map(Object::Int),
             Object::Float(val) => Some(Object::Float(-val)),
             _ => {
                 self.error_handler
-                    .set_type_error(format!("'{}' prefix is for type number", prefix));
-                return None;
+                    .set_type_error(msg!("E011.prefix_number", op = prefix));
+                None
             }
         }
     }
 
-    fn eval_return(&mut self, expr: Option<Expr>) -> Option<Object> {
-        if expr.is_none() {
-            return Some(Object::RetVal(Box::new(Object::Null)));
-        }
-        match self.eval_expr(expr.unwrap()) {
-            Some(object) => Some(Object::RetVal(Box::new(object))),
-            None => None,
-        }
+    fn eval_return(&mut self, expr: Option<&Expr>) -> Option<Object> {
+        let object = match expr {
+            Some(expr) => self.eval_expr(expr)?,
+            None => Object::Null,
+        };
+        self.returning = true;
+        Some(object)
     }
 
-    fn eval_assign_expr(&mut self, identifier: Identifier, expr: Expr) -> Option<Object> {
+    fn eval_assign_expr(&mut self, identifier: &Identifier, expr: &Expr) -> Option<Object> {
         let Identifier(name) = identifier;
-        let old_value = match self.env.borrow().resolve(&name) {
+        let resolved = self.env.borrow().resolve(name);
+        let old_value = match resolved {
             Some(object) => object,
             None => {
-                self.error_handler
-                    .set_name_error(format!("'{}' is not declared", &name));
+                self.set_undeclared_error(name);
                 return None;
             }
         };
 
         if !old_value.is_assignable {
             self.error_handler
-                .set_name_error(format!("'{}' is not assignable", name));
+                .set_name_error(msg!("E010.not_assignable", name = name));
             return None;
         }
 
-        let new_value = match self.eval_expr(expr) {
-            Some(value) => value,
-            None => return None,
-        };
+        let new_value = self.eval_expr(expr)?;
 
         if let Type::Array(Some(old_array_items_type)) = old_value.type_ {
             self.assign_array(name, *old_array_items_type, new_value);
@@ -278,10 +766,12 @@ impl Runtime {
 
         let new_value_type = object_to_type(&new_value);
 
-        if old_value.type_ != new_value_type {
-            self.error_handler.set_type_error(format!(
-                "'{}' expects value of type '{}' but provided value of type '{}'",
-                name, old_value.type_, new_value_type,
+        if !old_value.type_.accepts(&new_value_type) {
+            self.error_handler.set_type_error(msg!(
+                "E011.assign_mismatch",
+                name = name,
+                expected = old_value.type_,
+                found = new_value_type,
             ));
             return None;
         }
@@ -292,38 +782,37 @@ impl Runtime {
 
     fn assign_array(
         &mut self,
-        name: String,
+        name: &str,
         old_array_items_type: Type,
         new_array: Object,
     ) -> Option<Object> {
         let new_array_items_type = match object_to_type(&new_array) {
             Type::Array(opt_type) => opt_type,
             _ => {
-                self.error_handler.set_type_error(format!(
-                    "'{}' expects value of type Array<{}>",
-                    name, old_array_items_type
+                self.error_handler.set_type_error(msg!(
+                    "E011.assign_array_items",
+                    name = name,
+                    expected = old_array_items_type
                 ));
                 return None;
             }
         };
 
         if new_array_items_type.is_none() {
-            self.env.borrow_mut().mutate(
-                name,
-                Object::Array {
-                    inner: FilipeArray::new(vec![]),
-                    items_type: Some(old_array_items_type),
-                },
-            );
+            self.env
+                .borrow_mut()
+                .mutate(name, Object::new_array(vec![], Some(old_array_items_type)));
             return None;
         }
 
         let new_array_items_type = *new_array_items_type.unwrap();
 
-        if &new_array_items_type != &old_array_items_type {
-            self.error_handler.set_type_error(format!(
-                "'{}' expects array of type '{}' but provided array of type '{}'",
-                name, old_array_items_type, new_array_items_type
+        if new_array_items_type != old_array_items_type {
+            self.error_handler.set_type_error(msg!(
+                "E011.assign_array_mismatch",
+                name = name,
+                expected = old_array_items_type,
+                found = new_array_items_type
             ));
             return None;
         }
@@ -332,97 +821,269 @@ impl Runtime {
         None
     }
 
-    fn eval_block_stmt(&mut self, block: &BlockStmt) -> Object {
-        for stmt in block {
-            if let Some(Object::RetVal(object)) = self.eval_stmt(stmt.clone()) {
-                return *object;
+    fn eval_index_expr(&mut self, target: &Expr, index: &Expr) -> Option<Object> {
+        let target = self.eval_expr(target)?;
+        let index = self.eval_index(index)?;
+        let Object::Array(array) = target else {
+            self.error_handler
+                .set_type_error(msg!("E011.not_indexable", found = object_to_type(&target)));
+            return None;
+        };
+        match array.get(index) {
+            Some(item) => Some(item.clone()),
+            None => {
+                self.set_index_out_of_range_error(index, array.len());
+                None
             }
         }
-        Object::Null
     }
 
-    fn eval_infix_expr(&mut self, lhs: Expr, infix: Infix, rhs: Expr) -> Option<Object> {
-        let lhs = self.eval_expr(lhs);
-        let rhs = self.eval_expr(rhs);
+    /// Arrays have value semantics: every binding holding the same array
+    /// shares it until one of them is written to, at which point that binding
+    /// gets its own copy (`make_mut`). An array nobody else holds is
+    /// updated in place.
+    fn eval_index_assign_expr(
+        &mut self,
+        identifier: &Identifier,
+        index: &Expr,
+        expr: &Expr,
+    ) -> Option<Object> {
+        let Identifier(name) = identifier;
+        let index = self.eval_index(index)?;
+        let new_value = self.eval_expr(expr)?;
+        let new_value_type = object_to_type(&new_value);
 
-        if lhs.is_none() || rhs.is_none() {
-            return None;
+        let outcome = self.env.borrow_mut().with_mut(name, |old| {
+            if !old.is_assignable {
+                return Err(IndexAssignError::NotAssignable);
+            }
+            let Object::Array(ref mut array) = old.value else {
+                return Err(IndexAssignError::NotAnArray(old.type_.clone()));
+            };
+            if let Some(items_type) = &array.items_type {
+                if !items_type.accepts(&new_value_type) {
+                    return Err(IndexAssignError::TypeMismatch(items_type.clone()));
+                }
+            }
+            if index >= array.len() {
+                return Err(IndexAssignError::OutOfRange(array.len()));
+            }
+            let copied = Shared::strong_count(array) > 1;
+            *Shared::make_mut(array).get_mut(index).unwrap() = new_value;
+            Ok(copied.then(|| array.len()))
+        });
+
+        match outcome {
+            None => self.set_undeclared_error(name),
+            Some(Ok(Some(copied_len))) => {
+                self.track_allocation(copied_len * std::mem::size_of::<Object>());
+            }
+            Some(Ok(None)) => {}
+            Some(Err(IndexAssignError::NotAssignable)) => self
+                .error_handler
+                .set_name_error(msg!("E010.not_assignable", name = name)),
+            Some(Err(IndexAssignError::NotAnArray(type_))) => self
+                .error_handler
+                .set_type_error(msg!("E011.not_indexable", found = type_)),
+            Some(Err(IndexAssignError::TypeMismatch(items_type))) => {
+                self.error_handler.set_type_error(msg!(
+                    "E011.item_mismatch",
+                    name = name,
+                    expected = items_type,
+                    found = new_value_type
+                ))
+            }
+            Some(Err(IndexAssignError::OutOfRange(len))) => {
+                self.set_index_out_of_range_error(index, len)
+            }
+        }
+        None
+    }
+
+    fn eval_index(&mut self, index: &Expr) -> Option<usize> {
+        match self.eval_expr(index)? {
+            Object::Int(val) if val >= 0 => Some(val as usize),
+            Object::Int(val) => {
+                self.error_handler.set_error(
+                    ErrorKind::ValueError,
+                    msg!("E013.negative_index", index = val),
+                );
+                None
+            }
+            other => {
+                self.error_handler
+                    .set_type_error(msg!("E011.index_type", found = object_to_type(&other)));
+                None
+            }
         }
+    }
 
-        let lhs = lhs.unwrap();
-        let rhs = rhs.unwrap();
+    fn set_index_out_of_range_error(&mut self, index: usize, len: usize) {
+        self.error_handler.set_error(
+            ErrorKind::ValueError,
+            msg!("E013.index_out_of_range", index = index, len = len),
+        );
+    }
 
+    /// Evaluates the statements of a block, stopping at the first `return`.
+    /// Only yields a value when returning, so enclosing blocks know to keep
+    /// unwinding.
+    fn eval_block_stmt(&mut self, block: &BlockStmt) -> Option<Object> {
+        for stmt in block {
+            let object = self.eval_stmt(stmt);
+            if self.error_handler.has_error() {
+                return None;
+            }
+            if self.returning {
+                return object;
+            }
+        }
+        None
+    }
+
+    fn eval_infix_expr(&mut self, lhs: &Expr, infix: &Infix, rhs: &Expr) -> Option<Object> {
+        let lhs = self.eval_expr(lhs)?;
+        let rhs = self.eval_expr(rhs)?;
+        self.apply_infix(lhs, infix, rhs)
+    }
+
+    fn apply_infix(&mut self, lhs: Object, infix: &Infix, rhs: Object) -> Option<Object> {
+        if let Infix::In = infix {
+            return self.contains(&rhs, &lhs).map(Object::Boolean);
+        }
+        if let Some(names) = type_names(&lhs, &rhs) {
+            return self.compare_type_names(names, infix);
+        }
         if object_to_type(&lhs) != object_to_type(&rhs) {
-            self.error_handler.set_type_error(format!(
-                "'{}' operation not allowed between types {} and {}",
-                infix,
-                object_to_type(&lhs),
-                object_to_type(&rhs),
+            self.error_handler.set_type_error(msg!(
+                "E011.infix_types",
+                op = infix,
+                lhs = object_to_type(&lhs),
+                rhs = object_to_type(&rhs),
             ));
             return None;
         }
 
-        match lhs {
-            Object::Int(lval) => {
-                if let Object::Int(rval) = rhs {
-                    return Some(self.eval_infix_int_expr(lval, infix, rval));
-                }
-                None
+        let object = match (lhs, rhs) {
+            (Object::Int(lval), Object::Int(rval)) => self.eval_infix_int_expr(lval, infix, rval),
+            (Object::Float(lval), Object::Float(rval)) => {
+                self.eval_infix_float_expr(lval, infix, rval)
             }
-            Object::Float(lval) => {
-                if let Object::Float(rval) = rhs {
-                    return Some(self.eval_infix_float_expr(lval, infix, rval));
-                }
-                None
+            (Object::String(lval), Object::String(rval)) => {
+                self.eval_infix_string_expr(&lval, infix, &rval)
             }
-            Object::String(lval) => {
-                if let Object::String(rval) = rhs {
-                    return Some(self.eval_infix_string_expr(lval, infix, rval));
-                }
-                None
+            (Object::Boolean(lval), Object::Boolean(rval)) => {
+                self.eval_infix_bool_expr(lval, infix, rval)
             }
-            Object::Boolean(lval) => {
-                if let Object::Boolean(rval) = rhs {
-                    return Some(self.eval_infix_bool_expr(lval, infix, rval));
-                }
-                None
-            }
-            _ => None,
+            _ => return None,
+        };
+
+        if self.error_handler.has_error() {
+            return None;
         }
+        Some(object)
     }
 
-    fn eval_infix_string_expr(&mut self, lhs: String, infix: Infix, rhs: String) -> Object {
+    /// `item in collection`. Strings hold the strings they contain.
+    fn contains(&mut self, collection: &Object, item: &Object) -> Option<bool> {
+        let found = match (collection, item) {
+            (Object::Set(set), _) => set.contains(item),
+            (Object::Array(array), _) => array.iter().any(|x| x.same_value(item)),
+            (Object::String(string), Object::String(part)) => string.contains(&**part),
+            (Object::String(_), _) => {
+                self.error_handler.set_type_error(msg!(
+                    "E011.infix_types",
+                    op = Infix::In,
+                    lhs = object_to_type(item),
+                    rhs = Type::String,
+                ));
+                return None;
+            }
+            (Object::Range { .. }, Object::Int(n)) => {
+                FilipeIterator::new(collection).is_some_and(|range| range.contains_int(*n))
+            }
+            (Object::Range { .. }, _) => false,
+            _ => {
+                self.error_handler
+                    .set_type_error(msg!("E011.in_type", found = object_to_type(collection)));
+                return None;
+            }
+        };
+        Some(found)
+    }
+
+    fn eval_infix_string_expr(&mut self, lhs: &str, infix: &Infix, rhs: &str) -> Object {
         match infix {
-            Infix::Plus => Object::String(lhs.clone() + &rhs),
+            Infix::Plus => {
+                if !self.track_allocation(lhs.len() + rhs.len()) {
+                    return Object::Null;
+                }
+                Object::String(format!("{lhs}{rhs}").into())
+            }
             Infix::NotEqual => Object::Boolean(lhs != rhs),
             Infix::Equal => Object::Boolean(lhs == rhs),
             _ => {
-                self.error_handler.set_type_error(format!(
-                    "'{}' operation not implemented for type string",
-                    infix
+                self.error_handler.set_type_error(msg!(
+                    "E011.infix_not_implemented",
+                    op = infix,
+                    found = Type::String
                 ));
                 Object::Null
             }
         }
     }
 
-    fn eval_infix_int_expr(&mut self, lhs_val: i64, infix: Infix, rhs_val: i64) -> Object {
+    fn eval_infix_int_expr(&mut self, lhs_val: i64, infix: &Infix, rhs_val: i64) -> Object {
         match infix {
-            Infix::Plus => Object::Int(lhs_val + rhs_val),
-            Infix::Minus => Object::Int(lhs_val - rhs_val),
-            Infix::Devide => Object::Int(lhs_val / rhs_val),
-            Infix::Multiply => Object::Int(lhs_val * rhs_val),
-            Infix::Remainder => Object::Int(lhs_val % rhs_val),
+            Infix::Plus | Infix::Minus | Infix::Devide | Infix::Multiply | Infix::Remainder => self
+                .int_arithmetic(lhs_val, rhs_val, infix)
+                .map_or(Object::Null, Object::Int),
             Infix::Equal => Object::Boolean(lhs_val == rhs_val),
             Infix::LessThan => Object::Boolean(lhs_val < rhs_val),
             Infix::LessOrEqual => Object::Boolean(lhs_val <= rhs_val),
             Infix::GratherThan => Object::Boolean(lhs_val > rhs_val),
             Infix::GratherOrEqual => Object::Boolean(lhs_val >= rhs_val),
             Infix::NotEqual => Object::Boolean(lhs_val != rhs_val),
+            Infix::In => unreachable!("'in' is handled by `contains`"),
+        }
+    }
+
+    /// Integer arithmetic that reports overflow and division by zero as
+    /// runtime errors, or wraps around when wrapping mode is on.
+    fn int_arithmetic(&mut self, lhs_val: i64, rhs_val: i64, infix: &Infix) -> Option<i64> {
+        if rhs_val == 0 && matches!(infix, Infix::Devide | Infix::Remainder) {
+            self.error_handler.set_error(
+                ErrorKind::DivisionByZero,
+                msg!(
+                    "E017.divides_by_zero",
+                    lhs = lhs_val,
+                    op = infix,
+                    rhs = rhs_val
+                ),
+            );
+            return None;
         }
+
+        let (value, overflowed) = match infix {
+            Infix::Plus => lhs_val.overflowing_add(rhs_val),
+            Infix::Minus => lhs_val.overflowing_sub(rhs_val),
+            Infix::Multiply => lhs_val.overflowing_mul(rhs_val),
+            Infix::Devide => lhs_val.overflowing_div(rhs_val),
+            Infix::Remainder => lhs_val.overflowing_rem(rhs_val),
+            _ => unreachable!("'{}' is not an arithmetic operator", infix),
+        };
+
+        if overflowed && !self.wrapping_arithmetic {
+            self.error_handler.set_error(
+                ErrorKind::OverflowError,
+                msg!("E016.overflow", lhs = lhs_val, op = infix, rhs = rhs_val),
+            );
+            return None;
+        }
+        Some(value)
     }
 
-    fn eval_infix_float_expr(&mut self, lhs_val: f64, infix: Infix, rhs_val: f64) -> Object {
+    fn eval_infix_float_expr(&mut self, lhs_val: f64, infix: &Infix, rhs_val: f64) -> Object {
         match infix {
             Infix::Plus => Object::Float(lhs_val + rhs_val),
             Infix::Minus => Object::Float(lhs_val - rhs_val),
@@ -435,87 +1096,496 @@ impl Runtime {
             Infix::GratherThan => Object::Boolean(lhs_val > rhs_val),
             Infix::GratherOrEqual => Object::Boolean(lhs_val >= rhs_val),
             Infix::NotEqual => Object::Boolean(lhs_val != rhs_val),
+            Infix::In => unreachable!("'in' is handled by `contains`"),
         }
     }
 
-    fn eval_infix_bool_expr(&mut self, lhs_val: bool, infix: Infix, rhs_val: bool) -> Object {
+    /// `typeof(x) == "int"`: types are equal to their names.
+    fn compare_type_names(
+        &mut self,
+        (lhs, rhs): (String, String),
+        infix: &Infix,
+    ) -> Option<Object> {
+        match infix {
+            Infix::Equal => Some(Object::Boolean(lhs == rhs)),
+            Infix::NotEqual => Some(Object::Boolean(lhs != rhs)),
+            _ => {
+                self.error_handler.set_type_error(msg!(
+                    "E011.infix_not_implemented",
+                    op = infix,
+                    found = Type::TypeAnnot
+                ));
+                None
+            }
+        }
+    }
+
+    fn eval_infix_bool_expr(&mut self, lhs_val: bool, infix: &Infix, rhs_val: bool) -> Object {
         match infix {
             Infix::Equal => Object::Boolean(lhs_val == rhs_val),
-            Infix::LessThan => Object::Boolean(lhs_val < rhs_val),
+            Infix::LessThan => Object::Boolean(!lhs_val & rhs_val),
             Infix::LessOrEqual => Object::Boolean(lhs_val <= rhs_val),
-            Infix::GratherThan => Object::Boolean(lhs_val > rhs_val),
+            Infix::GratherThan => Object::Boolean(lhs_val & !rhs_val),
             Infix::GratherOrEqual => Object::Boolean(lhs_val >= rhs_val),
             Infix::NotEqual => Object::Boolean(lhs_val != rhs_val),
             _ => {
-                self.error_handler.set_type_error(format!(
-                    "'{}' operation not implemented for type boolean",
-                    infix
+                self.error_handler.set_type_error(msg!(
+                    "E011.infix_not_implemented",
+                    op = infix,
+                    found = Type::Boolean
                 ));
                 Object::Null
             }
         }
     }
 
-    fn eval_literal_expr(&mut self, literal: Literal) -> Option<Object> {
+    fn eval_literal_expr(&mut self, literal: &Literal) -> Option<Object> {
         match literal {
-            Literal::String(val) => Some(Object::String(val)),
-            Literal::Boolean(val) => Some(Object::Boolean(val)),
+            Literal::String(val) => {
+                if !self.track_allocation(val.len()) {
+                    return None;
+                }
+                Some(Object::String(val.as_str().into()))
+            }
+            Literal::Boolean(val) => Some(Object::Boolean(*val)),
             Literal::Null => Some(Object::Null),
-            Literal::Int(val) => Some(Object::Int(val)),
-            Literal::Float(val) => Some(Object::Float(val)),
+            Literal::Int(val) => Some(Object::Int(*val)),
+            Literal::Float(val) => Some(Object::Float(*val)),
             Literal::Array(val) => self.eval_array_literal(val),
         }
     }
 
-    fn eval_array_literal(&mut self, array_literal: Vec<Expr>) -> Option<Object> {
+    fn eval_array_literal(&mut self, array_literal: &[Expr]) -> Option<Object> {
         if array_literal.is_empty() {
-            return Some(Object::Array {
-                inner: FilipeArray::new(vec![]),
-                items_type: None,
-            });
+            return Some(Object::new_array(vec![], None));
         }
 
-        let mut expr_array = array_literal.to_owned();
-        let first_item = match self.eval_expr(expr_array.remove(0)) {
-            Some(object) => object,
-            None => return None,
-        };
+        let first_item = self.eval_expr(&array_literal[0])?;
 
         let first_item_type = object_to_type(&first_item);
 
         let mut objects: Vec<Object> = vec![];
         objects.push(first_item);
 
-        for expr in expr_array {
-            let item = match self.eval_expr(expr) {
-                Some(obj) => obj,
-                None => return None,
-            };
+        for expr in &array_literal[1..] {
+            let item = self.eval_expr(expr)?;
 
             if first_item_type != object_to_type(&item) {
                 self.error_handler
-                    .set_type_error("Array item's type mismatch".to_string());
+                    .set_type_error(msg!("E011.array_items_mismatch"));
                 return None;
             }
             objects.push(item);
         }
 
-        return Some(Object::Array {
-            inner: FilipeArray::new(objects),
-            items_type: Some(first_item_type),
-        });
+        if !self.track_allocation(objects.len() * std::mem::size_of::<Object>()) {
+            return None;
+        }
+        Some(Object::new_array(objects, Some(first_item_type)))
     }
 
-    fn resolve_identfier(&mut self, identifier: Identifier) -> Option<Object> {
+    fn resolve_identfier(&mut self, identifier: &Identifier) -> Option<Object> {
         let Identifier(name) = identifier;
-        let meta_object = match self.env.borrow().resolve(&name) {
+        let resolved = self.env.borrow().resolve(name);
+        let meta_object = match resolved {
             Some(meta_object) => meta_object,
             None => {
-                self.error_handler
-                    .set_name_error(format!("'{}' is not declared", &name));
+                self.set_undeclared_error(name);
                 return None;
             }
         };
         Some(meta_object.value)
     }
+
+    fn resolve_local(&mut self, identifier: &Identifier, slot: &Slot) -> Option<Object> {
+        let meta_object = self.env.borrow().resolve_slot(slot.depth, slot.index);
+        match meta_object {
+            Some(meta_object) => Some(meta_object.value),
+            None => self.resolve_identfier(identifier),
+        }
+    }
+}
+
+/// The names of two types, or of a type and the string it is compared to.
+fn type_names(lhs: &Object, rhs: &Object) -> Option<(String, String)> {
+    match (lhs, rhs) {
+        (Object::Type(lhs), Object::Type(rhs)) => Some((lhs.to_string(), rhs.to_string())),
+        (Object::Type(type_), Object::String(name)) => Some((type_.to_string(), name.to_string())),
+        (Object::String(name), Object::Type(type_)) => Some((name.to_string(), type_.to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::context::Context;
+    use super::flstdlib::builtins;
+    use super::object::Object;
+    use super::runtime_error::{ErrorKind, RuntimeError};
+    use super::{Program, Runtime};
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+    use crate::shared::{Lock, Shared};
+
+    fn parse(input: &str) -> Program {
+        let input = input.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        assert!(!p.has_error());
+        program
+    }
+
+    fn run(input: &str, max_steps: Option<u64>) -> (Runtime, Result<Object, RuntimeError>) {
+        let program = parse(input);
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_max_steps(max_steps);
+        let output = runtime.eval(program);
+        (runtime, output)
+    }
+
+    #[test]
+    fn test_max_steps_stops_long_loops() {
+        let error = run("for x in range(0, 1000000000) { x }", Some(100))
+            .1
+            .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::ExecutionLimitExceeded));
+
+        let (_, output) = run("for x in range(0, 10) { x }", Some(100));
+        assert!(output.is_ok());
+
+        // empty bodies have no statements, but each iteration is a step
+        let error = run("for x in range(0, 1000000000) {}", Some(100))
+            .1
+            .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::ExecutionLimitExceeded));
+
+        // the budget is per run, not for the runtime's whole life
+        let mut runtime = Runtime::builder().max_steps(50).build();
+        runtime.run_source("let x = 1").unwrap();
+        for _ in 0..100 {
+            runtime.run_source("x = x + 1").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_call_depth_stops_runaway_recursion() {
+        // the main thread's stack, which the default depth is meant for
+        let error = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let mut runtime = Runtime::builder()
+                    .max_call_depth(super::DEFAULT_MAX_CALL_DEPTH)
+                    .build();
+                runtime
+                    .run_source("define f(n: int): int {\n  return f(n + 1)\n}\nf(0)")
+                    .unwrap_err()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(error.code, "E014");
+        assert_eq!(error.msg, "Calls nested deeper than the limit of 400");
+
+        // without a limit, recursion goes as deep as the stack lets it
+        let input = "define sum(n: int): int {\n  if n == 0 { return 0 }\n  return n + sum(n - 1)\n}\nsum(1000)";
+        let sum = std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(|| run(input, None).1.unwrap().to_string())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(sum, "500500");
+    }
+
+    #[test]
+    fn test_memory_limit_stops_runaway_allocations() {
+        let input = "let s = \"x\"\nfor i in range(0, 64) { s = s + s }";
+        let input = input.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_memory_limit(Some(1 << 20));
+        let error = runtime.eval(program).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::MemoryLimitExceeded));
+    }
+
+    #[test]
+    fn test_locals_resolved_through_nested_scopes() {
+        let input = r#"
+define weigh(n: int): int {
+    let total = 0
+    for i in range(0, n) {
+        let doubled = i * 2
+        if doubled > 4 {
+            let extra = doubled + total
+            total = extra
+        }
+    }
+    if total > 10 {
+        return total
+    }
+    return 0
+}
+weigh(5)
+"#;
+        let (_, output) = run(input, None);
+        assert!(matches!(output, Ok(Object::Int(14))));
+    }
+
+    #[test]
+    fn test_arrays_are_copied_on_write() {
+        let input = r#"
+let a: Array<int> = [1, 2, 3]
+let b: Array<int> = a
+b[0] = 10
+define setFirst(xs: Array<int>): void {
+    xs[0] = 99
+}
+setFirst(a)
+a[1] = 20
+let both = [a, b]
+both
+"#;
+        let (_, output) = run(input, None);
+        assert_eq!(output.unwrap().to_string(), "[[1, 20, 3], [10, 2, 3]]");
+    }
+
+    #[test]
+    fn test_restore_brings_back_globals() {
+        let (mut runtime, _) = run("let xs = [1, 2]\nlet it = iter(xs)\nnext(it)", None);
+        let snapshot = runtime.snapshot();
+
+        let output = runtime.eval(parse("xs[0] = 5\nnext(it)\nlet extra = 1"));
+        assert!(output.is_ok());
+        runtime.restore(&snapshot);
+
+        let output = runtime.eval(parse("let extra = next(it)\nlet out = [xs[0], extra]\nout"));
+        assert_eq!(output.unwrap().to_string(), "[1, 2]");
+    }
+
+    #[test]
+    fn test_int_overflow_and_division_by_zero() {
+        let error = run("9223372036854775807 + 1", None).1.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::OverflowError));
+
+        let error = run("10 % 0", None).1.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DivisionByZero));
+
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_wrapping_arithmetic(true);
+        let output = runtime.eval(parse("9223372036854775807 + 1"));
+        assert!(matches!(output, Ok(Object::Int(i64::MIN))));
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_runtime_moves_to_another_thread() {
+        let (runtime, _) = run("let xs = [1, 2, 3]", None);
+        let handle = std::thread::spawn(move || {
+            let mut runtime = runtime;
+            runtime.eval(parse("let ys = xs\nys[0] = 5\nlet out = [xs, ys]\nout"))
+        });
+        let output = handle.join().unwrap();
+        assert_eq!(output.unwrap().to_string(), "[[1, 2, 3], [5, 2, 3]]");
+    }
+
+    #[test]
+    fn test_seeded_random_is_reproducible() {
+        let input = "let out = [random(1000000), random(1000000), random(1000000)]\nout";
+        let outputs = (0..2).map(|_| {
+            let (mut runtime, _) = run("null", None);
+            runtime.set_random_seed(42);
+            runtime.eval(parse(input)).unwrap().to_string()
+        });
+        let outputs = outputs.collect::<Vec<_>>();
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn test_errors_point_at_the_failing_expression() {
+        let (_, output) = run("let x = 1\n\nlet y = [x]\nprint(y[0] + \"s\")", None);
+        let error = output.unwrap_err();
+        let span = error.span.unwrap();
+        assert_eq!((span.line, span.col), (4, 12));
+        assert_eq!(error.expr.as_deref(), Some("y[0] + \"s\""));
+    }
+
+    #[test]
+    fn test_assert_shows_the_failing_values() {
+        let input =
+            "let items = [1, 2, 3]\nlet x = 10\nassert len(items) == 3\nassert x < len(items)";
+        let error = run(input, None).1.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::AssertionError));
+        assert_eq!(
+            error.msg,
+            "assertion failed: x < len(items) (x = 10, len(items) = 3)"
+        );
+    }
+
+    #[test]
+    fn test_errors_carry_the_call_trace() {
+        let input = "define inner(): int {\n  return 1 / 0\n}\ndefine outer(): int {\n  return inner()\n}\nouter()";
+        let (runtime, output) = run(input, None);
+        let error = output.unwrap_err();
+        let trace = error
+            .trace
+            .iter()
+            .map(|frame| (frame.function.as_str(), frame.call_site.line))
+            .collect::<Vec<_>>();
+        assert_eq!(trace, [("inner", 5), ("outer", 7)]);
+        assert!(runtime.call_stack.is_empty());
+    }
+
+    #[test]
+    fn test_missing_return_names_the_function() {
+        let input = "define sign(x: int): int {\n  if x < 0 {\n    return -1\n  }\n}\nsign(1)";
+        let error = run(input, None).1.unwrap_err();
+        assert_eq!(
+            error.msg,
+            "Function 'sign' ended without returning a value of type 'int'"
+        );
+    }
+
+    #[test]
+    fn test_union_types_take_any_of_their_types() {
+        let input = "define describe(x: int | string): string {\n\
+                     \x20 if typeof(x) == \"int\" {\n\
+                     \x20   return \"number\"\n\
+                     \x20 }\n\
+                     \x20 return x\n\
+                     }\n\
+                     let y: int | string = 1\n\
+                     y = \"word\"\n\
+                     describe(1) + \" \" + describe(y)";
+        let output = run(input, None).1.unwrap();
+        assert_eq!(output.to_string(), "'number word'");
+
+        let error = run("let y: int | string = 1\ny = true", None)
+            .1
+            .unwrap_err();
+        assert_eq!(
+            error.msg,
+            "'y' expects value of type 'int | string' but provided value of type 'boolean'"
+        );
+    }
+
+    #[test]
+    fn test_inferred_types_run_like_declared_ones() {
+        let input = "define fib(n: int) {\n\
+                     \x20 if n < 2 {\n\
+                     \x20   return n\n\
+                     \x20 }\n\
+                     \x20 return fib(n - 1) + fib(n - 2)\n\
+                     }\n\
+                     define total(xs: Array<int>): int {\n\
+                     \x20 let sum = 0\n\
+                     \x20 for x in xs {\n\
+                     \x20   sum = sum + x\n\
+                     \x20 }\n\
+                     \x20 return sum\n\
+                     }\n\
+                     let xs = []\n\
+                     let empty = total(xs)\n\
+                     xs = [fib(10), 1]\n\
+                     total(xs) + empty";
+        let (mut runtime, output) = run(input, None);
+        assert_eq!(output.unwrap().to_string(), "56");
+        let error = runtime.eval(parse("xs = [\"a\"]")).unwrap_err();
+        assert_eq!(
+            error.msg,
+            "'xs' expects array of type 'int' but provided array of type 'string'"
+        );
+
+        let input = "define half(n: int) {\n  return n / 2\n}\nhalf(3)";
+        assert_eq!(run(input, None).1.unwrap().to_string(), "1");
+        let error = run("define loop() {\n  return loop()\n}", None)
+            .1
+            .unwrap_err();
+        assert_eq!(
+            error.msg,
+            "Can't infer the return type of 'loop', annotate it"
+        );
+    }
+
+    #[test]
+    fn test_optional_chaining_stops_at_null() {
+        let input = "let calls = 0\n\
+                     define shout(s: string, times: int): string {\n\
+                     \x20 calls = calls + 1\n\
+                     \x20 return s + \"!\"\n\
+                     }\n\
+                     define count(): int {\n\
+                     \x20 calls = calls + 10\n\
+                     \x20 return 1\n\
+                     }\n\
+                     let name: string\n\
+                     let skipped = name?.shout(count())\n\
+                     let shouted = \"hi\"?.shout(1)?.shout(2)\n\
+                     skipped";
+        let (mut runtime, output) = run(input, None);
+        assert_eq!(output.unwrap().to_string(), "null");
+        let shouted = runtime.eval(parse("shouted")).unwrap();
+        assert_eq!(shouted.to_string(), "'hi!!'");
+        let calls = runtime.eval(parse("calls")).unwrap();
+        assert_eq!(calls.to_string(), "2");
+
+        let error = run("let name: string\nname.len()", None).1.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::TypeError));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        for input in ["if 1 {\n  print(1)\n}", "let x: int"] {
+            assert!(run(input, None).1.is_ok());
+
+            let env = Context::make_global(builtins());
+            let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+            runtime.set_strict(true);
+            let error = runtime.eval(parse(input)).unwrap_err();
+            assert!(matches!(error.kind, ErrorKind::TypeError));
+        }
+    }
+
+    #[test]
+    fn test_redeclaring_points_at_the_first_declaration() {
+        let error = run("let x = 1\nlet y = 2\nlet x = 3", None).1.unwrap_err();
+        assert_eq!(error.msg, "'x' is already declared in this scope");
+        assert_eq!(error.notes, ["'x' was first declared at 1:1"]);
+
+        let error = run("define len(): int { return 0 }", None).1.unwrap_err();
+        assert_eq!(error.notes, ["'len' is a builtin"]);
+
+        // inner scopes may shadow
+        let (_, output) = run("let x = 1\nif true { let x = 2 }\nx", None);
+        assert!(matches!(output, Ok(Object::Int(1))));
+    }
+
+    #[test]
+    fn test_undeclared_names_suggest_the_closest_one() {
+        let error = run("let total = 1\nprint(totl)", None).1.unwrap_err();
+        assert_eq!(error.msg, "'totl' is not declared; did you mean 'total'?");
+
+        let error = run("prnt(1)", None).1.unwrap_err();
+        assert_eq!(error.msg, "'prnt' is not declared; did you mean 'print'?");
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let input = "define check(age: int): int {\n  if age < 0 {\n    raise(error(\"InvalidAge\", \"negative\"))\n  }\n  return age\n}\nlet caught = \"\"\ntry {\n  check(-1)\n} catch e {\n  caught = errorKind(e)\n}\ntry {\n  caught = caught + 1 / 0\n} catch e {\n  caught = caught + \" \" + errorKind(e)\n}\ncaught";
+        let (_, output) = run(input, None);
+        assert_eq!(output.unwrap().to_string(), "'InvalidAge DivisionByZero'");
+
+        let error = run(
+            "try {\n  for i in range(0, 100) {\n    i\n  }\n} catch e {\n  e\n}",
+            Some(50),
+        )
+        .1
+        .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::ExecutionLimitExceeded));
+    }
 }
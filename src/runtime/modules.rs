@@ -0,0 +1,226 @@
+//! `import "name"`: runs another script once, in the global scope, so its
+//! functions and variables can be used by the importing one.
+//!
+//! A name that is a path, like `"./shapes"` or `"lib/shapes.fl"`, is found
+//! next to the importing script, with or without `.fl`. Any other name is a
+//! package, looked for in `fl_modules/<name>` in the script's directory or
+//! the nearest one above it, where `filipe add` puts packages, and run from
+//! its entry point, see `project`. A package that isn't there is looked
+//! for in the search paths, e.g. the `paths` of a project's `[run]` table,
+//! as `<path>/<name>.fl` or a package in `<path>/<name>`.
+//!
+//! Before all that, the name is looked up in the native modules of the
+//! program embedding filipe, see `Runtime::register_module`.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+
+use super::native::NativeModule;
+use super::object::Object;
+use super::permissions::Capability;
+use super::runtime_error::ErrorKind;
+use super::Runtime;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::frontend::token::Span;
+use crate::messages::msg;
+use crate::project::entry_point;
+use crate::utils::SCRIPT_EXTENSION;
+
+pub const MODULES_DIR: &str = "fl_modules";
+
+pub(super) type Loaded = HashSet<PathBuf>;
+
+#[derive(Clone, Default)]
+pub struct Modules {
+    /// Directory of the script being run, which its imports are relative to.
+    dir: PathBuf,
+    /// Modules already run, so each runs once however often it is imported.
+    pub(super) loaded: Loaded,
+    /// Where packages are looked for after `fl_modules`.
+    search_paths: Vec<PathBuf>,
+    /// Modules of the embedding program, by name.
+    pub(super) native: HashMap<String, NativeModule>,
+}
+
+/// The file `import name` loads from a script in `dir`.
+pub fn resolve(dir: &Path, search_paths: &[PathBuf], name: &str) -> Option<PathBuf> {
+    let is_path = name.starts_with('.')
+        || name.contains('/')
+        || name.ends_with(&format!(".{SCRIPT_EXTENSION}"));
+    if is_path {
+        let path = dir
+            .join(name)
+            .components()
+            .filter(|part| *part != Component::CurDir)
+            .collect::<PathBuf>();
+        return [path.with_extension(SCRIPT_EXTENSION), path]
+            .into_iter()
+            .find(|path| path.is_file());
+    }
+    let installed = dir
+        .ancestors()
+        .map(|dir| dir.join(MODULES_DIR).join(name))
+        .find(|package| package.is_dir());
+    if let Some(package) = installed {
+        return Some(entry_point(&package)).filter(|entry| entry.is_file());
+    }
+    search_paths.iter().find_map(|search_path| {
+        let path = search_path.join(name);
+        let script = match path.is_dir() {
+            true => entry_point(&path),
+            false => path.with_extension(SCRIPT_EXTENSION),
+        };
+        script.is_file().then_some(script)
+    })
+}
+
+impl Runtime {
+    /// Makes imports relative to the script at `path`.
+    pub fn set_script_path(&mut self, path: &str) {
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        self.modules.dir = dir.to_path_buf();
+    }
+
+    /// Directories to look for packages in after `fl_modules`.
+    pub fn set_import_paths(&mut self, paths: Vec<PathBuf>) {
+        self.modules.search_paths = paths;
+    }
+
+    pub(super) fn eval_import_stmt(&mut self, name: &str, span: Span) -> Option<Object> {
+        if self.import_native(name) {
+            return None;
+        }
+        // scripts are read from disk, so importing them takes `fs` too
+        for capability in [Capability::Import, Capability::Fs] {
+            if !self.permissions.allows(capability) {
+                log::debug!("{} denied", capability);
+                self.error_handler
+                    .set_error(ErrorKind::PermissionError, msg!("E021.import", name = name));
+                return None;
+            }
+        }
+        self.import_script(name, span);
+        None
+    }
+
+    fn import_script(&mut self, name: &str, span: Span) {
+        let Some(path) = resolve(&self.modules.dir, &self.modules.search_paths, name) else {
+            self.error_handler
+                .set_error(ErrorKind::ImportError, msg!("E020.not_found", name = name));
+            return;
+        };
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if !self.modules.loaded.insert(key) {
+            return;
+        }
+        let display = path.to_string_lossy().to_string();
+        log::debug!("importing '{}' from {}", name, display);
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) => {
+                let msg = msg!("E020.unreadable", path = display, error = err);
+                self.error_handler.set_error(ErrorKind::ImportError, msg);
+                return;
+            }
+        };
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        if let Some(error) = p.errors().first() {
+            let msg = msg!(
+                "E020.syntax",
+                path = display,
+                span = error.span,
+                msg = error.msg
+            );
+            self.error_handler.set_error(ErrorKind::ImportError, msg);
+            return;
+        }
+
+        // its lines aren't the script's, so it isn't stepped through or
+        // counted towards coverage
+        let debugger = self.debugger.take();
+        let coverage = self.coverage.take();
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let importer_dir = mem::replace(&mut self.modules.dir, dir);
+        for stmt in &program {
+            self.eval_stmt(stmt);
+            self.returning = false;
+            if self.error_handler.has_error() {
+                self.error_handler
+                    .relocate(span, |at| msg!("E020.raised_in", path = display, span = at));
+                break;
+            }
+        }
+        self.modules.dir = importer_dir;
+        self.debugger = debugger;
+        self.coverage = coverage;
+    }
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+    use crate::frontend::ast::Program;
+    use crate::runtime::{context::Context, flstdlib::builtins};
+    use crate::shared::{Lock, Shared};
+
+    fn parse(source: &str) -> Program {
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        Parser::new(&mut l).parse()
+    }
+
+    #[test]
+    fn test_imports_run_modules_once() {
+        let dir = std::env::temp_dir().join("filipe_import_test");
+        let package = dir.join(MODULES_DIR).join("greetings");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(
+            package.join("main.fl"),
+            "import \"./words\"\n\
+             define greet(name: string): string {\n\
+             \x20 return hello + \", \" + name\n\
+             }",
+        )
+        .unwrap();
+        fs::write(package.join("words.fl"), "let hello = \"Hello\"").unwrap();
+
+        let program = parse(
+            "import \"greetings\"\n\
+             import \"greetings\"\n\
+             greet(\"Ana\")",
+        );
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_script_path(&dir.join("main.fl").to_string_lossy());
+        let value = runtime.eval(program).map(|value| value.to_string());
+
+        let error = runtime.eval(parse("import \"nope\"")).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(value.unwrap(), "'Hello, Ana'");
+        assert_eq!(error.msg, "module 'nope' not found");
+    }
+
+    #[test]
+    fn test_restore_forgets_imports_made_since_the_snapshot() {
+        let dir = std::env::temp_dir().join("filipe_snapshot_import_test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("answer.fl"), "let answer = 42").unwrap();
+
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_script_path(&dir.join("main.fl").to_string_lossy());
+        let snapshot = runtime.snapshot();
+        runtime.eval(parse("import \"./answer\"")).unwrap();
+        runtime.restore(&snapshot);
+        let value = runtime.eval(parse("import \"./answer\"\nanswer"));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(value.unwrap().to_string(), "42");
+    }
+}
@@ -0,0 +1,320 @@
+//! Functions the program embedding filipe gives its scripts. Unlike the
+//! builtins they are closures, so they can hold on to the host's state:
+//!
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//! use filipe::{Runtime, Value};
+//!
+//! let calls = Arc::new(AtomicUsize::new(0));
+//! let counter = Arc::clone(&calls);
+//! let mut runtime = Runtime::builder().build();
+//! runtime.register_fn("double", move |args| {
+//!     args.expect_count(1)?;
+//!     counter.fetch_add(1, Ordering::Relaxed);
+//!     Ok(Value::Int(args.int(0)? * 2))
+//! });
+//! assert_eq!(runtime.run_source("double(21)").unwrap().to_string(), "42");
+//! assert_eq!(calls.load(Ordering::Relaxed), 1);
+//! ```
+//!
+//! With the `sync` feature they must be `Send + Sync` too.
+
+use std::fmt;
+
+use super::convert::{FromFilipe, ToFilipe};
+use super::object::{Object, ObjectInfo, Value};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::stdlib::{set::FilipeSet, FilipeArray};
+use super::type_system::{object_to_type, Type};
+use super::userdata::UserType;
+use super::Runtime;
+use crate::messages::{count_args, msg};
+use crate::shared::Shared;
+
+/// What a function registered with `Runtime::register_fn` can be.
+#[cfg(not(feature = "sync"))]
+pub trait NativeFn: Fn(&Args) -> Result<Value, RuntimeError> {}
+#[cfg(not(feature = "sync"))]
+impl<F: Fn(&Args) -> Result<Value, RuntimeError>> NativeFn for F {}
+
+/// What a function registered with `Runtime::register_fn` can be.
+#[cfg(feature = "sync")]
+pub trait NativeFn: Fn(&Args) -> Result<Value, RuntimeError> + Send + Sync {}
+#[cfg(feature = "sync")]
+impl<F: Fn(&Args) -> Result<Value, RuntimeError> + Send + Sync> NativeFn for F {}
+
+pub struct NativeFunction {
+    pub name: String,
+    pub function: Box<dyn NativeFn>,
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+/// The arguments a native function was called with, with getters that
+/// raise an `ArgumentError` naming the function when one is missing or of
+/// another type.
+pub struct Args<'a> {
+    name: &'a str,
+    args: &'a [ObjectInfo],
+    /// How many arguments of the call come before these, for messages.
+    offset: usize,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(name: &'a str, args: &'a [ObjectInfo]) -> Self {
+        Self {
+            name,
+            args,
+            offset: 0,
+        }
+    }
+
+    /// The arguments after the first one.
+    pub fn rest(&self) -> Args<'a> {
+        Args {
+            name: self.name,
+            args: self.args.get(1..).unwrap_or_default(),
+            offset: self.offset + 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// Fails unless there are exactly `count` arguments.
+    pub fn expect_count(&self, count: usize) -> Result<(), RuntimeError> {
+        self.expect_count_between(count, Some(count))
+    }
+
+    /// Fails unless there are at least `min` arguments and, with a `max`,
+    /// at most that many.
+    pub fn expect_count_between(&self, min: usize, max: Option<usize>) -> Result<(), RuntimeError> {
+        let len = self.args.len();
+        if len >= min && max.is_none_or(|max| len <= max) {
+            return Ok(());
+        }
+        Err(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!(
+                "E012.arg_count",
+                name = self.name,
+                expected = count_args(min, max),
+                found = len
+            ),
+        ))
+    }
+
+    /// The argument at `index`, if there is one.
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.args.get(index).map(|arg| &arg.value)
+    }
+
+    pub fn value(&self, index: usize) -> Result<&Value, RuntimeError> {
+        self.get(index).ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::ArgumentError,
+                msg!(
+                    "E012.arg_missing",
+                    name = self.name,
+                    index = self.offset + index + 1
+                ),
+            )
+        })
+    }
+
+    pub fn int(&self, index: usize) -> Result<i64, RuntimeError> {
+        match self.value(index)? {
+            Object::Int(val) => Ok(*val),
+            other => Err(self.type_error(index, Type::Int, other)),
+        }
+    }
+
+    /// A float, or an int converted to one.
+    pub fn float(&self, index: usize) -> Result<f64, RuntimeError> {
+        match self.value(index)? {
+            Object::Float(val) => Ok(*val),
+            Object::Int(val) => Ok(*val as f64),
+            other => Err(self.type_error(index, Type::Float, other)),
+        }
+    }
+
+    pub fn boolean(&self, index: usize) -> Result<bool, RuntimeError> {
+        match self.value(index)? {
+            Object::Boolean(val) => Ok(*val),
+            other => Err(self.type_error(index, Type::Boolean, other)),
+        }
+    }
+
+    pub fn string(&self, index: usize) -> Result<&str, RuntimeError> {
+        match self.value(index)? {
+            Object::String(val) => Ok(val),
+            other => Err(self.type_error(index, Type::String, other)),
+        }
+    }
+
+    pub fn array(&self, index: usize) -> Result<&FilipeArray, RuntimeError> {
+        match self.value(index)? {
+            Object::Array(val) => Ok(val),
+            other => Err(self.type_error(index, Type::Array(None), other)),
+        }
+    }
+
+    pub fn set(&self, index: usize) -> Result<&FilipeSet, RuntimeError> {
+        match self.value(index)? {
+            Object::Set(val) => Ok(val),
+            other => Err(self.type_error(index, Type::Set, other)),
+        }
+    }
+
+    /// The value of the embedding program at `index`, if it is a `T`.
+    pub fn user_data<T: UserType>(&self, index: usize) -> Result<&T, RuntimeError> {
+        let value = self.value(index)?;
+        let data = match value {
+            Object::UserData(data) => data.downcast_ref::<T>(),
+            _ => None,
+        };
+        data.ok_or_else(|| self.type_error(index, Type::UserData(T::type_name()), value))
+    }
+
+    /// The argument at `index` converted to `T`, e.g. `args.get_as::<Vec<i64>>(0)`.
+    pub fn get_as<T: FromFilipe>(&self, index: usize) -> Result<T, RuntimeError> {
+        let value = self.value(index)?;
+        T::from_filipe(value).map_err(|err| self.type_error(index, err.expected, value))
+    }
+
+    pub(super) fn type_error(&self, index: usize, expected: Type, found: &Object) -> RuntimeError {
+        RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!(
+                "E012.arg_type",
+                name = self.name,
+                index = self.offset + index + 1,
+                expected = expected,
+                found = object_to_type(found)
+            ),
+        )
+    }
+}
+
+/// Functions and constants scripts get with `import "name"` once the
+/// module is registered as `name` with `Runtime::register_module`, like a
+/// package that ships with the program:
+///
+/// ```
+/// use filipe::{NativeModule, Runtime, Value};
+///
+/// let gpio = NativeModule::new()
+///     .constant("HIGH", 1i64)
+///     .function("pinRead", |args| Ok(Value::Int(args.int(0)? % 2)));
+/// let mut runtime = Runtime::builder().build();
+/// runtime.register_module("gpio", gpio);
+/// let value = runtime.run_source("import \"gpio\"\npinRead(7) == HIGH");
+/// assert_eq!(value.unwrap().to_string(), "true");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NativeModule {
+    items: Vec<(String, Type, Value)>,
+}
+
+impl NativeModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `function` as `name`, like `Runtime::register_fn`.
+    pub fn function(mut self, name: &str, function: impl NativeFn + 'static) -> Self {
+        let value = native_function(name, function);
+        self.items.push((name.to_string(), Type::Function, value));
+        self
+    }
+
+    /// Adds `value` as the constant `name`.
+    pub fn constant(mut self, name: &str, value: impl ToFilipe) -> Self {
+        let value = value.to_filipe();
+        self.items
+            .push((name.to_string(), object_to_type(&value), value));
+        self
+    }
+}
+
+fn native_function(name: &str, function: impl NativeFn + 'static) -> Value {
+    Object::NativeFunction(Shared::new(NativeFunction {
+        name: name.to_string(),
+        function: Box::new(function),
+    }))
+}
+
+impl Runtime {
+    /// Makes `function` callable by scripts as `name`, a global that can't
+    /// be reassigned. Registering a name again replaces the function.
+    pub fn register_fn(&mut self, name: &str, function: impl NativeFn + 'static) {
+        self.define_native(name, Type::Function, native_function(name, function));
+    }
+
+    /// Lets scripts `import` the functions and constants of `module` as
+    /// `name`. Native modules are found before scripts and packages of the
+    /// same name, and are imported even without the `import` permission, as
+    /// no other script is run.
+    pub fn register_module(&mut self, name: &str, module: NativeModule) {
+        self.modules.native.insert(name.to_string(), module);
+    }
+
+    /// Defines the items of the native module `name` as globals, if there
+    /// is one.
+    pub(super) fn import_native(&mut self, name: &str) -> bool {
+        let Some(module) = self.modules.native.get(name) else {
+            return false;
+        };
+        log::debug!("importing native module '{}'", name);
+        for (name, type_, value) in module.items.clone() {
+            self.define_native(&name, type_, value);
+        }
+        true
+    }
+
+    /// Sets the global `name`, which scripts can't reassign, to `value`.
+    fn define_native(&mut self, name: &str, type_: Type, value: Value) {
+        let mut env = self.env.borrow_mut();
+        let replaced = env.with_mut(name, |info| {
+            info.value = value.clone();
+            info.type_ = type_.clone();
+        });
+        if replaced.is_none() {
+            env.set(name.to_string(), type_, value, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_function_errors_name_the_function() {
+        let mut runtime = Runtime::builder().build();
+        runtime.register_fn("shout", |args| {
+            args.expect_count(1)?;
+            Ok(Value::String(args.string(0)?.to_uppercase().into()))
+        });
+        let value = runtime.run_source("shout(\"hi\")").unwrap();
+        assert_eq!(value.to_string(), "'HI'");
+
+        let err = runtime.run_source("shout(1)").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "argument 1 of 'shout' must be of type 'string' but provided 'int'"
+        );
+        let err = runtime.run_source("shout()").unwrap_err();
+        assert!(err.msg.starts_with("'shout' expects 1 arg"), "{}", err.msg);
+    }
+}
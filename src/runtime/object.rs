@@ -1,15 +1,18 @@
 use core::fmt;
 
+use super::native::NativeFunction;
 use super::runtime_error::RuntimeError;
-use super::stdlib::FilipeArray;
+use super::stdlib::{iterator::FilipeIterator, set::FilipeSet, FilipeArray};
 use super::type_system::Type;
+use super::userdata::UserData;
 use super::BlockStmt;
+use crate::shared::{Lock, Shared};
 
 pub enum BuiltInFuncReturnValue {
     Object(Object),
     Error(RuntimeError),
 }
-type BuiltInFunction = fn(Vec<ObjectInfo>) -> BuiltInFuncReturnValue;
+type BuiltInFunction = fn(&[ObjectInfo]) -> BuiltInFuncReturnValue;
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct FunctionParam {
@@ -18,6 +21,31 @@ pub struct FunctionParam {
 }
 pub type FunctionParams = Vec<FunctionParam>;
 
+#[derive(Clone, Debug)]
+pub struct FilipeFunction {
+    pub params: FunctionParams,
+    pub body: Shared<BlockStmt>,
+    pub return_type: Type,
+    /// Calls return a task, see `tasks`.
+    pub is_async: bool,
+}
+
+/// Value of a caught error, or one made with `error` to be raised.
+#[derive(Clone, Debug)]
+pub struct FilipeError {
+    pub kind: String,
+    pub message: String,
+}
+
+impl fmt::Display for FilipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// Runtime value. Anything that owns heap data lives behind a `Shared` pointer, so
+/// cloning an object (resolving a variable, passing an argument) never
+/// copies strings, array items or function bodies.
 #[derive(Clone, Debug)]
 pub enum Object {
     Null,
@@ -25,23 +53,51 @@ pub enum Object {
     Int(i64),
     Float(f64),
     Boolean(bool),
-    String(String),
-    RetVal(Box<Object>),
-    Array {
-        inner: FilipeArray,
-        items_type: Option<Type>,
-    },
-    UserDefinedFunction {
-        params: FunctionParams,
-        body: BlockStmt,
-        return_type: Type,
-    },
+    String(Shared<str>),
+    Array(Shared<FilipeArray>),
+    Set(Shared<FilipeSet>),
+    UserDefinedFunction(Shared<FilipeFunction>),
     BuiltInFunction(BuiltInFunction),
+    /// Registered by the program embedding filipe, see `native`.
+    NativeFunction(Shared<NativeFunction>),
+    /// A value of the embedding program, see `userdata`.
+    UserData(Shared<UserData>),
     Range {
         start: i64,
         end: i64,
         step: i64,
     },
+    /// Shared so that advancing it through one binding is seen by all.
+    Iterator(Shared<Lock<FilipeIterator>>),
+    Error(Shared<FilipeError>),
+}
+
+/// A runtime value, as programs embedding filipe see it.
+pub type Value = Object;
+
+// Keep values small: they are copied around on every expression.
+const _: () = assert!(std::mem::size_of::<Object>() <= 32);
+
+impl Object {
+    pub fn new_array(items: Vec<Object>, items_type: Option<Type>) -> Self {
+        Self::Array(Shared::new(FilipeArray::new(items, items_type)))
+    }
+
+    /// Whether both hold the same data, arrays item by item. Values of
+    /// different types, and functions, iterators and the like, never do.
+    pub fn same_value(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a == b,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Array(a), Self::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.same_value(b))
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -57,21 +113,17 @@ impl fmt::Display for Object {
             Self::String(val) => write!(f, "'{}'", val),
             Self::Int(val) => write!(f, "{}", val),
             Self::Float(val) => write!(f, "{}", val),
-            Self::BuiltInFunction(_) => write!(f, "[Builtin Function]"),
+            Self::BuiltInFunction(_) | Self::NativeFunction(_) => write!(f, "[Builtin Function]"),
             Self::Null => write!(f, "null"),
-            Self::RetVal(val) => write!(f, "{}", val),
             Self::Boolean(val) => write!(f, "{}", val),
             Self::Type(val) => write!(f, "{}", val),
             Self::Range { start, end, step } => write!(f, "range({start}, {end}, {step})"),
-            Self::UserDefinedFunction {
-                params: _,
-                body: _,
-                return_type: _,
-            } => write!(f, "[User Defined Function]"),
-            Self::Array {
-                inner,
-                items_type:_,
-            } => write!(f, "{}", inner),
+            Self::UserDefinedFunction(_) => write!(f, "[User Defined Function]"),
+            Self::Array(inner) => write!(f, "{}", inner),
+            Self::Set(inner) => write!(f, "{}", inner),
+            Self::Iterator(_) => write!(f, "[Iterator]"),
+            Self::Error(error) => write!(f, "[Error {}]", error),
+            Self::UserData(data) => write!(f, "[{}]", data.type_name()),
         }
     }
 }
@@ -87,13 +139,22 @@ impl fmt::Display for Type {
             Self::String => write!(f, "string"),
             Self::Function => write!(f, "function"),
             Self::TypeAnnot => write!(f, "[Type Annotation]"),
-            Self::Range => write!(f, "{}", self),
+            Self::Range => write!(f, "range"),
+            Self::Set => write!(f, "Set"),
+            Self::Iterator(None) => write!(f, "iterator"),
+            Self::Iterator(Some(items_type)) => write!(f, "iterator<{}>", items_type),
+            Self::Error => write!(f, "error"),
+            Self::UserData(name) => write!(f, "{}", name),
+            Self::Union(members) => {
+                let members = members.iter().map(|member| member.to_string());
+                write!(f, "{}", members.collect::<Vec<_>>().join(" | "))
+            }
             Self::Array(items_type) => {
                 if let Some(items_type) = items_type {
-                    return write!(f, "Array<{}>", items_type)
+                    return write!(f, "Array<{}>", items_type);
                 }
                 write!(f, "Array<any>")
-            },
+            }
         }
     }
 }
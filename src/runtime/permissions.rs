@@ -0,0 +1,209 @@
+//! What a script may do outside the runtime. Whatever `RuntimePermissions`
+//! turns off raises a `PermissionError` instead of happening.
+//!
+//! The builtins only reach outside through `exit`, the timers, which keep
+//! the runtime waiting on the clock, and `import`, which reads scripts from
+//! disk and so needs both `import` and `fs`. `fs`, `net`,
+//! `process` and `env` are otherwise for native functions of the program
+//! embedding filipe, which check them with `require`. Builds without the
+//! `fs` cargo feature, on by default, have no imports of scripts and never
+//! allow `fs`, whatever the permissions say.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::runtime_error::{ErrorKind, RuntimeError};
+use crate::messages::msg;
+
+/// Everything is allowed unless turned off. Also the `[permissions]` table
+/// of a project's `filipe.toml`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimePermissions {
+    /// `import`, which runs other scripts. Reading them also takes `fs`.
+    pub import: bool,
+    /// `exit`, which stops the script with an exit code.
+    pub exit: bool,
+    /// `setTimeout` and `setInterval`, which keep the runtime waiting after
+    /// the program is done.
+    pub timers: bool,
+    /// Reading and writing files, in imports and native functions.
+    pub fs: bool,
+    /// Network connections, in native functions.
+    pub net: bool,
+    /// Starting other programs, in native functions.
+    pub process: bool,
+    /// Environment variables, in native functions.
+    pub env: bool,
+}
+
+impl Default for RuntimePermissions {
+    fn default() -> Self {
+        Self {
+            import: true,
+            exit: true,
+            timers: true,
+            fs: true,
+            net: true,
+            process: true,
+            env: true,
+        }
+    }
+}
+
+/// Something `RuntimePermissions` can turn off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Capability {
+    Import,
+    Exit,
+    Timers,
+    Fs,
+    Net,
+    Process,
+    Env,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Import => "import",
+            Self::Exit => "exit",
+            Self::Timers => "timers",
+            Self::Fs => "fs",
+            Self::Net => "net",
+            Self::Process => "process",
+            Self::Env => "env",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl RuntimePermissions {
+    /// Nothing allowed, for running scripts that aren't trusted. This only
+    /// covers what the permissions check: scripts still print to and read
+    /// from the runtime's streams, call every native function that doesn't
+    /// `require` a capability, and run for as long and use as much memory
+    /// as the runtime's `max_steps`, `max_call_depth` and `max_memory` let
+    /// them.
+    pub fn sandboxed() -> Self {
+        Self {
+            import: false,
+            exit: false,
+            timers: false,
+            fs: false,
+            net: false,
+            process: false,
+            env: false,
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        is_built_in(capability)
+            && match capability {
+                Capability::Import => self.import,
+                Capability::Exit => self.exit,
+                Capability::Timers => self.timers,
+                Capability::Fs => self.fs,
+                Capability::Net => self.net,
+                Capability::Process => self.process,
+                Capability::Env => self.env,
+            }
+    }
+}
+
+/// Whether the build has `capability` at all, which only the `fs` cargo
+/// feature takes away.
+pub fn is_built_in(capability: Capability) -> bool {
+    match capability {
+        Capability::Import | Capability::Fs => cfg!(feature = "fs"),
+        Capability::Net
+        | Capability::Process
+        | Capability::Env
+        | Capability::Exit
+        | Capability::Timers => true,
+    }
+}
+
+thread_local! {
+    /// The permissions of the runtime running on this thread, for the
+    /// builtins, which don't get to see it.
+    static CURRENT: RefCell<RuntimePermissions> = RefCell::new(RuntimePermissions::default());
+}
+
+/// Makes `permissions` the ones `require` checks on this thread, returning
+/// those it replaces, see `Runtime::isolated`.
+pub fn replace_current(permissions: RuntimePermissions) -> RuntimePermissions {
+    CURRENT.with(|current| std::mem::replace(&mut *current.borrow_mut(), permissions))
+}
+
+/// Fails with a `PermissionError` unless scripts may use `capability`. For
+/// builtins, and native functions of embedders, that reach outside the
+/// runtime.
+pub fn require(capability: Capability) -> Result<(), RuntimeError> {
+    if CURRENT.with(|current| current.borrow().allows(capability)) {
+        log::trace!("{} allowed", capability);
+        return Ok(());
+    }
+    log::debug!("{} denied", capability);
+    let msg = match capability {
+        Capability::Exit => msg!("E021.exit"),
+        _ => msg!("E021.denied", capability = capability),
+    };
+    Err(RuntimeError::new(ErrorKind::PermissionError, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::object::Object;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_exit_stops_the_script_but_not_the_host() {
+        let mut runtime = Runtime::builder().build();
+        let error = runtime
+            .run_source("let x = 1\ntry {\n  exit(3)\n} catch e {\n  x = 2\n}\nx = 3")
+            .unwrap_err();
+        assert_eq!(error.code, "E022");
+        assert_eq!(runtime.run_source("x").unwrap().to_string(), "1");
+        // codes the shell can't tell apart from others aren't let through
+        for code in ["256", "-1", "4294967296"] {
+            let error = runtime.run_source(&format!("exit({})", code)).unwrap_err();
+            assert_eq!(error.code, "E013");
+        }
+        assert_eq!(runtime.run_source("exit(255)").unwrap_err().code, "E022");
+
+        let mut runtime = Runtime::builder()
+            .permissions(RuntimePermissions::sandboxed())
+            .build();
+        let caught = runtime
+            .run_source(
+                "let kind = \"\"\ntry {\n  exit()\n} catch e {\n  kind = errorKind(e)\n}\nkind",
+            )
+            .unwrap();
+        assert_eq!(caught.to_string(), "'PermissionError'");
+        runtime.register_fn("readConfig", |_| {
+            require(Capability::Fs)?;
+            Ok(Object::Null)
+        });
+        let error = runtime.run_source("readConfig()").unwrap_err();
+        assert_eq!(error.msg, "'fs' isn't permitted here");
+
+        // builds without `fs` can't import scripts, whatever is permitted
+        let mut runtime = Runtime::builder().build();
+        let error = runtime.run_source("import \"./missing\"").unwrap_err();
+        let code = if cfg!(feature = "fs") { "E020" } else { "E021" };
+        assert_eq!(error.code, code);
+
+        // nor can runtimes that may import but not read files
+        let permissions = RuntimePermissions {
+            fs: false,
+            ..RuntimePermissions::default()
+        };
+        let mut runtime = Runtime::builder().permissions(permissions).build();
+        let error = runtime.run_source("import \"./missing\"").unwrap_err();
+        assert_eq!(error.code, "E021");
+    }
+}
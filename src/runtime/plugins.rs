@@ -0,0 +1,126 @@
+//! Native plugins: shared libraries that add builtins and native modules
+//! to a runtime when it starts, so the standard library can be extended
+//! without rebuilding the interpreter. A plugin is a `cdylib` crate
+//! depending on `filipe` that exports its registration function:
+//!
+//! ```
+//! use filipe::{export_plugin, NativeModule, Runtime, Value};
+//!
+//! fn register(runtime: &mut Runtime) {
+//!     runtime.register_fn("shout", |args| {
+//!         Ok(Value::String(args.string(0)?.to_uppercase().into()))
+//!     });
+//!     runtime.register_module("sqlite", NativeModule::new().constant("version", 3i64));
+//! }
+//!
+//! export_plugin!(register);
+//! ```
+//!
+//! Programs load it with `Runtime::load_plugin`, with the `plugins`
+//! feature, and scripts with `filipe run --plugin <path>`. As Rust has no
+//! stable ABI, a plugin must be built with the same compiler, version of
+//! filipe and `sync` feature as the program loading it; the version and
+//! the feature are checked, the compiler can't be. Plugins stay loaded
+//! until the process exits, as the values they make can outlive runtimes.
+
+#[cfg(all(feature = "plugins", not(target_arch = "wasm32")))]
+use std::path::Path;
+
+#[cfg(all(feature = "plugins", not(target_arch = "wasm32")))]
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::Runtime;
+#[cfg(all(feature = "plugins", not(target_arch = "wasm32")))]
+use crate::messages::msg;
+
+/// Bumped whenever `PluginDeclaration` changes, so older plugins are
+/// refused instead of misread.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// What `export_plugin!` exports as `FILIPE_PLUGIN`.
+#[repr(C)]
+pub struct PluginDeclaration {
+    /// First, so it can be checked before anything else is read.
+    pub abi_version: u32,
+    pub filipe_version: &'static str,
+    /// Whether filipe was built with the `sync` feature, which changes the
+    /// layout of runtime values.
+    pub sync: bool,
+    pub register: fn(&mut Runtime),
+}
+
+impl PluginDeclaration {
+    /// The declaration of a plugin built against this filipe.
+    pub const fn new(register: fn(&mut Runtime)) -> Self {
+        Self {
+            abi_version: PLUGIN_ABI_VERSION,
+            filipe_version: env!("CARGO_PKG_VERSION"),
+            sync: cfg!(feature = "sync"),
+            register,
+        }
+    }
+
+    #[cfg(all(feature = "plugins", not(target_arch = "wasm32")))]
+    fn is_compatible(&self) -> bool {
+        let current = Self::new(self.register);
+        self.abi_version == current.abi_version
+            && self.filipe_version == current.filipe_version
+            && self.sync == current.sync
+    }
+}
+
+/// Exports `register`, a `fn(&mut Runtime)`, as the plugin's entry point.
+#[macro_export]
+macro_rules! export_plugin {
+    ($register:expr) => {
+        #[no_mangle]
+        pub static FILIPE_PLUGIN: $crate::PluginDeclaration =
+            $crate::PluginDeclaration::new($register);
+    };
+}
+
+#[cfg(all(feature = "plugins", not(target_arch = "wasm32")))]
+impl Runtime {
+    /// Loads the plugin at `path` and lets it register its builtins and
+    /// modules. Fails with an `ImportError` when it can't be loaded,
+    /// exports no plugin or was built for another filipe.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and a plugin's
+    /// functions run unchecked: `path` must be a plugin built as said in
+    /// `plugins`, from code that is trusted.
+    pub unsafe fn load_plugin(&mut self, path: impl AsRef<Path>) -> Result<(), RuntimeError> {
+        let display = path.as_ref().to_string_lossy().to_string();
+        let error = |msg| RuntimeError::new(ErrorKind::ImportError, msg);
+        let library = libloading::Library::new(path.as_ref())
+            .map_err(|err| error(msg!("E020.plugin_unloadable", path = display, error = err)))?;
+        let declaration = library
+            .get::<*const PluginDeclaration>(b"FILIPE_PLUGIN\0")
+            .map_err(|_| error(msg!("E020.not_a_plugin", path = display)))?;
+        // the version is read first, as the rest may be laid out differently
+        if (**declaration).abi_version != PLUGIN_ABI_VERSION || !(**declaration).is_compatible() {
+            return Err(error(msg!("E020.plugin_incompatible", path = display)));
+        }
+        log::debug!("loading plugin {}", display);
+        ((**declaration).register)(self);
+        // what it registered points into it
+        std::mem::forget(library);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "plugins", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_plugin_reports_what_went_wrong() {
+        let mut runtime = Runtime::builder().build();
+        let error = unsafe { runtime.load_plugin("no/such/plugin.so") }.unwrap_err();
+        assert_eq!(error.kind.name(), "ImportError");
+        assert!(error
+            .msg
+            .starts_with("couldn't load the plugin no/such/plugin.so"));
+        assert!(PluginDeclaration::new(|_| {}).is_compatible());
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+#[derive(Default, Clone, Copy)]
+struct ProfileEntry {
+    calls: u64,
+    total: Duration,
+}
+
+/// Per-function call counts and cumulative (inclusive) time.
+#[derive(Default)]
+pub struct Profiler {
+    entries: HashMap<String, ProfileEntry>,
+}
+
+impl Profiler {
+    pub fn record(&mut self, fn_name: &str, elapsed: Duration) {
+        let entry = match self.entries.get_mut(fn_name) {
+            Some(entry) => entry,
+            None => self.entries.entry(fn_name.to_string()).or_default(),
+        };
+        entry.calls += 1;
+        entry.total += elapsed;
+    }
+
+    /// Renders the profile as a table, hottest functions first.
+    pub fn report(&self) -> String {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(b.0)));
+
+        let mut report = format!(
+            "{:<24} {:>10} {:>14} {:>14}\n",
+            "function", "calls", "total", "avg"
+        );
+        for (name, entry) in entries {
+            let _ = writeln!(
+                report,
+                "{:<24} {:>10} {:>14.2?} {:>14.2?}",
+                name,
+                entry.calls,
+                entry.total,
+                entry.total.div_f64(entry.calls as f64)
+            );
+        }
+        report
+    }
+}
@@ -0,0 +1,170 @@
+//! Reflection: `arity(function)`, `methods(value)` and `globals()` tell a
+//! script about the values it runs with, so helpers like serializers and
+//! test frameworks can be written in filipe itself:
+//!
+//! ```text
+//! define testSum(): void {
+//!   assert 1 + 1 == 2
+//! }
+//! for name in globals() {
+//!   print(name)
+//! }
+//! ```
+//!
+//! A method of a value is any function `value.name(args)` can call, one
+//! whose first parameter takes the value's type. Builtins don't declare
+//! the types they take, so only functions written in filipe are listed,
+//! and `arity` is `null` for builtins and native functions, which check
+//! their arguments themselves. `methods` and `globals` need the scope they
+//! are called from, so they run only when called by their names.
+
+use std::collections::HashSet;
+
+use super::flstdlib::builtins;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::type_system::Type;
+use super::Runtime;
+use crate::messages::msg;
+
+impl Runtime {
+    /// `methods` and `globals`, which unlike other builtins need the scope
+    /// of the runtime calling them.
+    pub(super) fn reflect(&mut self, name: &str, args: Vec<ObjectInfo>) -> Option<Object> {
+        let call = Args::new(name, &args);
+        let names = match name {
+            "methods" => call
+                .expect_count(1)
+                .map(|_| self.methods_of(&args[0].type_)),
+            _ => call.expect_count(0).map(|_| self.global_names()),
+        };
+        match names {
+            Ok(names) => Some(string_array(names)),
+            Err(error) => {
+                self.error_handler.set_error(error.kind, error.msg);
+                None
+            }
+        }
+    }
+
+    /// Names of the functions in scope whose first parameter takes a value
+    /// of type `type_`, sorted.
+    fn methods_of(&self, type_: &Type) -> Vec<String> {
+        let env = self.env.borrow();
+        let mut seen = HashSet::new();
+        let mut names = vec![];
+        // locals first, as they shadow the globals of the same name
+        for (name, info) in env
+            .visible_locals()
+            .into_iter()
+            .chain(env.visible_globals())
+        {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            if let Object::UserDefinedFunction(function) = &info.value {
+                if function
+                    .params
+                    .first()
+                    .is_some_and(|p| p.type_.accepts(type_))
+                {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+
+    /// Names of the globals the program declared, builtins left out, sorted.
+    fn global_names(&self) -> Vec<String> {
+        let builtins = builtins();
+        let globals = self.env.borrow().visible_globals();
+        globals
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| !builtins.contains_key(name))
+            .collect()
+    }
+}
+
+fn string_array(names: Vec<String>) -> Object {
+    let items = names
+        .into_iter()
+        .map(|name| Object::String(name.as_str().into()))
+        .collect();
+    Object::new_array(items, Some(Type::String))
+}
+
+/// How many arguments a function takes, `null` for builtins and native
+/// functions.
+pub fn filipe_arity(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let call = Args::new("arity", args);
+    if let Err(error) = call.expect_count(1) {
+        return BuiltInFuncReturnValue::Error(error);
+    }
+    match &args[0].value {
+        Object::UserDefinedFunction(function) => {
+            BuiltInFuncReturnValue::Object(Object::Int(function.params.len() as i64))
+        }
+        Object::BuiltInFunction(_) | Object::NativeFunction(_) => {
+            BuiltInFuncReturnValue::Object(Object::Null)
+        }
+        other => BuiltInFuncReturnValue::Error(call.type_error(0, Type::Function, other)),
+    }
+}
+
+/// Stands in for `methods` when it isn't called by its name, as the
+/// runtime only runs it then.
+pub fn filipe_methods(_: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    call_by_name("methods")
+}
+
+/// Stands in for `globals`, like `filipe_methods`.
+pub fn filipe_globals(_: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    call_by_name("globals")
+}
+
+fn call_by_name(name: &str) -> BuiltInFuncReturnValue {
+    BuiltInFuncReturnValue::Error(RuntimeError::new(
+        ErrorKind::TypeError,
+        msg!("E011.call_by_name", name = name),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_reflection_describes_functions_and_globals() {
+        let mut runtime = Runtime::builder().build();
+        let source = "define area(side: float): float {\n\
+                      \x20 return side * side\n\
+                      }\n\
+                      define shout(text: string): string {\n\
+                      \x20 return text\n\
+                      }\n\
+                      define half(n: float | int): float {\n\
+                      \x20 return 0.5\n\
+                      }\n\
+                      let count = 3";
+        runtime.run_source(source).unwrap();
+
+        let methods = runtime.run_source("methods(2.0)").unwrap();
+        assert_eq!(methods.to_string(), "['area', 'half']");
+        let methods = runtime.run_source("\"hi\".methods()").unwrap();
+        assert_eq!(methods.to_string(), "['shout']");
+        let globals = runtime.run_source("globals()").unwrap();
+        assert_eq!(globals.to_string(), "['area', 'count', 'half', 'shout']");
+        assert_eq!(runtime.run_source("arity(area)").unwrap().to_string(), "1");
+        assert_eq!(
+            runtime.run_source("arity(print)").unwrap().to_string(),
+            "null"
+        );
+
+        let error = runtime.run_source("arity(count)").unwrap_err();
+        assert_eq!(error.code, "E012");
+    }
+}
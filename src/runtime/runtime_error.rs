@@ -1,20 +1,65 @@
 use core::fmt;
 
-#[derive(Clone)]
+use super::call_stack::Frame;
+use crate::frontend::ast::Expr;
+use crate::frontend::token::Span;
+use crate::messages::msg;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Debug)]
 pub enum ErrorKind {
     NameError,
     TypeError,
     ArgumentError,
     ValueError,
+    ExecutionLimitExceeded,
+    MemoryLimitExceeded,
+    OverflowError,
+    DivisionByZero,
+    AssertionError,
+    ImportError,
+    PermissionError,
+    /// `exit` stopping the script with an exit code. The command line ends
+    /// the process with it, while a program embedding filipe keeps going.
+    Exit(i32),
+    /// Stopped through an `InterruptHandle`, e.g. on Ctrl-C.
+    Interrupted,
+    /// Raised by a script with `raise`, under a kind it picked.
+    Custom(String),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct RuntimeError {
     pub kind: ErrorKind,
     pub msg: String,
+    /// Innermost expression or statement being evaluated when it was raised.
+    pub span: Option<Span>,
+    /// Calls that were active when it was raised, innermost first.
+    pub trace: Vec<Frame>,
+    /// Source of the expression a type error happened in.
+    pub expr: Option<Box<str>>,
+    /// Extra context, e.g. where a redeclared name was first declared.
+    pub notes: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, msg: String) -> Self {
+        Self {
+            kind,
+            msg,
+            span: None,
+            trace: Vec::new(),
+            expr: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn exit(code: i32) -> Self {
+        Self::new(ErrorKind::Exit(code), msg!("E022.exited", code = code))
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct RuntimeErrorHandler {
     error: Option<RuntimeError>,
 }
@@ -38,31 +83,184 @@ impl RuntimeErrorHandler {
 
     pub fn set_type_error(&mut self, msg: String) {
         self.set_error(ErrorKind::TypeError, msg)
-
     }
 
     pub fn set_error(&mut self, kind: ErrorKind, msg: String) {
-        self.error = Some(RuntimeError {
-            kind,
-            msg,
-        });
+        self.error = Some(RuntimeError::new(kind, msg));
     }
 
+    /// Adds context to the current error that the message alone can't give.
+    pub fn add_note(&mut self, note: String) {
+        if let Some(error) = self.error.as_mut() {
+            error.notes.push(note);
+        }
+    }
+
+    /// Clears the current error and hands it over, e.g. to a `catch` block.
+    pub fn take_error(&mut self) -> Option<RuntimeError> {
+        self.error.take()
+    }
+
+    /// Records where the current error happened, unless a more precise
+    /// location was already attached.
+    pub fn attach_span(&mut self, span: Span) {
+        if let Some(error) = self.error.as_mut() {
+            error.span.get_or_insert(span);
+        }
+    }
+
+    /// Records the innermost expression the current error happened in.
+    /// Type errors also keep its source, which the message alone doesn't
+    /// always make clear.
+    pub fn attach_expr(&mut self, expr: &Expr) {
+        if let Some(error) = self.error.as_mut() {
+            if error.span.is_none() {
+                error.span = Some(expr.span);
+                if matches!(error.kind, ErrorKind::TypeError) {
+                    error.expr = Some(expr.to_string().into());
+                }
+            }
+        }
+    }
+
+    /// Moves the current error to `span`, e.g. from a module to the
+    /// `import` that ran it, keeping where it happened in a `note`.
+    pub fn relocate(&mut self, span: Span, note: impl FnOnce(Span) -> String) {
+        if let Some(error) = self.error.as_mut() {
+            if let Some(at) = error.span.replace(span) {
+                error.notes.push(note(at));
+            }
+            error.expr = None;
+        }
+    }
+
+    /// Records the active calls for the current error, unless a deeper
+    /// call already did.
+    pub fn attach_trace(&mut self, call_stack: &[Frame]) {
+        if let Some(error) = self.error.as_mut() {
+            if error.trace.is_empty() {
+                error.trace = call_stack.iter().rev().cloned().collect();
+            }
+        }
+    }
 }
 
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.kind, self.msg)
+        match self.span {
+            Some(span) => write!(f, "{} at {}: ", self.kind, span)?,
+            None => write!(f, "{} ", self.kind)?,
+        }
+        match &self.expr {
+            Some(expr) => write!(
+                f,
+                "{}",
+                msg!("diagnostic.in_expr", msg = self.msg, expr = expr)
+            )?,
+            None => write!(f, "{}", self.msg)?,
+        }
+        for frame in &self.trace {
+            write!(f, "\n    {}", frame)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n    {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl ErrorKind {
+    /// Stable code that `filipe explain` describes in detail.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NameError => "E010",
+            Self::TypeError => "E011",
+            Self::ArgumentError => "E012",
+            Self::ValueError => "E013",
+            Self::ExecutionLimitExceeded => "E014",
+            Self::MemoryLimitExceeded => "E015",
+            Self::OverflowError => "E016",
+            Self::DivisionByZero => "E017",
+            Self::AssertionError => "E019",
+            Self::ImportError => "E020",
+            Self::PermissionError => "E021",
+            Self::Exit(_) => "E022",
+            Self::Interrupted => "E023",
+            Self::Custom(_) => "E018",
+        }
+    }
+
+    /// Name scripts see in caught errors, and can pass to `error` to raise
+    /// one of the built-in kinds.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::NameError => "NameError",
+            Self::TypeError => "TypeError",
+            Self::ArgumentError => "ArgumentError",
+            Self::ValueError => "ValueError",
+            Self::ExecutionLimitExceeded => "ExecutionLimitExceeded",
+            Self::MemoryLimitExceeded => "MemoryLimitExceeded",
+            Self::OverflowError => "OverflowError",
+            Self::DivisionByZero => "DivisionByZero",
+            Self::AssertionError => "AssertionError",
+            Self::ImportError => "ImportError",
+            Self::PermissionError => "PermissionError",
+            Self::Exit(_) => "Exit",
+            Self::Interrupted => "Interrupted",
+            Self::Custom(name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "NameError" => Self::NameError,
+            "TypeError" => Self::TypeError,
+            "ArgumentError" => Self::ArgumentError,
+            "ValueError" => Self::ValueError,
+            "ExecutionLimitExceeded" => Self::ExecutionLimitExceeded,
+            "MemoryLimitExceeded" => Self::MemoryLimitExceeded,
+            "OverflowError" => Self::OverflowError,
+            "DivisionByZero" => Self::DivisionByZero,
+            "AssertionError" => Self::AssertionError,
+            "ImportError" => Self::ImportError,
+            "PermissionError" => Self::PermissionError,
+            _ => Self::Custom(name.to_string()),
+        }
+    }
+
+    /// Resource limits are enforced by the host, so scripts can't catch
+    /// them to keep going, and `exit` or an interrupt stops the script
+    /// wherever it is.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            Self::ExecutionLimitExceeded
+                | Self::MemoryLimitExceeded
+                | Self::Exit(_)
+                | Self::Interrupted
+        )
     }
 }
 
 impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::NameError => write!(f, "[Name error]"),
-            Self::TypeError => write!(f, "[Type Error]"),
-            Self::ArgumentError => write!(f, "[Argument Error]"),
-            Self::ValueError => write!(f, "[Value Error]"),
+            Self::NameError => write!(f, "[{}]", msg!("E010.kind")),
+            Self::TypeError => write!(f, "[{}]", msg!("E011.kind")),
+            Self::ArgumentError => write!(f, "[{}]", msg!("E012.kind")),
+            Self::ValueError => write!(f, "[{}]", msg!("E013.kind")),
+            Self::ExecutionLimitExceeded => write!(f, "[{}]", msg!("E014.kind")),
+            Self::MemoryLimitExceeded => write!(f, "[{}]", msg!("E015.kind")),
+            Self::OverflowError => write!(f, "[{}]", msg!("E016.kind")),
+            Self::DivisionByZero => write!(f, "[{}]", msg!("E017.kind")),
+            Self::AssertionError => write!(f, "[{}]", msg!("E019.kind")),
+            Self::ImportError => write!(f, "[{}]", msg!("E020.kind")),
+            Self::PermissionError => write!(f, "[{}]", msg!("E021.kind")),
+            Self::Exit(_) => write!(f, "[{}]", msg!("E022.kind")),
+            Self::Interrupted => write!(f, "[{}]", msg!("E023.kind")),
+            Self::Custom(name) => write!(f, "[{}]", name),
         }
     }
 }
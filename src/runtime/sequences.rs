@@ -0,0 +1,384 @@
+//! Lazy sequences: `map(iterable, function)`, `filter(iterable, function)`
+//! and `take(iterable, n)` return iterators that only call their functions
+//! as items are asked for, by a for-loop, `next` or `collect`, which puts
+//! what is left in an array. Written as methods they chain:
+//!
+//! ```text
+//! define square(n: int): int {
+//!   return n * n
+//! }
+//! define even(n: int): boolean {
+//!   return n % 2 == 0
+//! }
+//! let firsts = range(0, 1000000).map(square).filter(even).take(3).collect()
+//! ```
+//!
+//! only squares the first five numbers. Given an iterator, they advance it
+//! as they are advanced, like advancing it through another binding; any
+//! other iterable is walked from its start. `next` and `collect` need the
+//! runtime to call the functions and to count the steps and memory
+//! gathering takes, so when they aren't called by their names `next` runs
+//! only iterators without functions, and `collect` and `set` only gather
+//! arrays, strings, sets and deques.
+
+use std::mem::size_of;
+
+use super::evaluators::func_call_evaluator::call_function;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::sets::set_of;
+use super::stdlib::iterator::{Caller, FilipeIterator};
+use super::type_system::{object_to_type, Type};
+use super::Runtime;
+use crate::messages::msg;
+use crate::shared::{Lock, Shared};
+
+impl Runtime {
+    fn call_on_item(&mut self, name: &str, function: &Object, item: Object) -> Option<Object> {
+        let argument = ObjectInfo {
+            is_assignable: true,
+            type_: object_to_type(&item),
+            value: item,
+        };
+        // builtins run no statements, but calling them is work all the same
+        if !self.consume_step() {
+            return None;
+        }
+        let returned = call_function(self, name, function.clone(), vec![argument]);
+        if self.error_handler.has_error() {
+            return None;
+        }
+        Some(returned.unwrap_or(Object::Null))
+    }
+}
+
+impl Caller for Runtime {
+    fn call(&mut self, function: &Object, item: Object) -> Option<Object> {
+        self.call_on_item("map", function, item)
+    }
+
+    fn test(&mut self, function: &Object, item: Object) -> Option<bool> {
+        let kept = self.call_on_item("filter", function, item)?;
+        self.is_truthy(kept)
+    }
+}
+
+/// Stands in for the runtime when `next` or `collect` aren't called by
+/// their names: raises an error instead of calling functions.
+struct Detached(&'static str, Option<RuntimeError>);
+
+impl Caller for Detached {
+    fn call(&mut self, _: &Object, _: Object) -> Option<Object> {
+        self.1 = Some(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.call_by_name", name = self.0),
+        ));
+        None
+    }
+
+    fn test(&mut self, function: &Object, item: Object) -> Option<bool> {
+        self.call(function, item).map(|_| false)
+    }
+}
+
+/// The argument at `index` as an iterator: the same one when it is one, or
+/// a new one over it.
+fn iterator_arg(args: &Args, index: usize) -> Result<Shared<Lock<FilipeIterator>>, RuntimeError> {
+    let value = args.value(index)?;
+    if let Object::Iterator(iterator) = value {
+        return Ok(Shared::clone(iterator));
+    }
+    match FilipeIterator::new(value) {
+        Some(iterator) => Ok(Shared::new(Lock::new(iterator))),
+        None => Err(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.not_iterable", found = object_to_type(value)),
+        )),
+    }
+}
+
+fn function_arg(name: &str, args: &Args, index: usize) -> Result<Object, RuntimeError> {
+    let value = args.value(index)?;
+    match value {
+        Object::UserDefinedFunction(_) | Object::BuiltInFunction(_) | Object::NativeFunction(_) => {
+            Ok(value.clone())
+        }
+        _ => Err(RuntimeError::new(
+            ErrorKind::ArgumentError,
+            msg!(
+                "E012.arg_type",
+                index = index + 1,
+                name = name,
+                expected = "function",
+                found = object_to_type(value)
+            ),
+        )),
+    }
+}
+
+fn returned(result: Result<FilipeIterator, RuntimeError>) -> BuiltInFuncReturnValue {
+    match result {
+        Ok(iterator) => {
+            BuiltInFuncReturnValue::Object(Object::Iterator(Shared::new(Lock::new(iterator))))
+        }
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_map(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("map", args);
+    returned(args.expect_count(2).and_then(|_| {
+        Ok(FilipeIterator::Map {
+            source: iterator_arg(&args, 0)?,
+            function: function_arg("map", &args, 1)?,
+        })
+    }))
+}
+
+pub fn filipe_filter(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("filter", args);
+    returned(args.expect_count(2).and_then(|_| {
+        Ok(FilipeIterator::Filter {
+            source: iterator_arg(&args, 0)?,
+            function: function_arg("filter", &args, 1)?,
+        })
+    }))
+}
+
+/// A negative count takes nothing.
+pub fn filipe_take(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("take", args);
+    returned(args.expect_count(2).and_then(|_| {
+        Ok(FilipeIterator::Take {
+            source: iterator_arg(&args, 0)?,
+            left: args.int(1)?.max(0) as usize,
+        })
+    }))
+}
+
+/// Advances an iterator, yielding `null` once it's exhausted.
+pub fn filipe_next(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let mut detached = Detached("next", None);
+    match next_arg(&Args::new("next", args)) {
+        Ok(iterator) => {
+            let item = FilipeIterator::advance(&iterator, &mut detached);
+            match detached.1 {
+                Some(error) => BuiltInFuncReturnValue::Error(error),
+                None => BuiltInFuncReturnValue::Object(item.unwrap_or(Object::Null)),
+            }
+        }
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+/// Puts the items left in an iterable in an array.
+pub fn filipe_collect(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    gather("collect", args)
+}
+
+/// Makes a set, see `sets`.
+pub fn filipe_set(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    gather("set", args)
+}
+
+/// `collect` or `set` when they aren't called by their names.
+fn gather(name: &'static str, args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let call = Args::new(name, args);
+    // nothing counts the steps and memory it takes without the runtime, so
+    // only values that already hold their items are gathered
+    if matches!(
+        call.value(0),
+        Ok(Object::Range { .. } | Object::Iterator(_))
+    ) {
+        return BuiltInFuncReturnValue::Error(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.call_by_name", name = name),
+        ));
+    }
+    let iterator = match gathered_arg(name, &call) {
+        Ok(Some(iterator)) => iterator,
+        Ok(None) => return BuiltInFuncReturnValue::Object(Object::Set(Default::default())),
+        Err(error) => return BuiltInFuncReturnValue::Error(error),
+    };
+    let mut detached = Detached(name, None);
+    let mut items = vec![];
+    while let Some(item) = FilipeIterator::advance(&iterator, &mut detached) {
+        items.push(item);
+    }
+    let gathered = match detached.1 {
+        Some(error) => Err(error),
+        None => gathered(name, items),
+    };
+    match gathered {
+        Ok(object) => BuiltInFuncReturnValue::Object(object),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+/// The iterable `collect` or `set` gathers the items of, `None` for `set()`.
+fn gathered_arg(
+    name: &str,
+    args: &Args,
+) -> Result<Option<Shared<Lock<FilipeIterator>>>, RuntimeError> {
+    match name {
+        "set" => args.expect_count_between(0, Some(1))?,
+        _ => args.expect_count(1)?,
+    }
+    match args.is_empty() {
+        true => Ok(None),
+        false => iterator_arg(args, 0).map(Some),
+    }
+}
+
+/// What `collect` or `set` return for `items`.
+fn gathered(name: &str, items: Vec<Object>) -> Result<Object, RuntimeError> {
+    if name == "set" {
+        return set_of(items).map(|set| Object::Set(Shared::new(set)));
+    }
+    let type_ = items_type(&items)?;
+    Ok(Object::new_array(items, type_))
+}
+
+fn next_arg(args: &Args) -> Result<Shared<Lock<FilipeIterator>>, RuntimeError> {
+    args.expect_count(1)?;
+    match args.value(0)? {
+        Object::Iterator(iterator) => Ok(Shared::clone(iterator)),
+        _ => Err(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.next_type"),
+        )),
+    }
+}
+
+/// Arrays hold items of one type, like array literals.
+fn items_type(items: &[Object]) -> Result<Option<Type>, RuntimeError> {
+    let Some(first) = items.first().map(object_to_type) else {
+        return Ok(None);
+    };
+    match items.iter().all(|item| object_to_type(item) == first) {
+        true => Ok(Some(first)),
+        false => Err(RuntimeError::new(
+            ErrorKind::TypeError,
+            msg!("E011.array_items_mismatch"),
+        )),
+    }
+}
+
+impl Runtime {
+    /// `next`, `collect` and `set`, which unlike other builtins need the
+    /// runtime calling them to advance iterators made by `map` and `filter`.
+    pub(super) fn advance_sequence(&mut self, name: &str, args: Vec<ObjectInfo>) -> Option<Object> {
+        let call = Args::new(name, &args);
+        let iterator = match name {
+            "next" => next_arg(&call).map(Some),
+            _ => gathered_arg(name, &call),
+        };
+        let iterator = match iterator {
+            Ok(Some(iterator)) => iterator,
+            Ok(None) => return Some(Object::Set(Default::default())),
+            Err(error) => {
+                self.error_handler.set_error(error.kind, error.msg);
+                return None;
+            }
+        };
+        if name == "next" {
+            let item = FilipeIterator::advance(&iterator, self);
+            return match self.error_handler.has_error() {
+                true => None,
+                false => Some(item.unwrap_or(Object::Null)),
+            };
+        }
+
+        let mut items = vec![];
+        while let Some(item) = FilipeIterator::advance(&iterator, self) {
+            // the iterable may have almost no end
+            let held = (items.len() + 1) * size_of::<Object>();
+            if !self.consume_step() || !self.track_unscoped_allocation(size_of::<Object>(), held) {
+                return None;
+            }
+            items.push(item);
+        }
+        if self.error_handler.has_error() {
+            return None;
+        }
+        match gathered(name, items) {
+            Ok(object) => Some(object),
+            Err(error) => {
+                self.error_handler.set_error(error.kind, error.msg);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequences_are_lazy() {
+        let mut runtime = Runtime::builder().build();
+        let source = "let calls = 0\n\
+                      define square(n: int): int {\n\
+                      \x20 calls = calls + 1\n\
+                      \x20 return n * n\n\
+                      }\n\
+                      define even(n: int): boolean {\n\
+                      \x20 return n % 2 == 0\n\
+                      }\n\
+                      let firsts = range(0, 1000000).map(square).filter(even).take(3).collect()";
+        runtime.run_source(source).unwrap();
+        assert_eq!(
+            runtime.run_source("firsts").unwrap().to_string(),
+            "[0, 4, 16]"
+        );
+        assert_eq!(runtime.run_source("calls").unwrap().to_string(), "5");
+
+        // an iterator given to `map` is advanced with it
+        let source = "let letters = iter(\"abc\")\n\
+                      define upper(s: string): string {\n\
+                      \x20 return s + s\n\
+                      }\n\
+                      let doubled = map(letters, upper)\n\
+                      next(doubled)\n\
+                      collect(letters)";
+        let rest = runtime.run_source(source).unwrap();
+        assert_eq!(rest.to_string(), "['b', 'c']");
+
+        let error = runtime
+            .run_source("let f = next\nf(iter([1]).map(square))")
+            .unwrap_err();
+        assert_eq!(error.code, "E011");
+        let error = runtime.run_source("collect(map(5, square))").unwrap_err();
+        assert_eq!(error.code, "E011");
+    }
+
+    #[test]
+    fn test_gathering_counts_against_the_limits() {
+        let endless = "collect(range(0, 9223372036854775807))";
+        let mut runtime = Runtime::builder().max_steps(100).build();
+        assert_eq!(runtime.run_source(endless).unwrap_err().code, "E014");
+        let mut runtime = Runtime::builder().max_memory(100_000).build();
+        assert_eq!(runtime.run_source(endless).unwrap_err().code, "E015");
+
+        // calling a builtin on each item is a step too
+        let mut runtime = Runtime::builder().max_steps(80).build();
+        runtime.run_source("collect(range(0, 50))").unwrap();
+        let error = runtime
+            .run_source("collect(map(range(0, 50), typeof))")
+            .unwrap_err();
+        assert_eq!(error.code, "E014");
+
+        // without the runtime, only what is already there is gathered
+        let mut runtime = Runtime::builder().build();
+        let gathered = runtime
+            .run_source("let gather = collect\ngather(\"ab\")")
+            .unwrap();
+        assert_eq!(gathered.to_string(), "['a', 'b']");
+        let error = runtime
+            .run_source("gather(range(0, 9223372036854775807))")
+            .unwrap_err();
+        assert_eq!(error.code, "E011");
+    }
+}
@@ -0,0 +1,100 @@
+//! Sets: `set()` makes an empty one and `set(iterable)` one with the items
+//! of an array, string, range or iterator, each kept once, taken like
+//! `collect` takes them. `item in s` tells whether it has an item, without
+//! walking it like an array:
+//!
+//! ```text
+//! let seen = set(["a", "b"])
+//! let more = union(seen, set(["c"]))
+//! print("c" in more, " ", len(intersection(seen, more)))
+//! ```
+//!
+//! Sets are values like arrays: `union`, `intersection` and `difference`
+//! make new ones. Their items are nulls, numbers, booleans and strings, of
+//! any mix of types; `1` and `1.0` are different items.
+
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::stdlib::set::FilipeSet;
+use super::type_system::object_to_type;
+use crate::messages::msg;
+use crate::shared::Shared;
+
+/// A set of `items`, which `set` gathered like `collect`, see `sequences`.
+pub(super) fn set_of(items: Vec<Object>) -> Result<FilipeSet, RuntimeError> {
+    let mut set = FilipeSet::default();
+    for item in items {
+        let type_ = object_to_type(&item);
+        if !set.insert(item) {
+            return Err(RuntimeError::new(
+                ErrorKind::TypeError,
+                msg!("E011.set_item", found = type_),
+            ));
+        }
+    }
+    Ok(set)
+}
+
+/// Calls `operation` on the two sets `args` must be.
+fn combine(
+    name: &str,
+    args: &[ObjectInfo],
+    operation: fn(&FilipeSet, &FilipeSet) -> FilipeSet,
+) -> BuiltInFuncReturnValue {
+    let args = Args::new(name, args);
+    let combined = args
+        .expect_count(2)
+        .and_then(|_| Ok(operation(args.set(0)?, args.set(1)?)));
+    match combined {
+        Ok(set) => BuiltInFuncReturnValue::Object(Object::Set(Shared::new(set))),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+pub fn filipe_union(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    combine("union", args, FilipeSet::union)
+}
+
+pub fn filipe_intersection(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    combine("intersection", args, FilipeSet::intersection)
+}
+
+pub fn filipe_difference(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    combine("difference", args, FilipeSet::difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    fn run(source: &str) -> String {
+        let mut runtime = Runtime::builder().build();
+        runtime.run_source(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_sets() {
+        assert_eq!(run("set([3, 1, 3, 2])"), "Set{3, 1, 2}");
+        assert_eq!(run("len(set(\"hello\"))"), "4");
+        assert_eq!(run("set()"), "Set{}");
+        assert_eq!(run("set(range(0, 10).take(3))"), "Set{0, 1, 2}");
+        assert_eq!(
+            run("let a = set([1, 2, 3])\nlet b = set([2, 3, 4])\n\
+                 let lens = [len(union(a, b)), len(intersection(a, b)), len(difference(a, b))]\nlens"),
+            "[4, 2, 1]"
+        );
+        assert_eq!(
+            run("[2 in set([1, 2]), 5 in [1, 2], \"ell\" in \"hello\", 4 in range(0, 10, 2), 5 in range(0, 10, 2)]"),
+            "[true, false, true, true, false]"
+        );
+
+        let mut runtime = Runtime::builder().build();
+        let error = runtime.run_source("set([[1], [2]])").unwrap_err();
+        assert_eq!(error.code, "E011");
+        let error = runtime.run_source("1 in 2").unwrap_err();
+        assert_eq!(error.code, "E011");
+        let error = runtime.run_source("union(set(), [1])").unwrap_err();
+        assert_eq!(error.code, "E012");
+    }
+}
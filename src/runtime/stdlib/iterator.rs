@@ -0,0 +1,249 @@
+use super::super::deques::{deque_len, deque_pop_front};
+use super::super::object::Object;
+use super::super::userdata::UserData;
+use super::set::FilipeSet;
+use super::FilipeArray;
+use crate::shared::{Lock, Shared};
+
+/// What iterators made by `map` and `filter` call their functions with, see
+/// `runtime::sequences`.
+pub trait Caller {
+    /// What `function(item)` returns, `None` when it raised an error.
+    fn call(&mut self, function: &Object, item: Object) -> Option<Object>;
+
+    /// Whether `function(item)` holds, `None` when it raised an error.
+    fn test(&mut self, function: &Object, item: Object) -> Option<bool>;
+}
+
+/// Lazy cursor over an iterable value. For-loops and the `iter`/`next`
+/// builtins all walk values through this, so a new iterable type only has
+/// to teach `FilipeIterator::new` how to step through it.
+#[derive(Clone, Debug)]
+pub enum FilipeIterator {
+    Range {
+        next: i64,
+        end: i64,
+        step: i64,
+    },
+    Array {
+        array: Shared<FilipeArray>,
+        index: usize,
+    },
+    String {
+        string: Shared<str>,
+        offset: usize,
+    },
+    Set {
+        set: Shared<FilipeSet>,
+        index: usize,
+    },
+    /// Takes the items from the front of the deque, see `runtime::deques`.
+    Deque(Shared<UserData>),
+    /// The source is shared, so advancing this advances it too, like
+    /// advancing it through another binding.
+    Map {
+        source: Shared<Lock<FilipeIterator>>,
+        function: Object,
+    },
+    Filter {
+        source: Shared<Lock<FilipeIterator>>,
+        function: Object,
+    },
+    Take {
+        source: Shared<Lock<FilipeIterator>>,
+        left: usize,
+    },
+}
+
+impl FilipeIterator {
+    /// Starts iterating over `object`, or `None` if it isn't iterable.
+    pub fn new(object: &Object) -> Option<Self> {
+        match object {
+            Object::Range { start, end, step } => Some(Self::Range {
+                next: *start,
+                end: *end,
+                // `range` refuses to step by 0, only a host can build
+                // such a range
+                step: if *step == 0 { 1 } else { *step },
+            }),
+            Object::Array(array) => Some(Self::Array {
+                array: Shared::clone(array),
+                index: 0,
+            }),
+            Object::String(string) => Some(Self::String {
+                string: Shared::clone(string),
+                offset: 0,
+            }),
+            Object::Set(set) => Some(Self::Set {
+                set: Shared::clone(set),
+                index: 0,
+            }),
+            Object::UserData(data) if deque_len(data).is_some() => {
+                Some(Self::Deque(Shared::clone(data)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a range would yield `n`, without walking it.
+    pub fn contains_int(&self, n: i64) -> bool {
+        match self {
+            Self::Range { next, end, step } => {
+                let in_range = match *step > 0 {
+                    true => *next <= n && n < *end,
+                    false => *end < n && n <= *next,
+                };
+                in_range && (i128::from(n) - i128::from(*next)) % i128::from(*step) == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// `next_with` on a shared iterator. The functions it calls may advance
+    /// the same iterator, so it isn't borrowed while they run.
+    pub fn advance(iterator: &Shared<Lock<Self>>, caller: &mut impl Caller) -> Option<Object> {
+        let mut cursor = iterator.borrow().clone();
+        let item = cursor.next_with(caller);
+        *iterator.borrow_mut() = cursor;
+        item
+    }
+
+    /// The next item, `None` once there is none left or `caller` raised an
+    /// error.
+    pub fn next_with(&mut self, caller: &mut impl Caller) -> Option<Object> {
+        match self {
+            Self::Range { next, end, step } => {
+                let in_range = if *step > 0 {
+                    *next < *end
+                } else {
+                    *next > *end
+                };
+                if !in_range {
+                    return None;
+                }
+                let item = *next;
+                *next = next.saturating_add(*step);
+                Some(Object::Int(item))
+            }
+            Self::Array { array, index } => {
+                let item = array.get(*index)?.clone();
+                *index += 1;
+                Some(item)
+            }
+            Self::String { string, offset } => {
+                let ch = string[*offset..].chars().next()?;
+                *offset += ch.len_utf8();
+                Some(Object::String(ch.to_string().into()))
+            }
+            Self::Set { set, index } => {
+                let item = set.get(*index)?.clone();
+                *index += 1;
+                Some(item)
+            }
+            Self::Deque(deque) => deque_pop_front(deque),
+            Self::Map { source, function } => {
+                let item = Self::advance(source, caller)?;
+                caller.call(function, item)
+            }
+            Self::Filter { source, function } => loop {
+                let item = Self::advance(source, caller)?;
+                if caller.test(function, item.clone())? {
+                    return Some(item);
+                }
+            },
+            Self::Take { source, left } => {
+                if *left == 0 {
+                    return None;
+                }
+                *left -= 1;
+                Self::advance(source, caller)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Caller, FilipeIterator};
+    use crate::runtime::object::Object;
+    use crate::shared::{Lock, Shared};
+
+    /// Squares items and keeps the even ones.
+    struct Squares;
+
+    impl Caller for Squares {
+        fn call(&mut self, _: &Object, item: Object) -> Option<Object> {
+            match item {
+                Object::Int(n) => Some(Object::Int(n * n)),
+                _ => None,
+            }
+        }
+
+        fn test(&mut self, _: &Object, item: Object) -> Option<bool> {
+            Some(matches!(item, Object::Int(n) if n % 2 == 0))
+        }
+    }
+
+    fn collect(object: Object) -> Vec<String> {
+        items(FilipeIterator::new(&object).unwrap())
+    }
+
+    fn items(mut iterator: FilipeIterator) -> Vec<String> {
+        std::iter::from_fn(|| iterator.next_with(&mut Squares))
+            .map(|item| item.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_ranges_step_both_ways() {
+        let up = Object::Range {
+            start: 0,
+            end: 5,
+            step: 2,
+        };
+        assert_eq!(collect(up), ["0", "2", "4"]);
+
+        let down = Object::Range {
+            start: 3,
+            end: 0,
+            step: -1,
+        };
+        assert_eq!(collect(down), ["3", "2", "1"]);
+
+        let mut runtime = crate::runtime::Runtime::builder().build();
+        let error = runtime.run_source("range(0, 5, 0)").unwrap_err();
+        assert_eq!(error.code, "E013");
+        assert_eq!(error.msg, "range can't step by 0");
+    }
+
+    #[test]
+    fn test_strings_yield_chars() {
+        assert_eq!(collect(Object::String("añb".into())), ["'a'", "'ñ'", "'b'"]);
+    }
+
+    #[test]
+    fn test_adapters_are_lazy() {
+        let naturals = Object::Range {
+            start: 0,
+            end: i64::MAX,
+            step: 1,
+        };
+        let naturals = Shared::new(Lock::new(FilipeIterator::new(&naturals).unwrap()));
+        let squares = FilipeIterator::Map {
+            source: Shared::clone(&naturals),
+            function: Object::Null,
+        };
+        let even = FilipeIterator::Filter {
+            source: Shared::new(Lock::new(squares)),
+            function: Object::Null,
+        };
+        let first = FilipeIterator::Take {
+            source: Shared::new(Lock::new(even)),
+            left: 3,
+        };
+        assert_eq!(items(first), ["0", "4", "16"]);
+        // the source was advanced past the last item taken
+        let next = FilipeIterator::advance(&naturals, &mut Squares);
+        assert_eq!(next.unwrap().to_string(), "5");
+    }
+}
@@ -1,41 +1,43 @@
+pub mod iterator;
+pub mod set;
+
 use core::fmt;
 
 use super::object::Object;
+use super::type_system::Type;
 
 #[derive(Clone, Debug)]
 pub struct FilipeArray {
     inner: Vec<Object>,
-    __cursor__: usize,
+    pub items_type: Option<Type>,
 }
 
 impl FilipeArray {
-    pub fn new(init: Vec<Object>) -> Self {
+    pub fn new(init: Vec<Object>, items_type: Option<Type>) -> Self {
         Self {
             inner: init,
-            __cursor__: 0,
+            items_type,
         }
     }
 
-    /*
-    pub fn push(&mut self, item: Object) {
-        self.inner.push(item);
+    pub fn len(&self) -> usize {
+        self.inner.len()
     }
-    */
 
-    fn _next_item(&mut self) -> Option<Object> {
-        if self.inner.len() <= self.__cursor__ {
-            return None;
-        }
-        let item = self.inner[self.__cursor__].clone();
-        self.__cursor__ += 1;
-        Some(item)
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Object> {
+        self.inner.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Object> {
+        self.inner.get_mut(index)
     }
-}
 
-impl Iterator for FilipeArray {
-    type Item = Object;
-    fn next(&mut self) -> Option<Self::Item> {
-        self._next_item()
+    pub fn iter(&self) -> std::slice::Iter<'_, Object> {
+        self.inner.iter()
     }
 }
 
@@ -0,0 +1,142 @@
+use core::fmt;
+use std::collections::HashSet;
+
+use super::super::object::Object;
+use crate::shared::Shared;
+
+/// What a set tells its items apart by. Only values compared by what they
+/// hold can be items; floats are compared by their bits, with `-0.0` taken
+/// as `0.0`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Null,
+    Int(i64),
+    Float(u64),
+    Boolean(bool),
+    String(Shared<str>),
+}
+
+impl Key {
+    fn new(item: &Object) -> Option<Self> {
+        Some(match item {
+            Object::Null => Self::Null,
+            Object::Int(val) => Self::Int(*val),
+            Object::Float(val) if *val == 0.0 => Self::Float(0.0f64.to_bits()),
+            Object::Float(val) => Self::Float(val.to_bits()),
+            Object::Boolean(val) => Self::Boolean(*val),
+            Object::String(val) => Self::String(Shared::clone(val)),
+            _ => return None,
+        })
+    }
+}
+
+/// Unordered collection of distinct values, which iterates in the order
+/// they were added.
+#[derive(Clone, Debug, Default)]
+pub struct FilipeSet {
+    items: Vec<Object>,
+    keys: HashSet<Key>,
+}
+
+impl FilipeSet {
+    /// Adds `item` unless the set has it. Returns `false` when it can't be
+    /// an item, e.g. an array.
+    pub fn insert(&mut self, item: Object) -> bool {
+        let Some(key) = Key::new(&item) else {
+            return false;
+        };
+        if self.keys.insert(key) {
+            self.items.push(item);
+        }
+        true
+    }
+
+    pub fn contains(&self, item: &Object) -> bool {
+        Key::new(item).is_some_and(|key| self.keys.contains(&key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Object> {
+        self.items.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Object> {
+        self.items.iter()
+    }
+
+    /// The items of either set, those of `self` first.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for item in other.iter() {
+            union.insert(item.clone());
+        }
+        union
+    }
+
+    /// The items of `self` that `other` has too.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.retained(|item| other.contains(item))
+    }
+
+    /// The items of `self` that `other` doesn't have.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.retained(|item| !other.contains(item))
+    }
+
+    fn retained(&self, keep: impl Fn(&Object) -> bool) -> Self {
+        let mut retained = Self::default();
+        for item in self.iter().filter(|item| keep(item)) {
+            retained.insert(item.clone());
+        }
+        retained
+    }
+}
+
+impl fmt::Display for FilipeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = self
+            .items
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "Set{{{}}}", items.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilipeSet;
+    use crate::runtime::object::Object;
+
+    fn set(items: &[i64]) -> FilipeSet {
+        let mut set = FilipeSet::default();
+        for item in items {
+            assert!(set.insert(Object::Int(*item)));
+        }
+        set
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let (a, b) = (set(&[1, 2, 3, 2]), set(&[3, 4]));
+        assert_eq!(a.to_string(), "Set{1, 2, 3}");
+        assert_eq!(a.union(&b).to_string(), "Set{1, 2, 3, 4}");
+        assert_eq!(a.intersection(&b).to_string(), "Set{3}");
+        assert_eq!(a.difference(&b).to_string(), "Set{1, 2}");
+
+        let mut mixed = FilipeSet::default();
+        assert!(mixed.insert(Object::Float(-0.0)));
+        assert!(mixed.insert(Object::Float(0.0)));
+        assert!(mixed.insert(Object::String("0".into())));
+        assert_eq!(mixed.len(), 2);
+        assert!(!mixed.contains(&Object::Int(0)));
+        assert!(!mixed.insert(Object::new_array(vec![], None)));
+    }
+}
@@ -0,0 +1,178 @@
+//! The standard streams of scripts. `print` writes to stdout and builtins
+//! reading input read stdin; a program embedding filipe, the debug adapter
+//! or the playground can give a runtime streams of its own to capture or
+//! supply what its scripts see, e.g. with `Runtime::set_stdout`. Unset
+//! streams are the process' own.
+//!
+//! Builtins don't get the runtime, so the streams of the one running are
+//! put in a thread local while it runs, see `Runtime::isolated`. The tasks
+//! a runtime spawns share its streams.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::sync::Mutex;
+
+use crate::shared::Shared;
+
+/// What a runtime can write to: with the `sync` feature, it also has to be
+/// `Send` for the runtime to be.
+#[cfg(not(feature = "sync"))]
+pub trait Output: Write {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: Write> Output for T {}
+
+#[cfg(feature = "sync")]
+pub trait Output: Write + Send {}
+
+#[cfg(feature = "sync")]
+impl<T: Write + Send> Output for T {}
+
+/// What a runtime can read from, see `Output`.
+#[cfg(not(feature = "sync"))]
+pub trait Input: BufRead {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: BufRead> Input for T {}
+
+#[cfg(feature = "sync")]
+pub trait Input: BufRead + Send {}
+
+#[cfg(feature = "sync")]
+impl<T: BufRead + Send> Input for T {}
+
+type Stream<T> = Option<Shared<Mutex<Box<T>>>>;
+
+/// The streams of a runtime; `None` for the process' own.
+#[derive(Clone, Default)]
+pub(super) struct Streams {
+    stdout: Stream<dyn Output>,
+    stderr: Stream<dyn Output>,
+    stdin: Stream<dyn Input>,
+}
+
+impl Streams {
+    pub(super) fn set_stdout(&mut self, stdout: Box<dyn Output>) {
+        self.stdout = Some(Shared::new(Mutex::new(stdout)));
+    }
+
+    pub(super) fn set_stderr(&mut self, stderr: Box<dyn Output>) {
+        self.stderr = Some(Shared::new(Mutex::new(stderr)));
+    }
+
+    pub(super) fn set_stdin(&mut self, stdin: Box<dyn Input>) {
+        self.stdin = Some(Shared::new(Mutex::new(stdin)));
+    }
+}
+
+thread_local! {
+    /// The streams of the runtime running on this thread.
+    static CURRENT: RefCell<Streams> = RefCell::new(Streams::default());
+}
+
+/// Swaps `streams` with the ones builtins use on this thread.
+pub(super) fn swap(streams: &mut Streams) {
+    CURRENT.with(|current| std::mem::swap(&mut *current.borrow_mut(), streams));
+}
+
+/// The streams builtins use on this thread, for the tasks of the runtime
+/// running on it.
+#[cfg(feature = "sync")]
+pub(super) fn current() -> Streams {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+fn current_stream<T: ?Sized>(stream: impl FnOnce(&Streams) -> &Stream<T>) -> Stream<T> {
+    // cloned out, so whatever the stream runs can't find the thread local
+    // borrowed
+    CURRENT.with(|current| stream(&current.borrow()).clone())
+}
+
+pub fn write_stdout(text: &str) -> io::Result<()> {
+    match current_stream(|streams| &streams.stdout) {
+        Some(stdout) => lock(&stdout).write_all(text.as_bytes()),
+        None => io::stdout().lock().write_all(text.as_bytes()),
+    }
+}
+
+pub fn write_stderr(text: &str) -> io::Result<()> {
+    match current_stream(|streams| &streams.stderr) {
+        Some(stderr) => lock(&stderr).write_all(text.as_bytes()),
+        None => io::stderr().lock().write_all(text.as_bytes()),
+    }
+}
+
+/// The next line of stdin without its line break, or `None` at its end.
+pub fn read_line() -> io::Result<Option<String>> {
+    let mut line = String::new();
+    let read = match current_stream(|streams| &streams.stdin) {
+        Some(stdin) => lock(&stdin).read_line(&mut line),
+        None => io::stdin().lock().read_line(&mut line),
+    }?;
+    if read == 0 {
+        return Ok(None);
+    }
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
+
+fn lock<T: ?Sized>(stream: &Mutex<Box<T>>) -> std::sync::MutexGuard<'_, Box<T>> {
+    stream
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A `Write` that hands what is written to a function as text, e.g. to
+/// send each line `print` writes to an editor.
+pub struct TextWriter<F: FnMut(&str)>(pub F);
+
+impl<F: FnMut(&str)> Write for TextWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.0)(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::object::Object;
+    use crate::runtime::Runtime;
+    use crate::shared::Lock;
+
+    fn runtime(output: &Shared<Lock<String>>, input: &'static str) -> Runtime {
+        let written = Shared::clone(output);
+        let mut runtime = Runtime::builder()
+            .stdout(TextWriter(move |text: &str| {
+                written.borrow_mut().push_str(text)
+            }))
+            .stdin(io::Cursor::new(input))
+            .build();
+        runtime.register_fn("readLine", |_| {
+            let line = read_line().unwrap_or_default();
+            Ok(line.map_or(Object::Null, |line| Object::String(line.as_str().into())))
+        });
+        runtime
+    }
+
+    #[test]
+    fn test_runtimes_on_one_thread_keep_their_streams() {
+        let first = Shared::new(Lock::new(String::new()));
+        let second = Shared::new(Lock::new(String::new()));
+        let mut a = runtime(&first, "first\r\nsecond");
+        let mut b = runtime(&second, "other");
+        a.run_source("print(\"a: \", readLine())").unwrap();
+        b.run_source("print(\"b: \", readLine())").unwrap();
+        a.run_source("print(\"a: \", readLine(), readLine())")
+            .unwrap();
+
+        assert_eq!(*first.borrow(), "a: first\na: secondnull\n");
+        assert_eq!(*second.borrow(), "b: other\n");
+        write_stdout("").unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+//! Tasks: `spawn(function, args...)` calls a function on another thread
+//! and gives back a task, which `join(task)` waits for and returns what
+//! the function returned, or raises what it raised:
+//!
+//! ```text
+//! define fetch(url: string): int {
+//!   return len(url)
+//! }
+//! let a = spawn(fetch, "https://a.example")
+//! let b = spawn(fetch, "https://b.example")
+//! print(join(a) + join(b))
+//! ```
+//!
+//! Tasks need the `sync` feature, which makes values safe to share between
+//! threads. They run on a pool of threads that grows when all of them are
+//! busy, so a task waiting for another one never holds up the rest, and
+//! have as much stack as the main thread. Each gets a runtime of its own
+//! with the spawning one's permissions and scope, whose globals they share,
+//! and its limits: steps and memory tasks use count against the spawning
+//! run's budget. The hooks, tracer, debugger and profiler aren't carried
+//! over. Without `sync`, tasks run as soon as they
+//! are spawned, on the script's thread.
+//!
+//! Calling a function declared with `async define` spawns it the same way,
+//! and `await` joins what it is given when that is a task, so requests can
+//! overlap without naming `spawn` and `join`:
+//!
+//! ```text
+//! async define fetch(url: string): int {
+//!   return len(url)
+//! }
+//! let a = fetch("https://a.example")
+//! let b = fetch("https://b.example")
+//! print(await a + await b)
+//! ```
+//!
+//! Awaiting anything else gives it back as it is.
+
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+
+use super::evaluators::func_call_evaluator::call_function;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo, Value};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::userdata::{NativeBounds, UserType};
+use super::Runtime;
+use crate::messages::{count_args, msg};
+
+type TaskResult = Result<Value, RuntimeError>;
+
+enum TaskState {
+    Running(Receiver<TaskResult>),
+    Done(TaskResult),
+}
+
+/// What `spawn` and calls to `async` functions return.
+struct Task(Mutex<TaskState>);
+
+impl Task {
+    /// Waits for it to be done. Joining it again returns the same.
+    fn join(&self) -> TaskResult {
+        let mut state = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        if let TaskState::Running(receiver) = &*state {
+            let result = receiver.recv().unwrap_or_else(|_| {
+                Err(RuntimeError::new(
+                    ErrorKind::ValueError,
+                    msg!("E013.task_lost"),
+                ))
+            });
+            *state = TaskState::Done(result);
+        }
+        match &*state {
+            TaskState::Done(result) => result.clone(),
+            TaskState::Running(_) => unreachable!(),
+        }
+    }
+}
+
+impl UserType for Task {
+    fn type_name() -> &'static str {
+        "task"
+    }
+}
+
+impl Runtime {
+    /// `spawn`, which unlike other builtins needs the runtime calling it.
+    pub(super) fn spawn_task(&mut self, args: Vec<ObjectInfo>) -> Option<Object> {
+        let Some(function) = args.first().map(|arg| arg.value.clone()) else {
+            let msg = msg!(
+                "E012.arg_count",
+                name = "spawn",
+                expected = count_args(1, None),
+                found = 0
+            );
+            self.error_handler.set_error(ErrorKind::ArgumentError, msg);
+            return None;
+        };
+        if !matches!(
+            function,
+            Object::UserDefinedFunction(_) | Object::BuiltInFunction(_) | Object::NativeFunction(_)
+        ) {
+            let msg = msg!(
+                "E012.arg_type",
+                index = 1,
+                name = "spawn",
+                expected = "function",
+                found = args[0].type_
+            );
+            self.error_handler.set_error(ErrorKind::ArgumentError, msg);
+            return None;
+        }
+        let args = args[1..].to_vec();
+        Some(self.start_task(move |runtime| call_function(runtime, "task", function, args)))
+    }
+
+    /// Runs `job` as a task, see `tasks`, and returns the task.
+    pub(super) fn start_task(
+        &mut self,
+        job: impl FnOnce(&mut Runtime) -> Option<Object> + NativeBounds + 'static,
+    ) -> Object {
+        let (sender, receiver) = mpsc::channel();
+
+        #[cfg(feature = "sync")]
+        {
+            let mut task = self.task_runtime();
+            pool::execute(Box::new(move || {
+                let returned = task.isolated(|task| {
+                    let returned = job(task);
+                    if !task.error_handler.has_error() {
+                        task.run_timers();
+                    }
+                    returned
+                });
+                let _ = sender.send(task.take_result(returned));
+            }));
+        }
+        #[cfg(not(feature = "sync"))]
+        {
+            let returned = job(self);
+            let _ = sender.send(self.take_result(returned));
+        }
+
+        Object::user_data(Task(Mutex::new(TaskState::Running(receiver))))
+    }
+
+    /// `await value`: what the task returned when `value` is one, else
+    /// `value` itself.
+    pub(super) fn await_value(&mut self, value: Object) -> Option<Object> {
+        let task = match &value {
+            Object::UserData(data) => data.downcast_ref::<Task>(),
+            _ => None,
+        };
+        let Some(task) = task else {
+            return Some(value);
+        };
+        match task.join() {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error_handler.set_error(error.kind, error.msg);
+                None
+            }
+        }
+    }
+
+    /// A runtime for a task spawned from this one, see `tasks`.
+    #[cfg(feature = "sync")]
+    fn task_runtime(&mut self) -> Runtime {
+        use crate::shared::Shared;
+
+        let mut runtime = Runtime::new(Shared::clone(&self.env));
+        // the budgets are this one's, not copies of them
+        runtime.max_steps = self.max_steps;
+        runtime.steps = Shared::clone(&self.steps);
+        runtime.memory = self.memory.clone();
+        runtime.max_call_depth = self.max_call_depth;
+        runtime.max_timer_wait = self.max_timer_wait;
+        runtime.wrapping_arithmetic = self.wrapping_arithmetic;
+        runtime.strict = self.strict;
+        runtime.permissions = self.permissions.clone();
+        runtime.lang = self.lang;
+        runtime.interrupt = self.interrupt.clone();
+        runtime.modules = self.modules.clone();
+        // this one's are in place while it runs
+        runtime.streams = super::streams::current();
+        // seeded from this one's, so seeded scripts stay reproducible
+        runtime.rng = super::flstdlib::fork_rng();
+        runtime
+    }
+
+    /// What a task's function returned, or the error it raised, which is
+    /// taken so the spawning script doesn't see it until it joins.
+    fn take_result(&mut self, returned: Option<Object>) -> TaskResult {
+        self.returning = false;
+        match self.error_handler.take_error() {
+            Some(error) => Err(error),
+            None => Ok(returned.unwrap_or(Object::Null)),
+        }
+    }
+}
+
+/// Stands in for `spawn` when it isn't called by its name, as the runtime
+/// only runs it then.
+pub fn filipe_spawn(_: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    BuiltInFuncReturnValue::Error(RuntimeError::new(
+        ErrorKind::TypeError,
+        msg!("E011.call_by_name", name = "spawn"),
+    ))
+}
+
+/// Waits for a task to be done. Joining it again returns the same.
+pub fn filipe_join(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("join", args);
+    let task = match args.expect_count(1).and_then(|_| args.user_data::<Task>(0)) {
+        Ok(task) => task,
+        Err(error) => return BuiltInFuncReturnValue::Error(error),
+    };
+    match task.join() {
+        Ok(value) => BuiltInFuncReturnValue::Object(value),
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+#[cfg(feature = "sync")]
+mod pool {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::thread;
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    /// Of each thread, as much as the main thread has, so tasks recurse as
+    /// deep as the script could.
+    const STACK_SIZE: usize = 8 << 20;
+
+    struct Pool {
+        jobs: Sender<Job>,
+        queue: Arc<Mutex<Receiver<Job>>>,
+        /// Threads waiting for a job that no one has claimed.
+        idle: AtomicUsize,
+    }
+
+    fn pool() -> &'static Pool {
+        static POOL: OnceLock<Pool> = OnceLock::new();
+        POOL.get_or_init(|| {
+            let (jobs, queue) = mpsc::channel();
+            Pool {
+                jobs,
+                queue: Arc::new(Mutex::new(queue)),
+                idle: AtomicUsize::new(0),
+            }
+        })
+    }
+
+    /// Runs `job` on an idle thread of the pool, or on a new one when none
+    /// is, so it never waits behind jobs that wait for it.
+    pub fn execute(job: Job) {
+        let pool = pool();
+        let claimed = pool
+            .idle
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |idle| {
+                idle.checked_sub(1)
+            })
+            .is_ok();
+        if !claimed {
+            let queue = Arc::clone(&pool.queue);
+            thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn(move || work(queue))
+                .expect("failed to spawn a task thread");
+        }
+        let _ = pool.jobs.send(job);
+    }
+
+    fn work(queue: Arc<Mutex<Receiver<Job>>>) {
+        loop {
+            let job = queue.lock().unwrap_or_else(|err| err.into_inner()).recv();
+            let Ok(job) = job else {
+                return;
+            };
+            job();
+            pool().idle.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_and_join_tasks() {
+        let mut runtime = Runtime::builder().build();
+        let source = "define work(n: int): int {\n\
+                      \x20 let total = 0\n\
+                      \x20 for x in range(0, n) {\n\
+                      \x20   total = total + x\n\
+                      \x20 }\n\
+                      \x20 return total\n\
+                      }\n\
+                      let tasks = [spawn(work, 10), spawn(work, 100), spawn(work, 1000)]\n\
+                      join(tasks[0]) + join(tasks[1]) + join(tasks[2]) + join(tasks[2])";
+        let value = runtime.run_source(source).unwrap();
+        assert_eq!(value.to_string(), (45 + 4950 + 499500 * 2).to_string());
+        assert_eq!(
+            runtime.run_source("typeof(tasks[0])").unwrap().to_string(),
+            "task"
+        );
+
+        let error = runtime
+            .run_source("let failing = spawn(work, \"ten\")\njoin(failing)")
+            .unwrap_err();
+        assert_eq!(error.code, "E011");
+        let error = runtime.run_source("spawn(1)").unwrap_err();
+        assert_eq!(error.code, "E012");
+    }
+
+    #[test]
+    fn test_async_functions_are_awaited() {
+        let mut runtime = Runtime::builder().build();
+        let source = "async define square(n: int): int {\n\
+                      \x20 return n * n\n\
+                      }\n\
+                      let a = square(3)\n\
+                      let b = square(4)\n\
+                      let results = [await a + await b, await 7]\n\
+                      results";
+        let value = runtime.run_source(source).unwrap();
+        assert_eq!(value.to_string(), "[25, 7]");
+        assert_eq!(runtime.run_source("typeof(a)").unwrap().to_string(), "task");
+
+        let error = runtime
+            .run_source("let failing = square(\"three\")\nawait failing")
+            .unwrap_err();
+        assert_eq!(error.code, "E011");
+    }
+
+    #[test]
+    #[cfg(feature = "sync")]
+    fn test_tasks_recurse_as_deep_as_the_main_thread() {
+        let mut runtime = Runtime::builder()
+            .max_call_depth(crate::runtime::DEFAULT_MAX_CALL_DEPTH)
+            .build();
+        let source = "define f(n: int): int {\n  return f(n + 1)\n}\njoin(spawn(f, 0))";
+        let error = runtime.run_source(source).unwrap_err();
+        assert_eq!(error.code, "E014");
+        assert_eq!(error.msg, "Calls nested deeper than the limit of 400");
+    }
+
+    #[test]
+    fn test_tasks_share_the_limits_of_the_run() {
+        let source = "define work(n: int): int {\n\
+                      \x20 let total = 0\n\
+                      \x20 for x in range(0, n) {\n\
+                      \x20   total = total + x\n\
+                      \x20 }\n\
+                      \x20 return total\n\
+                      }\n";
+        let mut runtime = Runtime::builder().max_steps(1000).build();
+        runtime.run_source(source).unwrap();
+        // each fits in the budget, but not all of them together
+        runtime.run_source("join(spawn(work, 300))").unwrap();
+        let error = runtime
+            .run_source(
+                "let tasks = [spawn(work, 300), spawn(work, 300), spawn(work, 300)]\n\
+                         join(tasks[0]) + join(tasks[1]) + join(tasks[2])",
+            )
+            .unwrap_err();
+        assert_eq!(error.code, "E014");
+
+        let mut runtime = Runtime::builder().max_memory(1 << 16).build();
+        let source = "define grow(): int {\n\
+                      \x20 let s = \"x\"\n\
+                      \x20 for i in range(0, 20) { s = s + s }\n\
+                      \x20 return len(s)\n\
+                      }\n\
+                      join(spawn(grow))";
+        let error = runtime.run_source(source).unwrap_err();
+        assert_eq!(error.code, "E015");
+    }
+}
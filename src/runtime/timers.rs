@@ -0,0 +1,276 @@
+//! Timers: `setTimeout(function, ms)` calls a function once, `ms`
+//! milliseconds from now, and `setInterval(function, ms)` every `ms`
+//! milliseconds, until `cancel` is given the timer they returned:
+//!
+//! ```text
+//! let ticks = 0
+//! define tick(): void {
+//!   ticks = ticks + 1
+//!   print("tick ", ticks)
+//! }
+//! let ticking = setInterval(tick, 100)
+//! define stop(): void {
+//!   cancel(ticking)
+//! }
+//! setTimeout(stop, 350)
+//! ```
+//!
+//! Functions given to timers take no arguments. They are called by the
+//! runtime's event loop, which runs once the program is done, like `eval`
+//! returning, and waits for each timer in turn until none is left, so a
+//! script with an interval runs until it is cancelled or the runtime is
+//! interrupted. An error raised by a timer's function stops the loop and
+//! is the program's. A task runs its own timers before it is done. Each
+//! call counts as a step towards `--max-steps`, so an interval whose
+//! function does nothing still stops at the limit. Runtimes can also limit
+//! how long the loop waits, see `Runtime::set_max_timer_wait`, and those
+//! whose permissions turn `timers` off, like sandboxed ones, raise a
+//! `PermissionError` instead of scheduling any.
+//!
+//! `wasm32-unknown-unknown` has no clock, so there scheduling a timer
+//! raises a `ValueError`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::evaluators::func_call_evaluator::call_function;
+use super::native::Args;
+use super::object::{BuiltInFuncReturnValue, Object, ObjectInfo, Value};
+#[cfg(not(target_arch = "wasm32"))]
+use super::permissions::{self, Capability};
+use super::runtime_error::{ErrorKind, RuntimeError};
+use super::userdata::UserType;
+use super::Runtime;
+use crate::messages::msg;
+
+/// How long the event loop sleeps at most before checking whether it was
+/// interrupted.
+#[cfg(not(target_arch = "wasm32"))]
+const SLEEP_SLICE: Duration = Duration::from_millis(50);
+
+/// What `setTimeout` and `setInterval` return.
+#[derive(Default)]
+struct Timer {
+    cancelled: AtomicBool,
+}
+
+impl UserType for Timer {
+    fn type_name() -> &'static str {
+        "timer"
+    }
+}
+
+/// A timer waiting in the event loop.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(super) struct Scheduled {
+    due: Instant,
+    /// For `setInterval`.
+    every: Option<Duration>,
+    function: Value,
+    timer: Value,
+}
+
+impl Scheduled {
+    fn is_cancelled(&self) -> bool {
+        match &self.timer {
+            Object::UserData(data) => data
+                .downcast_ref::<Timer>()
+                .is_some_and(|timer| timer.cancelled.load(Ordering::SeqCst)),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Runtime {
+    /// `setTimeout` and `setInterval`, which unlike other builtins need the
+    /// runtime calling them.
+    pub(super) fn schedule_timer(&mut self, name: &str, args: Vec<ObjectInfo>) -> Option<Object> {
+        let call = Args::new(name, &args);
+        let scheduled = permissions::require(Capability::Timers)
+            .and_then(|_| call.expect_count(2))
+            .and_then(|_| call.int(1))
+            // like JavaScript, a negative delay is none
+            .map(|ms| Duration::from_millis(ms.max(0) as u64));
+        let delay = match scheduled {
+            Ok(delay) => delay,
+            Err(error) => {
+                self.error_handler.set_error(error.kind, error.msg);
+                return None;
+            }
+        };
+        let timer = Object::user_data(Timer::default());
+        self.timers.push(Scheduled {
+            due: Instant::now() + delay,
+            every: (name == "setInterval").then_some(delay),
+            function: args[0].value.clone(),
+            timer: timer.clone(),
+        });
+        Some(timer)
+    }
+
+    /// The event loop: calls the function of each timer when it is due,
+    /// until none is left or one raises an error.
+    pub(super) fn run_timers(&mut self) {
+        let deadline = self
+            .max_timer_wait
+            .map(|wait| (Instant::now() + wait, wait));
+        loop {
+            self.timers.retain(|scheduled| !scheduled.is_cancelled());
+            // the earliest, or the first scheduled of those due at once
+            let Some((index, due)) = self
+                .timers
+                .iter()
+                .enumerate()
+                .min_by_key(|(index, scheduled)| (scheduled.due, *index))
+                .map(|(index, scheduled)| (index, scheduled.due))
+            else {
+                return;
+            };
+            if !self.check_interrupt() {
+                return;
+            }
+            if let Some((_, wait)) = deadline.filter(|(deadline, _)| due > *deadline) {
+                // no use waiting for what comes too late anyway
+                self.error_handler.set_error(
+                    ErrorKind::ExecutionLimitExceeded,
+                    msg!("E014.timer_wait", max = wait.as_millis()),
+                );
+                return;
+            }
+            let wait = due.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                thread::sleep(wait.min(SLEEP_SLICE));
+                continue;
+            }
+
+            if !self.consume_step() {
+                return;
+            }
+            let scheduled = self.timers.remove(index);
+            let function = scheduled.function.clone();
+            if let Some(every) = scheduled.every {
+                self.timers.push(Scheduled {
+                    due: scheduled.due + every,
+                    ..scheduled
+                });
+            }
+            call_function(self, "timer", function, vec![]);
+            self.returning = false;
+            if self.error_handler.has_error() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Runtime {
+    /// Fails, as there is no clock to time the timer with.
+    pub(super) fn schedule_timer(&mut self, _: &str, _: Vec<ObjectInfo>) -> Option<Object> {
+        self.error_handler
+            .set_error(ErrorKind::ValueError, msg!("E013.no_clock"));
+        None
+    }
+
+    /// Nothing to do, as no timer can be scheduled.
+    pub(super) fn run_timers(&mut self) {}
+}
+
+/// Stands in for `setTimeout` and `setInterval` when they aren't called by
+/// their names, as the runtime only runs them then.
+pub fn filipe_set_timer(_: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    BuiltInFuncReturnValue::Error(RuntimeError::new(
+        ErrorKind::TypeError,
+        msg!("E011.call_by_name", name = "setTimeout"),
+    ))
+}
+
+/// Stops a timer. Cancelling it again does nothing.
+pub fn filipe_cancel(args: &[ObjectInfo]) -> BuiltInFuncReturnValue {
+    let args = Args::new("cancel", args);
+    match args
+        .expect_count(1)
+        .and_then(|_| args.user_data::<Timer>(0))
+    {
+        Ok(timer) => {
+            timer.cancelled.store(true, Ordering::SeqCst);
+            BuiltInFuncReturnValue::Object(Object::Null)
+        }
+        Err(error) => BuiltInFuncReturnValue::Error(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::permissions::RuntimePermissions;
+
+    #[test]
+    fn test_timers_run_after_the_program() {
+        let mut runtime = Runtime::builder().build();
+        let source = "let events = \"main\"\n\
+                      let ticks = 0\n\
+                      define record(event: string): void {\n\
+                      \x20 events = events + \" \" + event\n\
+                      }\n\
+                      define late(): void {\n\
+                      \x20 record(\"late\")\n\
+                      }\n\
+                      define soon(): void {\n\
+                      \x20 record(\"soon\")\n\
+                      }\n\
+                      define tick(): void {\n\
+                      \x20 record(\"tick\")\n\
+                      \x20 ticks = ticks + 1\n\
+                      \x20 if ticks == 4 {\n\
+                      \x20   cancel(ticking)\n\
+                      \x20 }\n\
+                      }\n\
+                      let ticking = setInterval(tick, 10)\n\
+                      setTimeout(late, 25)\n\
+                      setTimeout(soon, 0)\n\
+                      cancel(setTimeout(late, 1))";
+        let started = Instant::now();
+        runtime.run_source(source).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(25));
+        let events = runtime.run_source("events").unwrap();
+        assert_eq!(events.to_string(), "'main soon tick tick late tick tick'");
+
+        let error = runtime
+            .run_source("define fail(): void {\n  raise(error(\"Late\", \"failed\"))\n}\nsetTimeout(fail, 0)")
+            .unwrap_err();
+        assert_eq!(error.msg, "failed");
+
+        let mut runtime = Runtime::builder().max_steps(100).build();
+        let error = runtime
+            .run_source("define idle(): void {\n}\nsetInterval(idle, 0)")
+            .unwrap_err();
+        assert_eq!(error.code, "E014");
+
+        // a timer due past the limit fails without waiting for it
+        let mut runtime = Runtime::builder()
+            .max_timer_wait(Duration::from_millis(100))
+            .build();
+        let started = Instant::now();
+        let error = runtime
+            .run_source("define idle(): void {\n}\nsetTimeout(idle, 100000000)")
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(
+            error.msg,
+            "Timers would wait longer than the limit of 100 ms"
+        );
+        runtime.run_source("setTimeout(idle, 10)").unwrap();
+
+        let mut runtime = Runtime::builder()
+            .permissions(RuntimePermissions::sandboxed())
+            .build();
+        let error = runtime
+            .run_source("define idle(): void {\n}\nsetTimeout(idle, 0)")
+            .unwrap_err();
+        assert_eq!(error.msg, "'timers' isn't permitted here");
+    }
+}
@@ -0,0 +1,144 @@
+//! `--trace`: a line for every statement the runtime executes, with where
+//! it is, what it produced and how deep in calls it ran, indented two
+//! spaces per call:
+//!
+//! ```text
+//! 1:1 define double
+//! 2:3   return n * 2 => 42
+//! 4:1 let x = double(21) => 42
+//! 5:1 if x > 40
+//! 6:3 print(x) => null
+//! ```
+//!
+//! Statements with a block, like `if` and `for`, are logged as they start so
+//! the statements of the block follow them; the others once they have run.
+
+use std::io::Write;
+
+use super::object::Object;
+use super::Runtime;
+use crate::frontend::ast::*;
+use crate::frontend::token::Span;
+
+pub struct Tracer {
+    out: Box<dyn Write + Send>,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self { out }
+    }
+}
+
+impl Runtime {
+    /// Logs `stmt` before it runs when it has a block.
+    pub(super) fn trace_start(&mut self, stmt: &Stmt) {
+        if has_block(&stmt.kind) && self.tracer.is_some() {
+            self.trace(stmt.span, describe(&stmt.kind));
+        }
+    }
+
+    /// Logs `stmt` after it ran, with the value it produced or the error it
+    /// raised. Statements with a block are only logged again for errors.
+    pub(super) fn trace_end(&mut self, stmt: &Stmt, value: Option<&Object>) {
+        if self.tracer.is_none() {
+            return;
+        }
+        let error = self.error_handler.get_error();
+        if has_block(&stmt.kind) && error.is_none() {
+            return;
+        }
+        let value = match &stmt.kind {
+            StmtKind::Let(Identifier(name), ..) => {
+                self.env.borrow().resolve(name).map(|info| info.value)
+            }
+            _ => value.cloned(),
+        };
+        let line = match (error, value) {
+            (Some(error), _) => format!("{} raised {}", describe(&stmt.kind), error.kind),
+            (None, Some(value)) => format!("{} => {}", describe(&stmt.kind), value),
+            (None, None) => describe(&stmt.kind),
+        };
+        self.trace(stmt.span, line);
+    }
+
+    fn trace(&mut self, span: Span, line: String) {
+        let indent = "  ".repeat(self.call_stack.len());
+        if let Some(tracer) = self.tracer.as_mut() {
+            let _ = writeln!(tracer.out, "{} {}{}", span, indent, line);
+        }
+    }
+}
+
+fn has_block(stmt: &StmtKind) -> bool {
+    matches!(
+        stmt,
+        StmtKind::If { .. } | StmtKind::ForLoop { .. } | StmtKind::Try { .. }
+    )
+}
+
+/// A one line summary of a statement.
+fn describe(stmt: &StmtKind) -> String {
+    match stmt {
+        StmtKind::Expr(expr) => expr.to_string(),
+        StmtKind::Let(Identifier(name), _, Some(expr)) => format!("let {} = {}", name, expr),
+        StmtKind::Let(Identifier(name), _, None) => format!("let {}", name),
+        StmtKind::Func(Identifier(name), ..) => format!("define {}", name),
+        StmtKind::Return(Some(expr)) => format!("return {}", expr),
+        StmtKind::Return(None) => "return".to_string(),
+        StmtKind::If { condition, .. } => format!("if {}", condition),
+        StmtKind::ForLoop {
+            cursor, iterable, ..
+        } => format!("for {} in {}", cursor, iterable),
+        StmtKind::Try { .. } => "try".to_string(),
+        StmtKind::Assert(condition) => format!("assert {}", condition),
+        StmtKind::Import(name) => format!("import \"{}\"", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::frontend::{lexer::Lexer, parser::Parser};
+    use crate::runtime::{context::Context, flstdlib::builtins};
+    use crate::shared::{Lock, Shared};
+
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Log {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_indents_calls() {
+        let input = "define double(n: int): int {\n  return n * 2\n}\n\
+                     if true {\n  let x = double(2)\n}"
+            .chars()
+            .collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+
+        let log = Log::default();
+        let env = Context::make_global(builtins());
+        let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+        runtime.set_tracer(Box::new(log.clone()));
+        runtime.eval(program).unwrap();
+        let trace = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            trace,
+            "1:1 define double\n\
+             4:1 if true\n\
+             2:3   return n * 2 => 4\n\
+             5:3 let x = double(2) => 4\n"
+        );
+    }
+}
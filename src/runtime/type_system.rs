@@ -1,5 +1,6 @@
 use super::{object::Object, ExprType};
 
+#[allow(clippy::enum_variant_names)]
 #[derive(PartialEq, Clone, Debug)]
 pub enum Type {
     Null,
@@ -10,8 +11,56 @@ pub enum Type {
     Boolean,
     Function,
     Range,
+    /// With the type of its items when the checker knows it; iterators
+    /// made at runtime don't.
+    Iterator(Option<Box<Type>>),
+    Error,
     TypeAnnot,
     Array(Option<Box<Type>>),
+    Set,
+    /// Of a variable declared with `int | string`, which can hold a value of
+    /// any of the types. Values themselves always have one.
+    Union(Vec<Type>),
+    /// Of a value of the embedding program, by the name it picked.
+    UserData(&'static str),
+}
+
+impl Type {
+    /// Whether a value of type `found` can be stored where `self` is
+    /// declared: the same type, or one of the types of a union.
+    pub fn accepts(&self, found: &Type) -> bool {
+        match self {
+            Type::Union(members) => members.iter().any(|member| member.accepts(found)),
+            _ => self == found,
+        }
+    }
+
+    /// The most precise type that both `self` and `other` can be, where
+    /// containers with unknown items take the items of the other, e.g.
+    /// `Array<int>` for `Array<any>` and `Array<int>`. `None` when they
+    /// can't be the same type.
+    pub fn unify(&self, other: &Type) -> Option<Type> {
+        let items = |a: &Option<Box<Type>>, b: &Option<Box<Type>>| match (a, b) {
+            (Some(a), Some(b)) => a.unify(b).map(|items| Some(Box::new(items))),
+            (items, None) | (None, items) => Some(items.clone()),
+        };
+        match (self, other) {
+            (Type::Array(a), Type::Array(b)) => items(a, b).map(Type::Array),
+            (Type::Iterator(a), Type::Iterator(b)) => items(a, b).map(Type::Iterator),
+            _ => (self == other).then(|| self.clone()),
+        }
+    }
+
+    /// Type of the items a for-loop over a value of this type gets, when it
+    /// is known.
+    pub fn items_type(&self) -> Option<Type> {
+        match self {
+            Type::Range => Some(Type::Int),
+            Type::String => Some(Type::String),
+            Type::Array(items_type) | Type::Iterator(items_type) => items_type.as_deref().cloned(),
+            _ => None,
+        }
+    }
 }
 
 pub fn expr_type_to_object_type(var_type: &ExprType) -> Type {
@@ -21,24 +70,46 @@ pub fn expr_type_to_object_type(var_type: &ExprType) -> Type {
         ExprType::Void => Type::Void,
         ExprType::Int => Type::Int,
         ExprType::Float => Type::Float,
+        ExprType::Set => Type::Set,
+        ExprType::Union(members) => {
+            Type::Union(members.iter().map(expr_type_to_object_type).collect())
+        }
         ExprType::Array(items_type) => {
-            Type::Array(Some(Box::new(expr_type_to_object_type(&items_type))))
+            Type::Array(Some(Box::new(expr_type_to_object_type(items_type))))
         }
     }
 }
 
+/// The annotation declaring `type_`, if one can: there are none for e.g.
+/// functions or `null`.
+pub fn object_type_to_expr_type(type_: &Type) -> Option<ExprType> {
+    Some(match type_ {
+        Type::String => ExprType::String,
+        Type::Boolean => ExprType::Boolean,
+        Type::Void => ExprType::Void,
+        Type::Int => ExprType::Int,
+        Type::Float => ExprType::Float,
+        Type::Set => ExprType::Set,
+        Type::Union(members) => ExprType::Union(
+            members
+                .iter()
+                .map(object_type_to_expr_type)
+                .collect::<Option<_>>()?,
+        ),
+        Type::Array(Some(items_type)) => {
+            ExprType::Array(Box::new(object_type_to_expr_type(items_type)?))
+        }
+        _ => return None,
+    })
+}
+
 pub fn object_to_type(object: &Object) -> Type {
     match object {
         Object::Null => Type::Null,
         Object::String(_) => Type::String,
         Object::Boolean(_) => Type::Boolean,
-        Object::BuiltInFunction(_) => Type::Function,
-        Object::UserDefinedFunction {
-            params: _,
-            body: _,
-            return_type: _,
-        } => Type::Function,
-        Object::RetVal(val) => object_to_type(&val),
+        Object::BuiltInFunction(_) | Object::NativeFunction(_) => Type::Function,
+        Object::UserDefinedFunction(_) => Type::Function,
         Object::Type(_) => Type::TypeAnnot,
         Object::Range {
             start: _,
@@ -47,14 +118,10 @@ pub fn object_to_type(object: &Object) -> Type {
         } => Type::Range,
         Object::Int(_) => Type::Int,
         Object::Float(_) => Type::Float,
-        Object::Array {
-            inner: _,
-            items_type,
-        } => {
-            if items_type.is_none() {
-                return Type::Array(None);
-            }
-            return Type::Array(Some(Box::new(items_type.clone().unwrap())));
-        },
+        Object::Array(inner) => Type::Array(inner.items_type.clone().map(Box::new)),
+        Object::Set(_) => Type::Set,
+        Object::Iterator(_) => Type::Iterator(None),
+        Object::Error(_) => Type::Error,
+        Object::UserData(data) => Type::UserData(data.type_name()),
     }
 }
@@ -0,0 +1,156 @@
+//! Values of the program embedding filipe that scripts hold on to as they
+//! are, like database connections or game entities. Scripts can't look
+//! inside them, only pass them to the native functions made for them:
+//!
+//! ```
+//! use std::sync::atomic::{AtomicI64, Ordering};
+//! use filipe::{Runtime, UserType, Value};
+//!
+//! struct Counter(AtomicI64);
+//!
+//! impl UserType for Counter {
+//!     fn type_name() -> &'static str {
+//!         "Counter"
+//!     }
+//! }
+//!
+//! let mut runtime = Runtime::builder().build();
+//! runtime.register_fn("newCounter", |_| Ok(Value::user_data(Counter(AtomicI64::new(0)))));
+//! runtime.register_method("add", |counter: &Counter, args| {
+//!     let n = counter.0.fetch_add(args.int(0)?, Ordering::Relaxed);
+//!     Ok(Value::Int(n))
+//! });
+//! let total = runtime.run_source("let c = newCounter()\nadd(c, 2)\nadd(c, 3)\nadd(c, 0)");
+//! assert_eq!(total.unwrap().to_string(), "5");
+//! ```
+
+use std::any::Any;
+use std::fmt;
+
+use super::native::Args;
+use super::object::{Object, Value};
+use super::runtime_error::RuntimeError;
+use super::Runtime;
+use crate::shared::Shared;
+
+/// A Rust type scripts can hold values of. With the `sync` feature it must
+/// be `Send + Sync` too; values are shared, so changing one takes interior
+/// mutability.
+#[cfg(not(feature = "sync"))]
+pub trait UserType: Any {
+    /// What scripts see as its type, e.g. in `typeof` and errors.
+    fn type_name() -> &'static str;
+}
+
+/// A Rust type scripts can hold values of. With the `sync` feature it must
+/// be `Send + Sync` too; values are shared, so changing one takes interior
+/// mutability.
+#[cfg(feature = "sync")]
+pub trait UserType: Any + Send + Sync {
+    /// What scripts see as its type, e.g. in `typeof` and errors.
+    fn type_name() -> &'static str;
+}
+
+#[cfg(not(feature = "sync"))]
+type AnyValue = dyn Any;
+#[cfg(feature = "sync")]
+type AnyValue = dyn Any + Send + Sync;
+
+pub struct UserData {
+    type_name: &'static str,
+    value: Box<AnyValue>,
+}
+
+impl UserData {
+    pub fn new<T: UserType>(value: T) -> Self {
+        Self {
+            type_name: T::type_name(),
+            value: Box::new(value),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The value, if it is a `T`.
+    pub fn downcast_ref<T: UserType>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+}
+
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserData({})", self.type_name)
+    }
+}
+
+impl Object {
+    /// `value` as a value scripts can hold.
+    pub fn user_data<T: UserType>(value: T) -> Value {
+        Object::UserData(Shared::new(UserData::new(value)))
+    }
+}
+
+impl Runtime {
+    /// Registers `method` as a native function whose first argument is a
+    /// `T`. It gets that value and the arguments after it, so `args.int(0)`
+    /// is the one right after the `T`.
+    pub fn register_method<T: UserType>(
+        &mut self,
+        name: &str,
+        method: impl Fn(&T, &Args) -> Result<Value, RuntimeError> + NativeBounds + 'static,
+    ) {
+        self.register_fn(name, move |args: &Args| {
+            let this = args.user_data::<T>(0)?;
+            method(this, &args.rest())
+        });
+    }
+}
+
+/// `Send + Sync` with the `sync` feature, which native functions must be.
+#[cfg(not(feature = "sync"))]
+pub trait NativeBounds {}
+#[cfg(not(feature = "sync"))]
+impl<T> NativeBounds for T {}
+
+/// `Send + Sync` with the `sync` feature, which native functions must be.
+#[cfg(feature = "sync")]
+pub trait NativeBounds: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync> NativeBounds for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Door {
+        open: bool,
+    }
+
+    impl UserType for Door {
+        fn type_name() -> &'static str {
+            "Door"
+        }
+    }
+
+    #[test]
+    fn test_methods_check_their_receiver() {
+        let mut runtime = Runtime::builder().build();
+        runtime.register_fn("door", |_| Ok(Value::user_data(Door { open: true })));
+        runtime.register_method("isOpen", |door: &Door, args| {
+            args.expect_count(0)?;
+            Ok(Value::Boolean(door.open))
+        });
+        let value = runtime.run_source("let d = door()\nisOpen(d)").unwrap();
+        assert_eq!(value.to_string(), "true");
+        let value = runtime.run_source("typeof(d)").unwrap();
+        assert_eq!(value.to_string(), "Door");
+
+        let error = runtime.run_source("isOpen(1)").unwrap_err();
+        assert_eq!(
+            error.msg,
+            "argument 1 of 'isOpen' must be of type 'Door' but provided 'int'"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Evaluation server started with `filipe serve`, for notebooks and web
+//! backends that run many snippets without starting a process for each.
+//! It speaks JSON-RPC 2.0, framed with `Content-Length` headers like the
+//! language server, over stdin and stdout or, with `--port=<port>`, over
+//! TCP connections to `127.0.0.1`.
+//!
+//! Each request names a `session`, which keeps its globals between
+//! requests:
+//!
+//! - `run` with `source` runs a script and returns `{ "value": ... }`, the
+//!   value of its last statement;
+//! - `evalExpr` with `source` and `bindings`, an object of variables,
+//!   evaluates an expression, see `Runtime::eval_expression`;
+//! - `reset` forgets the session.
+//!
+//! What a script prints is sent while it runs as `output` notifications
+//! with the `session` and the `text`. Errors of scripts are JSON-RPC errors
+//! whose `data` is the diagnostic as `--error-format=json` writes it.
+//! Sessions belong to a connection.
+//!
+//! Whoever connects runs code, so sessions are sandboxed, see
+//! `RuntimePermissions::sandboxed`, and each request gets a budget of
+//! steps and memory. A script going over it, or recursing without end,
+//! fails with an `E014` or `E015` error while the server keeps going.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpListener;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+use serde_json::{json, Value as Json};
+
+use crate::diagnostics::{Diagnostic, ErrorFormat, Renderer};
+use crate::lsp::{read_message, write_message};
+use crate::runtime::expression::Bindings;
+use crate::runtime::flstdlib::to_text;
+use crate::runtime::object::{Object, Value};
+use crate::runtime::permissions::RuntimePermissions;
+use crate::runtime::streams::{Output, TextWriter};
+use crate::runtime::{Runtime, DEFAULT_MAX_CALL_DEPTH};
+use crate::shared::Shared;
+
+// Error codes of JSON-RPC.
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A script failing, with its diagnostic as `data`.
+const SCRIPT_ERROR: i64 = -32000;
+
+/// Steps each request may take.
+const MAX_STEPS: u64 = 10_000_000;
+/// Memory each session may hold, in bytes.
+const MAX_MEMORY: usize = 64 << 20;
+/// Of the threads serving connections, as much as the main thread has, so
+/// calls hit `DEFAULT_MAX_CALL_DEPTH` before the end of the stack.
+const STACK_SIZE: usize = 8 << 20;
+
+/// Serves stdin and stdout until the input ends. Returns the exit status.
+pub fn serve_stdio(input: impl BufRead, output: impl Output + 'static) -> i32 {
+    match Server::new(output).serve(input) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("[ERROR]: {}", err);
+            1
+        }
+    }
+}
+
+/// Serves each connection to `port` on a thread of its own, until the
+/// listener fails.
+pub fn serve_tcp(port: u16) -> i32 {
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("[ERROR]: Couldn't listen on port {}: {}", port, err);
+            return 1;
+        }
+    };
+    eprintln!("Serving on 127.0.0.1:{}", port);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let spawned = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let Ok(output) = stream.try_clone() else {
+                    return;
+                };
+                // a connection going away only ends its sessions
+                let _ = Server::new(output).serve(BufReader::new(stream));
+            });
+        if let Err(err) = spawned {
+            eprintln!("[ERROR]: Couldn't serve a connection: {}", err);
+        }
+    }
+    0
+}
+
+struct Server {
+    /// Shared with the sessions, which send what their scripts print.
+    output: Shared<Mutex<Box<dyn Output>>>,
+    sessions: HashMap<String, Runtime>,
+}
+
+impl Server {
+    fn new(output: impl Output + 'static) -> Self {
+        Self {
+            output: Shared::new(Mutex::new(Box::new(output))),
+            sessions: HashMap::new(),
+        }
+    }
+
+    fn output(&self) -> MutexGuard<'_, Box<dyn Output>> {
+        lock(&self.output)
+    }
+
+    fn serve(&mut self, mut input: impl BufRead) -> io::Result<()> {
+        while let Some(request) = read_message(&mut input)? {
+            let response = match self.handle(&request) {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                Err(error) => json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+            };
+            // notifications get no response
+            if !request["id"].is_null() {
+                write_message(&mut *self.output(), &response)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: &Json) -> Result<Json, Json> {
+        let params = &request["params"];
+        let method = request["method"].as_str().unwrap_or_default();
+        let Some(session) = params["session"].as_str() else {
+            return Err(rpc_error(INVALID_PARAMS, "missing 'session'"));
+        };
+        if method == "reset" {
+            self.sessions.remove(session);
+            return Ok(Json::Null);
+        }
+        if method != "run" && method != "evalExpr" {
+            let message = format!("unknown method '{}'", method);
+            return Err(rpc_error(METHOD_NOT_FOUND, &message));
+        }
+        let Some(source) = params["source"].as_str() else {
+            return Err(rpc_error(INVALID_PARAMS, "missing 'source'"));
+        };
+        let bindings =
+            bindings(&params["bindings"]).map_err(|message| rpc_error(INVALID_PARAMS, &message))?;
+
+        let output = Shared::clone(&self.output);
+        let runtime = self.sessions.entry(session.to_string()).or_insert_with(|| {
+            let name = session.to_string();
+            Runtime::builder()
+                .permissions(RuntimePermissions::sandboxed())
+                .max_memory(MAX_MEMORY)
+                .max_call_depth(DEFAULT_MAX_CALL_DEPTH)
+                .stdout(TextWriter(move |text: &str| {
+                    let notification = json!({
+                        "jsonrpc": "2.0",
+                        "method": "output",
+                        "params": { "session": name, "text": text },
+                    });
+                    let _ = write_message(&mut *lock(&output), &notification);
+                }))
+                .build()
+        });
+        runtime.set_max_steps(Some(MAX_STEPS));
+        let result = match method {
+            "run" => runtime.run_source(source),
+            _ => runtime.eval_expression(source, &bindings),
+        };
+
+        match result {
+            Ok(value) => Ok(json!({ "value": to_json(&value) })),
+            Err(diagnostic) => Err(script_error(&diagnostic)),
+        }
+    }
+}
+
+fn lock(output: &Mutex<Box<dyn Output>>) -> MutexGuard<'_, Box<dyn Output>> {
+    output
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn rpc_error(code: i64, message: &str) -> Json {
+    json!({ "code": code, "message": message })
+}
+
+fn script_error(diagnostic: &Diagnostic) -> Json {
+    let rendered = Renderer::new(false)
+        .with_format(ErrorFormat::Json)
+        .render(diagnostic, None, None);
+    json!({
+        "code": SCRIPT_ERROR,
+        "message": diagnostic.msg,
+        "data": serde_json::from_str::<Json>(&rendered).unwrap_or_default(),
+    })
+}
+
+/// Arrays become JSON arrays, and values JSON has nothing for, like
+/// functions, the text `print` shows for them.
+fn to_json(value: &Value) -> Json {
+    match value {
+        Object::Null => Json::Null,
+        Object::Int(val) => json!(val),
+        Object::Float(val) => json!(val),
+        Object::Boolean(val) => json!(val),
+        Object::String(val) => json!(val.to_string()),
+        Object::Array(items) => Json::Array(items.iter().map(to_json).collect()),
+        other => json!(to_text(other)),
+    }
+}
+
+fn bindings(params: &Json) -> Result<Bindings, String> {
+    let mut bindings = Bindings::new();
+    let Some(variables) = params.as_object() else {
+        return Ok(bindings);
+    };
+    for (name, value) in variables {
+        bindings = bindings.set(name, from_json(value)?);
+    }
+    Ok(bindings)
+}
+
+fn from_json(value: &Json) -> Result<Value, String> {
+    Ok(match value {
+        Json::Null => Object::Null,
+        Json::Bool(val) => Object::Boolean(*val),
+        Json::Number(number) => match number.as_i64() {
+            Some(val) => Object::Int(val),
+            None => Object::Float(number.as_f64().unwrap_or_default()),
+        },
+        Json::String(val) => Object::String(val.as_str().into()),
+        Json::Array(items) => {
+            let items = items.iter().map(from_json).collect::<Result<Vec<_>, _>>()?;
+            let items_type = items
+                .first()
+                .map(crate::runtime::type_system::object_to_type);
+            Object::new_array(items, items_type)
+        }
+        Json::Object(_) => return Err("objects can't be bound, as there are no maps".to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::Lock;
+
+    #[test]
+    fn test_sessions_keep_their_globals() {
+        let requests = [
+            json!({ "id": 1, "method": "run", "params": { "session": "a", "source": "let x = 20\nprint(x)" } }),
+            json!({ "id": 2, "method": "evalExpr", "params": { "session": "a", "source": "x + y", "bindings": { "y": 22 } } }),
+            json!({ "id": 3, "method": "run", "params": { "session": "b", "source": "x" } }),
+            json!({ "id": 4, "method": "reset", "params": { "session": "a" } }),
+        ];
+        let mut input = Vec::new();
+        for request in &requests {
+            write_message(&mut input, request).unwrap();
+        }
+        let output = Shared::new(Lock::new(Vec::new()));
+        let written = Shared::clone(&output);
+        let mut server = Server::new(TextWriter(move |text: &str| {
+            written.borrow_mut().extend_from_slice(text.as_bytes())
+        }));
+        server.serve(&input[..]).unwrap();
+
+        let output = output.borrow().clone();
+        let mut reader = &output[..];
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        assert_eq!(
+            messages[0]["params"],
+            json!({ "session": "a", "text": "20\n" })
+        );
+        assert_eq!(messages[1]["result"], json!({ "value": null }));
+        assert_eq!(messages[2]["result"], json!({ "value": 42 }));
+        assert_eq!(messages[3]["error"]["data"]["code"], "E010");
+        assert_eq!(messages[4]["result"], Json::Null);
+        assert!(!server.sessions.contains_key("a"));
+    }
+
+    #[test]
+    fn test_runaway_sessions_fail_without_stopping_the_server() {
+        let requests = [
+            json!({ "id": 1, "method": "run", "params": { "session": "a", "source": "define f(n: int): int {\n  return f(n + 1)\n}\nf(0)" } }),
+            json!({ "id": 2, "method": "run", "params": { "session": "a", "source": "import \"./secrets\"" } }),
+            json!({ "id": 3, "method": "run", "params": { "session": "a", "source": "1 + 1" } }),
+        ];
+        let mut input = Vec::new();
+        for request in &requests {
+            write_message(&mut input, request).unwrap();
+        }
+        // as deep as `serve_tcp` lets calls go
+        let output = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let output = Shared::new(Lock::new(Vec::new()));
+                let written = Shared::clone(&output);
+                let mut server = Server::new(TextWriter(move |text: &str| {
+                    written.borrow_mut().extend_from_slice(text.as_bytes())
+                }));
+                server.serve(&input[..]).unwrap();
+                let output = output.borrow().clone();
+                output
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        let mut reader = &output[..];
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        assert_eq!(messages[0]["error"]["data"]["code"], "E014");
+        assert_eq!(messages[1]["error"]["data"]["code"], "E021");
+        assert_eq!(messages[2]["result"], json!({ "value": 2 }));
+    }
+}
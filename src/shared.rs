@@ -0,0 +1,42 @@
+//! Pointer types for runtime data that is shared or mutated through shared
+//! references.
+//!
+//! By default these are the single-threaded `Rc` and `RefCell`. With the
+//! `sync` feature they become `Arc` and a `RwLock` wrapper, which makes the
+//! runtime `Send` so it can be moved to a worker thread, at the cost of
+//! atomic refcounts and locking.
+
+#[cfg(not(feature = "sync"))]
+pub use std::{cell::RefCell as Lock, rc::Rc as Shared};
+
+#[cfg(feature = "sync")]
+pub use self::sync::Lock;
+#[cfg(feature = "sync")]
+pub use std::sync::Arc as Shared;
+
+#[cfg(feature = "sync")]
+mod sync {
+    use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+    /// `RwLock` with the `RefCell` borrowing API.
+    #[derive(Debug, Default)]
+    pub struct Lock<T>(RwLock<T>);
+
+    impl<T> Lock<T> {
+        pub fn new(value: T) -> Self {
+            Self(RwLock::new(value))
+        }
+
+        pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+            self.0
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        }
+
+        pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+            self.0
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        }
+    }
+}
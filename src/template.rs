@@ -0,0 +1,114 @@
+//! Text templates whose `${...}` placeholders are filipe expressions,
+//! evaluated like `Runtime::eval_expression` with the caller's variables:
+//!
+//! ```
+//! use filipe::{template, Bindings};
+//!
+//! let context = Bindings::new().set("name", "Ana").set("items", vec![3i64, 4]);
+//! let text = template::render("Hello ${name}, you have ${len(items)} items", &context);
+//! assert_eq!(text.unwrap(), "Hello Ana, you have 2 items");
+//! ```
+//!
+//! Values are written like `print` writes them, so strings have no
+//! quotes. `$${` is a literal `${`. As in scripts, there are no maps or
+//! fields, so nested data is bound under names of its own, e.g. `userName`.
+
+use crate::diagnostics::Diagnostic;
+use crate::frontend::parser::{ParserError, ParserErrorKind};
+use crate::frontend::token::Span;
+use crate::messages::msg;
+use crate::runtime::expression::Bindings;
+use crate::runtime::flstdlib::to_text;
+use crate::runtime::Runtime;
+
+/// `template` with its placeholders replaced by what they evaluate to. The
+/// spans of errors point into `template`.
+pub fn render(template: &str, context: &Bindings) -> Result<String, Diagnostic> {
+    let mut runtime = Runtime::builder().build();
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+        let offset = template.len() - rest.len() + start;
+        let source = &rest[start + 2..];
+        let Some(end) = placeholder_end(source) else {
+            return Err(Diagnostic::from(&ParserError {
+                kind: ParserErrorKind::SyntaxError,
+                msg: msg!("E001.unclosed_placeholder"),
+                span: span_at(template, offset),
+            }));
+        };
+        let value = runtime
+            .eval_expression(&source[..end], context)
+            .map_err(|mut error| {
+                let base = span_at(template, offset + 2);
+                error.span = error.span.map(|span| shift(span, base));
+                error
+            })?;
+        output.push_str(&to_text(&value));
+        rest = &source[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Where the `}` closing a placeholder is, skipping those in strings.
+fn placeholder_end(source: &str) -> Option<usize> {
+    let mut in_string = false;
+    for (index, c) in source.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '}' if !in_string => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The span of the byte at `offset` in `text`.
+fn span_at(text: &str, offset: usize) -> Span {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    Span {
+        line: before.matches('\n').count() + 1,
+        col: before[line_start..].chars().count() + 1,
+        offset,
+    }
+}
+
+/// `span`, relative to a placeholder starting at `base`, relative to the
+/// whole template.
+fn shift(span: Span, base: Span) -> Span {
+    Span {
+        line: base.line + span.line - 1,
+        col: match span.line {
+            1 => base.col + span.col - 1,
+            _ => span.col,
+        },
+        offset: base.offset + span.offset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_placeholders() {
+        let context = Bindings::new().set("total", 4.5).set("paid", false);
+        let text = render("Total: ${total * 2.0}\n$${total} ${\"}\"}", &context);
+        assert_eq!(text.unwrap(), "Total: 9\n${total} }");
+
+        let error = render("ok\n  ${paid + 1}", &context).unwrap_err();
+        assert_eq!(error.code, "E011");
+        assert_eq!(error.span.map(|span| (span.line, span.col)), Some((2, 10)));
+        let error = render("${total", &context).unwrap_err();
+        assert_eq!(error.msg, "'${' isn't closed");
+    }
+}
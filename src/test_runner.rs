@@ -0,0 +1,224 @@
+//! `filipe test`: finds the test functions of scripts and runs each one on
+//! its own. A test is a function without parameters whose name is `test`
+//! followed by a capital letter, like `testAddition`; it fails when it
+//! raises an error, e.g. with a failing `assert`.
+
+use std::fs;
+use std::path::Path;
+
+use crate::commands::write_coverage;
+use crate::diagnostics::{stderr_supports_color, Diagnostic, Renderer};
+use crate::frontend::ast::{Expr, ExprKind, Identifier, Program, Stmt, StmtKind};
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::runtime::coverage::{Coverage, FileCoverage, ReportFormat};
+use crate::runtime::modules::MODULES_DIR;
+use crate::runtime::{context::Context, flstdlib::builtins, Runtime};
+use crate::shared::{Lock, Shared};
+use crate::utils::{read_file, script_path, SCRIPT_EXTENSION};
+
+/// Runs the tests of the scripts at `paths`, looking through directories
+/// for `.fl` files, and writes a coverage report of them in `coverage`
+/// format if given. Returns whether every test passed.
+pub fn run_tests(paths: &[String], coverage: Option<ReportFormat>) -> bool {
+    let mut files = vec![];
+    for path in paths {
+        let path = script_path(path);
+        if Path::new(&path).is_dir() {
+            collect_scripts(Path::new(&path), &mut files);
+        } else {
+            files.push(path);
+        }
+    }
+
+    let renderer = Renderer::new(stderr_supports_color());
+    let (mut passed, mut failed) = (0, 0);
+    let mut covered = vec![];
+    for file in &files {
+        let Some(source) = read_file(file) else {
+            failed += 1;
+            continue;
+        };
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let mut p = Parser::new(&mut l);
+        let program = p.parse();
+        if p.has_error() {
+            for error in p.errors() {
+                let diagnostic = Diagnostic::from(error);
+                eprint!(
+                    "{}",
+                    renderer.render(&diagnostic, Some(&source), Some(file))
+                );
+            }
+            failed += 1;
+            continue;
+        }
+
+        let mut file_coverage = FileCoverage::new(file, Some(&source), &program);
+        for test in tests(&program) {
+            let StmtKind::Func(Identifier(name), ..) = &test.kind else {
+                continue;
+            };
+            let hits = coverage.map(|_| &mut file_coverage.coverage);
+            match run_test(&program, file, test, hits) {
+                Ok(()) => {
+                    println!("test {}::{} ... ok", file, name);
+                    passed += 1;
+                }
+                Err(diagnostic) => {
+                    println!("test {}::{} ... FAILED", file, name);
+                    eprint!(
+                        "{}",
+                        renderer.render(&diagnostic, Some(&source), Some(file))
+                    );
+                    failed += 1;
+                }
+            }
+        }
+        covered.push(file_coverage);
+    }
+
+    println!();
+    println!("{} passed; {} failed", passed, failed);
+    if let Some(format) = coverage {
+        write_coverage(format, &covered);
+    }
+    failed == 0
+}
+
+/// The `.fl` files under `dir`, in name order.
+fn collect_scripts(dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        eprintln!("[IOError]: Couldn't read directory {}", dir.display());
+        return;
+    };
+    let mut paths = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        // packages are tested in their own projects
+        let skipped = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.') || name == MODULES_DIR);
+        if skipped {
+            continue;
+        }
+        if path.is_dir() {
+            collect_scripts(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION) {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+/// The top-level test functions of a program.
+fn tests(program: &Program) -> impl Iterator<Item = &Stmt> {
+    program.iter().filter(|stmt| match &stmt.kind {
+        StmtKind::Func(Identifier(name), params, ..) => params.is_empty() && is_test_name(name),
+        _ => false,
+    })
+}
+
+fn is_test_name(name: &str) -> bool {
+    name.strip_prefix("test")
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(char::is_uppercase)
+}
+
+/// Calls `test` in a fresh global scope that only has the top-level
+/// declarations and imports of the script at `path`; its other statements
+/// don't run. The lines it runs are added to `coverage`.
+fn run_test(
+    program: &Program,
+    path: &str,
+    test: &Stmt,
+    coverage: Option<&mut Coverage>,
+) -> Result<(), Diagnostic> {
+    let StmtKind::Func(name, ..) = &test.kind else {
+        return Ok(());
+    };
+    let mut declarations = program
+        .iter()
+        .filter(|stmt| {
+            matches!(
+                stmt.kind,
+                StmtKind::Let(..) | StmtKind::Func(..) | StmtKind::Import(_)
+            )
+        })
+        .cloned()
+        .collect::<Program>();
+    let call = Expr::new(
+        ExprKind::Call(
+            Box::new(Expr::new(ExprKind::Identifier(name.clone()), test.span)),
+            vec![],
+        ),
+        test.span,
+    );
+    declarations.push(Stmt {
+        kind: StmtKind::Expr(call),
+        span: test.span,
+    });
+
+    let env = Context::make_global(builtins());
+    let mut evaltr = Runtime::new(Shared::new(Lock::new(env)));
+    evaltr.set_script_path(path);
+    if coverage.is_some() {
+        evaltr.enable_coverage();
+    }
+    let result = evaltr.eval(declarations);
+    if let (Some(coverage), Some(hits)) = (coverage, evaltr.coverage()) {
+        coverage.merge(hits);
+    }
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) => Err(Diagnostic::from(&error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tests_run_in_isolation() {
+        let source = "let total = 0\n\
+                      print(\"not run\")\n\
+                      define add(x: int): void {\n\
+                      \x20 total = total + x\n\
+                      }\n\
+                      define testAdd(): void {\n\
+                      \x20 add(2)\n\
+                      \x20 assert total == 2\n\
+                      }\n\
+                      define testAddAgain(): void {\n\
+                      \x20 add(3)\n\
+                      \x20 assert total == 2\n\
+                      }\n\
+                      define testing(): void {}\n\
+                      define testWithArg(x: int): void {}";
+        let input = source.chars().collect::<Vec<char>>();
+        let mut l = Lexer::new(&input);
+        let program = Parser::new(&mut l).parse();
+
+        let names = tests(&program)
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Func(Identifier(name), ..) => name.as_str(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["testAdd", "testAddAgain"]);
+
+        let results = tests(&program)
+            .map(|test| {
+                run_test(&program, "test.fl", test, None).map_err(|diagnostic| diagnostic.msg)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(results[0], Ok(()));
+        assert_eq!(
+            results[1],
+            Err("assertion failed: total == 2 (total = 3)".to_string())
+        );
+    }
+}
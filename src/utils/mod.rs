@@ -1,20 +1,101 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+
+/// Extension of filipe scripts.
+pub const SCRIPT_EXTENSION: &str = "fl";
+
+/// `path`, or `path.fl` when only that one exists, so scripts can be named
+/// without their extension.
+pub fn script_path(path: &str) -> String {
+    let with_extension = format!("{path}.{SCRIPT_EXTENSION}");
+    if !Path::new(path).exists() && Path::new(&with_extension).is_file() {
+        return with_extension;
+    }
+    path.to_string()
+}
 
 pub fn read_file(path: &str) -> Option<String> {
+    let bytes = read_file_bytes(path)?;
+    match String::from_utf8(bytes) {
+        Ok(contents) => Some(contents),
+        Err(_) => {
+            eprintln!("[IOError]: Couldn't read from file {path}");
+            None
+        }
+    }
+}
+
+pub fn read_file_bytes(path: &str) -> Option<Vec<u8>> {
     match File::open(path) {
         Ok(mut file) => {
-            let mut contents = String::new();
-            if file.read_to_string(&mut contents).is_ok() {
-                Some(contents)
-            } else {
-                eprintln!("[IOError]: Couldn't read from file {path}");
-                None
+            let mut contents = vec![];
+            match file.read_to_end(&mut contents) {
+                Ok(_) => Some(contents),
+                Err(err) => {
+                    eprintln!("[IOError]: Couldn't read from file {path}: {err}");
+                    None
+                }
             }
         }
-        Err(_) => {
-            eprintln!("[IOError]: Couldn't open file {path}");
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            eprintln!("[IOError]: File {path} doesn't exist");
             None
-        },
+        }
+        Err(err) => {
+            eprintln!("[IOError]: Couldn't open file {path}: {err}");
+            None
+        }
     }
 }
+
+pub fn write_file(path: &str, contents: &[u8]) -> bool {
+    match std::fs::write(path, contents) {
+        Ok(_) => true,
+        Err(_) => {
+            eprintln!("[IOError]: Couldn't write to file {path}");
+            false
+        }
+    }
+}
+
+/// `text` with the characters that mean something in HTML escaped.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Number of single-character insertions, deletions and substitutions that
+/// turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The candidate closest to `name`, for "did you mean" hints. Only names
+/// within a third of its length in edits are considered, and ties go to the
+/// alphabetically first one so hints are stable.
+pub fn closest_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
@@ -0,0 +1,76 @@
+//! The API of the WebAssembly build, for running filipe in a browser, e.g.
+//! in a playground:
+//!
+//! ```js
+//! import init, { run } from "./pkg/filipe.js";
+//!
+//! await init();
+//! const result = run('print("Hello")', (text) => console.log(text));
+//! result.output; // "Hello\n"
+//! result.errors; // []
+//! ```
+//!
+//! Build it with `wasm-pack build --target web`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::{Diagnostic, Renderer};
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::runtime::context::Context;
+use crate::runtime::flstdlib::builtins;
+use crate::runtime::runtime_error::ErrorKind;
+use crate::runtime::streams::{Output, TextWriter};
+use crate::runtime::Runtime;
+use crate::shared::{Lock, Shared};
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct RunResult {
+    /// Everything `print` wrote.
+    pub output: String,
+    /// The syntax errors, or the runtime error that stopped the program,
+    /// rendered like the command line does without colors.
+    pub errors: Vec<String>,
+}
+
+/// Runs `source` in a fresh global scope. `on_print`, when given, is called
+/// with each line `print` writes as it is written.
+#[wasm_bindgen]
+pub fn run(source: &str, on_print: Option<js_sys::Function>) -> RunResult {
+    let output = Shared::new(Lock::new(String::new()));
+    let written = Shared::clone(&output);
+    let stdout = TextWriter(move |text: &str| {
+        written.borrow_mut().push_str(text);
+        if let Some(on_print) = &on_print {
+            let _ = on_print.call1(&JsValue::NULL, &JsValue::from_str(text));
+        }
+    });
+    let errors = run_program(source, stdout);
+
+    let output = output.borrow().clone();
+    RunResult { output, errors }
+}
+
+fn run_program(source: &str, stdout: impl Output + 'static) -> Vec<String> {
+    let renderer = Renderer::new(false);
+    let input = source.chars().collect::<Vec<char>>();
+    let mut l = Lexer::new(&input);
+    let mut p = Parser::new(&mut l);
+    let program = p.parse();
+    if p.has_error() {
+        return p
+            .errors()
+            .iter()
+            .map(|error| renderer.render(&Diagnostic::from(error), Some(source), None))
+            .collect();
+    }
+
+    let env = Context::make_global(builtins());
+    let mut runtime = Runtime::new(Shared::new(Lock::new(env)));
+    runtime.set_stdout(stdout);
+    match runtime.eval(program) {
+        Ok(_) => vec![],
+        Err(error) if matches!(error.kind, ErrorKind::Exit(_)) => vec![],
+        Err(error) => vec![renderer.render(&Diagnostic::from(&error), Some(source), None)],
+    }
+}
@@ -0,0 +1,80 @@
+//! `filipe watch`: runs a script again each time it is saved. Every run is
+//! a `filipe run` child process, so a script that calls `exit`, fails or
+//! never ends doesn't stop the watching; a change cuts a run short.
+
+use std::fs;
+use std::io::{stdout, IsTerminal};
+use std::path::Path;
+use std::process::{exit, Child, Command};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+use crate::utils::script_path;
+
+/// How often the script is looked at for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Watches the script at `path`, running it with the `run` options in
+/// `run_args`, until interrupted.
+pub fn watch(path: &str, run_args: &[String]) {
+    let path = script_path(path);
+    if !Path::new(&path).is_file() {
+        eprintln!("[IOError]: File {path} doesn't exist");
+        exit(1);
+    }
+    let mut modified = modified_at(&path);
+    loop {
+        if stdout().is_terminal() {
+            // clears the screen and puts the cursor at the top
+            print!("\x1b[2J\x1b[H");
+        }
+        let mut child = start(&path, run_args);
+        let mut finished = false;
+        loop {
+            sleep(POLL_INTERVAL);
+            let now = modified_at(&path);
+            if now != modified {
+                modified = now;
+                if !finished {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                break;
+            }
+            if finished {
+                continue;
+            }
+            if let Ok(Some(status)) = child.try_wait() {
+                finished = true;
+                match status.code() {
+                    Some(0) | None => eprintln!("\n[Waiting for changes to {path}]"),
+                    Some(code) => {
+                        eprintln!("\n[Exited with status {code}, waiting for changes to {path}]")
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn start(path: &str, run_args: &[String]) -> Child {
+    let filipe = std::env::current_exe().unwrap_or_else(|_| "filipe".into());
+    match Command::new(filipe)
+        .arg("run")
+        .arg(path)
+        .args(run_args)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("[ERROR]: Couldn't run {path}: {err}");
+            exit(1);
+        }
+    }
+}
+
+/// When the file was last changed, `None` while it can't be read, e.g.
+/// when an editor replaces it on save.
+fn modified_at(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}